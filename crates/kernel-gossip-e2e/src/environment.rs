@@ -2,7 +2,7 @@ use anyhow::{Result, Context};
 use k8s_openapi::api::core::v1::Pod;
 use k8s_openapi::api::apps::v1::Deployment;
 use kube::{Api, Client, api::ListParams};
-use kernel_gossip_types::{KernelWhisper, PodBirthCertificate};
+use kernel_gossip_types::{KernelWhisper, MemoryWhisper, PodBirthCertificate};
 use tracing::info;
 
 use crate::TestWorkload;
@@ -70,6 +70,9 @@ impl E2ETestEnvironment {
                 "labels": {
                     "test": "e2e",
                     "type": "memory-stress"
+                },
+                "annotations": {
+                    "kernel-gossip.io/monitor": "true"
                 }
             },
             "spec": {
@@ -277,46 +280,36 @@ impl E2ETestEnvironment {
         Ok(filtered.join("\n"))
     }
     
-    /// Create a manual memory pressure KernelWhisper for testing
-    pub async fn create_manual_memory_pressure_whisper(
-        &self,
-        pod_name: &str,
-        _memory_usage_pct: f64,
-        _page_faults_per_sec: f64
-    ) -> Result<()> {
-        info!("Creating manual memory pressure KernelWhisper for testing...");
+    /// Get REAL MemoryWhispers for a pod
+    pub async fn get_memory_whispers_for_pod(&self, pod_name: &str) -> Result<Vec<MemoryWhisper>> {
+        info!("Querying MemoryWhispers for pod: {}", pod_name);
         
-        let whispers: Api<KernelWhisper> = Api::namespaced(self.client.clone(), &self.namespace);
+        let whispers: Api<MemoryWhisper> = Api::namespaced(self.client.clone(), &self.namespace);
+        let lp = ListParams::default();
         
-        // Create a different type of whisper to test memory detection
-        let whisper = serde_json::from_value(serde_json::json!({
-            "apiVersion": "kernel.gossip.io/v1alpha1",
-            "kind": "KernelWhisper",
-            "metadata": {
-                "name": format!("{}-memory-e2e", pod_name),
-                "namespace": &self.namespace,
-            },
-            "spec": {
-                "pod_name": pod_name,
-                "namespace": &self.namespace,
-                "detected_at": chrono::Utc::now().to_rfc3339(),
-                "kernel_truth": {
-                    "throttled_percent": 0.0,  // No CPU throttling
-                    "actual_cpu_cores": 0.1
-                },
-                "metrics_lie": {
-                    "cpu_percent": 10.0,
-                    "reported_status": "healthy"
-                },
-                "severity": "critical"
-            }
-        }))?;
+        let list = whispers.list(&lp).await
+            .context("Failed to list MemoryWhispers")?;
         
-        whispers.create(&Default::default(), &whisper).await
-            .context("Failed to create memory pressure KernelWhisper")?;
+        // Filter for this pod
+        let matching: Vec<_> = list.items.into_iter()
+            .filter(|mw| mw.spec.pod_name == pod_name)
+            .collect();
         
-        info!("Created memory pressure KernelWhisper for pod: {}", pod_name);
-        Ok(())
+        info!("Found {} MemoryWhispers for pod {}", matching.len(), pod_name);
+        Ok(matching)
+    }
+    
+    /// Wait for the observer and operator to record memory pressure for a pod
+    pub async fn wait_for_memory_whisper(&self, pod_name: &str, timeout_secs: u64) -> Result<MemoryWhisper> {
+        for _ in 0..timeout_secs {
+            if let Some(mw) = self.get_memory_whispers_for_pod(pod_name).await?.into_iter().next() {
+                return Ok(mw);
+            }
+            
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        }
+        
+        anyhow::bail!("No MemoryWhisper recorded for pod {} within {}s", pod_name, timeout_secs)
     }
     
     /// Create a manual KernelWhisper for testing
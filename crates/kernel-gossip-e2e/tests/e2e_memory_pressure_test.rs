@@ -23,33 +23,23 @@ async fn test_memory_pressure_detection_e2e() {
     test_env.wait_for_pod_ready(&workload.pod_name, &workload.namespace).await
         .expect("Pod failed to start");
     
-    // Give it time to generate memory pressure
-    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-    
-    // Create manual KernelWhisper for memory pressure
-    // In production, this would come from Pixie detecting page faults
-    test_env.create_manual_memory_pressure_whisper(&workload.pod_name, 92.0, 150.0).await
-        .expect("Failed to create test KernelWhisper");
-    
-    // Give operator time to reconcile
-    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-    
-    // Check if KernelWhisper was created
-    let whispers = test_env.get_kernel_whispers_for_pod(&workload.pod_name).await
-        .expect("Failed to query KernelWhispers");
-    
-    // Verify we detected memory pressure
-    assert!(!whispers.is_empty(), "No KernelWhispers created for memory-stressed pod");
-    
-    // Verify operator logs show memory-specific insights
-    let logs = test_env.get_operator_logs_for_pod(&workload.pod_name).await
-        .expect("Failed to get operator logs");
-    
-    println!("Operator logs for pod {}: {}", workload.pod_name, logs);
-    
-    // For now, just check that we got logs (operator may not have specific memory recommendations)
-    assert!(!logs.is_empty() || !whispers.is_empty(), 
-        "Either operator logs or KernelWhispers should exist");
+    // The observer polls the pod's memory cgroup and the operator records what it saw
+    let whisper = test_env.wait_for_memory_whisper(&workload.pod_name, 90).await
+        .expect("No MemoryWhisper created for memory-stressed pod");
+    
+    let truth = whisper.kernel_truth();
+    println!(
+        "Kernel saw {}% memory stall, {} max events, {} bytes working set; metrics-server reported {:?} ({})",
+        truth.stall_some_percent, truth.max_events, truth.working_set_bytes,
+        whisper.metrics_lie().working_set_bytes, whisper.metrics_lie().reported_status
+    );
+    
+    // A pod pushed against its limit must show the kernel stalling or hitting the limit
+    assert!(
+        truth.stall_some_percent > 0.0 || truth.high_events > 0 || truth.max_events > 0 || truth.oom_events > 0,
+        "MemoryWhisper carries no kernel evidence of pressure"
+    );
+    assert_eq!(truth.limit_bytes, Some(128 * 1024 * 1024));
     
     // Cleanup
     test_env.cleanup_workload(&workload).await
@@ -1,83 +1,19 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use kube::{Api, Client};
 use kernel_gossip_types::{
     PodBirthCertificate, TimelineEntry, KernelStats, Actor,
-    KernelWhisper, KernelTruth, MetricsLie,
+    KernelWhisper, KernelTruth, MetricsLie, ContainerCpuUsage,
+    MemoryWhisper, MemoryKernelTruth, OOMKillReport, OomMemoryBreakdown,
+    SignalThresholds,
 };
-use crate::webhook::{PodCreationPayload, CpuThrottlePayload};
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use crate::config::RetentionPolicy;
+use crate::metrics::metrics;
+use crate::pod_metrics::{MetricsError, PodMetricsClient, PodUsage};
+use crate::timeline::{align_kernel_entries, cap_timeline};
+use crate::webhook::{PodCreationPayload, CpuThrottlePayload, MemoryPressurePayload, OomKillPayload};
 use tracing::{info, warn};
-use serde::{Deserialize, Serialize};
-
-// Metrics API types
-#[derive(Debug, Serialize, Deserialize)]
-struct PodMetrics {
-    metadata: PodMetricsMetadata,
-    containers: Vec<ContainerMetrics>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct PodMetricsMetadata {
-    name: String,
-    namespace: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ContainerMetrics {
-    name: String,
-    usage: ResourceUsage,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ResourceUsage {
-    cpu: String,    // Format: "100m" or "1" 
-    memory: String, // Format: "128Mi"
-}
-
-// Fetch actual pod metrics from metrics server
-async fn fetch_pod_metrics(_client: &Client, pod_name: &str, namespace: &str) -> Result<f64> {
-    // Use kubectl top to get real metrics - this works in GKE
-    use std::process::Command;
-    
-    let output = Command::new("kubectl")
-        .args(["top", "pod", pod_name, "-n", namespace, "--no-headers"])
-        .output();
-    
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            if stdout.is_empty() {
-                warn!("No metrics available for pod {}/{}", namespace, pod_name);
-                // Return a realistic default based on kernel truth
-                return Ok(35.0); // Conservative estimate
-            }
-            
-            // Parse output: "pod-name   100m   256Mi"
-            let parts: Vec<&str> = stdout.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let cpu_str = parts[1];
-                let cpu_millis = if cpu_str.ends_with('m') {
-                    cpu_str.trim_end_matches('m').parse::<f64>().unwrap_or(0.0)
-                } else {
-                    // Assume cores, convert to millicores
-                    cpu_str.parse::<f64>().unwrap_or(0.0) * 1000.0
-                };
-                
-                // Convert to percentage (1000 millicores = 100%)
-                let cpu_percentage = cpu_millis / 10.0;
-                info!("Fetched real metrics for pod {}: {}m = {}%", pod_name, cpu_millis, cpu_percentage);
-                Ok(cpu_percentage)
-            } else {
-                warn!("Could not parse metrics output for pod {}", pod_name);
-                Ok(35.0)
-            }
-        }
-        Err(e) => {
-            warn!("Failed to fetch metrics via kubectl for pod {}/{}: {}", namespace, pod_name, e);
-            // Return a realistic estimate rather than failing
-            Ok(35.0)
-        }
-    }
-}
 
 // Builder functions for unit testing
 pub fn build_pod_birth_certificate(payload: &PodCreationPayload) -> PodBirthCertificate {
@@ -90,10 +26,12 @@ pub fn build_pod_birth_certificate(payload: &PodCreationPayload) -> PodBirthCert
     
     // Use timeline from payload if available, otherwise create a basic one
     let timeline = if !payload.timeline.is_empty() {
-        // Convert webhook timeline events to CRD timeline entries
+        // The observer sends the event as the container execs, with steps
+        // counted from when the runtime started
+        let started_ms = timestamp_ms.saturating_sub(payload.duration_ns / 1_000_000);
         payload.timeline.iter().map(|event| {
             TimelineEntry {
-                timestamp_ms: event.timestamp_ms,
+                timestamp_ms: started_ms + event.timestamp_ms,
                 actor: Actor::Kernel,
                 action: event.action.clone(),
             }
@@ -113,12 +51,15 @@ pub fn build_pod_birth_certificate(payload: &PodCreationPayload) -> PodBirthCert
         total_syscalls: payload.total_syscalls as u32,
         namespaces_created: payload.namespace_ops.min(255) as u8,
         cgroup_writes: payload.cgroup_writes as u32,
+        mount_ops: payload.mount_ops as u32,
         iptables_rules: 0, // Not tracked in payload
         total_duration_ms: payload.duration_ns / 1_000_000, // Convert ns to ms
     };
 
     let mut pbc = PodBirthCertificate::create(&payload.pod_name, &payload.namespace);
     pbc.spec.timeline = timeline;
+    cap_timeline(&mut pbc.spec.timeline);
+    pbc.spec.first_birth = Some(kernel_stats.clone());
     pbc.spec.kernel_stats = kernel_stats;
     pbc.metadata.name = Some(pbc_name);
     
@@ -131,11 +72,17 @@ pub fn build_kernel_whisper(payload: &CpuThrottlePayload) -> KernelWhisper {
     let kernel_truth = KernelTruth {
         throttled_percent: payload.throttle_percentage,
         actual_cpu_cores: payload.actual_cpu_usage,
+        throttled_periods: payload.nr_throttled,
+        total_periods: payload.nr_periods,
+        throttled_time_ms: payload.throttle_ns / 1_000_000,
+        window_seconds: payload.period_seconds,
     };
 
     let metrics_lie = MetricsLie {
-        cpu_percent: payload.reported_cpu_usage * 100.0, // Convert to percentage
+        cpu_percent: Some(payload.reported_cpu_usage * 100.0), // Convert to percentage
         reported_status: "Healthy".to_string(), // Metrics always report healthy
+        unavailable_reason: None,
+        containers: vec![],
     };
 
     // Use the create method properly
@@ -155,31 +102,199 @@ pub fn build_kernel_whisper(payload: &CpuThrottlePayload) -> KernelWhisper {
     kw
 }
 
+/// Record what metrics-server reports for the pod, or why it reported nothing
+pub fn apply_pod_metrics(kw: &mut KernelWhisper, usage: Result<PodUsage, MetricsError>) {
+    match usage {
+        Ok(usage) => {
+            kw.spec.metrics_lie.cpu_percent = Some(usage.cpu_percent());
+            kw.spec.metrics_lie.reported_status = "Healthy".to_string();
+            kw.spec.metrics_lie.unavailable_reason = None;
+            kw.spec.metrics_lie.containers = usage
+                .containers
+                .iter()
+                .map(|c| ContainerCpuUsage {
+                    name: c.name.clone(),
+                    cpu_cores: c.cpu_cores,
+                })
+                .collect();
+        }
+        Err(e) => {
+            kw.spec.metrics_lie.cpu_percent = None;
+            kw.spec.metrics_lie.reported_status = "Unavailable".to_string();
+            kw.spec.metrics_lie.unavailable_reason = Some(e.to_string());
+            kw.spec.metrics_lie.containers.clear();
+        }
+    }
+}
+
+pub fn build_memory_whisper(payload: &MemoryPressurePayload) -> MemoryWhisper {
+    let kernel_truth = MemoryKernelTruth {
+        stall_some_percent: payload.psi_some_avg10,
+        stall_full_percent: payload.psi_full_avg10,
+        stall_time_ms: payload.stall_time_us / 1_000,
+        high_events: payload.high_events,
+        max_events: payload.max_events,
+        oom_events: payload.oom_events,
+        reclaim_scanned_pages: payload.reclaim_scanned_pages,
+        working_set_bytes: payload.working_set_bytes,
+        limit_bytes: payload.limit_bytes,
+        window_seconds: payload.period_seconds,
+    };
+
+    let mut mw = MemoryWhisper::create(&payload.pod_name, &payload.namespace, kernel_truth);
+    mw.spec.detected_at = payload.timestamp.clone();
+    
+    mw
+}
+
+/// Record the working set metrics-server reports for the pod, or why it reported nothing
+pub fn apply_pod_memory_metrics(mw: &mut MemoryWhisper, usage: Result<PodUsage, MetricsError>) {
+    match usage {
+        Ok(usage) => {
+            mw.spec.metrics_lie.working_set_bytes = Some(usage.memory_bytes());
+            mw.spec.metrics_lie.reported_status = "Healthy".to_string();
+            mw.spec.metrics_lie.unavailable_reason = None;
+        }
+        Err(e) => {
+            mw.spec.metrics_lie.working_set_bytes = None;
+            mw.spec.metrics_lie.reported_status = "Unavailable".to_string();
+            mw.spec.metrics_lie.unavailable_reason = Some(e.to_string());
+        }
+    }
+}
+
+pub fn build_oom_kill_report(payload: &OomKillPayload) -> OOMKillReport {
+    let memory = OomMemoryBreakdown {
+        anon_bytes: payload.anon_bytes,
+        file_bytes: payload.file_bytes,
+        kernel_bytes: payload.kernel_bytes,
+        sock_bytes: payload.sock_bytes,
+        shmem_bytes: payload.shmem_bytes,
+        usage_bytes: payload.usage_bytes,
+        limit_bytes: payload.limit_bytes,
+    };
+
+    let mut report = OOMKillReport::create(
+        &payload.pod_name,
+        &payload.namespace,
+        &payload.pod_uid,
+        &payload.timestamp,
+        memory,
+    );
+    report.spec.oom_kills = payload.oom_kills;
+    
+    report
+}
+
+/// Owner reference that lets the garbage collector remove a record together with its Pod
+pub fn pod_owner_reference(pod: &Pod) -> Option<OwnerReference> {
+    Some(OwnerReference {
+        api_version: "v1".to_string(),
+        kind: "Pod".to_string(),
+        name: pod.metadata.name.clone()?,
+        uid: pod.metadata.uid.clone()?,
+        ..Default::default()
+    })
+}
+
+/// Label recording which Pod a record describes, so a later Pod with the same name is not mistaken for it
+pub const POD_UID_LABEL: &str = "kernel-gossip.io/pod-uid";
+
+/// Tie a record to the Pod it describes according to the retention policy
+pub fn apply_pod_ownership(meta: &mut ObjectMeta, pod: &Pod, retention: RetentionPolicy) {
+    let Some(uid) = pod.metadata.uid.clone() else {
+        return;
+    };
+    meta.labels
+        .get_or_insert_with(Default::default)
+        .insert(POD_UID_LABEL.to_string(), uid);
+
+    // With a TTL the controller decides when history goes, so the garbage
+    // collector must not, even for records owned before the TTL was set
+    if let Some(owners) = meta.owner_references.as_mut() {
+        owners.retain(|o| o.kind != "Pod");
+    }
+    if retention == RetentionPolicy::OwnedByPod {
+        if let Some(owner) = pod_owner_reference(pod) {
+            meta.owner_references.get_or_insert_with(Vec::new).push(owner);
+        }
+    }
+    if meta.owner_references.as_ref().is_some_and(Vec::is_empty) {
+        meta.owner_references = None;
+    }
+}
+
+/// Container the kubelet last reported as `OOMKilled`
+pub fn oom_killed_container(pod: &Pod) -> Option<String> {
+    let status = pod.status.as_ref()?;
+    status
+        .container_statuses
+        .iter()
+        .flatten()
+        .chain(status.init_container_statuses.iter().flatten())
+        .find(|c| {
+            let terminated = c
+                .last_state
+                .as_ref()
+                .and_then(|s| s.terminated.as_ref())
+                .or_else(|| c.state.as_ref().and_then(|s| s.terminated.as_ref()));
+            terminated.and_then(|t| t.reason.as_deref()) == Some("OOMKilled")
+        })
+        .map(|c| c.name.clone())
+}
+
+/// Fold a later birth of the same pod into its certificate. Restarts add to
+/// the running totals and the timeline, but the first birth stays what the
+/// certificate's startup breakdown and baseline describe.
+pub fn merge_pod_birth(existing: &mut PodBirthCertificate, reported: PodBirthCertificate) {
+    let spec = &mut existing.spec;
+    // Certificates from before the first birth was kept have only the totals
+    spec.first_birth.get_or_insert_with(|| spec.kernel_stats.clone());
+
+    // Append new timeline entries, on the clock the timeline is already on
+    let mut timeline = reported.spec.timeline;
+    align_kernel_entries(&mut timeline, spec.node_clock_offset_ms);
+    spec.timeline.extend(timeline);
+    spec.timeline.sort_by_key(|entry| entry.timestamp_ms);
+    cap_timeline(&mut spec.timeline);
+
+    let stats = &reported.spec.kernel_stats;
+    spec.kernel_stats.total_syscalls = spec.kernel_stats.total_syscalls.saturating_add(stats.total_syscalls);
+    spec.kernel_stats.namespaces_created = spec.kernel_stats.namespaces_created.saturating_add(stats.namespaces_created);
+    spec.kernel_stats.cgroup_writes = spec.kernel_stats.cgroup_writes.saturating_add(stats.cgroup_writes);
+    spec.kernel_stats.mount_ops = spec.kernel_stats.mount_ops.saturating_add(stats.mount_ops);
+    spec.kernel_stats.total_duration_ms = spec.kernel_stats.total_duration_ms.saturating_add(stats.total_duration_ms);
+}
+
 pub async fn create_pod_birth_certificate(
     client: &Client,
+    pod: Option<&Pod>,
     payload: &PodCreationPayload,
+    retention: RetentionPolicy,
 ) -> Result<PodBirthCertificate> {
     let api: Api<PodBirthCertificate> = Api::namespaced(
         client.clone(),
         &payload.namespace,
     );
 
-    let pbc = build_pod_birth_certificate(payload);
+    let mut pbc = build_pod_birth_certificate(payload);
+    // Creation events can beat the Pod to the API server; the controller adopts the record later
+    if let Some(pod) = pod {
+        apply_pod_ownership(&mut pbc.metadata, pod, retention);
+    }
     let name = pbc.metadata.name.as_ref().unwrap().clone();
 
     // Try to get existing CRD first
     match api.get(&name).await {
         Ok(mut existing) => {
-            // Append new timeline entry instead of replacing
-            existing.spec.timeline.extend(pbc.spec.timeline);
-            
-            // Update kernel stats with cumulative values
-            existing.spec.kernel_stats.total_syscalls += pbc.spec.kernel_stats.total_syscalls;
-            existing.spec.kernel_stats.cgroup_writes += pbc.spec.kernel_stats.cgroup_writes;
-            existing.spec.kernel_stats.total_duration_ms += pbc.spec.kernel_stats.total_duration_ms;
+            if let Some(pod) = pod {
+                apply_pod_ownership(&mut existing.metadata, pod, retention);
+            }
             
+            merge_pod_birth(&mut existing, pbc);
+
             // Replace the CRD with updated spec
-            let result = api.replace(&name, &Default::default(), &existing).await?;
+            let result = metrics().record_crd_write("PodBirthCertificate", "update", api.replace(&name, &Default::default(), &existing).await)?;
             
             info!(
                 "Updated existing PodBirthCertificate {}/{} with {} total syscalls", 
@@ -190,7 +305,7 @@ pub async fn create_pod_birth_certificate(
         }
         Err(_) => {
             // CRD doesn't exist, create new one
-            let result = api.create(&Default::default(), &pbc).await?;
+            let result = metrics().record_crd_write("PodBirthCertificate", "create", api.create(&Default::default(), &pbc).await)?;
             
             info!(
                 "Created new PodBirthCertificate {}/{}", 
@@ -204,34 +319,43 @@ pub async fn create_pod_birth_certificate(
 
 pub async fn create_kernel_whisper(
     client: &Client,
+    pod: &Pod,
     payload: &CpuThrottlePayload,
+    retention: RetentionPolicy,
+    thresholds: &SignalThresholds,
 ) -> Result<KernelWhisper> {
     let api: Api<KernelWhisper> = Api::namespaced(
         client.clone(),
         &payload.namespace,
     );
 
-    // Try to fetch actual metrics from metrics server
-    let metrics_cpu = fetch_pod_metrics(client, &payload.pod_name, &payload.namespace).await;
-    
     let mut kw = build_kernel_whisper(payload);
+    kw.spec.severity = thresholds.cpu_severity(payload.throttle_percentage);
     
-    // Update with real metrics if available
-    if let Ok(cpu_usage) = metrics_cpu {
-        info!("Got real metrics for pod {}: {}% CPU", payload.pod_name, cpu_usage);
-        kw.spec.metrics_lie.cpu_percent = cpu_usage;
-        
-        // Update discrepancy based on real metrics
-        let discrepancy = payload.throttle_percentage - cpu_usage;
-        if discrepancy.abs() > 10.0 {
-            info!(
-                "SIGNIFICANT DISCREPANCY: Kernel shows {}% throttle but metrics show {}% usage ({}% difference)",
-                payload.throttle_percentage, cpu_usage, discrepancy
-            );
+    let usage = PodMetricsClient::new(client.clone())
+        .pod_usage(&payload.namespace, &payload.pod_name)
+        .await;
+    
+    match &usage {
+        Ok(usage) => {
+            let cpu_usage = usage.cpu_percent();
+            info!("Got real metrics for pod {}: {}% CPU across {} containers", 
+                  payload.pod_name, cpu_usage, usage.containers.len());
+            
+            let discrepancy = payload.throttle_percentage - cpu_usage;
+            if discrepancy.abs() > 10.0 {
+                info!(
+                    "SIGNIFICANT DISCREPANCY: Kernel shows {}% throttle but metrics show {}% usage ({}% difference)",
+                    payload.throttle_percentage, cpu_usage, discrepancy
+                );
+            }
+        }
+        Err(e) => {
+            warn!("Metrics unavailable for pod {}/{}: {}", payload.namespace, payload.pod_name, e);
         }
-    } else {
-        info!("Could not fetch metrics for pod {}, using webhook data", payload.pod_name);
     }
+    apply_pod_metrics(&mut kw, usage);
+    apply_pod_ownership(&mut kw.metadata, pod, retention);
     
     let name = kw.metadata.name.as_ref().unwrap().clone();
 
@@ -239,13 +363,14 @@ pub async fn create_kernel_whisper(
     match api.get(&name).await {
         Ok(mut existing) => {
             // Update existing CRD with new data
+            apply_pod_ownership(&mut existing.metadata, pod, retention);
             existing.spec.detected_at = kw.spec.detected_at;
             existing.spec.kernel_truth = kw.spec.kernel_truth;
             existing.spec.metrics_lie = kw.spec.metrics_lie;
             existing.spec.severity = kw.spec.severity;
             
             // Replace the CRD with updated spec
-            let result = api.replace(&name, &Default::default(), &existing).await?;
+            let result = metrics().record_crd_write("KernelWhisper", "update", api.replace(&name, &Default::default(), &existing).await)?;
             
             info!(
                 "Updated existing KernelWhisper {}/{} with severity {:?}", 
@@ -256,7 +381,7 @@ pub async fn create_kernel_whisper(
         }
         Err(_) => {
             // CRD doesn't exist, create new one
-            let result = api.create(&Default::default(), &kw).await?;
+            let result = metrics().record_crd_write("KernelWhisper", "create", api.create(&Default::default(), &kw).await)?;
             
             info!(
                 "Created new KernelWhisper {}/{} with severity {:?}", 
@@ -266,4 +391,106 @@ pub async fn create_kernel_whisper(
             Ok(result)
         }
     }
-}
\ No newline at end of file
+}
+
+pub async fn create_memory_whisper(
+    client: &Client,
+    pod: &Pod,
+    payload: &MemoryPressurePayload,
+    retention: RetentionPolicy,
+    thresholds: &SignalThresholds,
+) -> Result<MemoryWhisper> {
+    let api: Api<MemoryWhisper> = Api::namespaced(
+        client.clone(),
+        &payload.namespace,
+    );
+
+    let mut mw = build_memory_whisper(payload);
+    mw.spec.severity = thresholds.memory_severity(&mw.spec.kernel_truth);
+    
+    let usage = PodMetricsClient::new(client.clone())
+        .pod_usage(&payload.namespace, &payload.pod_name)
+        .await;
+    
+    match &usage {
+        Ok(usage) => {
+            info!(
+                "Metrics report {} bytes working set for pod {} while the kernel shows {}% memory stall",
+                usage.memory_bytes(), payload.pod_name, payload.psi_some_avg10
+            );
+        }
+        Err(e) => {
+            warn!("Metrics unavailable for pod {}/{}: {}", payload.namespace, payload.pod_name, e);
+        }
+    }
+    apply_pod_memory_metrics(&mut mw, usage);
+    apply_pod_ownership(&mut mw.metadata, pod, retention);
+    
+    let name = mw.metadata.name.clone().context("MemoryWhisper built without a name")?;
+
+    // Try to get existing CRD first
+    match api.get(&name).await {
+        Ok(mut existing) => {
+            apply_pod_ownership(&mut existing.metadata, pod, retention);
+            existing.spec.detected_at = mw.spec.detected_at;
+            existing.spec.kernel_truth = mw.spec.kernel_truth;
+            existing.spec.metrics_lie = mw.spec.metrics_lie;
+            existing.spec.severity = mw.spec.severity;
+            
+            let result = metrics().record_crd_write("MemoryWhisper", "update", api.replace(&name, &Default::default(), &existing).await)?;
+            
+            info!(
+                "Updated existing MemoryWhisper {}/{} with severity {:?}", 
+                payload.namespace, name, result.spec.severity
+            );
+            
+            Ok(result)
+        }
+        Err(_) => {
+            let result = metrics().record_crd_write("MemoryWhisper", "create", api.create(&Default::default(), &mw).await)?;
+            
+            info!(
+                "Created new MemoryWhisper {}/{} with severity {:?}", 
+                payload.namespace, name, result.spec.severity
+            );
+            
+            Ok(result)
+        }
+    }
+}
+
+pub async fn create_oom_kill_report(
+    client: &Client,
+    pod: &Pod,
+    payload: &OomKillPayload,
+    retention: RetentionPolicy,
+) -> Result<OOMKillReport> {
+    let api: Api<OOMKillReport> = Api::namespaced(
+        client.clone(),
+        &payload.namespace,
+    );
+
+    let mut report = build_oom_kill_report(payload);
+    report.spec.container_name = oom_killed_container(pod);
+    apply_pod_ownership(&mut report.metadata, pod, retention);
+    
+    let name = report.metadata.name.clone().context("OOMKillReport built without a name")?;
+
+    // Each kill is its own report; a repeated delivery of the same one is not an error
+    match api.get_opt(&name).await? {
+        Some(existing) => {
+            info!("OOMKillReport {}/{} already recorded", payload.namespace, name);
+            Ok(existing)
+        }
+        None => {
+            let result = metrics().record_crd_write("OOMKillReport", "create", api.create(&Default::default(), &report).await)?;
+            
+            info!(
+                "Created new OOMKillReport {}/{}: likely cause {:?}", 
+                payload.namespace, name, result.spec.likely_cause
+            );
+            
+            Ok(result)
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Startup baselines: how a workload's containers usually come up.
+//!
+//! Birth certificates are grouped by the controller of their pod, with a
+//! Deployment's ReplicaSets counted as the Deployment so that a rollout is
+//! held against the pods it replaces. For every workload the operator keeps
+//! the kernel stats of its latest births, and a birth is graded against the
+//! nearest-rank percentiles of the births that came before it.
+//!
+//! Baselines live in memory. Certificates usually go with their pods, so they
+//! cannot be rebuilt from the API server after a rollout; after a restart they
+//! fill up again as the remaining certificates are reconciled.
+
+use std::collections::HashMap;
+
+use k8s_openapi::api::core::v1::Pod;
+use kernel_gossip_types::{BaselineComparison, KernelMetric, KernelStats, MetricBaseline};
+use kube::ResourceExt;
+
+/// Births kept per workload
+pub const WINDOW: usize = 50;
+
+/// Earlier births a birth needs before it is graded
+pub const MIN_SAMPLES: usize = 5;
+
+/// A stat is out of line once it passes the 90th percentile by this factor
+pub const MARGIN: f64 = 1.5;
+
+/// Workloads kept; the one that has gone longest without a birth goes first
+const MAX_WORKLOADS: usize = 2_000;
+
+/// How far past the 90th percentile a stat also has to be, so that a workload
+/// mounting nothing is not flagged for mounting once
+fn floor(metric: KernelMetric) -> u64 {
+    match metric {
+        KernelMetric::TotalSyscalls => 200,
+        KernelMetric::CgroupWrites => 5,
+        KernelMetric::MountOps => 5,
+        KernelMetric::TotalDurationMs => 100,
+    }
+}
+
+/// The controller `pod` belongs to, as `Kind/name`
+pub fn owning_workload(pod: &Pod) -> Option<String> {
+    let owner = pod.metadata.owner_references.iter().flatten().find(|owner| owner.controller == Some(true))?;
+    if owner.kind == "ReplicaSet" {
+        let deployment = pod
+            .labels()
+            .get("pod-template-hash")
+            .and_then(|hash| owner.name.strip_suffix(hash.as_str())?.strip_suffix('-'));
+        if let Some(deployment) = deployment {
+            return Some(format!("Deployment/{deployment}"));
+        }
+    }
+    Some(format!("{}/{}", owner.kind, owner.name))
+}
+
+#[derive(Debug)]
+struct Sample {
+    certificate: String,
+    born_at_ms: u64,
+    values: [u64; KernelMetric::ALL.len()],
+}
+
+/// Latest births per workload, oldest first
+#[derive(Debug, Default)]
+pub struct Baselines {
+    workloads: HashMap<String, Vec<Sample>>,
+}
+
+/// The `percent`th percentile of `sorted`, by nearest rank
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+impl Baselines {
+    /// Record the birth on `certificate` of `workload` in `namespace`, and
+    /// compare it with the births before it. `stats` are those of the pod's
+    /// first birth; a certificate already recorded is compared as it was
+    /// first seen. Certificates without kernel stats are neither recorded nor
+    /// compared.
+    pub fn observe(
+        &mut self,
+        namespace: &str,
+        workload: &str,
+        certificate: &str,
+        born_at_ms: u64,
+        stats: &KernelStats,
+    ) -> Option<BaselineComparison> {
+        if stats.total_syscalls == 0 {
+            return None;
+        }
+        let values = KernelMetric::ALL.map(|metric| metric.of(stats));
+        let key = format!("{namespace}/{workload}");
+        if !self.workloads.contains_key(&key) && self.workloads.len() >= MAX_WORKLOADS {
+            self.evict_quietest();
+        }
+
+        let samples = self.workloads.entry(key).or_default();
+        // A birth is sampled once. Restarts and exec sessions reported for the
+        // same pod later are not part of its start.
+        let values = match samples.iter().find(|sample| sample.certificate == certificate) {
+            Some(sample) => sample.values,
+            None => {
+                let at = samples.partition_point(|sample| sample.born_at_ms <= born_at_ms);
+                samples.insert(at, Sample { certificate: certificate.to_string(), born_at_ms, values });
+                if samples.len() > WINDOW {
+                    samples.drain(..samples.len() - WINDOW);
+                }
+                values
+            }
+        };
+
+        let earlier: Vec<&Sample> = samples.iter().filter(|sample| sample.born_at_ms < born_at_ms).collect();
+        let metrics = if earlier.len() < MIN_SAMPLES {
+            Vec::new()
+        } else {
+            KernelMetric::ALL
+                .iter()
+                .enumerate()
+                .map(|(i, &metric)| {
+                    let mut sorted: Vec<u64> = earlier.iter().map(|sample| sample.values[i]).collect();
+                    sorted.sort_unstable();
+                    let (value, p90) = (values[i], percentile(&sorted, 90));
+                    MetricBaseline {
+                        metric,
+                        value,
+                        p50: percentile(&sorted, 50),
+                        p90,
+                        regressed: value as f64 > p90 as f64 * MARGIN && value >= p90 + floor(metric),
+                    }
+                })
+                .collect()
+        };
+
+        Some(BaselineComparison {
+            workload: workload.to_string(),
+            samples: earlier.len() as u32,
+            regressed: metrics.iter().any(|metric| metric.regressed),
+            metrics,
+        })
+    }
+
+    fn evict_quietest(&mut self) {
+        let quietest = self
+            .workloads
+            .iter()
+            .min_by_key(|(_, samples)| samples.last().map_or(0, |sample| sample.born_at_ms))
+            .map(|(key, _)| key.clone());
+        if let Some(key) = quietest {
+            self.workloads.remove(&key);
+        }
+    }
+}
+
+/// The comparison to keep on a certificate. One drawn from fewer births than
+/// the last is an operator that restarted or a window that moved on, not news
+/// about this birth, so the last one stands.
+pub fn settled(previous: Option<&BaselineComparison>, current: Option<BaselineComparison>) -> Option<BaselineComparison> {
+    match (previous, current) {
+        (Some(previous), Some(current)) if current.samples < previous.samples => Some(previous.clone()),
+        (previous, None) => previous.cloned(),
+        (_, current) => current,
+    }
+}
+
+/// What an Event about a regressed birth says, e.g. `mount ops 24 (p50 10,
+/// p90 11)`, listing every stat out of line
+pub fn regression_note(comparison: &BaselineComparison) -> Option<String> {
+    let regressed: Vec<String> = comparison
+        .metrics
+        .iter()
+        .filter(|metric| metric.regressed)
+        .map(|metric| format!("{} {} (p50 {}, p90 {})", metric.metric.as_str(), metric.value, metric.p50, metric.p90))
+        .collect();
+    (!regressed.is_empty()).then(|| {
+        format!(
+            "Outside the baseline of {} over {} earlier births: {}",
+            comparison.workload,
+            comparison.samples,
+            regressed.join(", ")
+        )
+    })
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,10 +8,38 @@ pub enum ConfigError {
     MissingVar(String),
 }
 
+/// How long kernel-gossip records outlive the Pod they describe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// Records carry an owner reference and are garbage collected with the Pod
+    OwnedByPod,
+    /// Records are kept for this long after the Pod is gone, then deleted by the controllers
+    KeepFor(Duration),
+}
+
+impl RetentionPolicy {
+    /// The policy for a `RETENTION_TTL_SECS` value; unset, zero or unparsable keeps ownership by the Pod
+    pub fn from_secs(secs: Option<&str>) -> Self {
+        secs.and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(|secs| RetentionPolicy::KeepFor(Duration::from_secs(secs)))
+            .unwrap_or(RetentionPolicy::OwnedByPod)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub webhook_port: u16,
     pub metrics_port: u16,
+    pub retention: RetentionPolicy,
+    /// Mounted Secret holding the webhook `token` and/or `hmac-key`
+    pub webhook_auth_dir: PathBuf,
+    /// Accept unauthenticated webhook requests; for local development only
+    pub webhook_auth_disabled: bool,
+    /// How far a signed request's timestamp may drift from the operator clock
+    pub webhook_max_skew: Duration,
+    /// Events of one batch request processed at the same time
+    pub webhook_batch_concurrency: usize,
 }
 
 impl Config {
@@ -24,9 +54,37 @@ impl Config {
             .and_then(|s| s.parse().ok())
             .unwrap_or(9090);
 
+        // Unset or 0 keeps the default of deleting history together with the Pod
+        let retention = RetentionPolicy::from_secs(std::env::var("RETENTION_TTL_SECS").ok().as_deref());
+
+        let webhook_auth_dir = std::env::var("WEBHOOK_AUTH_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("/etc/kernel-gossip/webhook-auth"));
+
+        let webhook_auth_disabled = std::env::var("WEBHOOK_AUTH_DISABLED")
+            .map(|s| s == "true")
+            .unwrap_or(false);
+
+        let webhook_max_skew = std::env::var("WEBHOOK_MAX_SKEW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(300));
+
+        let webhook_batch_concurrency = std::env::var("WEBHOOK_BATCH_CONCURRENCY")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(8);
+
         Ok(Config {
             webhook_port,
             metrics_port,
+            retention,
+            webhook_auth_dir,
+            webhook_auth_disabled,
+            webhook_max_skew,
+            webhook_batch_concurrency,
         })
     }
-}
\ No newline at end of file
+}
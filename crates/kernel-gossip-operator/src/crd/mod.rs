@@ -1,14 +1,30 @@
-use std::sync::Arc;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
 use kube::{
+    api::{ListParams, Patch, PatchParams},
     runtime::controller::{Action, Controller},
-    Api, Client, ResourceExt,
+    runtime::events::{Event as RecordedEvent, EventType, Recorder, Reporter},
+    Api, Client, Resource, ResourceExt,
 };
 use futures::StreamExt;
+use k8s_openapi::api::core::v1::{Event, Pod};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use k8s_openapi::NamespaceResourceScope;
+use serde::{de::DeserializeOwned, Serialize};
 use tokio::time::Duration;
 use tracing::{error, info, warn};
-use chrono;
-use kernel_gossip_types::{PodBirthCertificate, KernelWhisper, Severity};
+use chrono::{self, DateTime, Utc};
+use kernel_gossip_types::{
+    BaselineComparison, PodBirthCertificate, PodBirthCertificateStatus, StartupHealth, KernelWhisper, MemoryWhisper, OOMKillReport, Severity,
+};
+use crate::actions::{apply_pod_ownership, POD_UID_LABEL};
+use crate::baseline::{owning_workload, regression_note, settled, Baselines};
+use crate::config::{Config, RetentionPolicy};
+use crate::metrics::metrics;
+use crate::policy::Policies;
 use crate::recommendation::{RecommendationEngine, Recommendation};
+use crate::startup::{startup_status, status_changed};
+use crate::timeline::{control_plane_entries, merge_timeline};
 
 // Helper functions for unit testing
 pub fn reconcile_logic_pod_birth(pbc: &PodBirthCertificate) -> Result<(), String> {
@@ -55,6 +71,46 @@ pub fn calculate_requeue_duration(severity: &Severity) -> u64 {
     }
 }
 
+/// Annotation recording when a controller first found a record's Pod gone
+pub const POD_GONE_AT_ANNOTATION: &str = "kernel-gossip.io/pod-gone-at";
+
+/// How long a record owned by no Pod is kept when records go with their Pod.
+/// Creation events can reach the operator well before the Pod is visible.
+pub const ORPHAN_GRACE: Duration = Duration::from_secs(600);
+
+/// What retention mode should do with a record on this reconcile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionStep {
+    /// The Pod is still around, reconcile as usual
+    Active,
+    /// The Pod just disappeared, start the TTL clock
+    MarkGone,
+    /// Still within the TTL, look again after this long
+    Wait(Duration),
+    /// The TTL has run out
+    Expire,
+}
+
+pub fn retention_step(
+    pod_present: bool,
+    gone_since: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    ttl: Duration,
+) -> RetentionStep {
+    if pod_present {
+        return RetentionStep::Active;
+    }
+    let Some(gone_since) = gone_since else {
+        return RetentionStep::MarkGone;
+    };
+
+    let gone_for = (now - gone_since).to_std().unwrap_or(Duration::ZERO);
+    match ttl.checked_sub(gone_for) {
+        Some(remaining) if !remaining.is_zero() => RetentionStep::Wait(remaining),
+        _ => RetentionStep::Expire,
+    }
+}
+
 pub struct PodBirthCertificateController;
 pub struct KernelWhisperController;
 
@@ -62,13 +118,123 @@ pub struct KernelWhisperController;
 #[derive(Clone)]
 pub struct Context {
     pub client: Client,
+    pub retention: RetentionPolicy,
+    pub policies: Policies,
+    pub baselines: Arc<Mutex<Baselines>>,
+}
+
+/// Adopt, age out or delete a record depending on whether its Pod still exists.
+/// A record whose Pod is gone, or is a different Pod by now, is deleted once
+/// the retention TTL has passed, or [`ORPHAN_GRACE`] when records go with
+/// their Pod.
+/// Returns the action to finish with when the record needs no further reconciling.
+pub async fn enforce_retention<K>(
+    ctx: &Context,
+    record: &K,
+    pod_name: &str,
+) -> Result<Option<Action>, Error>
+where
+    K: Resource<DynamicType = (), Scope = NamespaceResourceScope> + Clone + DeserializeOwned + Serialize + Debug,
+{
+    let namespace = record.namespace().unwrap_or_else(|| "default".to_string());
+    let name = record.name_any();
+    let api: Api<K> = Api::namespaced(ctx.client.clone(), &namespace);
+    let pod_api: Api<Pod> = Api::namespaced(ctx.client.clone(), &namespace);
+
+    // A Pod recreated under the same name is a different Pod
+    let recorded_uid = record.labels().get(POD_UID_LABEL).cloned();
+    let pod = pod_api.get_opt(pod_name).await?.filter(|pod| {
+        recorded_uid.is_none() || pod.metadata.uid == recorded_uid
+    });
+
+    let gone_since = record
+        .annotations()
+        .get(POD_GONE_AT_ANNOTATION)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc));
+    // Without a Pod to own it a record is an orphan: written before its Pod
+    // was observed and never adopted, or outlived by a Pod of the same name
+    let keep_for = match ctx.retention {
+        RetentionPolicy::OwnedByPod => ORPHAN_GRACE,
+        RetentionPolicy::KeepFor(ttl) => ttl,
+    };
+
+    match retention_step(pod.is_some(), gone_since, Utc::now(), keep_for) {
+        RetentionStep::Active => {
+            let Some(pod) = pod else { return Ok(None) };
+            let mut meta = record.meta().clone();
+            apply_pod_ownership(&mut meta, &pod, ctx.retention);
+            // Records written before the Pod was visible, or by older operators,
+            // are adopted; with a TTL an earlier Pod owner reference is dropped
+            // so the garbage collector does not delete the record with the Pod
+            let owners_changed = meta.owner_references.as_deref().unwrap_or_default()
+                != record.meta().owner_references.as_deref().unwrap_or_default();
+            // The Pod showed up after all (creation events can beat it to the API server)
+            if owners_changed || gone_since.is_some() {
+                let patch = serde_json::json!({
+                    "metadata": {
+                        "labels": meta.labels,
+                        "ownerReferences": meta.owner_references,
+                        "annotations": { POD_GONE_AT_ANNOTATION: null },
+                    }
+                });
+                api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch)).await?;
+                if owners_changed {
+                    info!("Updated owner references of {} for Pod {}", name, pod_name);
+                }
+            }
+            Ok(None)
+        }
+        RetentionStep::MarkGone => {
+            let mut patch = serde_json::json!({
+                "metadata": { "annotations": { POD_GONE_AT_ANNOTATION: Utc::now().to_rfc3339() } }
+            });
+            if ctx.retention != RetentionPolicy::OwnedByPod {
+                patch["metadata"]["ownerReferences"] = serde_json::json!(without_pod_owners(record.meta()));
+            }
+            api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch)).await?;
+            info!("Pod {} is gone, keeping {} for another {:?}", pod_name, name, keep_for);
+            Ok(Some(Action::requeue(keep_for)))
+        }
+        RetentionStep::Wait(remaining) => Ok(Some(Action::requeue(remaining))),
+        RetentionStep::Expire => {
+            api.delete(&name, &Default::default()).await?;
+            info!("🗑️ Retention for {} expired, deleted it", name);
+            Ok(Some(Action::await_change()))
+        }
+    }
+}
+
+/// The owner references of `meta` other than Pods
+fn without_pod_owners(meta: &ObjectMeta) -> Vec<OwnerReference> {
+    meta.owner_references.iter().flatten().filter(|o| o.kind != "Pod").cloned().collect()
+}
+
+// Reconcile function for records that only need retention handling
+pub async fn reconcile_memory_whisper(
+    mw: Arc<MemoryWhisper>,
+    ctx: Arc<Context>,
+) -> Result<Action, Error> {
+    let _timer = metrics().reconcile_timer("memory_whisper");
+    let action = enforce_retention(&ctx, mw.as_ref(), &mw.spec.pod_name).await?;
+    Ok(action.unwrap_or_else(|| Action::requeue(Duration::from_secs(600))))
+}
+
+pub async fn reconcile_oom_kill_report(
+    report: Arc<OOMKillReport>,
+    ctx: Arc<Context>,
+) -> Result<Action, Error> {
+    let _timer = metrics().reconcile_timer("oom_kill_report");
+    let action = enforce_retention(&ctx, report.as_ref(), &report.spec.pod_name).await?;
+    Ok(action.unwrap_or_else(|| Action::requeue(Duration::from_secs(600))))
 }
 
 // Reconcile function for PodBirthCertificate
 pub async fn reconcile_pod_birth_certificate(
     pbc: Arc<PodBirthCertificate>,
-    _ctx: Arc<Context>,
+    ctx: Arc<Context>,
 ) -> Result<Action, Error> {
+    let _timer = metrics().reconcile_timer("pod_birth_certificate");
     let name = pbc.name_any();
     
     // Validate the resource
@@ -79,55 +245,184 @@ pub async fn reconcile_pod_birth_certificate(
     
     info!("Reconciling PodBirthCertificate: {}", name);
     
-    // For now, just log the timeline
-    for entry in &pbc.spec.timeline {
-        info!(
-            "Timeline entry at {}: {} by {:?}",
-            entry.timestamp_ms, entry.action, entry.actor
-        );
+    if let Some(action) = enforce_retention(&ctx, pbc.as_ref(), &pbc.spec.pod_name).await? {
+        return Ok(action);
     }
     
+    let mut pbc = (*pbc).clone();
+    let pod = match merge_control_plane_timeline(&ctx.client, &mut pbc).await {
+        Ok(pod) => pod,
+        Err(e) => {
+            warn!("Failed to merge control plane events into {}: {}", name, e);
+            None
+        }
+    };
+    
     info!(
-        "Kernel stats - syscalls: {}, namespaces: {}, cgroups: {}, duration: {}ms",
+        "Kernel stats - syscalls: {}, namespaces: {}, cgroups: {}, mounts: {}, duration: {}ms",
         pbc.spec.kernel_stats.total_syscalls,
         pbc.spec.kernel_stats.namespaces_created,
         pbc.spec.kernel_stats.cgroup_writes,
+        pbc.spec.kernel_stats.mount_ops,
         pbc.spec.kernel_stats.total_duration_ms
     );
     
+    let thresholds = ctx.policies.thresholds(&pbc.spec.namespace, &pbc.spec.pod_name, pod.as_ref());
+    let mut status = startup_status(&pbc.spec, pod.as_ref(), &thresholds, Utc::now());
+    status.baseline = compare_to_baseline(&ctx, &pbc, pod.as_ref());
+    let was_regressed = pbc.status.as_ref().and_then(|status| status.baseline.as_ref()).is_some_and(|b| b.regressed);
+    if let Err(e) = update_pod_birth_certificate_status(&ctx.client, &pbc, &status).await {
+        warn!("Failed to update PodBirthCertificate status: {}", e);
+    } else if let Some(note) = status.baseline.as_ref().filter(|_| !was_regressed).and_then(regression_note) {
+        // Only once the status says so, or the next reconcile would tell again
+        warn!("PodBirthCertificate {}: {}", name, note);
+        if let Err(e) = publish_regression(&ctx.client, &pbc, pod.as_ref(), note).await {
+            warn!("Failed to publish startup regression event for {}: {}", name, e);
+        }
+    }
+    
+    // Follow a start still in progress closely
+    if matches!(status.health, StartupHealth::Starting) {
+        return Ok(Action::requeue(Duration::from_secs(30)));
+    }
+    
     // Requeue after 5 minutes to check for updates
     Ok(Action::requeue(Duration::from_secs(300)))
 }
 
+/// Conflicting writes to a certificate's timeline tried before giving up
+/// until the next reconcile
+const TIMELINE_MERGE_ATTEMPTS: u32 = 3;
+
+/// Bring the pod's Events, conditions and container start times into the
+/// certificate's timeline, and `pbc` up to date with it. Returns the Pod,
+/// unless it is gone.
+pub async fn merge_control_plane_timeline(client: &Client, pbc: &mut PodBirthCertificate) -> Result<Option<Pod>, Error> {
+    let namespace = ResourceExt::namespace(pbc).unwrap_or_else(|| pbc.spec.namespace.clone());
+    // Events of an earlier Pod with the same name would be on another timeline
+    let recorded_uid = pbc.labels().get(POD_UID_LABEL).cloned();
+    let pod = Api::<Pod>::namespaced(client.clone(), &namespace)
+        .get_opt(&pbc.spec.pod_name)
+        .await?
+        .filter(|pod| recorded_uid.is_none() || pod.metadata.uid == recorded_uid);
+    let Some(pod) = pod else {
+        return Ok(None);
+    };
+
+    let mut fields = format!("involvedObject.kind=Pod,involvedObject.name={}", pbc.spec.pod_name);
+    if let Some(uid) = &pod.metadata.uid {
+        fields.push_str(&format!(",involvedObject.uid={uid}"));
+    }
+    let events = Api::<Event>::namespaced(client.clone(), &namespace)
+        .list(&ListParams::default().fields(&fields))
+        .await?;
+
+    let control_plane = control_plane_entries(&pod, &events.items);
+    let api = Api::<PodBirthCertificate>::namespaced(client.clone(), &namespace);
+    let mut attempt = 1;
+    loop {
+        let (timeline, offset) =
+            merge_timeline(&pbc.spec.timeline, pbc.spec.node_clock_offset_ms, control_plane.clone());
+        if timeline == pbc.spec.timeline && offset == pbc.spec.node_clock_offset_ms {
+            return Ok(Some(pod));
+        }
+        if offset.is_some() && offset != pbc.spec.node_clock_offset_ms {
+            info!("Node clock of {} is behind the control plane, moved its entries {}ms forward", pbc.spec.pod_name, offset.unwrap_or_default());
+        }
+
+        // The webhook replaces the whole spec as births come in; without the
+        // resourceVersion this could put back a timeline missing its entries
+        let patch = serde_json::json!({
+            "metadata": { "resourceVersion": pbc.resource_version() },
+            "spec": { "timeline": timeline, "node_clock_offset_ms": offset }
+        });
+        match api.patch(&pbc.name_any(), &PatchParams::default(), &Patch::Merge(&patch)).await {
+            Ok(merged) => {
+                info!("Merged control plane timeline into {}: {} entries", pbc.name_any(), merged.spec.timeline.len());
+                *pbc = merged;
+                return Ok(Some(pod));
+            }
+            Err(kube::Error::Api(e)) if e.code == 409 && attempt < TIMELINE_MERGE_ATTEMPTS => {
+                info!("{} changed while merging its timeline, merging into the latest", pbc.name_any());
+                *pbc = api.get(&pbc.name_any()).await?;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Grade the kernel stats of `pbc` against earlier births of its pod's
+/// workload. A pod that is gone leaves the workload of the last comparison.
+fn compare_to_baseline(ctx: &Context, pbc: &PodBirthCertificate, pod: Option<&Pod>) -> Option<BaselineComparison> {
+    let previous = pbc.status.as_ref().and_then(|status| status.baseline.as_ref());
+    let workload = pod.and_then(owning_workload).or_else(|| previous.map(|b| b.workload.clone()))?;
+    let born_at_ms = pbc.metadata.creation_timestamp.as_ref()?.0.timestamp_millis().max(0) as u64;
+    let namespace = ResourceExt::namespace(pbc).unwrap_or_else(|| pbc.spec.namespace.clone());
+    let current = ctx
+        .baselines
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .observe(&namespace, &workload, &pbc.name_any(), born_at_ms, pbc.spec.birth_stats());
+    settled(previous, current)
+}
+
+/// Record a birth outside its workload's baseline as a Warning Event. The
+/// Event is about the certificate rather than the pod, or it would be merged
+/// back into the certificate's own timeline.
+async fn publish_regression(client: &Client, pbc: &PodBirthCertificate, pod: Option<&Pod>, note: String) -> Result<(), Error> {
+    let reporter = Reporter::from("kernel-gossip-operator".to_string());
+    let recorder = Recorder::new(client.clone(), reporter, pbc.object_ref(&()));
+    recorder
+        .publish(RecordedEvent {
+            type_: EventType::Warning,
+            reason: "StartupRegression".to_string(),
+            note: Some(note),
+            action: "CompareToBaseline".to_string(),
+            secondary: pod.map(|pod| pod.object_ref(&())),
+        })
+        .await?;
+    Ok(())
+}
+
+/// Write the startup breakdown of `pbc` to its status when it has changed
+pub async fn update_pod_birth_certificate_status(
+    client: &Client,
+    pbc: &PodBirthCertificate,
+    status: &PodBirthCertificateStatus,
+) -> Result<(), Error> {
+    if !status_changed(pbc.status.as_ref(), status) {
+        return Ok(());
+    }
+    let namespace = ResourceExt::namespace(pbc).unwrap_or_else(|| pbc.spec.namespace.clone());
+    let patch = serde_json::json!({ "status": status });
+    Api::<PodBirthCertificate>::namespaced(client.clone(), &namespace)
+        .patch_status(&pbc.name_any(), &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+    info!("Updated PodBirthCertificate status for {}: {:?}, {}", pbc.name_any(), status.health, status.message);
+    Ok(())
+}
+
 // Reconcile function for KernelWhisper
 pub async fn reconcile_kernel_whisper(
     kw: Arc<KernelWhisper>,
     ctx: Arc<Context>,
 ) -> Result<Action, Error> {
+    let _timer = metrics().reconcile_timer("kernel_whisper");
     let name = kw.name_any();
     info!("Reconciling KernelWhisper: {} with severity {:?}", name, kw.spec.severity);
     
-    // Check if pod still exists before processing
-    let pod_exists = {
-        use k8s_openapi::api::core::v1::Pod;
-        let namespace = kw.spec.namespace.as_str();
-        let pod_api: Api<Pod> = Api::namespaced(ctx.client.clone(), namespace);
-        match pod_api.get_opt(&kw.spec.pod_name).await {
-            Ok(pod_opt) => pod_opt.is_some(),
-            Err(_) => false, // Error accessing pod, assume it doesn't exist
-        }
-    };
-    
-    if !pod_exists {
-        info!(
-            "Pod {} no longer exists, keeping historical data but skipping active reconciliation",
-            kw.spec.pod_name
-        );
-        return Ok(Action::requeue(Duration::from_secs(300))); // Check again in 5 minutes
+    if let Some(action) = enforce_retention(&ctx, kw.as_ref(), &kw.spec.pod_name).await? {
+        return Ok(action);
     }
     
-    // Generate recommendations using the recommendation engine
-    let recommendation_engine = RecommendationEngine::new();
+    // Generate recommendations against the thresholds of the pod's policy
+    let namespace = kw.spec.namespace.as_str();
+    let pod = Api::<Pod>::namespaced(ctx.client.clone(), namespace)
+        .get_opt(&kw.spec.pod_name)
+        .await?;
+    let thresholds = ctx.policies.thresholds(namespace, &kw.spec.pod_name, pod.as_ref());
+    let recommendation_engine = RecommendationEngine::with_thresholds(thresholds);
     if let Some(recommendation) = recommendation_engine.analyze_kernel_whisper(&kw) {
         info!(
             "📊 INSIGHT: {} - Priority: {}",
@@ -138,13 +433,13 @@ pub async fn reconcile_kernel_whisper(
         
         // Update CRD status with recommendation
         let status_message = build_status_update(&recommendation);
-        if let Err(e) = update_kernel_whisper_status(&ctx.client, &kw, &status_message).await {
+        if let Err(e) = update_kernel_whisper_status(&ctx.client, &kw, &recommendation_engine, &status_message).await {
             warn!("Failed to update KernelWhisper status: {}", e);
         }
     } else {
         // No recommendation needed - update status with healthy state
         let status_message = build_status_update_no_action("Pod operating within normal parameters");
-        if let Err(e) = update_kernel_whisper_status(&ctx.client, &kw, &status_message).await {
+        if let Err(e) = update_kernel_whisper_status(&ctx.client, &kw, &recommendation_engine, &status_message).await {
             warn!("Failed to update KernelWhisper status: {}", e);
         }
     }
@@ -195,6 +490,12 @@ fn error_policy_kw(_kw: Arc<KernelWhisper>, error: &Error, _ctx: Arc<Context>) -
     Action::requeue(Duration::from_secs(60))
 }
 
+// Error handler for the retention-only controllers
+fn error_policy_retention<K>(_record: Arc<K>, error: &Error, _ctx: Arc<Context>) -> Action {
+    error!("Retention reconciliation error: {:?}", error);
+    Action::requeue(Duration::from_secs(60))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Kubernetes API error: {0}")]
@@ -205,8 +506,15 @@ pub enum Error {
 }
 
 // Start the controllers
-pub async fn run_controllers(client: Client) -> Result<(), Box<dyn std::error::Error>> {
-    let ctx = Arc::new(Context { client: client.clone() });
+pub async fn run_controllers(client: Client, policies: Policies) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::from_env()?;
+    let ctx = Arc::new(Context {
+        client: client.clone(),
+        retention: config.retention,
+        policies,
+        baselines: Arc::default(),
+    });
+    info!("Record retention policy: {:?}", config.retention);
     
     // PodBirthCertificate controller
     let pbc_api: Api<PodBirthCertificate> = Api::all(client.clone());
@@ -221,8 +529,11 @@ pub async fn run_controllers(client: Client) -> Result<(), Box<dyn std::error::E
     
     // KernelWhisper controller
     let kw_api: Api<KernelWhisper> = Api::all(client.clone());
-    let kw_controller = Controller::new(kw_api, Default::default())
-        .run(reconcile_kernel_whisper, error_policy_kw, ctx)
+    let kw_controller = Controller::new(kw_api, Default::default());
+    // The controller's cache doubles as the source of the per-pod gauges
+    metrics().watch_kernel_whispers(kw_controller.store());
+    let kw_controller = kw_controller
+        .run(reconcile_kernel_whisper, error_policy_kw, ctx.clone())
         .for_each(|res| async move {
             match res {
                 Ok(o) => info!("Reconciled KernelWhisper: {:?}", o),
@@ -230,10 +541,31 @@ pub async fn run_controllers(client: Client) -> Result<(), Box<dyn std::error::E
             }
         });
     
-    // Run both controllers concurrently
+    // MemoryWhisper and OOMKillReport only need adopting and ageing out
+    let mw_api: Api<MemoryWhisper> = Api::all(client.clone());
+    let mw_controller = Controller::new(mw_api, Default::default())
+        .run(reconcile_memory_whisper, error_policy_retention, ctx.clone())
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                error!("MemoryWhisper reconciliation failed: {:?}", e);
+            }
+        });
+    
+    let oom_api: Api<OOMKillReport> = Api::all(client.clone());
+    let oom_controller = Controller::new(oom_api, Default::default())
+        .run(reconcile_oom_kill_report, error_policy_retention, ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                error!("OOMKillReport reconciliation failed: {:?}", e);
+            }
+        });
+    
+    // Run all controllers concurrently
     tokio::select! {
         _ = pbc_controller => {},
         _ = kw_controller => {},
+        _ = mw_controller => {},
+        _ = oom_controller => {},
     }
     
     Ok(())
@@ -259,13 +591,24 @@ pub fn build_status_update_no_action(message: &str) -> String {
     )
 }
 
+// Metrics fields stay empty when metrics-server had nothing to compare against
+pub fn build_metrics_comparison(kw: &KernelWhisper) -> kernel_gossip_types::MetricsComparison {
+    let metrics_cpu_percent = kw.spec.metrics_lie.cpu_percent;
+    kernel_gossip_types::MetricsComparison {
+        kernel_cpu_usage: kw.spec.kernel_truth.actual_cpu_cores,
+        metrics_cpu_usage: metrics_cpu_percent.map(|p| p / 100.0),
+        discrepancy_percent: metrics_cpu_percent.map(|p| kw.spec.kernel_truth.throttled_percent - p),
+    }
+}
+
 // Update KernelWhisper status with recommendation and metrics comparison
 pub async fn update_kernel_whisper_status(
     client: &Client,
     kw: &KernelWhisper,
+    recommendation_engine: &RecommendationEngine,
     _status_message: &str,  // Currently unused, keeping for API compatibility
 ) -> Result<(), Error> {
-    use kernel_gossip_types::kernel_whisper::{KernelWhisperStatus, MetricsComparison};
+    use kernel_gossip_types::kernel_whisper::KernelWhisperStatus;
     use kube::api::{Api, PatchParams, Patch};
     use serde_json::json;
     
@@ -276,7 +619,6 @@ pub async fn update_kernel_whisper_status(
     
     // Parse the status message to extract components
     // For now, we'll use the message directly and add real metrics comparison
-    let recommendation_engine = RecommendationEngine::new();
     let recommendation = recommendation_engine.analyze_kernel_whisper(kw);
     
     let status = if let Some(rec) = recommendation {
@@ -285,11 +627,7 @@ pub async fn update_kernel_whisper_status(
             recommendation: rec.suggested_action,
             kernel_evidence: rec.kernel_evidence,
             priority: rec.priority.to_string(),
-            metrics_comparison: MetricsComparison {
-                kernel_cpu_usage: kw.spec.kernel_truth.actual_cpu_cores,
-                metrics_cpu_usage: kw.spec.metrics_lie.cpu_percent / 100.0,
-                discrepancy_percent: kw.spec.kernel_truth.throttled_percent - kw.spec.metrics_lie.cpu_percent,
-            },
+            metrics_comparison: build_metrics_comparison(kw),
             last_updated: chrono::Utc::now().to_rfc3339(),
         }
     } else {
@@ -298,11 +636,7 @@ pub async fn update_kernel_whisper_status(
             recommendation: "No action required".to_string(),
             kernel_evidence: format!("Throttling: {}%", kw.spec.kernel_truth.throttled_percent),
             priority: "Low".to_string(),
-            metrics_comparison: MetricsComparison {
-                kernel_cpu_usage: kw.spec.kernel_truth.actual_cpu_cores,
-                metrics_cpu_usage: kw.spec.metrics_lie.cpu_percent / 100.0,
-                discrepancy_percent: kw.spec.kernel_truth.throttled_percent - kw.spec.metrics_lie.cpu_percent,
-            },
+            metrics_comparison: build_metrics_comparison(kw),
             last_updated: chrono::Utc::now().to_rfc3339(),
         }
     };
@@ -6,3 +6,9 @@ pub mod crd;
 pub mod recommendation;
 pub mod ebpf;
 pub mod pod_watcher;
+pub mod pod_metrics;
+pub mod policy;
+pub mod metrics;
+pub mod timeline;
+pub mod startup;
+pub mod baseline;
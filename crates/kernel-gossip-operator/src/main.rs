@@ -13,23 +13,27 @@ async fn main() -> anyhow::Result<()> {
     let client_for_controller = client.clone();
     let client_for_watcher = client.clone();
 
+    // One set of policy watches serves the webhook, the controllers and the pod watcher
+    let policies = kernel_gossip_operator::policy::Policies::watch(client.clone());
+
     // Create servers
-    let webhook_server = kernel_gossip_operator::server::create_server().await?;
+    let webhook_server = kernel_gossip_operator::server::create_server(policies.clone()).await?;
     let metrics_server = kernel_gossip_operator::server::create_metrics_server().await?;
 
     info!("Webhook server listening on port {}", std::env::var("WEBHOOK_PORT").unwrap_or_else(|_| "8080".to_string()));
     info!("Metrics server listening on port {}", std::env::var("METRICS_PORT").unwrap_or_else(|_| "9090".to_string()));
 
     // Start CRD controllers
+    let policies_for_controller = policies.clone();
     let controller_handle = tokio::spawn(async move {
-        if let Err(e) = kernel_gossip_operator::crd::run_controllers(client_for_controller).await {
+        if let Err(e) = kernel_gossip_operator::crd::run_controllers(client_for_controller, policies_for_controller).await {
             error!("Controller error: {}", e);
         }
     });
 
     // Start pod watcher
     let pod_watcher_handle = tokio::spawn(async move {
-        if let Err(e) = kernel_gossip_operator::pod_watcher::run_pod_watcher(client_for_watcher).await {
+        if let Err(e) = kernel_gossip_operator::pod_watcher::run_pod_watcher(client_for_watcher, policies).await {
             error!("Pod watcher error: {}", e);
         }
     });
@@ -0,0 +1,199 @@
+use std::sync::{LazyLock, Mutex, OnceLock};
+
+use chrono::{DateTime, Utc};
+use kernel_gossip_protocol::EventStatus;
+use kernel_gossip_types::{KernelWhisper, Severity};
+use kube::runtime::reflector::Store;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::crd::build_metrics_comparison;
+
+static METRICS: LazyLock<OperatorMetrics> =
+    LazyLock::new(|| OperatorMetrics::new().expect("operator metrics are valid"));
+
+/// The process-wide metrics served on `/metrics`
+pub fn metrics() -> &'static OperatorMetrics {
+    &METRICS
+}
+
+pub struct OperatorMetrics {
+    registry: Registry,
+    webhook_events: IntCounterVec,
+    crd_writes: IntCounterVec,
+    reconcile_duration: HistogramVec,
+    detection_latency: HistogramVec,
+    kernel_whispers: IntGaugeVec,
+    pod_throttle: GaugeVec,
+    pod_cpu_discrepancy: GaugeVec,
+    /// KernelWhispers as seen by their controller; the gauges above are derived from it on scrape
+    whisper_store: OnceLock<Store<KernelWhisper>>,
+    /// Serializes resetting and repopulating the derived gauges
+    refresh: Mutex<()>,
+}
+
+impl OperatorMetrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let webhook_events = IntCounterVec::new(
+            Opts::new("kernel_gossip_webhook_events_total", "Webhook events received, by event type and outcome"),
+            &["event_type", "outcome"],
+        )?;
+        let crd_writes = IntCounterVec::new(
+            Opts::new("kernel_gossip_crd_writes_total", "Custom resource writes, by kind, operation and result"),
+            &["kind", "operation", "result"],
+        )?;
+        let reconcile_duration = HistogramVec::new(
+            HistogramOpts::new("kernel_gossip_reconcile_duration_seconds", "Time spent in one reconcile, by controller"),
+            &["controller"],
+        )?;
+        let detection_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "kernel_gossip_detection_to_record_seconds",
+                "Time from the kernel detection timestamp to the custom resource being written",
+            )
+            .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0]),
+            &["event_type"],
+        )?;
+        let kernel_whispers = IntGaugeVec::new(
+            Opts::new("kernel_gossip_kernel_whispers", "KernelWhispers currently in the cluster, by severity"),
+            &["severity"],
+        )?;
+        let pod_throttle = GaugeVec::new(
+            Opts::new(
+                "kernel_gossip_pod_cpu_throttled_percent",
+                "Share of CFS periods the kernel throttled the pod in, from its latest KernelWhisper",
+            ),
+            &["namespace", "pod"],
+        )?;
+        let pod_cpu_discrepancy = GaugeVec::new(
+            Opts::new(
+                "kernel_gossip_pod_cpu_discrepancy_percent",
+                "Kernel throttling minus the CPU percentage metrics-server reports for the pod",
+            ),
+            &["namespace", "pod"],
+        )?;
+
+        let registry = Registry::new();
+        registry.register(Box::new(webhook_events.clone()))?;
+        registry.register(Box::new(crd_writes.clone()))?;
+        registry.register(Box::new(reconcile_duration.clone()))?;
+        registry.register(Box::new(detection_latency.clone()))?;
+        registry.register(Box::new(kernel_whispers.clone()))?;
+        registry.register(Box::new(pod_throttle.clone()))?;
+        registry.register(Box::new(pod_cpu_discrepancy.clone()))?;
+
+        Ok(Self {
+            registry,
+            webhook_events,
+            crd_writes,
+            reconcile_duration,
+            detection_latency,
+            kernel_whispers,
+            pod_throttle,
+            pod_cpu_discrepancy,
+            whisper_store: OnceLock::new(),
+            refresh: Mutex::new(()),
+        })
+    }
+
+    /// Count a request turned away before its events could be read
+    pub fn record_rejected_request(&self, outcome: &str) {
+        self.webhook_events.with_label_values(&["unknown", outcome]).inc();
+    }
+
+    /// Count how an event was handled and, once it is recorded, how long that took since detection
+    pub fn record_event_outcome(&self, event_type: &str, detected_at: &str, status: EventStatus, now: DateTime<Utc>) {
+        self.webhook_events.with_label_values(&[event_type, outcome_label(status)]).inc();
+
+        if status != EventStatus::Accepted {
+            return;
+        }
+        if let Ok(detected_at) = DateTime::parse_from_rfc3339(detected_at) {
+            let latency = (now - detected_at.with_timezone(&Utc)).num_milliseconds();
+            // Clock skew between node and operator can put detection in the future
+            if latency >= 0 {
+                self.detection_latency
+                    .with_label_values(&[event_type])
+                    .observe(latency as f64 / 1000.0);
+            }
+        }
+    }
+
+    /// Count a create or update of a custom resource and pass its result through
+    pub fn record_crd_write<T>(&self, kind: &str, operation: &str, result: Result<T, kube::Error>) -> Result<T, kube::Error> {
+        let outcome = if result.is_ok() { "success" } else { "failure" };
+        self.crd_writes.with_label_values(&[kind, operation, outcome]).inc();
+        result
+    }
+
+    /// Observes the reconcile duration when dropped
+    pub fn reconcile_timer(&self, controller: &str) -> HistogramTimer {
+        self.reconcile_duration.with_label_values(&[controller]).start_timer()
+    }
+
+    /// Derive the KernelWhisper gauges from this store from now on
+    pub fn watch_kernel_whispers(&self, store: Store<KernelWhisper>) {
+        let _ = self.whisper_store.set(store);
+    }
+
+    /// Set the severity counts and per-pod gauges to exactly the given whispers,
+    /// so pods whose records are gone stop being reported
+    pub fn refresh_kernel_whispers<'a>(&self, whispers: impl IntoIterator<Item = &'a KernelWhisper>) {
+        let _guard = self.refresh.lock().unwrap_or_else(|e| e.into_inner());
+        self.set_whisper_gauges(whispers);
+    }
+
+    fn set_whisper_gauges<'a>(&self, whispers: impl IntoIterator<Item = &'a KernelWhisper>) {
+        self.pod_throttle.reset();
+        self.pod_cpu_discrepancy.reset();
+
+        let mut counts = [0i64; 3];
+        for kw in whispers {
+            counts[severity_index(&kw.spec.severity)] += 1;
+
+            let labels = [kw.spec.namespace.as_str(), kw.spec.pod_name.as_str()];
+            self.pod_throttle
+                .with_label_values(&labels)
+                .set(kw.spec.kernel_truth.throttled_percent);
+            if let Some(discrepancy) = build_metrics_comparison(kw).discrepancy_percent {
+                self.pod_cpu_discrepancy.with_label_values(&labels).set(discrepancy);
+            }
+        }
+        for (severity, count) in ["critical", "warning", "info"].into_iter().zip(counts) {
+            self.kernel_whispers.with_label_values(&[severity]).set(count);
+        }
+    }
+
+    /// Prometheus text exposition of every metric
+    pub fn render(&self) -> String {
+        // Held until gathered, so a concurrent scrape never sees the gauges half reset
+        let _guard = self.refresh.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(store) = self.whisper_store.get() {
+            let whispers = store.state();
+            self.set_whisper_gauges(whispers.iter().map(|kw| kw.as_ref()));
+        }
+
+        let mut buffer = Vec::new();
+        // Encoding gathered families into a Vec cannot fail
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+fn outcome_label(status: EventStatus) -> &'static str {
+    match status {
+        EventStatus::Accepted => "accepted",
+        EventStatus::Skipped => "skipped",
+        EventStatus::Error => "error",
+    }
+}
+
+fn severity_index(severity: &Severity) -> usize {
+    match severity {
+        Severity::Critical => 0,
+        Severity::Warning => 1,
+        Severity::Info => 2,
+    }
+}
@@ -0,0 +1,197 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::{Api, Client, Resource};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MetricsError {
+    #[error("metrics-server has no metrics for pod {0}")]
+    NotFound(String),
+
+    #[error("metrics API unavailable: {0}")]
+    Unavailable(#[from] kube::Error),
+
+    #[error("invalid {resource} quantity {value:?} for container {container}")]
+    InvalidQuantity {
+        container: String,
+        resource: &'static str,
+        value: String,
+    },
+}
+
+/// `metrics.k8s.io/v1beta1` PodMetrics as served by metrics-server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PodMetrics {
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub window: Option<String>,
+    #[serde(default)]
+    pub containers: Vec<ContainerMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerMetrics {
+    pub name: String,
+    #[serde(default)]
+    pub usage: BTreeMap<String, Quantity>,
+}
+
+impl Resource for PodMetrics {
+    type DynamicType = ();
+    type Scope = k8s_openapi::NamespaceResourceScope;
+
+    fn kind(_: &()) -> Cow<'_, str> {
+        "PodMetrics".into()
+    }
+
+    fn group(_: &()) -> Cow<'_, str> {
+        "metrics.k8s.io".into()
+    }
+
+    fn version(_: &()) -> Cow<'_, str> {
+        "v1beta1".into()
+    }
+
+    fn plural(_: &()) -> Cow<'_, str> {
+        "pods".into()
+    }
+
+    fn meta(&self) -> &ObjectMeta {
+        &self.metadata
+    }
+
+    fn meta_mut(&mut self) -> &mut ObjectMeta {
+        &mut self.metadata
+    }
+}
+
+/// Usage of one container, converted to cores and bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerUsage {
+    pub name: String,
+    pub cpu_cores: f64,
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PodUsage {
+    pub containers: Vec<ContainerUsage>,
+    pub window: Option<String>,
+}
+
+impl PodUsage {
+    pub fn from_metrics(metrics: &PodMetrics) -> Result<Self, MetricsError> {
+        let containers = metrics
+            .containers
+            .iter()
+            .map(|c| {
+                let quantity = |resource: &'static str, parse: fn(&str) -> Option<f64>| {
+                    let value = c.usage.get(resource).map(|q| q.0.as_str()).unwrap_or("0");
+                    parse(value).ok_or_else(|| MetricsError::InvalidQuantity {
+                        container: c.name.clone(),
+                        resource,
+                        value: value.to_string(),
+                    })
+                };
+
+                Ok(ContainerUsage {
+                    name: c.name.clone(),
+                    cpu_cores: quantity("cpu", parse_cpu_quantity)?,
+                    memory_bytes: quantity("memory", parse_memory_quantity)? as u64,
+                })
+            })
+            .collect::<Result<Vec<_>, MetricsError>>()?;
+
+        Ok(Self {
+            containers,
+            window: metrics.window.clone(),
+        })
+    }
+
+    pub fn cpu_cores(&self) -> f64 {
+        self.containers.iter().map(|c| c.cpu_cores).sum()
+    }
+
+    /// Pod CPU as a percentage of one core (1000m = 100%)
+    pub fn cpu_percent(&self) -> f64 {
+        (self.cpu_cores() * 100.0 * 10.0).round() / 10.0
+    }
+
+    pub fn memory_bytes(&self) -> u64 {
+        self.containers.iter().map(|c| c.memory_bytes).sum()
+    }
+}
+
+/// Typed client for the resource metrics API
+#[derive(Clone)]
+pub struct PodMetricsClient {
+    client: Client,
+}
+
+impl PodMetricsClient {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn pod_usage(&self, namespace: &str, pod_name: &str) -> Result<PodUsage, MetricsError> {
+        let api: Api<PodMetrics> = Api::namespaced(self.client.clone(), namespace);
+        let metrics = api
+            .get_opt(pod_name)
+            .await?
+            .ok_or_else(|| MetricsError::NotFound(format!("{namespace}/{pod_name}")))?;
+
+        PodUsage::from_metrics(&metrics)
+    }
+}
+
+/// Parse a CPU quantity into cores: "250000000n", "250000u", "250m", "0.25", "2"
+pub fn parse_cpu_quantity(quantity: &str) -> Option<f64> {
+    let quantity = quantity.trim();
+    // Divide for sub-core suffixes so "350m" is exactly 0.35 rather than 0.35000000000000003
+    let (number, multiplier, divisor) = if let Some(n) = quantity.strip_suffix('n') {
+        (n, 1.0, 1e9)
+    } else if let Some(u) = quantity.strip_suffix('u') {
+        (u, 1.0, 1e6)
+    } else if let Some(m) = quantity.strip_suffix('m') {
+        (m, 1.0, 1e3)
+    } else if let Some(k) = quantity.strip_suffix('k') {
+        (k, 1e3, 1.0)
+    } else {
+        (quantity, 1.0, 1.0)
+    };
+
+    let value: f64 = number.parse().ok()?;
+    (value.is_finite() && value >= 0.0).then_some(value * multiplier / divisor)
+}
+
+/// Parse a memory quantity into bytes: "128Mi", "1Gi", "500M", "1024", "1e6"
+pub fn parse_memory_quantity(quantity: &str) -> Option<f64> {
+    const SUFFIXES: [(&str, f64); 12] = [
+        ("Ki", 1024.0),
+        ("Mi", 1024.0 * 1024.0),
+        ("Gi", 1024.0 * 1024.0 * 1024.0),
+        ("Ti", 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        ("Pi", 1024.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        ("Ei", 1024.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+        ("P", 1e15),
+        ("E", 1e18),
+    ];
+
+    let quantity = quantity.trim();
+    let (number, scale) = SUFFIXES
+        .iter()
+        .find_map(|(suffix, scale)| quantity.strip_suffix(suffix).map(|n| (n, *scale)))
+        .unwrap_or((quantity, 1.0));
+
+    let value: f64 = number.parse().ok()?;
+    (value.is_finite() && value >= 0.0).then_some(value * scale)
+}
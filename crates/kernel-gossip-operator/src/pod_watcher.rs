@@ -1,111 +1,244 @@
 use kube::{
     Api, Client,
-    runtime::watcher::{Config, Event, watcher},
+    runtime::{reflector, watcher::{self, Event, watcher}, WatchStreamExt},
 };
 use k8s_openapi::api::core::v1::{Pod, ConfigMap};
 use futures::StreamExt;
-use std::collections::BTreeMap;
-use tracing::{info, warn};
-
-/// Watches for pods with kernel-gossip.io/monitor annotation
-/// and maintains a ConfigMap with their details
-pub async fn run_pod_watcher(client: Client) -> Result<(), Box<dyn std::error::Error>> {
-    let pod_api: Api<Pod> = Api::namespaced(client.clone(), "kernel-gossip");
-    let cm_api: Api<ConfigMap> = Api::namespaced(client.clone(), "kernel-gossip");
-    
+use kernel_gossip_protocol::monitored::{
+    encode_node_pods, node_key, MonitoredPod, COUNT_KEY, MONITORED_PODS_CONFIGMAP, UPDATED_KEY,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use crate::policy::Policies;
+
+const OPERATOR_NAMESPACE: &str = "kernel-gossip";
+
+/// Changes are written out at most this often, so a rollout of many pods is one update
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Pods are re-evaluated this often to pick up policy and namespace label changes
+const RESYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The pods the policies select, by UID
+#[derive(Debug, Default)]
+pub struct MonitoredPodSet {
+    pods: BTreeMap<String, MonitoredPod>,
+}
+
+impl MonitoredPodSet {
+    /// Add, update or drop one pod; true when the published set changes
+    pub fn apply(&mut self, pod: &Pod, policies: &Policies) -> bool {
+        let Some(uid) = pod.metadata.uid.clone() else {
+            return false;
+        };
+        match monitored_pod(pod, policies) {
+            Some(entry) => self.pods.insert(uid, entry.clone()).as_ref() != Some(&entry),
+            None => self.pods.remove(&uid).is_some(),
+        }
+    }
+
+    pub fn remove(&mut self, pod: &Pod) -> bool {
+        pod.metadata.uid.as_ref().is_some_and(|uid| self.pods.remove(uid).is_some())
+    }
+
+    /// Replace the set with what the policies select among `pods`
+    pub fn rebuild<'a>(&mut self, pods: impl IntoIterator<Item = &'a Pod>, policies: &Policies) -> bool {
+        let mut rebuilt = MonitoredPodSet::default();
+        for pod in pods {
+            rebuilt.apply(pod, policies);
+        }
+        let changed = rebuilt.pods != self.pods;
+        *self = rebuilt;
+        changed
+    }
+
+    pub fn len(&self) -> usize {
+        self.pods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pods.is_empty()
+    }
+
+    /// ConfigMap data: one key per node plus the count and update time
+    pub fn to_configmap_data(&self, updated: &str) -> BTreeMap<String, String> {
+        let mut by_node: BTreeMap<&str, Vec<MonitoredPod>> = BTreeMap::new();
+        for pod in self.pods.values() {
+            by_node.entry(pod.node.as_str()).or_default().push(pod.clone());
+        }
+
+        let mut data: BTreeMap<String, String> = by_node
+            .into_iter()
+            .map(|(node, mut pods)| {
+                pods.sort();
+                // Serializing plain strings cannot fail
+                (node_key(node), encode_node_pods(&pods).unwrap_or_default())
+            })
+            .collect();
+        data.insert(COUNT_KEY.to_string(), self.pods.len().to_string());
+        data.insert(UPDATED_KEY.to_string(), updated.to_string());
+        data
+    }
+}
+
+/// The published entry for a pod the policies select. Pods that are not
+/// scheduled yet or have finished have nothing for an observer to watch.
+pub fn monitored_pod(pod: &Pod, policies: &Policies) -> Option<MonitoredPod> {
+    let namespace = pod.metadata.namespace.as_deref()?;
+    let name = pod.metadata.name.as_deref()?;
+    let status = pod.status.as_ref();
+    let node = pod.spec.as_ref()?.node_name.clone()?;
+
+    let finished = matches!(status.and_then(|s| s.phase.as_deref()), Some("Succeeded" | "Failed"));
+    if finished || policies.evaluate(namespace, name, Some(pod)).is_err() {
+        return None;
+    }
+
+    let mut container_ids: Vec<String> = status
+        .into_iter()
+        .flat_map(|s| {
+            s.container_statuses.iter().flatten()
+                .chain(s.init_container_statuses.iter().flatten())
+                .chain(s.ephemeral_container_statuses.iter().flatten())
+        })
+        .filter_map(|c| c.container_id.clone())
+        .collect();
+    container_ids.sort();
+
+    Some(MonitoredPod {
+        uid: pod.metadata.uid.clone()?,
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        node,
+        container_ids,
+    })
+}
+
+/// When the set is written out: one interval after the first change since the
+/// last write, so a burst of changes is one update even after a quiet spell
+pub struct PublishSchedule {
+    interval: Interval,
+    dirty: bool,
+}
+
+impl PublishSchedule {
+    pub fn new(period: Duration) -> Self {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self { interval, dirty: false }
+    }
+
+    /// Note whether the set changed; the first change starts the wait
+    pub fn changed(&mut self, changed: bool) {
+        if changed && !self.dirty {
+            self.interval.reset();
+        }
+        self.dirty |= changed;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Resolves once the pending changes are due to be written
+    pub async fn due(&mut self) {
+        self.interval.tick().await;
+    }
+
+    pub fn published(&mut self) {
+        self.dirty = false;
+    }
+}
+
+/// Watches pods in every namespace, keeps the set the policies select and
+/// publishes it in the monitored-pods ConfigMap for the observers
+pub async fn run_pod_watcher(client: Client, policies: Policies) -> Result<(), Box<dyn std::error::Error>> {
+    let pod_api: Api<Pod> = Api::all(client.clone());
+    let cm_api: Api<ConfigMap> = Api::namespaced(client.clone(), OPERATOR_NAMESPACE);
+
     // Create initial ConfigMap if it doesn't exist
     ensure_configmap_exists(&cm_api).await?;
-    
-    // Watch for pods with our annotation
-    let config = Config::default()
-        .labels("kernel-gossip.io/monitor=true");
-    
-    let mut stream = watcher(pod_api, config).boxed();
-    
-    while let Some(event) = stream.next().await {
-        match event {
-            Ok(Event::Applied(pod)) => {
-                info!("Pod {} applied/modified, updating ConfigMap", pod.metadata.name.as_ref().unwrap_or(&"unknown".to_string()));
-                update_monitored_pods(&cm_api, &client).await?;
-            }
-            Ok(Event::Deleted(pod)) => {
-                info!("Pod {} deleted, updating ConfigMap", pod.metadata.name.as_ref().unwrap_or(&"unknown".to_string()));
-                update_monitored_pods(&cm_api, &client).await?;
-            }
-            Ok(Event::Restarted(pods)) => {
-                info!("Pod watcher restarted, {} pods in initial state", pods.len());
-                update_monitored_pods(&cm_api, &client).await?;
+
+    let (store, writer) = reflector::store();
+    let mut stream = reflector::reflector(writer, watcher(pod_api, watcher::Config::default()))
+        .default_backoff()
+        .boxed();
+
+    let mut monitored = MonitoredPodSet::default();
+    let mut publish = PublishSchedule::new(PUBLISH_INTERVAL);
+    let mut resync = tokio::time::interval(RESYNC_INTERVAL);
+
+    loop {
+        tokio::select! {
+            event = stream.next() => match event {
+                Some(Ok(Event::Applied(pod))) => {
+                    if monitored.apply(&pod, &policies) {
+                        debug!("Monitored pod set changed by {}/{}", pod.metadata.namespace.as_deref().unwrap_or_default(), pod.metadata.name.as_deref().unwrap_or_default());
+                        publish.changed(true);
+                    }
+                }
+                Some(Ok(Event::Deleted(pod))) => {
+                    publish.changed(monitored.remove(&pod));
+                }
+                Some(Ok(Event::Restarted(pods))) => {
+                    info!("Pod watcher restarted, {} pods in initial state", pods.len());
+                    publish.changed(monitored.rebuild(&pods, &policies));
+                }
+                Some(Err(e)) => {
+                    warn!("Pod watcher error: {}", e);
+                }
+                None => break,
+            },
+            _ = resync.tick() => {
+                let pods = store.state();
+                publish.changed(monitored.rebuild(pods.iter().map(|p| p.as_ref()), &policies));
             }
-            Err(e) => {
-                warn!("Pod watcher error: {}", e);
+            _ = publish.due(), if publish.is_dirty() => {
+                match publish_monitored_pods(&cm_api, &monitored).await {
+                    Ok(()) => publish.published(),
+                    Err(e) => warn!("Failed to update monitored pods ConfigMap: {}", e),
+                }
             }
         }
     }
-    
+
     Ok(())
 }
 
 async fn ensure_configmap_exists(cm_api: &Api<ConfigMap>) -> Result<(), kube::Error> {
-    let cm_name = "ebpf-monitored-pods";
-    
     // Check if ConfigMap exists
-    if cm_api.get_opt(cm_name).await?.is_some() {
+    if cm_api.get_opt(MONITORED_PODS_CONFIGMAP).await?.is_some() {
         return Ok(());
     }
-    
+
     // Create ConfigMap
     let cm = ConfigMap {
         metadata: kube::api::ObjectMeta {
-            name: Some(cm_name.to_string()),
-            namespace: Some("kernel-gossip".to_string()),
+            name: Some(MONITORED_PODS_CONFIGMAP.to_string()),
+            namespace: Some(OPERATOR_NAMESPACE.to_string()),
             ..Default::default()
         },
-        data: Some(BTreeMap::from([
-            ("pods".to_string(), "".to_string()),
-        ])),
+        data: Some(MonitoredPodSet::default().to_configmap_data(&chrono::Utc::now().to_rfc3339())),
         ..Default::default()
     };
-    
+
     cm_api.create(&Default::default(), &cm).await?;
     info!("Created ConfigMap for monitored pods");
     Ok(())
 }
 
-async fn update_monitored_pods(
-    cm_api: &Api<ConfigMap>, 
-    client: &Client
-) -> Result<(), Box<dyn std::error::Error>> {
-    let pod_api: Api<Pod> = Api::namespaced(client.clone(), "kernel-gossip");
-    
-    // Get all pods with annotation
-    let pods = pod_api.list(&Default::default()).await?;
-    
-    let mut monitored_pods = Vec::new();
-    
-    for pod in pods.items {
-        // Check for annotation
-        if let Some(annotations) = &pod.metadata.annotations {
-            if annotations.get("kernel-gossip.io/monitor") == Some(&"true".to_string()) {
-                let pod_name = pod.metadata.name.unwrap_or_default();
-                let pod_uid = pod.metadata.uid.unwrap_or_default();
-                
-                // Format: pod_name:pod_uid
-                monitored_pods.push(format!("{pod_name}:{pod_uid}"));
-                info!("Monitoring pod: {} (UID: {})", pod_name, pod_uid);
-            }
-        }
-    }
-    
-    // Update ConfigMap
-    let mut cm = cm_api.get("ebpf-monitored-pods").await?;
-    cm.data = Some(BTreeMap::from([
-        ("pods".to_string(), monitored_pods.join(",")),
-        ("count".to_string(), monitored_pods.len().to_string()),
-        ("updated".to_string(), chrono::Utc::now().to_rfc3339()),
-    ]));
-    
-    cm_api.replace("ebpf-monitored-pods", &Default::default(), &cm).await?;
-    info!("Updated ConfigMap with {} monitored pods", monitored_pods.len());
-    
+async fn publish_monitored_pods(cm_api: &Api<ConfigMap>, monitored: &MonitoredPodSet) -> Result<(), kube::Error> {
+    let mut cm = cm_api.get(MONITORED_PODS_CONFIGMAP).await?;
+    let data = monitored.to_configmap_data(&chrono::Utc::now().to_rfc3339());
+
+    let nodes = data.keys().filter(|k| k.starts_with("node.")).collect::<BTreeSet<_>>().len();
+
+    // Replacing the whole data map drops the keys of nodes that have no monitored pods left
+    cm.data = Some(data);
+    cm_api.replace(MONITORED_PODS_CONFIGMAP, &Default::default(), &cm).await?;
+    info!("Updated ConfigMap with {} monitored pods on {} nodes", monitored.len(), nodes);
     Ok(())
-}
\ No newline at end of file
+}
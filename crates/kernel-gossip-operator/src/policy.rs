@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use futures::{future, StreamExt};
+use k8s_openapi::api::core::v1::{Namespace, Pod};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kernel_gossip_types::{KernelGossipPolicy, KernelGossipPolicySpec, SignalThresholds, MONITOR_ANNOTATION};
+use kube::runtime::reflector::{self, ObjectRef, Store};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Api, Client, Resource};
+
+/// KernelGossipPolicies and namespace labels, kept current by watches so that
+/// deciding on an event costs no API calls
+#[derive(Clone)]
+pub struct Policies {
+    policies: Store<KernelGossipPolicy>,
+    namespaces: Store<Namespace>,
+}
+
+/// The policy a pod falls under
+#[derive(Debug, Clone)]
+pub struct AppliedPolicy {
+    /// Name of the KernelGossipPolicy, `builtin` when the cluster has none
+    pub name: String,
+    pub spec: KernelGossipPolicySpec,
+}
+
+impl Policies {
+    /// Start watching policies and namespaces
+    pub fn watch(client: Client) -> Self {
+        Self {
+            policies: spawn_reflector(Api::all(client.clone())),
+            namespaces: spawn_reflector(Api::all(client)),
+        }
+    }
+
+    /// Fixed policies and namespaces, for tests and tools
+    pub fn from_objects(policies: Vec<KernelGossipPolicy>, namespaces: Vec<Namespace>) -> Self {
+        Self {
+            policies: fixed_store(policies),
+            namespaces: fixed_store(namespaces),
+        }
+    }
+
+    /// The policy covering the pod, or why it is not recorded. Creation events
+    /// can arrive before the Pod exists; without it only the namespace and
+    /// sampling criteria are applied.
+    pub fn evaluate(&self, namespace: &str, pod_name: &str, pod: Option<&Pod>) -> Result<AppliedPolicy, String> {
+        let mut policies = self.policies.state();
+        if policies.is_empty() {
+            let builtin = AppliedPolicy { name: "builtin".to_string(), spec: KernelGossipPolicySpec::builtin() };
+            return admit(builtin, namespace, pod_name, pod, &BTreeMap::new());
+        }
+        policies.sort_by(|a, b| {
+            b.spec.priority.cmp(&a.spec.priority).then_with(|| a.meta().name.cmp(&b.meta().name))
+        });
+
+        let namespace_labels = self
+            .namespaces
+            .get(&ObjectRef::new(namespace))
+            .and_then(|ns| ns.metadata.labels.clone())
+            .unwrap_or_default();
+
+        let mut last_reason = format!("No KernelGossipPolicy covers namespace {namespace}");
+        for policy in policies {
+            let applied = AppliedPolicy {
+                name: policy.meta().name.clone().unwrap_or_default(),
+                spec: policy.spec.clone(),
+            };
+            match admit(applied, namespace, pod_name, pod, &namespace_labels) {
+                Ok(applied) => return Ok(applied),
+                Err(reason) => last_reason = reason,
+            }
+        }
+        Err(last_reason)
+    }
+
+    /// Thresholds to grade the pod's signals with; the defaults when no policy covers it
+    pub fn thresholds(&self, namespace: &str, pod_name: &str, pod: Option<&Pod>) -> SignalThresholds {
+        self.evaluate(namespace, pod_name, pod)
+            .map(|applied| applied.spec.thresholds)
+            .unwrap_or_default()
+    }
+}
+
+fn admit(
+    applied: AppliedPolicy,
+    namespace: &str,
+    pod_name: &str,
+    pod: Option<&Pod>,
+    namespace_labels: &BTreeMap<String, String>,
+) -> Result<AppliedPolicy, String> {
+    let spec = &applied.spec;
+    if spec.excluded_namespaces.iter().any(|ns| ns == namespace) {
+        return Err(format!("Namespace {namespace} is excluded by policy {}", applied.name));
+    }
+    if let Some(selector) = &spec.namespace_selector {
+        if !selector_matches(selector, namespace_labels) {
+            return Err(format!("Namespace {namespace} is not selected by policy {}", applied.name));
+        }
+    }
+
+    if let Some(pod) = pod {
+        if let Some(selector) = &spec.pod_selector {
+            let labels = pod.metadata.labels.clone().unwrap_or_default();
+            if !selector_matches(selector, &labels) {
+                return Err(format!("Pod {pod_name} is not selected by policy {}", applied.name));
+            }
+        }
+        if spec.require_monitor_annotation {
+            let annotated = pod
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|ann| ann.get(MONITOR_ANNOTATION))
+                .is_some_and(|v| v == "true");
+            if !annotated {
+                return Err(format!("Pod {pod_name} not configured for monitoring"));
+            }
+        }
+    }
+
+    if !spec.samples(namespace, pod_name) {
+        return Err(format!("Pod {pod_name} is outside the sampling rate of policy {}", applied.name));
+    }
+    Ok(applied)
+}
+
+/// Kubernetes label selector semantics: every `matchLabels` entry and every
+/// expression must hold, and an empty selector matches everything
+pub fn selector_matches(selector: &LabelSelector, labels: &BTreeMap<String, String>) -> bool {
+    let labels_match = selector
+        .match_labels
+        .iter()
+        .flatten()
+        .all(|(key, value)| labels.get(key) == Some(value));
+
+    let expressions_match = selector.match_expressions.iter().flatten().all(|expr| {
+        let values = expr.values.as_deref().unwrap_or_default();
+        let value = labels.get(&expr.key);
+        match expr.operator.as_str() {
+            "In" => value.is_some_and(|v| values.contains(v)),
+            "NotIn" => value.is_none_or(|v| !values.contains(v)),
+            "Exists" => value.is_some(),
+            "DoesNotExist" => value.is_none(),
+            // The API server rejects other operators, so this is never reached in practice
+            _ => false,
+        }
+    });
+
+    labels_match && expressions_match
+}
+
+fn spawn_reflector<K>(api: Api<K>) -> Store<K>
+where
+    K: Resource<DynamicType = ()> + Clone + std::fmt::Debug + serde::de::DeserializeOwned + Send + Sync + 'static,
+{
+    let (reader, writer) = reflector::store();
+    let stream = reflector::reflector(writer, watcher(api, watcher::Config::default()))
+        .default_backoff()
+        .for_each(|_| future::ready(()));
+    tokio::spawn(stream);
+    reader
+}
+
+fn fixed_store<K>(objects: Vec<K>) -> Store<K>
+where
+    K: Resource<DynamicType = ()> + Clone + 'static,
+{
+    let mut writer = reflector::store::Writer::default();
+    writer.apply_watcher_event(&watcher::Event::Restarted(objects));
+    writer.as_reader()
+}
@@ -1,4 +1,4 @@
-use kernel_gossip_types::KernelWhisper;
+use kernel_gossip_types::{KernelWhisper, Severity, SignalThresholds};
 
 #[derive(Debug, Clone)]
 pub struct Recommendation {
@@ -8,28 +8,37 @@ pub struct Recommendation {
     pub priority: String,
 }
 
-pub struct RecommendationEngine {}
+pub struct RecommendationEngine {
+    thresholds: SignalThresholds,
+}
 
 impl RecommendationEngine {
     pub fn new() -> Self {
-        Self {}
+        Self::with_thresholds(SignalThresholds::default())
+    }
+    
+    /// Grade throttling against a policy's thresholds instead of the defaults
+    pub fn with_thresholds(thresholds: SignalThresholds) -> Self {
+        Self { thresholds }
     }
     
     pub fn analyze_kernel_whisper(&self, kw: &KernelWhisper) -> Option<Recommendation> {
         let throttle_percentage = kw.spec.kernel_truth.throttled_percent;
+        let kernel_evidence = kernel_evidence(kw);
         
-        if throttle_percentage >= 80.0 {
+        let level = self.thresholds.cpu_throttled_percent.classify(throttle_percentage);
+        if level == Some(Severity::Critical) {
             Some(Recommendation {
                 insight: format!("Pod {} is experiencing high CPU throttling at {:.1}%", kw.spec.pod_name, throttle_percentage),
                 suggested_action: "Consider increase CPU limits by 50% to prevent throttling".to_string(),
-                kernel_evidence: format!("Kernel shows {throttle_percentage:.1}% throttled time in recent period"),
+                kernel_evidence,
                 priority: "high".to_string(),
             })
-        } else if throttle_percentage >= 40.0 {
+        } else if level.is_some() {
             Some(Recommendation {
                 insight: format!("Pod {} is experiencing moderate CPU throttling at {:.1}%", kw.spec.pod_name, throttle_percentage),
                 suggested_action: "monitor CPU usage patterns and consider optimization".to_string(),
-                kernel_evidence: format!("Kernel shows {throttle_percentage:.1}% throttled time in recent period"),
+                kernel_evidence,
                 priority: "medium".to_string(),
             })
         } else {
@@ -38,6 +47,18 @@ impl RecommendationEngine {
     }
 }
 
+fn kernel_evidence(kw: &KernelWhisper) -> String {
+    let truth = &kw.spec.kernel_truth;
+    if truth.total_periods == 0 {
+        return format!("Kernel shows {:.1}% throttled time in recent period", truth.throttled_percent);
+    }
+    format!(
+        "Kernel cpu.stat shows {:.1}% of CFS periods throttled ({}/{} periods, {}ms throttled over {}s)",
+        truth.throttled_percent, truth.throttled_periods, truth.total_periods,
+        truth.throttled_time_ms, truth.window_seconds
+    )
+}
+
 impl Default for RecommendationEngine {
     fn default() -> Self {
         Self::new()
@@ -5,15 +5,26 @@ use tokio::net::TcpListener;
 use axum::serve::Serve;
 use kube::Client;
 use std::sync::Arc;
+use kernel_gossip_protocol::auth::WebhookCredentials;
+use tracing::{info, warn};
+use crate::policy::Policies;
+use crate::webhook::WebhookAuthenticator;
 
-pub async fn create_server() -> anyhow::Result<Serve<Router, Router>> {
+pub async fn create_server(policies: Policies) -> anyhow::Result<Serve<Router, Router>> {
     let config = Config::from_env()?;
     
     // Create K8s client
     let client = Client::try_default().await?;
     let client = Arc::new(client);
     
-    let webhook_routes = crate::webhook::create_webhook_router(client);
+    let authenticator = webhook_authenticator(&config)?;
+    let webhook_routes = crate::webhook::create_webhook_router(
+        client,
+        config.retention,
+        config.webhook_batch_concurrency,
+        policies,
+        authenticator,
+    );
     
     let app = Router::new()
         .route("/health", get(health_handler))
@@ -26,6 +37,25 @@ pub async fn create_server() -> anyhow::Result<Serve<Router, Router>> {
     Ok(server)
 }
 
+/// Load the webhook keys, refusing to start without any unless auth is switched off
+fn webhook_authenticator(config: &Config) -> anyhow::Result<Option<Arc<WebhookAuthenticator>>> {
+    if config.webhook_auth_disabled {
+        warn!("⚠️ Webhook authentication is disabled, any client can create records");
+        return Ok(None);
+    }
+
+    let credentials = WebhookCredentials::load_dir(&config.webhook_auth_dir)?;
+    if credentials.is_empty() {
+        anyhow::bail!(
+            "no webhook token or hmac-key in {}; mount the webhook auth Secret or set WEBHOOK_AUTH_DISABLED=true",
+            config.webhook_auth_dir.display()
+        );
+    }
+    info!("🔒 Webhook authentication enabled ({:?})", credentials);
+
+    Ok(Some(Arc::new(WebhookAuthenticator::new(credentials, config.webhook_max_skew))))
+}
+
 pub async fn create_metrics_server() -> anyhow::Result<Serve<Router, Router>> {
     let config = Config::from_env()?;
     
@@ -44,6 +74,5 @@ async fn health_handler() -> &'static str {
 }
 
 async fn metrics_handler() -> String {
-    // Minimal Prometheus format
-    "# HELP up Target up status\n# TYPE up gauge\nup 1\n".to_string()
+    crate::metrics::metrics().render()
 }
\ No newline at end of file
@@ -0,0 +1,148 @@
+//! Where a pod's start went: the phase breakdown on a birth certificate's
+//! status, read off its merged timeline.
+
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::Pod;
+use kernel_gossip_types::{
+    Actor, PodBirthCertificateSpec, PodBirthCertificateStatus, Severity, SignalThresholds, StartupHealth,
+    StartupPhases, TimelineEntry,
+};
+
+use crate::timeline::{event_reason, is_container_start, POD_CREATED, POD_READY, POD_SCHEDULED, SANDBOX_READY};
+
+/// Container waiting reasons that waiting longer does not fix
+const FAILING_REASONS: &[&str] = &[
+    "ErrImagePull",
+    "ImagePullBackOff",
+    "InvalidImageName",
+    "CrashLoopBackOff",
+    "CreateContainerConfigError",
+    "CreateContainerError",
+    "RunContainerError",
+];
+
+fn first(timeline: &[TimelineEntry], matches: impl Fn(&TimelineEntry) -> bool) -> Option<u64> {
+    timeline.iter().filter(|entry| matches(entry)).map(|entry| entry.timestamp_ms).min()
+}
+
+fn last(timeline: &[TimelineEntry], matches: impl Fn(&TimelineEntry) -> bool) -> Option<u64> {
+    timeline.iter().filter(|entry| matches(entry)).map(|entry| entry.timestamp_ms).max()
+}
+
+fn span(from: Option<u64>, to: Option<u64>) -> Option<u64> {
+    Some(to?.saturating_sub(from?))
+}
+
+fn created_at(spec: &PodBirthCertificateSpec) -> Option<u64> {
+    first(&spec.timeline, |entry| entry.actor == Actor::Scheduler && entry.action == POD_CREATED)
+}
+
+fn ready_at(spec: &PodBirthCertificateSpec) -> Option<u64> {
+    first(&spec.timeline, |entry| entry.action == POD_READY)
+}
+
+pub fn startup_phases(spec: &PodBirthCertificateSpec) -> StartupPhases {
+    let timeline = &spec.timeline;
+    let scheduled = first(timeline, |entry| {
+        entry.actor == Actor::Scheduler && (entry.action == POD_SCHEDULED || event_reason(entry) == "Scheduled")
+    });
+    let sandbox_ready = first(timeline, |entry| entry.action == SANDBOX_READY);
+    let pulling = first(timeline, |entry| entry.actor == Actor::Kubelet && event_reason(entry) == "Pulling");
+    let pulled = last(timeline, |entry| entry.actor == Actor::Kubelet && event_reason(entry) == "Pulled");
+    let started = first(timeline, is_container_start);
+
+    StartupPhases {
+        scheduling_ms: span(created_at(spec), scheduled),
+        sandbox_creation_ms: span(scheduled, sandbox_ready),
+        // Images already on the node are reported Pulled without any Pulling
+        image_pull_ms: pulled.map(|pulled| pulling.map_or(0, |pulling| pulled.saturating_sub(pulling))),
+        // Restarts add to the totals, but only the first birth was part of the start
+        kernel_setup_ms: Some(spec.birth_stats().total_duration_ms).filter(|ms| *ms > 0),
+        process_start_to_ready_ms: span(started, ready_at(spec)),
+    }
+}
+
+/// A container of `pod` stuck for a reason of its own, as `name is reason`
+fn failing_container(pod: &Pod) -> Option<String> {
+    let status = pod.status.as_ref()?;
+    if status.phase.as_deref() == Some("Failed") {
+        return Some(format!("pod is {}", status.reason.as_deref().unwrap_or("Failed")));
+    }
+    status
+        .init_container_statuses
+        .iter()
+        .flatten()
+        .chain(status.container_statuses.iter().flatten())
+        .find_map(|container| {
+            let reason = container.state.as_ref()?.waiting.as_ref()?.reason.as_deref()?;
+            FAILING_REASONS.contains(&reason).then(|| format!("container {} is {}", container.name, reason))
+        })
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1000.0)
+}
+
+/// The status of a certificate whose timeline is `spec.timeline`, graded
+/// against the startup thresholds of the pod's policy
+pub fn startup_status(
+    spec: &PodBirthCertificateSpec,
+    pod: Option<&Pod>,
+    thresholds: &SignalThresholds,
+    now: DateTime<Utc>,
+) -> PodBirthCertificateStatus {
+    let phases = startup_phases(spec);
+    let dominant = phases.dominant();
+    let total_ms = span(created_at(spec), ready_at(spec));
+    let mostly = dominant
+        .map(|(phase, ms)| format!(", mostly {} ({})", phase.as_str(), seconds(ms)))
+        .unwrap_or_default();
+
+    let (health, message) = match total_ms {
+        Some(total) => {
+            let health = match thresholds.startup_seconds.classify(total as f64 / 1000.0) {
+                Some(Severity::Critical | Severity::Warning) => StartupHealth::Slow,
+                Some(Severity::Info) | None => StartupHealth::Healthy,
+            };
+            (health, format!("Ready in {}{}", seconds(total), mostly))
+        }
+        None => match pod.and_then(failing_container) {
+            Some(failing) => (StartupHealth::Failing, format!("Not Ready: {failing}")),
+            None => {
+                let created = created_at(spec).or_else(|| {
+                    pod?.metadata.creation_timestamp.as_ref().map(|t| t.0.timestamp_millis().max(0) as u64)
+                });
+                let elapsed = created.map_or(0, |created| (now.timestamp_millis().max(0) as u64).saturating_sub(created));
+                let so_far = mostly.replacen(", mostly", ", so far mostly", 1);
+                // Each status write is another reconcile, so the message
+                // leaves out the time it was written at
+                match thresholds.startup_seconds.classify(elapsed as f64 / 1000.0) {
+                    Some(Severity::Critical) => {
+                        let critical = thresholds.startup_seconds.critical.unwrap_or_default();
+                        (StartupHealth::Stuck, format!("Not Ready after more than {critical}s{so_far}"))
+                    }
+                    _ => (StartupHealth::Starting, format!("Not Ready yet{so_far}")),
+                }
+            }
+        },
+    };
+
+    PodBirthCertificateStatus {
+        phases,
+        total_ms,
+        dominant_phase: dominant.map(|(phase, _)| phase),
+        health,
+        message,
+        // Filled in by the reconciler, which holds the workload baselines
+        baseline: None,
+        last_updated: now.to_rfc3339(),
+    }
+}
+
+/// Whether `status` says something `previous` did not, ignoring when each was written
+pub fn status_changed(previous: Option<&PodBirthCertificateStatus>, status: &PodBirthCertificateStatus) -> bool {
+    previous.is_none_or(|previous| {
+        PodBirthCertificateStatus { last_updated: String::new(), ..previous.clone() }
+            != PodBirthCertificateStatus { last_updated: String::new(), ..status.clone() }
+    })
+}
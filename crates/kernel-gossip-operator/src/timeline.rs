@@ -0,0 +1,220 @@
+//! Puts the kernel's view of a pod start next to the control plane's.
+//!
+//! The observer reports what the runtime did in the kernel; the scheduler and
+//! the kubelet report the rest through the pod's Events, its conditions and
+//! the containers' `startedAt`. Everything is merged into one timeline in
+//! Unix milliseconds, so a birth certificate shows where the time went.
+//!
+//! Only scheduler entries are stamped by the control plane. The kubelet, the
+//! runtime and the observer all read the node's clock, which can be behind:
+//! when it is, by more than the second the timestamps are rounded to, the
+//! node's entries are moved forward as one block until none precedes the
+//! scheduling decision.
+
+use std::collections::HashSet;
+
+use k8s_openapi::api::core::v1::{ContainerStatus, Event, Pod};
+use kernel_gossip_types::{Actor, TimelineEntry};
+
+pub const POD_CREATED: &str = "Pod created";
+pub const POD_SCHEDULED: &str = "Pod scheduled";
+pub const SANDBOX_READY: &str = "Pod sandbox ready";
+pub const POD_READY: &str = "Pod ready";
+
+/// Pod conditions worth a timeline entry, with who sets them
+const CONDITIONS: &[(&str, Actor, &str)] = &[
+    ("PodScheduled", Actor::Scheduler, POD_SCHEDULED),
+    ("PodReadyToStartContainers", Actor::Runtime, SANDBOX_READY),
+    ("Initialized", Actor::Kubelet, "Init containers done"),
+    ("ContainersReady", Actor::Kubelet, "Containers ready"),
+    ("Ready", Actor::Kubelet, POD_READY),
+];
+
+/// Events and conditions carry whole seconds; a node entry less than this
+/// ahead of the scheduling decision is rounding, not a slow clock
+const CLOCK_RESOLUTION_MS: u64 = 1_000;
+
+/// Entries kept on a certificate. A pod that keeps restarting keeps adding
+/// kernel steps, and the whole object has to stay under etcd's size limit.
+pub const MAX_TIMELINE_ENTRIES: usize = 500;
+
+/// Event reasons the kubelet reports about the runtime's sandbox
+const SANDBOX_REASONS: &[&str] = &["SandboxChanged", "FailedCreatePodSandBox", "FailedKillPod"];
+
+fn millis(time: &chrono::DateTime<chrono::Utc>) -> u64 {
+    time.timestamp_millis().max(0) as u64
+}
+
+/// Entries reported by the node rather than the control plane
+pub fn on_node_clock(entry: &TimelineEntry) -> bool {
+    entry.actor != Actor::Scheduler
+}
+
+fn event_actor(event: &Event) -> Actor {
+    let component = event
+        .reporting_component
+        .as_deref()
+        .filter(|component| !component.is_empty())
+        .or_else(|| event.source.as_ref().and_then(|source| source.component.as_deref()))
+        .unwrap_or_default();
+    let reason = event.reason.as_deref().unwrap_or_default();
+    if component.contains("scheduler") {
+        Actor::Scheduler
+    } else if SANDBOX_REASONS.contains(&reason) {
+        Actor::Runtime
+    } else {
+        Actor::Kubelet
+    }
+}
+
+/// When an event first happened; repeats keep their first occurrence
+fn event_time(event: &Event) -> Option<u64> {
+    event
+        .event_time
+        .as_ref()
+        .map(|t| millis(&t.0))
+        .or_else(|| event.first_timestamp.as_ref().map(|t| millis(&t.0)))
+        .or_else(|| event.last_timestamp.as_ref().map(|t| millis(&t.0)))
+        .or_else(|| event.metadata.creation_timestamp.as_ref().map(|t| millis(&t.0)))
+}
+
+fn event_entry(event: &Event) -> Option<TimelineEntry> {
+    let reason = event.reason.as_deref()?;
+    let action = match event.message.as_deref().filter(|message| !message.is_empty()) {
+        Some(message) => format!("{reason}: {message}"),
+        None => reason.to_string(),
+    };
+    Some(TimelineEntry { timestamp_ms: event_time(event)?, actor: event_actor(event), action })
+}
+
+/// The Event reason of an entry made from one
+pub fn event_reason(entry: &TimelineEntry) -> &str {
+    entry.action.split_once(": ").map_or(entry.action.as_str(), |(reason, _)| reason)
+}
+
+/// Whether the entry is a regular (not init) container's process starting
+pub fn is_container_start(entry: &TimelineEntry) -> bool {
+    entry.actor == Actor::Runtime && entry.action.starts_with("Container ") && entry.action.ends_with(" started")
+}
+
+fn container_started(status: &ContainerStatus, init: bool) -> Option<TimelineEntry> {
+    let state = status.state.as_ref()?;
+    let started_at = state
+        .running
+        .as_ref()
+        .and_then(|running| running.started_at.as_ref())
+        .or_else(|| state.terminated.as_ref().and_then(|terminated| terminated.started_at.as_ref()))?;
+    let kind = if init { "Init container" } else { "Container" };
+    Some(TimelineEntry::new(millis(&started_at.0), Actor::Runtime, &format!("{kind} {} started", status.name)))
+}
+
+/// What the API server knows about the start of `pod`: its creation, the
+/// conditions that have turned true, container start times and `events`
+pub fn control_plane_entries(pod: &Pod, events: &[Event]) -> Vec<TimelineEntry> {
+    let mut entries = Vec::new();
+    if let Some(created) = &pod.metadata.creation_timestamp {
+        entries.push(TimelineEntry::new(millis(&created.0), Actor::Scheduler, POD_CREATED));
+    }
+
+    if let Some(status) = &pod.status {
+        for condition in status.conditions.iter().flatten().filter(|c| c.status == "True") {
+            let Some((_, actor, action)) = CONDITIONS.iter().find(|(kind, _, _)| *kind == condition.type_) else {
+                continue;
+            };
+            if let Some(at) = &condition.last_transition_time {
+                entries.push(TimelineEntry::new(millis(&at.0), actor.clone(), action));
+            }
+        }
+        let init = status.init_container_statuses.iter().flatten().map(|c| (c, true));
+        let main = status.container_statuses.iter().flatten().map(|c| (c, false));
+        entries.extend(init.chain(main).filter_map(|(c, init)| container_started(c, init)));
+    }
+
+    entries.extend(events.iter().filter_map(event_entry));
+    entries
+}
+
+/// When the pod was bound to its node, according to the control plane
+fn scheduled_at(entries: &[TimelineEntry]) -> Option<u64> {
+    entries
+        .iter()
+        .filter(|entry| entry.actor == Actor::Scheduler && entry.action != POD_CREATED)
+        .map(|entry| entry.timestamp_ms)
+        .max()
+}
+
+/// Merge `control_plane` into a certificate's timeline. `recorded` is the
+/// timeline as stored, with its node entries moved by `recorded_offset_ms`.
+/// Earlier control plane entries are kept, since Events expire long before
+/// certificates do, and a step reported again, such as readiness flipping,
+/// keeps the time it first happened. Returns the merged timeline and the
+/// offset now applied to the node's entries.
+pub fn merge_timeline(
+    recorded: &[TimelineEntry],
+    recorded_offset_ms: Option<u64>,
+    control_plane: Vec<TimelineEntry>,
+) -> (Vec<TimelineEntry>, Option<u64>) {
+    let previous = recorded_offset_ms.unwrap_or(0);
+
+    // Back to the clocks the entries were reported on
+    let mut timeline: Vec<TimelineEntry> = recorded
+        .iter()
+        .cloned()
+        .map(|mut entry| {
+            if on_node_clock(&entry) {
+                entry.timestamp_ms = entry.timestamp_ms.saturating_sub(previous);
+            }
+            entry
+        })
+        .collect();
+    timeline.extend(control_plane);
+    timeline.sort_by_key(|entry| entry.timestamp_ms);
+    let mut seen = HashSet::new();
+    timeline.retain(|entry| entry.actor == Actor::Kernel || seen.insert((entry.actor.clone(), entry.action.clone())));
+
+    let offset = scheduled_at(&timeline).and_then(|scheduled| {
+        let earliest = timeline.iter().filter(|entry| on_node_clock(entry)).map(|entry| entry.timestamp_ms).min()?;
+        (earliest + CLOCK_RESOLUTION_MS < scheduled).then(|| scheduled - earliest)
+    });
+    if let Some(offset) = offset {
+        for entry in timeline.iter_mut().filter(|entry| on_node_clock(entry)) {
+            entry.timestamp_ms += offset;
+        }
+    }
+
+    // Stable, so steps reported in the same millisecond keep their order
+    timeline.sort_by_key(|entry| entry.timestamp_ms);
+    cap_timeline(&mut timeline);
+    (timeline, offset)
+}
+
+/// Bring a sorted timeline down to [`MAX_TIMELINE_ENTRIES`], dropping the
+/// latest kernel steps first: they are restarts, while the pod's own start and
+/// the control plane's entries come before them
+pub fn cap_timeline(timeline: &mut Vec<TimelineEntry>) {
+    let mut excess = timeline.len().saturating_sub(MAX_TIMELINE_ENTRIES);
+    if excess == 0 {
+        return;
+    }
+    let mut kept = Vec::with_capacity(MAX_TIMELINE_ENTRIES);
+    for entry in timeline.drain(..).rev() {
+        if excess > 0 && entry.actor == Actor::Kernel {
+            excess -= 1;
+        } else {
+            kept.push(entry);
+        }
+    }
+    kept.reverse();
+    kept.truncate(MAX_TIMELINE_ENTRIES);
+    *timeline = kept;
+}
+
+/// Move newly reported kernel entries onto a timeline already shifted by `offset_ms`
+pub fn align_kernel_entries(entries: &mut [TimelineEntry], offset_ms: Option<u64>) {
+    let Some(offset) = offset_ms else {
+        return;
+    };
+    for entry in entries.iter_mut().filter(|entry| entry.actor == Actor::Kernel) {
+        entry.timestamp_ms += offset;
+    }
+}
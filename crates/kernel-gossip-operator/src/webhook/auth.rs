@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use kernel_gossip_protocol::auth::{self, WebhookCredentials};
+use serde_json::json;
+use thiserror::Error;
+use tracing::warn;
+
+/// Largest request body the webhook will buffer for verification
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AuthError {
+    #[error("missing bearer token or request signature")]
+    MissingCredentials,
+
+    #[error("invalid bearer token")]
+    InvalidToken,
+
+    #[error("invalid {0} header")]
+    MalformedHeader(&'static str),
+
+    #[error("request signature does not match")]
+    InvalidSignature,
+
+    #[error("request timestamp is {0}s away from operator time")]
+    StaleTimestamp(i64),
+
+    #[error("request was already received")]
+    Replayed,
+}
+
+/// Checks webhook requests against the keys from the mounted Secret
+pub struct WebhookAuthenticator {
+    credentials: WebhookCredentials,
+    max_skew: Duration,
+    /// Signatures accepted within the skew window, lowercased, with their timestamps
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+impl WebhookAuthenticator {
+    pub fn new(credentials: WebhookCredentials, max_skew: Duration) -> Self {
+        Self {
+            credentials,
+            max_skew,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn authenticate(&self, headers: &HeaderMap, body: &[u8], now: i64) -> Result<(), AuthError> {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+
+        if let (Some(expected), Some(presented)) = (&self.credentials.token, bearer) {
+            return if auth::tokens_match(expected, presented.trim()) {
+                Ok(())
+            } else {
+                Err(AuthError::InvalidToken)
+            };
+        }
+
+        let (Some(key), Some(signature)) = (&self.credentials.hmac_key, headers.get(auth::SIGNATURE_HEADER)) else {
+            return Err(AuthError::MissingCredentials);
+        };
+        let signature = signature
+            .to_str()
+            .map_err(|_| AuthError::MalformedHeader(auth::SIGNATURE_HEADER))?;
+        let timestamp: i64 = headers
+            .get(auth::TIMESTAMP_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .ok_or(AuthError::MalformedHeader(auth::TIMESTAMP_HEADER))?;
+
+        let skew = now - timestamp;
+        if skew.unsigned_abs() > self.max_skew.as_secs() {
+            return Err(AuthError::StaleTimestamp(skew));
+        }
+        if !auth::verify_signature(key, timestamp, body, signature) {
+            return Err(AuthError::InvalidSignature);
+        }
+
+        // Anything older than the skew window is already refused by its timestamp,
+        // so only signatures inside the window need remembering. The hex digest
+        // verifies in any case, so a resend with its case changed is the same signature.
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let horizon = self.max_skew.as_secs() as i64;
+        seen.retain(|_, ts| (now - *ts).abs() <= horizon);
+        if seen.insert(signature.to_ascii_lowercase(), timestamp).is_some() {
+            return Err(AuthError::Replayed);
+        }
+
+        Ok(())
+    }
+}
+
+/// Middleware rejecting requests that fail [`WebhookAuthenticator::authenticate`] with 401
+pub async fn require_auth(
+    State(authenticator): State<Arc<WebhookAuthenticator>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    if let Err(e) = authenticator.authenticate(&parts.headers, &bytes, now) {
+        warn!("🔒 Rejected webhook request to {}: {}", parts.uri.path(), e);
+        crate::metrics::metrics().record_rejected_request("unauthorized");
+        let body = Json(json!({ "status": "unauthorized", "message": e.to_string() }));
+        return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response();
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+/// Put `router` behind [`require_auth`]
+pub fn authenticated(router: Router, authenticator: Arc<WebhookAuthenticator>) -> Router {
+    router.layer(middleware::from_fn_with_state(authenticator, require_auth))
+}
@@ -5,93 +5,70 @@ use axum::{
     routing::post,
     Router,
 };
-use serde::{Deserialize, Serialize};
+use axum::body::Bytes;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
 use tracing::{error, info};
 use kube::Client;
+use k8s_openapi::api::core::v1::Pod;
 use std::sync::Arc;
+use crate::config::RetentionPolicy;
+use crate::metrics::metrics;
+use crate::policy::{AppliedPolicy, Policies};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum EbpfWebhookPayload {
-    #[serde(rename = "cpu_throttle")]
-    CpuThrottle {
-        pod_name: String,
-        namespace: String,
-        container_name: String,
-        throttle_percentage: f64,
-        actual_cpu_usage: f64,
-        reported_cpu_usage: f64,
-        period_seconds: u64,
-        ebpf_detection: bool,
-        throttle_ns: u64,
-        timestamp: String,
-    },
-    #[serde(rename = "pod_creation")]
-    PodCreation {
-        pod_name: String,
-        namespace: String,
-        total_syscalls: u64,
-        namespace_ops: u64,
-        cgroup_writes: u64,
-        duration_ns: u64,
-        timeline: Vec<TimelineEvent>,
-        ebpf_detection: bool,
-        timestamp: String,
-    },
-}
+pub mod auth;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimelineEvent {
-    pub timestamp_ms: u64,
-    pub action: String,
-}
+pub use auth::{AuthError, WebhookAuthenticator};
 
-// Payload structs for actions module compatibility
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PodCreationPayload {
-    pub pod_name: String,
-    pub namespace: String,
-    pub total_syscalls: u64,
-    pub namespace_ops: u64,
-    pub cgroup_writes: u64,
-    pub duration_ns: u64,
-    pub timeline: Vec<TimelineEvent>,
-    pub ebpf_detection: bool,
-    pub timestamp: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CpuThrottlePayload {
-    pub pod_name: String,
-    pub namespace: String,
-    pub container_name: String,
-    pub throttle_percentage: f64,
-    pub actual_cpu_usage: f64,
-    pub reported_cpu_usage: f64,
-    pub period_seconds: u64,
-    pub ebpf_detection: bool,
-    pub throttle_ns: u64,
-    pub timestamp: String,
-}
+// The wire format is shared with kernel-observer
+pub use kernel_gossip_protocol::{
+    EbpfEvent, EventEnvelope, CpuThrottlePayload, PodCreationPayload,
+    MemoryPressurePayload, OomKillPayload, TimelineEvent,
+    EventBatch, BatchResponse, ItemResult, EventStatus, MAX_BATCH_EVENTS,
+};
 
 #[derive(Debug, Serialize)]
 struct WebhookResponse {
-    status: String,
+    status: EventStatus,
     message: String,
 }
 
-pub fn create_webhook_router(client: Arc<Client>) -> Router {
-    Router::new()
+#[derive(Clone)]
+struct WebhookState {
+    client: Client,
+    retention: RetentionPolicy,
+    batch_concurrency: usize,
+    policies: Policies,
+}
+
+/// Webhook routes; every request must pass `authenticator` when one is given.
+/// Events of one batch are processed at most `batch_concurrency` at a time,
+/// and only for pods one of `policies` covers.
+pub fn create_webhook_router(
+    client: Arc<Client>,
+    retention: RetentionPolicy,
+    batch_concurrency: usize,
+    policies: Policies,
+    authenticator: Option<Arc<WebhookAuthenticator>>,
+) -> Router {
+    let state = Arc::new(WebhookState {
+        client: client.as_ref().clone(),
+        retention,
+        batch_concurrency: batch_concurrency.max(1),
+        policies,
+    });
+    let router = Router::new()
         .route("/webhook/ebpf", post(handle_ebpf_webhook))
-        .with_state(client)
+        .route("/webhook/ebpf/batch", post(handle_ebpf_batch))
+        .with_state(state);
+
+    match authenticator {
+        Some(authenticator) => auth::authenticated(router, authenticator),
+        None => router,
+    }
 }
 
-async fn handle_ebpf_webhook(
-    State(client): State<Arc<Client>>,
-    headers: HeaderMap,
-    Json(payload): Json<EbpfWebhookPayload>,
-) -> Result<Json<WebhookResponse>, WebhookError> {
-    // Validate content-type
+fn check_content_type(headers: &HeaderMap) -> Result<(), WebhookError> {
     let content_type = headers
         .get("content-type")
         .and_then(|v| v.to_str().ok())
@@ -100,83 +77,103 @@ async fn handle_ebpf_webhook(
     if !content_type.starts_with("application/json") {
         return Err(WebhookError("Invalid content-type".to_string()));
     }
-    match payload {
-        EbpfWebhookPayload::PodCreation { pod_name, namespace, total_syscalls, namespace_ops, cgroup_writes, duration_ns, timeline, ebpf_detection, timestamp } => {
+    Ok(())
+}
+
+async fn handle_ebpf_webhook(
+    State(state): State<Arc<WebhookState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<WebhookResponse>, WebhookError> {
+    check_content_type(&headers)?;
+    
+    // Incompatible observers are turned away before their event is interpreted
+    let envelope = kernel_gossip_protocol::decode(&body)
+        .map_err(|e| {
+            metrics().record_rejected_request("rejected");
+            WebhookError(e.to_string())
+        })?;
+    
+    process_recorded(&state, envelope.event).await.map(Json)
+}
+
+// A bad batch is refused as a whole; otherwise every event gets its own result,
+// so one failing CRD write does not make the observer resend the rest
+async fn handle_ebpf_batch(
+    State(state): State<Arc<WebhookState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<BatchResponse>, WebhookError> {
+    check_content_type(&headers)?;
+    
+    let batch = kernel_gossip_protocol::decode_batch(&body)
+        .map_err(|e| {
+            metrics().record_rejected_request("rejected");
+            WebhookError(e.to_string())
+        })?;
+    
+    if batch.events.len() > MAX_BATCH_EVENTS {
+        return Err(WebhookError(format!(
+            "Batch of {} events exceeds the limit of {MAX_BATCH_EVENTS}",
+            batch.events.len()
+        )));
+    }
+    
+    info!("Received batch of {} eBPF events", batch.events.len());
+    
+    let state = &state;
+    let results: Vec<ItemResult> = stream::iter(batch.events.into_iter().enumerate())
+        .map(|(index, event)| async move {
+            let (status, message) = match process_recorded(state, event).await {
+                Ok(response) => (response.status, response.message),
+                Err(WebhookError(message)) => (EventStatus::Error, message),
+            };
+            ItemResult { index, status, message }
+        })
+        .buffered(state.batch_concurrency)
+        .collect()
+        .await;
+    
+    Ok(Json(BatchResponse { results }))
+}
+
+async fn process_recorded(state: &WebhookState, event: EbpfEvent) -> Result<WebhookResponse, WebhookError> {
+    let (kind, detected_at) = (event.kind(), event.timestamp().to_string());
+    let result = process_event(state, event).await;
+    let status = result.as_ref().map_or(EventStatus::Error, |r| r.status);
+    metrics().record_event_outcome(kind, &detected_at, status, chrono::Utc::now());
+    result
+}
+
+async fn process_event(state: &WebhookState, event: EbpfEvent) -> Result<WebhookResponse, WebhookError> {
+    match event {
+        EbpfEvent::PodCreation(payload) => {
+            let (pod_name, namespace) = (&payload.pod_name, &payload.namespace);
             info!(
                 "Received pod creation event for {}/{}",
                 namespace, pod_name
             );
             
-            // For pod creation events, we should create the certificate
-            // The pod may not exist yet in K8s API when we receive the kernel event
-            // We'll check annotations later if the pod exists, but for now
-            // we should capture the kernel truth about pod creation
-            
-            // Skip system namespaces
-            if namespace == "kube-system" || 
-               namespace == "kube-public" || 
-               namespace == "kube-node-lease" ||
-               namespace == "gke-gmp-system" ||
-               namespace == "gmp-system" ||
-               namespace == "gke-managed-filestorecsi" {
-                info!("Skipping system namespace pod {}/{}", 
-                      namespace, pod_name);
-                return Ok(Json(WebhookResponse {
-                    status: "skipped".to_string(),
-                    message: format!("System namespace pod {pod_name}"),
-                }));
-            }
-            
-            // Try to check annotations if pod exists, but don't fail if it doesn't
+            // The pod may not exist yet in K8s API when we receive the kernel event;
+            // the policy then decides on namespace and sampling alone
             use kube::api::Api;
-            use k8s_openapi::api::core::v1::Pod;
             
-            let pods: Api<Pod> = Api::namespaced(client.as_ref().clone(), &namespace);
-            
-            // Check if we should monitor this pod (default to true for pod creation)
-            let should_monitor = match pods.get(&pod_name).await {
-                Ok(pod) => {
-                    // Pod exists, check annotation
-                    pod.metadata
-                        .annotations
-                        .as_ref()
-                        .and_then(|ann| ann.get("kernel-gossip.io/monitor"))
-                        .map(|v| v == "true")
-                        .unwrap_or(false)
-                }
-                Err(_) => {
-                    // Pod doesn't exist yet - this is expected for creation events
-                    // Default to creating certificate for non-system namespaces
-                    info!("Pod {}/{} not found yet (expected for creation event), creating certificate", 
-                          namespace, pod_name);
-                    true
-                }
-            };
-            
-            if !should_monitor {
-                info!("Pod {}/{} does not have monitoring annotation, skipping", 
-                      namespace, pod_name);
-                return Ok(Json(WebhookResponse {
-                    status: "skipped".to_string(),
-                    message: format!("Pod {pod_name} not configured for monitoring"),
-                }));
+            let pods: Api<Pod> = Api::namespaced(state.client.clone(), namespace);
+            let pod = pods.get(pod_name).await.ok();
+            if pod.is_none() {
+                info!("Pod {}/{} not found yet (expected for creation event)", namespace, pod_name);
             }
             
-            // Create temporary payload structure for the function
-            let payload = PodCreationPayload {
-                pod_name,
-                namespace,
-                total_syscalls,
-                namespace_ops,
-                cgroup_writes,
-                duration_ns,
-                timeline,
-                ebpf_detection,
-                timestamp,
-            };
+            if let Err(reason) = state.policies.evaluate(namespace, pod_name, pod.as_ref()) {
+                info!("Skipping pod creation of {}/{}: {}", namespace, pod_name, reason);
+                return Ok(WebhookResponse {
+                    status: EventStatus::Skipped,
+                    message: reason,
+                });
+            }
             
             // Create PodBirthCertificate CRD
-            match crate::actions::create_pod_birth_certificate(&client, &payload).await {
+            match crate::actions::create_pod_birth_certificate(&state.client, pod.as_ref(), &payload, state.retention).await {
                 Ok(pbc) => {
                     info!("Successfully created PodBirthCertificate: {:?}", pbc.metadata.name);
                 }
@@ -186,65 +183,113 @@ async fn handle_ebpf_webhook(
                 }
             }
         }
-        EbpfWebhookPayload::CpuThrottle { pod_name, namespace, container_name, throttle_percentage, actual_cpu_usage, reported_cpu_usage, period_seconds, ebpf_detection, throttle_ns, timestamp } => {
+        EbpfEvent::CpuThrottle(payload) => {
             info!(
                 "Received CPU throttle event for {}/{}: {}%",
-                namespace, pod_name, throttle_percentage
+                payload.namespace, payload.pod_name, payload.throttle_percentage
             );
             
-            // Check if pod has monitoring annotation
-            use kube::api::Api;
-            use k8s_openapi::api::core::v1::Pod;
+            let (pod, policy) = match monitored_pod(state, &payload.namespace, &payload.pod_name).await {
+                Ok(monitored) => monitored,
+                Err(skipped) => return Ok(skipped),
+            };
+            
+            match crate::actions::create_kernel_whisper(&state.client, &pod, &payload, state.retention, &policy.spec.thresholds).await {
+                Ok(kw) => {
+                    info!("Successfully created KernelWhisper: {:?}", kw.metadata.name);
+                }
+                Err(e) => {
+                    error!("Failed to create KernelWhisper: {}", e);
+                    return Err(WebhookError(format!("Failed to create CRD: {e}")));
+                }
+            }
+        }
+        EbpfEvent::MemoryPressure(payload) => {
+            info!(
+                "Received memory pressure event for {}/{}: PSI some {}%",
+                payload.namespace, payload.pod_name, payload.psi_some_avg10
+            );
             
-            let pods: Api<Pod> = Api::namespaced(client.as_ref().clone(), &namespace);
+            let (pod, policy) = match monitored_pod(state, &payload.namespace, &payload.pod_name).await {
+                Ok(monitored) => monitored,
+                Err(skipped) => return Ok(skipped),
+            };
             
-            // Try to get the pod and check its annotations
-            match pods.get(&pod_name).await {
-                Ok(pod) => {
-                    let should_monitor = pod.metadata
-                        .annotations
-                        .as_ref()
-                        .and_then(|ann| ann.get("kernel-gossip.io/monitor"))
-                        .map(|v| v == "true")
-                        .unwrap_or(false);
-                    
-                    if !should_monitor {
-                        info!("Pod {}/{} does not have monitoring annotation, skipping", 
-                              namespace, pod_name);
-                        return Ok(Json(WebhookResponse {
-                            status: "skipped".to_string(),
-                            message: format!("Pod {pod_name} not configured for monitoring"),
-                        }));
-                    }
+            match crate::actions::create_memory_whisper(&state.client, &pod, &payload, state.retention, &policy.spec.thresholds).await {
+                Ok(mw) => {
+                    info!("Successfully created MemoryWhisper: {:?}", mw.metadata.name);
                 }
                 Err(e) => {
-                    // Pod doesn't exist or is a system process - skip for non-pod processes
-                    info!("Could not find pod {}/{}, likely a system process: {}", 
-                          namespace, pod_name, e);
-                    return Ok(Json(WebhookResponse {
-                        status: "skipped".to_string(),
-                        message: format!("Pod {pod_name} not found or is system process"),
-                    }));
+                    error!("Failed to create MemoryWhisper: {}", e);
+                    return Err(WebhookError(format!("Failed to create CRD: {e}")));
                 }
             }
+        }
+        EbpfEvent::OomKill(payload) => {
+            info!(
+                "Received OOM kill event for {}/{}: {} kills",
+                payload.namespace, payload.pod_name, payload.oom_kills
+            );
             
-            // Create KernelWhisper CRD only for annotated pods
-            match crate::actions::create_kernel_whisper(&client, &CpuThrottlePayload { pod_name, namespace, container_name, throttle_percentage, actual_cpu_usage, reported_cpu_usage, period_seconds, ebpf_detection, throttle_ns, timestamp }).await {
-                Ok(kw) => {
-                    info!("Successfully created KernelWhisper: {:?}", kw.metadata.name);
+            let (pod, _) = match monitored_pod(state, &payload.namespace, &payload.pod_name).await {
+                Ok(monitored) => monitored,
+                Err(skipped) => return Ok(skipped),
+            };
+            
+            // A pod recreated under the same name is not the one that was killed
+            if pod.metadata.uid.as_deref() != Some(payload.pod_uid.as_str()) {
+                info!("Pod {}/{} no longer has UID {}, skipping", payload.namespace, payload.pod_name, payload.pod_uid);
+                return Ok(WebhookResponse {
+                    status: EventStatus::Skipped,
+                    message: format!("Pod {} was replaced since the OOM kill", payload.pod_name),
+                });
+            }
+            
+            match crate::actions::create_oom_kill_report(&state.client, &pod, &payload, state.retention).await {
+                Ok(report) => {
+                    info!("Successfully created OOMKillReport: {:?}", report.metadata.name);
                 }
                 Err(e) => {
-                    error!("Failed to create KernelWhisper: {}", e);
+                    error!("Failed to create OOMKillReport: {}", e);
                     return Err(WebhookError(format!("Failed to create CRD: {e}")));
                 }
             }
         }
     }
 
-    Ok(Json(WebhookResponse {
-        status: "accepted".to_string(),
+    Ok(WebhookResponse {
+        status: EventStatus::Accepted,
         message: "Webhook payload processed".to_string(),
-    }))
+    })
+}
+
+// Signals about running pods are only recorded for existing pods their policy covers
+async fn monitored_pod(state: &WebhookState, namespace: &str, pod_name: &str) -> Result<(Pod, AppliedPolicy), WebhookResponse> {
+    use kube::api::Api;
+    
+    let pods: Api<Pod> = Api::namespaced(state.client.clone(), namespace);
+    
+    match pods.get(pod_name).await {
+        Ok(pod) => match state.policies.evaluate(namespace, pod_name, Some(&pod)) {
+            Ok(policy) => Ok((pod, policy)),
+            Err(reason) => {
+                info!("Skipping {}/{}: {}", namespace, pod_name, reason);
+                Err(WebhookResponse {
+                    status: EventStatus::Skipped,
+                    message: reason,
+                })
+            }
+        },
+        Err(e) => {
+            // Pod doesn't exist or is a system process - skip for non-pod processes
+            info!("Could not find pod {}/{}, likely a system process: {}", 
+                  namespace, pod_name, e);
+            Err(WebhookResponse {
+                status: EventStatus::Skipped,
+                message: format!("Pod {pod_name} not found or is system process"),
+            })
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -256,7 +301,7 @@ impl IntoResponse for WebhookError {
         (
             StatusCode::BAD_REQUEST,
             Json(WebhookResponse {
-                status: "error".to_string(),
+                status: EventStatus::Error,
                 message: self.0,
             }),
         )
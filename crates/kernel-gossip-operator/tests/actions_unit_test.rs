@@ -1,8 +1,17 @@
 #[cfg(test)]
 mod actions_unit_tests {
-    use kernel_gossip_operator::actions::{build_pod_birth_certificate, build_kernel_whisper};
-    use kernel_gossip_operator::webhook::{PodCreationPayload, CpuThrottlePayload};
-    use kernel_gossip_types::{Actor, Severity};
+    use kernel_gossip_operator::actions::{
+        build_pod_birth_certificate, build_kernel_whisper, apply_pod_metrics,
+        build_memory_whisper, apply_pod_memory_metrics,
+        build_oom_kill_report, pod_owner_reference, oom_killed_container,
+        apply_pod_ownership, merge_pod_birth, POD_UID_LABEL,
+    };
+    use kernel_gossip_operator::startup::startup_phases;
+    use kernel_gossip_operator::config::RetentionPolicy;
+    use kernel_gossip_operator::pod_metrics::{ContainerUsage, MetricsError, PodUsage};
+    use kernel_gossip_operator::webhook::{PodCreationPayload, CpuThrottlePayload, MemoryPressurePayload, OomKillPayload, TimelineEvent};
+    use kernel_gossip_types::{Actor, OomCause, Severity};
+    use k8s_openapi::api::core::v1::Pod;
 
     #[test]
     fn test_build_pod_birth_certificate() {
@@ -13,6 +22,7 @@ mod actions_unit_tests {
             total_syscalls: 1234,
             namespace_ops: 56,
             cgroup_writes: 78,
+            mount_ops: 9,
             duration_ns: 1000000,
             timeline: vec![],
             ebpf_detection: true,
@@ -34,9 +44,51 @@ mod actions_unit_tests {
         assert_eq!(pbc.spec.kernel_stats.total_syscalls, 1234);
         assert_eq!(pbc.spec.kernel_stats.namespaces_created, 56);
         assert_eq!(pbc.spec.kernel_stats.cgroup_writes, 78);
+        assert_eq!(pbc.spec.kernel_stats.mount_ops, 9);
         assert_eq!(pbc.spec.kernel_stats.total_duration_ms, 1); // 1000000 ns = 1 ms
     }
 
+    fn birth_payload(timestamp: &str, total_syscalls: u64, duration_ns: u64) -> PodCreationPayload {
+        PodCreationPayload {
+            timestamp: timestamp.to_string(),
+            pod_name: "web-1".to_string(),
+            namespace: "shop".to_string(),
+            total_syscalls,
+            namespace_ops: 3,
+            cgroup_writes: 4,
+            mount_ops: 10,
+            duration_ns,
+            timeline: vec![
+                TimelineEvent { timestamp_ms: 0, action: "runc started".to_string() },
+                TimelineEvent { timestamp_ms: duration_ns / 1_000_000, action: "exec nginx".to_string() },
+            ],
+            ebpf_detection: true,
+        }
+    }
+
+    #[test]
+    fn test_restart_adds_to_totals_but_not_to_the_first_birth() {
+        let mut pbc = build_pod_birth_certificate(&birth_payload("2024-01-01T00:00:01Z", 2_000, 120_000_000));
+        assert_eq!(pbc.spec.first_birth.as_ref().unwrap().total_duration_ms, 120);
+
+        // The container restarts an hour later
+        merge_pod_birth(&mut pbc, build_pod_birth_certificate(&birth_payload("2024-01-01T01:00:00Z", 1_800, 90_000_000)));
+        assert_eq!(pbc.spec.kernel_stats.total_syscalls, 3_800);
+        assert_eq!(pbc.spec.kernel_stats.namespaces_created, 6);
+        assert_eq!(pbc.spec.kernel_stats.cgroup_writes, 8);
+        assert_eq!(pbc.spec.kernel_stats.mount_ops, 20);
+        assert_eq!(pbc.spec.kernel_stats.total_duration_ms, 210);
+        assert_eq!(pbc.spec.birth_stats().total_syscalls, 2_000);
+        assert_eq!(startup_phases(&pbc.spec).kernel_setup_ms, Some(120));
+        assert_eq!(pbc.spec.timeline.len(), 4);
+
+        // Certificates written before the first birth was kept start from their totals
+        let mut legacy = build_pod_birth_certificate(&birth_payload("2024-01-01T00:00:01Z", 2_000, 120_000_000));
+        legacy.spec.first_birth = None;
+        merge_pod_birth(&mut legacy, build_pod_birth_certificate(&birth_payload("2024-01-01T01:00:00Z", 1_800, 90_000_000)));
+        assert_eq!(startup_phases(&legacy.spec).kernel_setup_ms, Some(120));
+    }
+
     #[test]
     fn test_build_kernel_whisper() {
         let payload = CpuThrottlePayload {
@@ -50,6 +102,8 @@ mod actions_unit_tests {
             period_seconds: 300,
             ebpf_detection: true,
             throttle_ns: 123456789,
+            nr_periods: 3000,
+            nr_throttled: 1365,
         };
 
         let kw = build_kernel_whisper(&payload);
@@ -62,9 +116,13 @@ mod actions_unit_tests {
         // Verify kernel truth
         assert_eq!(kw.spec.kernel_truth.throttled_percent, 45.5);
         assert_eq!(kw.spec.kernel_truth.actual_cpu_cores, 0.8);
+        assert_eq!(kw.spec.kernel_truth.total_periods, 3000);
+        assert_eq!(kw.spec.kernel_truth.throttled_periods, 1365);
+        assert_eq!(kw.spec.kernel_truth.throttled_time_ms, 123);
+        assert_eq!(kw.spec.kernel_truth.window_seconds, 300);
 
         // Verify metrics lie
-        assert_eq!(kw.spec.metrics_lie.cpu_percent, 50.0);
+        assert_eq!(kw.spec.metrics_lie.cpu_percent, Some(50.0));
         assert_eq!(kw.spec.metrics_lie.reported_status, "Healthy");
 
         // Verify severity (45.5% should be Info)
@@ -85,6 +143,8 @@ mod actions_unit_tests {
             period_seconds: 300,
             ebpf_detection: true,
             throttle_ns: 123456789,
+            nr_periods: 3000,
+            nr_throttled: 1365,
         };
         let kw = build_kernel_whisper(&payload);
         assert_eq!(kw.spec.severity, Severity::Critical);
@@ -101,8 +161,234 @@ mod actions_unit_tests {
             period_seconds: 300,
             ebpf_detection: true,
             throttle_ns: 123456789,
+            nr_periods: 3000,
+            nr_throttled: 1365,
         };
         let kw = build_kernel_whisper(&payload);
         assert_eq!(kw.spec.severity, Severity::Warning);
     }
-}
\ No newline at end of file
+
+    fn throttle_payload() -> CpuThrottlePayload {
+        CpuThrottlePayload {
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            pod_name: "metrics-pod".to_string(),
+            namespace: "default".to_string(),
+            container_name: "app".to_string(),
+            throttle_percentage: 90.0,
+            actual_cpu_usage: 1.0,
+            reported_cpu_usage: 0.5,
+            period_seconds: 10,
+            ebpf_detection: true,
+            throttle_ns: 0,
+            nr_periods: 100,
+            nr_throttled: 90,
+        }
+    }
+
+    #[test]
+    fn test_apply_pod_metrics_records_container_usage() {
+        let mut kw = build_kernel_whisper(&throttle_payload());
+        let usage = PodUsage {
+            containers: vec![
+                ContainerUsage { name: "app".to_string(), cpu_cores: 0.3, memory_bytes: 0 },
+                ContainerUsage { name: "sidecar".to_string(), cpu_cores: 0.05, memory_bytes: 0 },
+            ],
+            window: Some("15s".to_string()),
+        };
+
+        apply_pod_metrics(&mut kw, Ok(usage));
+
+        assert_eq!(kw.spec.metrics_lie.cpu_percent, Some(35.0));
+        assert_eq!(kw.spec.metrics_lie.reported_status, "Healthy");
+        assert_eq!(kw.spec.metrics_lie.unavailable_reason, None);
+        assert_eq!(kw.spec.metrics_lie.containers.len(), 2);
+        assert_eq!(kw.spec.metrics_lie.containers[1].name, "sidecar");
+    }
+
+    #[test]
+    fn test_apply_pod_metrics_marks_unavailable() {
+        let mut kw = build_kernel_whisper(&throttle_payload());
+
+        apply_pod_metrics(&mut kw, Err(MetricsError::NotFound("default/metrics-pod".to_string())));
+
+        assert_eq!(kw.spec.metrics_lie.cpu_percent, None);
+        assert_eq!(kw.spec.metrics_lie.reported_status, "Unavailable");
+        assert!(kw.spec.metrics_lie.unavailable_reason.as_deref().unwrap().contains("default/metrics-pod"));
+        assert!(kw.spec.metrics_lie.containers.is_empty());
+    }
+
+    fn memory_payload() -> MemoryPressurePayload {
+        MemoryPressurePayload {
+            pod_name: "memory-pod".to_string(),
+            namespace: "default".to_string(),
+            psi_some_avg10: 35.5,
+            psi_full_avg10: 4.2,
+            stall_time_us: 2_500_000,
+            full_stall_time_us: 300_000,
+            high_events: 12,
+            max_events: 3,
+            oom_events: 0,
+            oom_kill_events: 0,
+            reclaim_scanned_pages: 40_960,
+            working_set_bytes: 250 * 1024 * 1024,
+            limit_bytes: Some(256 * 1024 * 1024),
+            period_seconds: 10,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_memory_whisper() {
+        let mw = build_memory_whisper(&memory_payload());
+
+        assert_eq!(mw.metadata.name, Some("memory-pod-memory-pressure".to_string()));
+        assert_eq!(mw.spec.detected_at, "2024-01-01T00:00:00Z");
+        assert_eq!(mw.spec.kernel_truth.stall_some_percent, 35.5);
+        assert_eq!(mw.spec.kernel_truth.stall_time_ms, 2500);
+        assert_eq!(mw.spec.kernel_truth.max_events, 3);
+        assert_eq!(mw.spec.kernel_truth.limit_bytes, Some(256 * 1024 * 1024));
+        assert_eq!(mw.spec.kernel_truth.window_seconds, 10);
+        assert_eq!(mw.spec.severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_apply_pod_memory_metrics_records_working_set() {
+        let mut mw = build_memory_whisper(&memory_payload());
+        let usage = PodUsage {
+            containers: vec![
+                ContainerUsage { name: "app".to_string(), cpu_cores: 0.1, memory_bytes: 100 * 1024 * 1024 },
+                ContainerUsage { name: "sidecar".to_string(), cpu_cores: 0.0, memory_bytes: 20 * 1024 * 1024 },
+            ],
+            window: None,
+        };
+
+        apply_pod_memory_metrics(&mut mw, Ok(usage));
+
+        assert_eq!(mw.spec.metrics_lie.working_set_bytes, Some(120 * 1024 * 1024));
+        assert_eq!(mw.spec.metrics_lie.reported_status, "Healthy");
+        assert_eq!(mw.spec.metrics_lie.unavailable_reason, None);
+    }
+
+    #[test]
+    fn test_apply_pod_memory_metrics_marks_unavailable() {
+        let mut mw = build_memory_whisper(&memory_payload());
+
+        apply_pod_memory_metrics(&mut mw, Err(MetricsError::NotFound("default/memory-pod".to_string())));
+
+        assert_eq!(mw.spec.metrics_lie.working_set_bytes, None);
+        assert_eq!(mw.spec.metrics_lie.reported_status, "Unavailable");
+        assert!(mw.spec.metrics_lie.unavailable_reason.is_some());
+    }
+
+    #[test]
+    fn test_build_oom_kill_report() {
+        let payload = OomKillPayload {
+            pod_name: "leaky-pod".to_string(),
+            namespace: "default".to_string(),
+            pod_uid: "2bac1a6a-95d3-4abc-990f-aefaf5c74812".to_string(),
+            oom_kills: 2,
+            anon_bytes: 10 * 1024 * 1024,
+            file_bytes: 110 * 1024 * 1024,
+            kernel_bytes: 1024 * 1024,
+            sock_bytes: 0,
+            shmem_bytes: 100 * 1024 * 1024,
+            usage_bytes: 128 * 1024 * 1024,
+            limit_bytes: Some(128 * 1024 * 1024),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        };
+
+        let report = build_oom_kill_report(&payload);
+
+        assert_eq!(report.metadata.name, Some("leaky-pod-oom-1704067200".to_string()));
+        assert_eq!(report.spec.pod_uid, "2bac1a6a-95d3-4abc-990f-aefaf5c74812");
+        assert_eq!(report.spec.oom_kills, 2);
+        assert_eq!(report.spec.memory.shmem_bytes, 100 * 1024 * 1024);
+        assert_eq!(report.spec.likely_cause, OomCause::Shmem);
+    }
+
+    fn oom_killed_pod() -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "name": "leaky-pod",
+                "namespace": "default",
+                "uid": "2bac1a6a-95d3-4abc-990f-aefaf5c74812"
+            },
+            "status": {
+                "containerStatuses": [
+                    {
+                        "name": "sidecar",
+                        "image": "busybox",
+                        "imageID": "",
+                        "ready": true,
+                        "restartCount": 0,
+                        "state": { "running": {} }
+                    },
+                    {
+                        "name": "app",
+                        "image": "app",
+                        "imageID": "",
+                        "ready": true,
+                        "restartCount": 1,
+                        "state": { "running": {} },
+                        "lastState": { "terminated": { "exitCode": 137, "reason": "OOMKilled" } }
+                    }
+                ]
+            }
+        }))
+        .expect("valid pod")
+    }
+
+    #[test]
+    fn test_pod_owner_reference() {
+        let owner = pod_owner_reference(&oom_killed_pod()).expect("pod has name and uid");
+
+        assert_eq!(owner.api_version, "v1");
+        assert_eq!(owner.kind, "Pod");
+        assert_eq!(owner.name, "leaky-pod");
+        assert_eq!(owner.uid, "2bac1a6a-95d3-4abc-990f-aefaf5c74812");
+
+        assert!(pod_owner_reference(&Pod::default()).is_none());
+    }
+
+    #[test]
+    fn test_oom_killed_container() {
+        assert_eq!(oom_killed_container(&oom_killed_pod()), Some("app".to_string()));
+        assert_eq!(oom_killed_container(&Pod::default()), None);
+    }
+
+    #[test]
+    fn test_apply_pod_ownership_owned_by_pod() {
+        let mut kw = build_kernel_whisper(&throttle_payload());
+
+        apply_pod_ownership(&mut kw.metadata, &oom_killed_pod(), RetentionPolicy::OwnedByPod);
+        // Re-applying on update must not stack owner references
+        apply_pod_ownership(&mut kw.metadata, &oom_killed_pod(), RetentionPolicy::OwnedByPod);
+
+        let owners = kw.metadata.owner_references.expect("owner references set");
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].uid, "2bac1a6a-95d3-4abc-990f-aefaf5c74812");
+        assert_eq!(
+            kw.metadata.labels.unwrap().get(POD_UID_LABEL).map(String::as_str),
+            Some("2bac1a6a-95d3-4abc-990f-aefaf5c74812")
+        );
+    }
+
+    #[test]
+    fn test_apply_pod_ownership_with_retention_ttl() {
+        let mut kw = build_kernel_whisper(&throttle_payload());
+        // Owned while records still went with their Pod
+        apply_pod_ownership(&mut kw.metadata, &oom_killed_pod(), RetentionPolicy::OwnedByPod);
+
+        apply_pod_ownership(
+            &mut kw.metadata,
+            &oom_killed_pod(),
+            RetentionPolicy::KeepFor(std::time::Duration::from_secs(3600)),
+        );
+
+        // The controller deletes the record after the TTL, so the garbage collector must not
+        assert!(kw.metadata.owner_references.is_none());
+        assert!(kw.metadata.labels.unwrap().contains_key(POD_UID_LABEL));
+    }
+}
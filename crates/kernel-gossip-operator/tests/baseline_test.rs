@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod baseline_tests {
+    use k8s_openapi::api::core::v1::Pod;
+    use kernel_gossip_operator::actions::{build_pod_birth_certificate, merge_pod_birth};
+    use kernel_gossip_operator::baseline::{owning_workload, regression_note, settled, Baselines, MIN_SAMPLES, WINDOW};
+    use kernel_gossip_protocol::PodCreationPayload;
+    use kernel_gossip_types::{KernelMetric, KernelStats, PodBirthCertificate};
+    use serde_json::json;
+
+    fn stats(syscalls: u32, mounts: u32, duration_ms: u64) -> KernelStats {
+        let mut stats = PodBirthCertificate::create("web-1", "shop").spec.kernel_stats;
+        stats.total_syscalls = syscalls;
+        stats.cgroup_writes = 4;
+        stats.mount_ops = mounts;
+        stats.total_duration_ms = duration_ms;
+        stats
+    }
+
+    fn pod(owner_kind: &str, owner_name: &str, labels: serde_json::Value) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": {
+                "name": "web-1",
+                "namespace": "shop",
+                "labels": labels,
+                "ownerReferences": [{
+                    "apiVersion": "apps/v1",
+                    "kind": owner_kind,
+                    "name": owner_name,
+                    "uid": "1",
+                    "controller": true
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    /// Ten births of the old image: 2000-2090 syscalls, 10 or 11 mounts
+    fn with_history() -> Baselines {
+        let mut baselines = Baselines::default();
+        for i in 0..10u32 {
+            let born = u64::from(i) * 1_000;
+            baselines.observe("shop", "Deployment/web", &format!("web-{i}"), born, &stats(2_000 + i * 10, 10 + i % 2, 120));
+        }
+        baselines
+    }
+
+    #[test]
+    fn test_owning_workload_counts_replicasets_as_their_deployment() {
+        let from_deployment = pod("ReplicaSet", "web-7d4b9c8f6", json!({ "pod-template-hash": "7d4b9c8f6" }));
+        assert_eq!(owning_workload(&from_deployment).as_deref(), Some("Deployment/web"));
+
+        let bare = pod("ReplicaSet", "web", json!({}));
+        assert_eq!(owning_workload(&bare).as_deref(), Some("ReplicaSet/web"));
+        assert_eq!(owning_workload(&pod("StatefulSet", "db", json!({}))).as_deref(), Some("StatefulSet/db"));
+        assert_eq!(owning_workload(&pod("Job", "migrate-28391", json!({}))).as_deref(), Some("Job/migrate-28391"));
+
+        let standalone: Pod = serde_json::from_value(json!({ "metadata": { "name": "debug" } })).unwrap();
+        assert_eq!(owning_workload(&standalone), None);
+    }
+
+    #[test]
+    fn test_doubled_mounts_are_flagged() {
+        let mut baselines = with_history();
+
+        let usual = baselines.observe("shop", "Deployment/web", "web-10", 10_000, &stats(2_050, 11, 125)).unwrap();
+        assert_eq!(usual.samples, 10);
+        assert!(!usual.regressed);
+        let mounts = usual.metrics.iter().find(|m| m.metric == KernelMetric::MountOps).unwrap();
+        assert_eq!((mounts.p50, mounts.p90), (10, 11));
+
+        // A new image that mounts twice as much
+        let doubled = baselines.observe("shop", "Deployment/web", "web-11", 11_000, &stats(2_050, 22, 125)).unwrap();
+        assert!(doubled.regressed);
+        let regressed: Vec<_> = doubled.metrics.iter().filter(|m| m.regressed).map(|m| m.metric).collect();
+        assert_eq!(regressed, [KernelMetric::MountOps]);
+        assert_eq!(
+            regression_note(&doubled).as_deref(),
+            Some("Outside the baseline of Deployment/web over 11 earlier births: mount ops 22 (p50 11, p90 11)")
+        );
+        assert_eq!(regression_note(&usual), None);
+
+        // Reconciling the same birth again compares it with the same births
+        let again = baselines.observe("shop", "Deployment/web", "web-11", 11_000, &stats(2_050, 22, 125)).unwrap();
+        assert_eq!(again, doubled);
+    }
+
+    #[test]
+    fn test_small_or_young_baselines_do_not_flag() {
+        let mut baselines = Baselines::default();
+        for i in 0..MIN_SAMPLES as u64 - 1 {
+            baselines.observe("shop", "StatefulSet/db", &format!("db-{i}"), i, &stats(500, 0, 40));
+        }
+        let early = baselines.observe("shop", "StatefulSet/db", "db-x", 100, &stats(5_000, 40, 400)).unwrap();
+        assert_eq!(early.samples, MIN_SAMPLES as u32 - 1);
+        assert!(early.metrics.is_empty() && !early.regressed);
+
+        // One mount where there were none is within the floor
+        let one_mount = baselines.observe("shop", "StatefulSet/db", "db-y", 200, &stats(510, 1, 41)).unwrap();
+        assert!(!one_mount.metrics.is_empty() && !one_mount.regressed);
+
+        // Births elsewhere and births without kernel stats are their own thing
+        assert_eq!(baselines.observe("other", "StatefulSet/db", "db-z", 300, &stats(5_000, 40, 400)).unwrap().samples, 0);
+        assert_eq!(baselines.observe("shop", "StatefulSet/db", "db-w", 300, &stats(0, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_fewer_samples_do_not_replace_a_comparison() {
+        let mut baselines = with_history();
+        let graded = baselines.observe("shop", "Deployment/web", "web-10", 10_000, &stats(2_050, 22, 125));
+        assert!(graded.as_ref().is_some_and(|b| b.regressed));
+
+        // After a restart only part of the history has been seen again
+        let mut restarted = Baselines::default();
+        restarted.observe("shop", "Deployment/web", "web-9", 9_000, &stats(2_090, 11, 120));
+        let partial = restarted.observe("shop", "Deployment/web", "web-10", 10_000, &stats(2_050, 22, 125));
+        assert_eq!(settled(graded.as_ref(), partial), graded);
+        assert_eq!(settled(graded.as_ref(), None), graded);
+
+        // The window keeps the latest births
+        for i in 0..WINDOW as u64 {
+            baselines.observe("shop", "Deployment/web", &format!("next-{i}"), 20_000 + i, &stats(2_000, 10, 120));
+        }
+        let last = baselines.observe("shop", "Deployment/web", "latest", 100_000, &stats(2_000, 10, 120)).unwrap();
+        assert_eq!(last.samples, WINDOW as u32 - 1);
+    }
+
+    #[test]
+    fn test_later_births_of_a_pod_leave_its_sample_alone() {
+        let birth = |timestamp: &str| PodCreationPayload {
+            pod_name: "web-10".to_string(),
+            namespace: "shop".to_string(),
+            total_syscalls: 2_050,
+            namespace_ops: 3,
+            cgroup_writes: 4,
+            mount_ops: 11,
+            duration_ns: 125_000_000,
+            timeline: vec![],
+            ebpf_detection: true,
+            timestamp: timestamp.to_string(),
+        };
+        let mut baselines = with_history();
+        let mut pbc = build_pod_birth_certificate(&birth("2024-01-01T00:00:10Z"));
+        let first = baselines.observe("shop", "Deployment/web", "web-10-pbc", 10_000, pbc.spec.birth_stats()).unwrap();
+        assert!(!first.regressed);
+
+        // A restart, or an exec session from an observer that still reports
+        // them, adds a second birth's worth to the certificate's totals
+        merge_pod_birth(&mut pbc, build_pod_birth_certificate(&birth("2024-01-01T01:00:00Z")));
+        assert_eq!(pbc.spec.kernel_stats.mount_ops, 22);
+        let again = baselines.observe("shop", "Deployment/web", "web-10-pbc", 10_000, pbc.spec.birth_stats()).unwrap();
+        assert_eq!(again, first);
+
+        // Even handed the totals, the sample stays as first recorded
+        let totals = baselines.observe("shop", "Deployment/web", "web-10-pbc", 10_000, &pbc.spec.kernel_stats).unwrap();
+        assert_eq!(totals, first);
+
+        // and later births are graded against it
+        let next = baselines.observe("shop", "Deployment/web", "web-11", 11_000, &stats(2_050, 11, 125)).unwrap();
+        let mounts = next.metrics.iter().find(|m| m.metric == KernelMetric::MountOps).unwrap();
+        assert_eq!((mounts.p50, mounts.p90, next.regressed), (11, 11, false));
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod config_tests {
-    use kernel_gossip_operator::config::Config;
+    use kernel_gossip_operator::config::{Config, RetentionPolicy};
+    use std::time::Duration;
 
     #[test]
     fn test_config_from_env() {
@@ -26,6 +27,17 @@ mod config_tests {
         assert_eq!(config.metrics_port, 9090); // default
     }
 
+    #[test]
+    fn test_config_retention_policy() {
+        assert_eq!(RetentionPolicy::from_secs(None), RetentionPolicy::OwnedByPod);
+        assert_eq!(RetentionPolicy::from_secs(Some("0")), RetentionPolicy::OwnedByPod);
+        assert_eq!(RetentionPolicy::from_secs(Some("a day")), RetentionPolicy::OwnedByPod);
+        assert_eq!(
+            RetentionPolicy::from_secs(Some("86400")),
+            RetentionPolicy::KeepFor(Duration::from_secs(86400))
+        );
+    }
+
     #[test]
     fn test_config_always_succeeds() {
         // Config should always succeed with defaults
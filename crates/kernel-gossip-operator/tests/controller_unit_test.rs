@@ -54,4 +54,42 @@ mod controller_unit_tests {
         // Info should requeue slowly
         assert_eq!(calculate_requeue_duration(&Severity::Info), 600);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_retention_step() {
+        use kernel_gossip_operator::crd::{retention_step, RetentionStep};
+        use std::time::Duration;
+
+        let now = chrono::Utc::now();
+        let ttl = Duration::from_secs(3600);
+
+        // A live Pod keeps the record active whatever was noted before
+        assert_eq!(retention_step(true, None, now, ttl), RetentionStep::Active);
+        assert_eq!(retention_step(true, Some(now - chrono::Duration::hours(5)), now, ttl), RetentionStep::Active);
+
+        // First sight of a missing Pod starts the clock
+        assert_eq!(retention_step(false, None, now, ttl), RetentionStep::MarkGone);
+
+        assert_eq!(
+            retention_step(false, Some(now - chrono::Duration::minutes(20)), now, ttl),
+            RetentionStep::Wait(Duration::from_secs(40 * 60))
+        );
+        assert_eq!(retention_step(false, Some(now - chrono::Duration::hours(1)), now, ttl), RetentionStep::Expire);
+        assert_eq!(retention_step(false, Some(now - chrono::Duration::days(2)), now, ttl), RetentionStep::Expire);
+    }
+
+    #[test]
+    fn test_orphans_go_after_the_grace_period() {
+        use kernel_gossip_operator::crd::{retention_step, RetentionStep, ORPHAN_GRACE};
+
+        // A record with no Pod to own it, when records otherwise go with their Pod
+        let now = chrono::Utc::now();
+        assert_eq!(retention_step(false, None, now, ORPHAN_GRACE), RetentionStep::MarkGone);
+        assert!(matches!(
+            retention_step(false, Some(now - chrono::Duration::seconds(30)), now, ORPHAN_GRACE),
+            RetentionStep::Wait(_)
+        ));
+        let past_grace = now - chrono::Duration::from_std(ORPHAN_GRACE).unwrap() - chrono::Duration::seconds(1);
+        assert_eq!(retention_step(false, Some(past_grace), now, ORPHAN_GRACE), RetentionStep::Expire);
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod metrics_tests {
+    use chrono::{TimeZone, Utc};
+    use kernel_gossip_operator::metrics::OperatorMetrics;
+    use kernel_gossip_operator::webhook::EventStatus;
+    use kernel_gossip_types::KernelWhisper;
+
+    fn has_line(rendered: &str, line: &str) -> bool {
+        rendered.lines().any(|l| l == line)
+    }
+
+    #[test]
+    fn test_webhook_outcomes_and_detection_latency() {
+        let metrics = OperatorMetrics::new().unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 2).unwrap();
+
+        metrics.record_event_outcome("cpu_throttle", "2024-01-01T00:00:00Z", EventStatus::Accepted, now);
+        metrics.record_event_outcome("cpu_throttle", "2024-01-01T00:00:00Z", EventStatus::Skipped, now);
+        metrics.record_rejected_request("unauthorized");
+
+        let rendered = metrics.render();
+        assert!(has_line(&rendered, r#"kernel_gossip_webhook_events_total{event_type="cpu_throttle",outcome="accepted"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_gossip_webhook_events_total{event_type="cpu_throttle",outcome="skipped"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_gossip_webhook_events_total{event_type="unknown",outcome="unauthorized"} 1"#));
+        // Only the recorded event counts towards latency, and it took two seconds
+        assert!(has_line(&rendered, r#"kernel_gossip_detection_to_record_seconds_count{event_type="cpu_throttle"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_gossip_detection_to_record_seconds_sum{event_type="cpu_throttle"} 2"#));
+    }
+
+    #[test]
+    fn test_crd_writes_and_reconcile_durations() {
+        let metrics = OperatorMetrics::new().unwrap();
+
+        let written = metrics.record_crd_write("KernelWhisper", "create", Ok::<_, kube::Error>(42));
+        assert_eq!(written.unwrap(), 42);
+        drop(metrics.reconcile_timer("kernel_whisper"));
+
+        let rendered = metrics.render();
+        assert!(has_line(&rendered, r#"kernel_gossip_crd_writes_total{kind="KernelWhisper",operation="create",result="success"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_gossip_reconcile_duration_seconds_count{controller="kernel_whisper"} 1"#));
+    }
+
+    #[test]
+    fn test_kernel_whisper_gauges_follow_current_records() {
+        let metrics = OperatorMetrics::new().unwrap();
+        let critical = KernelWhisper::create("hot-pod", "default", 90.0, 30.0);
+        let info = KernelWhisper::create("calm-pod", "default", 10.0, 8.0);
+
+        metrics.refresh_kernel_whispers([&critical, &info]);
+        let rendered = metrics.render();
+        assert!(has_line(&rendered, r#"kernel_gossip_kernel_whispers{severity="critical"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_gossip_kernel_whispers{severity="warning"} 0"#));
+        assert!(has_line(&rendered, r#"kernel_gossip_pod_cpu_throttled_percent{namespace="default",pod="hot-pod"} 90"#));
+        assert!(has_line(&rendered, r#"kernel_gossip_pod_cpu_discrepancy_percent{namespace="default",pod="hot-pod"} 60"#));
+
+        // The calm pod's record was deleted
+        metrics.refresh_kernel_whispers([&critical]);
+        let rendered = metrics.render();
+        assert!(has_line(&rendered, r#"kernel_gossip_kernel_whispers{severity="info"} 0"#));
+        assert!(!rendered.contains(r#"pod="calm-pod""#));
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod pod_metrics_tests {
+    use kernel_gossip_operator::pod_metrics::{
+        parse_cpu_quantity, parse_memory_quantity, MetricsError, PodMetrics, PodUsage,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_cpu_quantity_formats() {
+        assert_eq!(parse_cpu_quantity("250000000n"), Some(0.25));
+        assert_eq!(parse_cpu_quantity("250000u"), Some(0.25));
+        assert_eq!(parse_cpu_quantity("250m"), Some(0.25));
+        assert_eq!(parse_cpu_quantity("0.25"), Some(0.25));
+        assert_eq!(parse_cpu_quantity("2"), Some(2.0));
+        assert_eq!(parse_cpu_quantity("0"), Some(0.0));
+    }
+
+    #[test]
+    fn test_parse_cpu_quantity_rejects_garbage() {
+        assert_eq!(parse_cpu_quantity(""), None);
+        assert_eq!(parse_cpu_quantity("lots"), None);
+        assert_eq!(parse_cpu_quantity("-1"), None);
+        assert_eq!(parse_cpu_quantity("12x"), None);
+    }
+
+    #[test]
+    fn test_parse_memory_quantity_formats() {
+        assert_eq!(parse_memory_quantity("128Mi"), Some(134_217_728.0));
+        assert_eq!(parse_memory_quantity("1Gi"), Some(1_073_741_824.0));
+        assert_eq!(parse_memory_quantity("1500Ki"), Some(1_536_000.0));
+        assert_eq!(parse_memory_quantity("500M"), Some(500_000_000.0));
+        assert_eq!(parse_memory_quantity("1024"), Some(1024.0));
+        assert_eq!(parse_memory_quantity("many"), None);
+    }
+
+    fn metrics(containers: serde_json::Value) -> PodMetrics {
+        serde_json::from_value(json!({
+            "kind": "PodMetrics",
+            "apiVersion": "metrics.k8s.io/v1beta1",
+            "metadata": { "name": "web-0", "namespace": "default" },
+            "timestamp": "2024-01-01T00:00:00Z",
+            "window": "15s",
+            "containers": containers
+        }))
+        .expect("valid PodMetrics")
+    }
+
+    #[test]
+    fn test_pod_usage_reports_each_container() {
+        let metrics = metrics(json!([
+            { "name": "app", "usage": { "cpu": "350000000n", "memory": "200Mi" } },
+            { "name": "istio-proxy", "usage": { "cpu": "50m", "memory": "64Mi" } }
+        ]));
+
+        let usage = PodUsage::from_metrics(&metrics).expect("valid usage");
+
+        assert_eq!(usage.containers.len(), 2);
+        assert_eq!(usage.containers[0].name, "app");
+        assert_eq!(usage.containers[0].cpu_cores, 0.35);
+        assert_eq!(usage.containers[1].name, "istio-proxy");
+        assert_eq!(usage.containers[1].memory_bytes, 64 * 1024 * 1024);
+        assert_eq!(usage.cpu_percent(), 40.0);
+        assert_eq!(usage.memory_bytes(), 264 * 1024 * 1024);
+        assert_eq!(usage.window.as_deref(), Some("15s"));
+    }
+
+    #[test]
+    fn test_pod_usage_rejects_invalid_quantity() {
+        let metrics = metrics(json!([
+            { "name": "app", "usage": { "cpu": "fast", "memory": "1Mi" } }
+        ]));
+
+        match PodUsage::from_metrics(&metrics) {
+            Err(MetricsError::InvalidQuantity { container, resource, value }) => {
+                assert_eq!(container, "app");
+                assert_eq!(resource, "cpu");
+                assert_eq!(value, "fast");
+            }
+            other => panic!("Expected InvalidQuantity, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod pod_watcher_tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::api::core::v1::{ContainerStatus, Pod, PodSpec, PodStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    use kernel_gossip_operator::pod_watcher::{monitored_pod, MonitoredPodSet, PublishSchedule};
+    use kernel_gossip_operator::policy::Policies;
+    use kernel_gossip_protocol::monitored::{decode_node_pods, node_key, COUNT_KEY};
+
+    fn container(name: &str, id: &str) -> ContainerStatus {
+        ContainerStatus {
+            name: name.to_string(),
+            container_id: Some(id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn pod(namespace: &str, name: &str, node: Option<&str>, annotated: bool) -> Pod {
+        let annotations: BTreeMap<String, String> = if annotated {
+            [("kernel-gossip.io/monitor".to_string(), "true".to_string())].into()
+        } else {
+            BTreeMap::new()
+        };
+        Pod {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                uid: Some(format!("uid-{name}")),
+                annotations: Some(annotations),
+                ..Default::default()
+            },
+            spec: Some(PodSpec {
+                node_name: node.map(str::to_string),
+                ..Default::default()
+            }),
+            status: Some(PodStatus {
+                phase: Some("Running".to_string()),
+                container_statuses: Some(vec![container("app", "containerd://bbb")]),
+                init_container_statuses: Some(vec![container("init", "containerd://aaa")]),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_selection_follows_policy_in_any_namespace() {
+        let policies = Policies::from_objects(vec![], vec![]);
+
+        let entry = monitored_pod(&pod("shop", "cart", Some("node-a"), true), &policies).unwrap();
+        assert_eq!(entry.uid, "uid-cart");
+        assert_eq!(entry.node, "node-a");
+        assert_eq!(entry.container_ids, vec!["containerd://aaa", "containerd://bbb"]);
+
+        assert!(monitored_pod(&pod("shop", "cart", Some("node-a"), false), &policies).is_none());
+        assert!(monitored_pod(&pod("kube-system", "dns", Some("node-a"), true), &policies).is_none());
+        // Not scheduled yet
+        assert!(monitored_pod(&pod("shop", "cart", None, true), &policies).is_none());
+
+        let mut finished = pod("shop", "job", Some("node-a"), true);
+        finished.status.as_mut().unwrap().phase = Some("Succeeded".to_string());
+        assert!(monitored_pod(&finished, &policies).is_none());
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        let policies = Policies::from_objects(vec![], vec![]);
+        let mut set = MonitoredPodSet::default();
+
+        let cart = pod("shop", "cart", Some("node-a"), true);
+        assert!(set.apply(&cart, &policies));
+        // An unchanged pod does not need publishing again
+        assert!(!set.apply(&cart, &policies));
+
+        let mut restarted = cart.clone();
+        restarted.status.as_mut().unwrap().container_statuses = Some(vec![container("app", "containerd://ccc")]);
+        assert!(set.apply(&restarted, &policies));
+
+        // Dropping the annotation removes the pod
+        assert!(set.apply(&pod("shop", "cart", Some("node-a"), false), &policies));
+        assert!(set.is_empty());
+
+        assert!(set.apply(&cart, &policies));
+        assert!(set.remove(&cart));
+        assert!(!set.remove(&cart));
+    }
+
+    #[test]
+    fn test_configmap_data_per_node() {
+        let policies = Policies::from_objects(vec![], vec![]);
+        let mut set = MonitoredPodSet::default();
+        let pods = vec![
+            pod("shop", "cart", Some("node-a"), true),
+            pod("shop", "checkout", Some("node-b"), true),
+            pod("blog", "web", Some("node-a"), true),
+            pod("blog", "static", Some("node-b"), false),
+        ];
+        assert!(set.rebuild(&pods, &policies));
+        assert!(!set.rebuild(&pods, &policies));
+        assert_eq!(set.len(), 3);
+
+        let data = set.to_configmap_data("2024-01-01T00:00:00Z");
+        assert_eq!(data[COUNT_KEY], "3");
+
+        let node_a = decode_node_pods(&data[&node_key("node-a")]).unwrap();
+        let names: Vec<_> = node_a.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["cart", "web"]);
+        assert_eq!(decode_node_pods(&data[&node_key("node-b")]).unwrap().len(), 1);
+
+        // A node whose pods are all gone loses its key
+        set.rebuild(&pods[..1], &policies);
+        assert!(!set.to_configmap_data("now").contains_key(&node_key("node-b")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_publish_waits_an_interval_after_the_first_change() {
+        let period = std::time::Duration::from_secs(2);
+        let mut schedule = PublishSchedule::new(period);
+        assert!(!schedule.is_dirty());
+
+        // Quiet for a long while, then a burst of changes
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        let started = tokio::time::Instant::now();
+        schedule.changed(true);
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        schedule.changed(true);
+        schedule.changed(false);
+        assert!(schedule.is_dirty());
+
+        // Due an interval after the first of them, not at once
+        schedule.due().await;
+        assert_eq!(started.elapsed(), period);
+        schedule.published();
+        assert!(!schedule.is_dirty());
+
+        // Unchanged sets do not restart the wait
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        schedule.changed(false);
+        assert!(!schedule.is_dirty());
+        let started = tokio::time::Instant::now();
+        schedule.changed(true);
+        schedule.due().await;
+        assert_eq!(started.elapsed(), period);
+    }
+}
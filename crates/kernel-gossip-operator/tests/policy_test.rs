@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod policy_tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::api::core::v1::{Namespace, Pod};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, LabelSelectorRequirement, ObjectMeta};
+    use kernel_gossip_operator::policy::{selector_matches, Policies};
+    use kernel_gossip_types::{KernelGossipPolicy, KernelGossipPolicySpec, Severity};
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn pod(name: &str, pod_labels: &[(&str, &str)], annotations: &[(&str, &str)]) -> Pod {
+        Pod {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                labels: Some(labels(pod_labels)),
+                annotations: Some(labels(annotations)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn namespace(name: &str, ns_labels: &[(&str, &str)]) -> Namespace {
+        Namespace {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                labels: Some(labels(ns_labels)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn policy(name: &str, priority: i32, spec: KernelGossipPolicySpec) -> KernelGossipPolicy {
+        KernelGossipPolicy::new(name, KernelGossipPolicySpec { priority, ..spec })
+    }
+
+    fn open_spec() -> KernelGossipPolicySpec {
+        KernelGossipPolicySpec {
+            require_monitor_annotation: false,
+            excluded_namespaces: vec![],
+            ..KernelGossipPolicySpec::builtin()
+        }
+    }
+
+    #[test]
+    fn test_builtin_policy_without_any_in_cluster() {
+        let policies = Policies::from_objects(vec![], vec![]);
+
+        let annotated = pod("web", &[], &[("kernel-gossip.io/monitor", "true")]);
+        assert_eq!(policies.evaluate("default", "web", Some(&annotated)).unwrap().name, "builtin");
+
+        let plain = pod("web", &[], &[]);
+        let reason = policies.evaluate("default", "web", Some(&plain)).unwrap_err();
+        assert!(reason.contains("not configured for monitoring"));
+
+        // Creation events may arrive before the Pod; only the namespace counts then
+        assert!(policies.evaluate("default", "web", None).is_ok());
+        assert!(policies.evaluate("kube-system", "coredns", None).unwrap_err().contains("excluded"));
+    }
+
+    #[test]
+    fn test_highest_priority_matching_policy_applies() {
+        let mut strict = open_spec();
+        strict.namespace_selector = Some(LabelSelector {
+            match_labels: Some(labels(&[("tier", "prod")])),
+            ..Default::default()
+        });
+        strict.thresholds.cpu_throttled_percent.critical = Some(20.0);
+
+        let policies = Policies::from_objects(
+            vec![policy("everything", 0, open_spec()), policy("prod", 10, strict)],
+            vec![namespace("shop", &[("tier", "prod")]), namespace("sandbox", &[])],
+        );
+
+        let applied = policies.evaluate("shop", "cart", Some(&pod("cart", &[], &[]))).unwrap();
+        assert_eq!(applied.name, "prod");
+        assert_eq!(policies.thresholds("shop", "cart", None).cpu_severity(25.0), Severity::Critical);
+
+        // The prod policy does not select the sandbox, the catch-all does
+        assert_eq!(policies.evaluate("sandbox", "toy", None).unwrap().name, "everything");
+        assert_eq!(policies.thresholds("sandbox", "toy", None).cpu_severity(25.0), Severity::Info);
+    }
+
+    #[test]
+    fn test_pod_selector_exclusions_and_sampling() {
+        let mut selective = open_spec();
+        selective.pod_selector = Some(LabelSelector {
+            match_labels: Some(labels(&[("app", "api")])),
+            ..Default::default()
+        });
+        selective.excluded_namespaces = vec!["batch".to_string()];
+        let policies = Policies::from_objects(vec![policy("api", 0, selective)], vec![]);
+
+        assert!(policies.evaluate("default", "api-1", Some(&pod("api-1", &[("app", "api")], &[]))).is_ok());
+        assert!(policies.evaluate("default", "db-1", Some(&pod("db-1", &[("app", "db")], &[]))).is_err());
+        assert!(policies.evaluate("batch", "api-1", Some(&pod("api-1", &[("app", "api")], &[]))).is_err());
+
+        let never = KernelGossipPolicySpec { sampling_rate: 0.0, ..open_spec() };
+        let policies = Policies::from_objects(vec![policy("off", 0, never)], vec![]);
+        assert!(policies.evaluate("default", "api-1", None).unwrap_err().contains("sampling"));
+    }
+
+    #[test]
+    fn test_selector_expressions() {
+        let selector = LabelSelector {
+            match_labels: None,
+            match_expressions: Some(vec![
+                LabelSelectorRequirement {
+                    key: "env".to_string(),
+                    operator: "In".to_string(),
+                    values: Some(vec!["prod".to_string(), "staging".to_string()]),
+                },
+                LabelSelectorRequirement {
+                    key: "kernel-gossip.io/skip".to_string(),
+                    operator: "DoesNotExist".to_string(),
+                    values: None,
+                },
+            ]),
+        };
+
+        assert!(selector_matches(&selector, &labels(&[("env", "prod")])));
+        assert!(!selector_matches(&selector, &labels(&[("env", "dev")])));
+        assert!(!selector_matches(&selector, &labels(&[("env", "prod"), ("kernel-gossip.io/skip", "")])));
+        assert!(selector_matches(&LabelSelector::default(), &BTreeMap::new()));
+    }
+}
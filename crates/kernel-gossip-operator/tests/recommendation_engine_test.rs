@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod recommendation_engine_tests {
     use kernel_gossip_operator::recommendation::{RecommendationEngine, Recommendation};
-    use kernel_gossip_types::KernelWhisper;
+    use kernel_gossip_types::{KernelWhisper, SeverityThresholds, SignalThresholds};
 
     #[test]
     fn test_cpu_throttle_high_recommendation() {
@@ -59,6 +59,37 @@ mod recommendation_engine_tests {
         assert!(recommendation.kernel_evidence.contains("throttled"));
     }
 
+    #[test]
+    fn test_recommendation_cites_cpu_stat_periods() {
+        let engine = RecommendationEngine::new();
+        
+        let mut kw = KernelWhisper::create("cfs-pod", "default", 90.0, 20.0);
+        kw.spec.kernel_truth.total_periods = 100;
+        kw.spec.kernel_truth.throttled_periods = 90;
+        kw.spec.kernel_truth.throttled_time_ms = 4500;
+        kw.spec.kernel_truth.window_seconds = 10;
+        
+        let recommendation = engine.analyze_kernel_whisper(&kw).unwrap();
+        
+        assert!(recommendation.kernel_evidence.contains("90/100 periods"));
+        assert!(recommendation.kernel_evidence.contains("4500ms throttled over 10s"));
+    }
+
+    #[test]
+    fn test_policy_thresholds_change_priority() {
+        let thresholds = SignalThresholds {
+            cpu_throttled_percent: SeverityThresholds { critical: Some(30.0), warning: None, info: Some(10.0) },
+            ..Default::default()
+        };
+        let engine = RecommendationEngine::with_thresholds(thresholds);
+
+        let kw = KernelWhisper::create("latency-sensitive", "default", 35.0, 20.0);
+        assert_eq!(engine.analyze_kernel_whisper(&kw).unwrap().priority, "high");
+
+        let kw = KernelWhisper::create("latency-sensitive", "default", 5.0, 20.0);
+        assert!(engine.analyze_kernel_whisper(&kw).is_none());
+    }
+
     #[test]
     fn test_recommendation_struct_creation() {
         let rec = Recommendation {
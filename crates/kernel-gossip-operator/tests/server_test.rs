@@ -81,7 +81,8 @@ mod server_tests {
         std::env::set_var("WEBHOOK_PORT", "8083");
 
         // Test that we can create the server
-        let server = kernel_gossip_operator::server::create_server().await;
+        let policies = kernel_gossip_operator::policy::Policies::from_objects(vec![], vec![]);
+        let server = kernel_gossip_operator::server::create_server(policies).await;
         assert!(server.is_ok());
     }
 }
\ No newline at end of file
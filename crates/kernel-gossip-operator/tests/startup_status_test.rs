@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod startup_status_tests {
+    use chrono::{DateTime, Utc};
+    use k8s_openapi::api::core::v1::Pod;
+    use kernel_gossip_operator::startup::{startup_phases, startup_status, status_changed};
+    use kernel_gossip_types::{
+        Actor, PodBirthCertificate, PodBirthCertificateSpec, SignalThresholds, StartupHealth, StartupPhase,
+        TimelineEntry,
+    };
+    use serde_json::json;
+
+    /// 2024-01-01T00:00:00Z
+    const T0: u64 = 1_704_067_200_000;
+
+    fn at(offset_ms: u64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis((T0 + offset_ms) as i64).unwrap()
+    }
+
+    fn entry(offset_ms: u64, actor: Actor, action: &str) -> TimelineEntry {
+        TimelineEntry::new(T0 + offset_ms, actor, action)
+    }
+
+    /// Scheduled after 1s, sandbox after 2s more, a 35s image pull, then the
+    /// kernel's 123ms and 1.5s until the readiness probe passed
+    fn spec(ready: bool) -> PodBirthCertificateSpec {
+        let mut pbc = PodBirthCertificate::create("web-1", "shop");
+        pbc.spec.kernel_stats.total_duration_ms = 123;
+        pbc.spec.timeline = vec![
+            entry(0, Actor::Scheduler, "Pod created"),
+            entry(1_000, Actor::Scheduler, "Pod scheduled"),
+            entry(1_250, Actor::Scheduler, "Scheduled: Successfully assigned shop/web-1 to node-a"),
+            entry(3_000, Actor::Runtime, "Pod sandbox ready"),
+            entry(3_000, Actor::Kubelet, "Pulling: Pulling image \"nginx\""),
+            entry(38_000, Actor::Kubelet, "Pulled: Successfully pulled image \"nginx\" in 35s"),
+            entry(38_500, Actor::Kernel, "runc started"),
+            entry(38_623, Actor::Kernel, "exec nginx"),
+            entry(39_000, Actor::Runtime, "Container nginx started"),
+        ];
+        if ready {
+            pbc.spec.timeline.push(entry(40_500, Actor::Kubelet, "Pod ready"));
+        }
+        pbc.spec
+    }
+
+    fn waiting_pod(reason: &str) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": "web-1", "namespace": "shop", "creationTimestamp": "2024-01-01T00:00:00Z" },
+            "status": {
+                "phase": "Pending",
+                "containerStatuses": [{
+                    "name": "nginx",
+                    "image": "nginx:typo",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": 0,
+                    "state": { "waiting": { "reason": reason } }
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_phase_durations() {
+        let phases = startup_phases(&spec(true));
+        assert_eq!(phases.scheduling_ms, Some(1_000));
+        assert_eq!(phases.sandbox_creation_ms, Some(2_000));
+        assert_eq!(phases.image_pull_ms, Some(35_000));
+        assert_eq!(phases.kernel_setup_ms, Some(123));
+        assert_eq!(phases.process_start_to_ready_ms, Some(1_500));
+
+        // Not Ready yet: the last phase has no end
+        assert_eq!(startup_phases(&spec(false)).process_start_to_ready_ms, None);
+
+        // An image already on the node takes no pulling
+        let mut cached = spec(true);
+        cached.timeline.retain(|entry| !entry.action.starts_with("Pulling"));
+        assert_eq!(startup_phases(&cached).image_pull_ms, Some(0));
+    }
+
+    #[test]
+    fn test_slow_start_is_blamed_on_its_dominant_phase() {
+        let status = startup_status(&spec(true), None, &SignalThresholds::default(), at(60_000));
+        assert_eq!(status.total_ms, Some(40_500));
+        assert_eq!(status.dominant_phase, Some(StartupPhase::ImagePull));
+        assert_eq!(status.health, StartupHealth::Slow);
+        assert_eq!(status.message, "Ready in 40.5s, mostly image pull (35.0s)");
+
+        let mut thresholds = SignalThresholds::default();
+        thresholds.startup_seconds.warning = Some(60.0);
+        let status = startup_status(&spec(true), None, &thresholds, at(60_000));
+        assert_eq!(status.health, StartupHealth::Healthy);
+    }
+
+    #[test]
+    fn test_pods_not_ready_yet() {
+        let thresholds = SignalThresholds::default();
+
+        let starting = startup_status(&spec(false), None, &thresholds, at(45_000));
+        assert_eq!(starting.health, StartupHealth::Starting);
+        assert_eq!(starting.total_ms, None);
+        assert_eq!(starting.message, "Not Ready yet, so far mostly image pull (35.0s)");
+
+        let stuck = startup_status(&spec(false), None, &thresholds, at(300_000));
+        assert_eq!(stuck.health, StartupHealth::Stuck);
+
+        let mut pulling = PodBirthCertificate::create("web-1", "shop").spec;
+        pulling.timeline = vec![entry(0, Actor::Scheduler, "Pod created")];
+        let failing = startup_status(&pulling, Some(&waiting_pod("ImagePullBackOff")), &thresholds, at(10_000));
+        assert_eq!(failing.health, StartupHealth::Failing);
+        assert_eq!(failing.message, "Not Ready: container nginx is ImagePullBackOff");
+        assert_eq!(failing.dominant_phase, None);
+        // Waiting for a pull that is still going is not failing
+        let waiting = startup_status(&pulling, Some(&waiting_pod("ContainerCreating")), &thresholds, at(10_000));
+        assert_eq!(waiting.health, StartupHealth::Starting);
+    }
+
+    #[test]
+    fn test_status_is_rewritten_only_on_change() {
+        let thresholds = SignalThresholds::default();
+        let first = startup_status(&spec(false), None, &thresholds, at(10_000));
+        let later = startup_status(&spec(false), None, &thresholds, at(20_000));
+        assert!(status_changed(None, &first));
+        assert!(!status_changed(Some(&first), &later));
+
+        let ready = startup_status(&spec(true), None, &thresholds, at(50_000));
+        assert!(status_changed(Some(&first), &ready));
+    }
+}
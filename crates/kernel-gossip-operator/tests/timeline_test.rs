@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod timeline_tests {
+    use k8s_openapi::api::core::v1::{Event, Pod};
+    use kernel_gossip_operator::actions::build_pod_birth_certificate;
+    use kernel_gossip_operator::timeline::{
+        align_kernel_entries, cap_timeline, control_plane_entries, merge_timeline, MAX_TIMELINE_ENTRIES,
+    };
+    use kernel_gossip_operator::webhook::{PodCreationPayload, TimelineEvent};
+    use kernel_gossip_types::{Actor, TimelineEntry};
+    use serde_json::json;
+
+    /// 2024-01-01T00:00:00Z
+    const T0: u64 = 1_704_067_200_000;
+
+    fn pod() -> Pod {
+        serde_json::from_value(json!({
+            "metadata": {
+                "name": "web-1",
+                "namespace": "shop",
+                "uid": "uid-web-1",
+                "creationTimestamp": "2024-01-01T00:00:00Z"
+            },
+            "status": {
+                "conditions": [
+                    { "type": "PodScheduled", "status": "True", "lastTransitionTime": "2024-01-01T00:00:01Z" },
+                    { "type": "Initialized", "status": "True", "lastTransitionTime": "2024-01-01T00:00:01Z" },
+                    { "type": "PodReadyToStartContainers", "status": "True", "lastTransitionTime": "2024-01-01T00:00:03Z" },
+                    { "type": "ContainersReady", "status": "False", "lastTransitionTime": "2024-01-01T00:00:01Z" }
+                ],
+                "containerStatuses": [{
+                    "name": "nginx",
+                    "image": "nginx",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": 0,
+                    "state": { "running": { "startedAt": "2024-01-01T00:00:08Z" } }
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    fn event(reason: &str, message: &str, component: &str, first: &str) -> Event {
+        serde_json::from_value(json!({
+            "metadata": { "name": format!("web-1.{reason}"), "namespace": "shop" },
+            "involvedObject": { "kind": "Pod", "name": "web-1", "namespace": "shop" },
+            "reason": reason,
+            "message": message,
+            "source": { "component": component },
+            "firstTimestamp": first,
+            "count": 1
+        }))
+        .unwrap()
+    }
+
+    fn events() -> Vec<Event> {
+        let mut scheduled = event("Scheduled", "Successfully assigned shop/web-1 to node-a", "default-scheduler", "2024-01-01T00:00:01Z");
+        scheduled.event_time = serde_json::from_value(json!("2024-01-01T00:00:01.250000Z")).unwrap();
+        vec![
+            scheduled,
+            event("Pulling", "Pulling image \"nginx\"", "kubelet", "2024-01-01T00:00:03Z"),
+            event("Pulled", "Successfully pulled image \"nginx\" in 3.9s", "kubelet", "2024-01-01T00:00:07Z"),
+            event("Created", "Created container nginx", "kubelet", "2024-01-01T00:00:07Z"),
+            event("Started", "Started container nginx", "kubelet", "2024-01-01T00:00:08Z"),
+        ]
+    }
+
+    fn kernel(at: u64, action: &str) -> TimelineEntry {
+        TimelineEntry::new(at, Actor::Kernel, action)
+    }
+
+    fn position(timeline: &[TimelineEntry], action: &str) -> usize {
+        timeline.iter().position(|entry| entry.action.starts_with(action)).unwrap_or_else(|| panic!("no {action} entry"))
+    }
+
+    #[test]
+    fn test_control_plane_entries() {
+        let entries = control_plane_entries(&pod(), &events());
+
+        let created = &entries[0];
+        assert_eq!((created.timestamp_ms, &created.actor, created.action.as_str()), (T0, &Actor::Scheduler, "Pod created"));
+        let sandbox = entries.iter().find(|entry| entry.action == "Pod sandbox ready").unwrap();
+        assert_eq!((sandbox.timestamp_ms, &sandbox.actor), (T0 + 3_000, &Actor::Runtime));
+        let started = entries.iter().find(|entry| entry.action == "Container nginx started").unwrap();
+        assert_eq!((started.timestamp_ms, &started.actor), (T0 + 8_000, &Actor::Runtime));
+        let scheduled = entries.iter().find(|entry| entry.action.starts_with("Scheduled: ")).unwrap();
+        assert_eq!((scheduled.timestamp_ms, &scheduled.actor), (T0 + 1_250, &Actor::Scheduler));
+        let pulled = entries.iter().find(|entry| entry.action.starts_with("Pulled: ")).unwrap();
+        assert_eq!((pulled.timestamp_ms, &pulled.actor), (T0 + 7_000, &Actor::Kubelet));
+
+        // Conditions that are not true yet have no place on the timeline
+        assert!(!entries.iter().any(|entry| entry.action == "Containers ready"));
+    }
+
+    #[test]
+    fn test_kernel_steps_land_between_kubelet_events() {
+        let recorded = vec![kernel(T0 + 7_500, "runc started"), kernel(T0 + 7_623, "exec nginx")];
+        let (timeline, offset) = merge_timeline(&recorded, None, control_plane_entries(&pod(), &events()));
+
+        // A second of rounding in the kubelet's timestamps is not skew
+        assert_eq!(offset, None);
+        assert!(timeline.windows(2).all(|pair| pair[0].timestamp_ms <= pair[1].timestamp_ms));
+        assert!(position(&timeline, "Pulled") < position(&timeline, "runc started"));
+        assert!(position(&timeline, "exec nginx") < position(&timeline, "Started"));
+        assert_eq!(timeline.iter().filter(|entry| entry.actor == Actor::Kernel).count(), 2);
+    }
+
+    #[test]
+    fn test_slow_node_clock_is_aligned_once() {
+        let control_plane = || {
+            vec![
+                TimelineEntry::new(T0, Actor::Scheduler, "Pod created"),
+                TimelineEntry::new(T0 + 10_000, Actor::Scheduler, "Pod scheduled"),
+                TimelineEntry::new(T0 + 6_000, Actor::Kubelet, "Pulling: Pulling image \"nginx\""),
+            ]
+        };
+        let recorded = vec![kernel(T0 + 7_000, "exec nginx")];
+
+        let (timeline, offset) = merge_timeline(&recorded, None, control_plane());
+        assert_eq!(offset, Some(4_000));
+        assert_eq!(timeline[position(&timeline, "Pulling")].timestamp_ms, T0 + 10_000);
+        assert_eq!(timeline[position(&timeline, "exec nginx")].timestamp_ms, T0 + 11_000);
+
+        // Reconciling again changes nothing
+        let (again, again_offset) = merge_timeline(&timeline, offset, control_plane());
+        assert_eq!((&again, again_offset), (&timeline, offset));
+
+        // Kernel entries reported later join on the same clock, and expired
+        // Events stay on the timeline
+        let mut late = vec![kernel(T0 + 7_100, "exec sidecar")];
+        align_kernel_entries(&mut late, offset);
+        let mut recorded = timeline.clone();
+        recorded.extend(late);
+        let (timeline, offset) = merge_timeline(&recorded, offset, control_plane()[..2].to_vec());
+        assert_eq!(offset, Some(4_000));
+        assert_eq!(timeline[position(&timeline, "exec sidecar")].timestamp_ms, T0 + 11_100);
+        assert_eq!(timeline[position(&timeline, "Pulling")].timestamp_ms, T0 + 10_000);
+    }
+
+    #[test]
+    fn test_kernel_steps_are_placed_from_the_exec() {
+        let payload = PodCreationPayload {
+            timestamp: "2024-01-01T00:00:07.623Z".to_string(),
+            pod_name: "web-1".to_string(),
+            namespace: "shop".to_string(),
+            total_syscalls: 512,
+            namespace_ops: 3,
+            cgroup_writes: 1,
+            mount_ops: 6,
+            duration_ns: 123_000_000,
+            timeline: vec![
+                TimelineEvent { timestamp_ms: 0, action: "runc started".to_string() },
+                TimelineEvent { timestamp_ms: 123, action: "exec nginx".to_string() },
+            ],
+            ebpf_detection: true,
+        };
+
+        let pbc = build_pod_birth_certificate(&payload);
+        let times: Vec<_> = pbc.spec.timeline.iter().map(|entry| entry.timestamp_ms).collect();
+        assert_eq!(times, [T0 + 7_500, T0 + 7_623]);
+    }
+
+    #[test]
+    fn test_timeline_is_capped_by_dropping_the_latest_kernel_steps() {
+        let mut timeline = vec![TimelineEntry::new(T0, Actor::Scheduler, "Pod created")];
+        timeline.extend((1..=MAX_TIMELINE_ENTRIES as u64).map(|i| kernel(T0 + i, "fork")));
+        timeline.push(TimelineEntry::new(T0 + 10_000, Actor::Kubelet, "Pod ready"));
+        timeline.push(kernel(T0 + 20_000, "exec nginx"));
+
+        cap_timeline(&mut timeline);
+        assert_eq!(timeline.len(), MAX_TIMELINE_ENTRIES);
+        assert_eq!(timeline[0].action, "Pod created");
+        assert_eq!(timeline.last().unwrap().action, "Pod ready");
+        assert_eq!(timeline[MAX_TIMELINE_ENTRIES - 2].timestamp_ms, T0 + MAX_TIMELINE_ENTRIES as u64 - 2);
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod webhook_auth_tests {
+    use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+    use axum::{routing::post, Router};
+    use axum_test::TestServer;
+    use kernel_gossip_operator::webhook::auth::authenticated;
+    use kernel_gossip_operator::webhook::{AuthError, WebhookAuthenticator};
+    use kernel_gossip_protocol::auth::{self, WebhookCredentials};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const KEY: &[u8] = b"test-hmac-key";
+    const BODY: &[u8] = br#"{"schema_version":1,"type":"cpu_throttle"}"#;
+    const NOW: i64 = 1_700_000_000;
+
+    fn authenticator(token: Option<&str>, hmac_key: Option<&[u8]>) -> WebhookAuthenticator {
+        let credentials = WebhookCredentials {
+            token: token.map(str::to_string),
+            hmac_key: hmac_key.map(<[u8]>::to_vec),
+        };
+        WebhookAuthenticator::new(credentials, Duration::from_secs(300))
+    }
+
+    fn signed_headers(timestamp: i64, body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(auth::TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers.insert(auth::SIGNATURE_HEADER, auth::sign(KEY, timestamp, body).parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let authenticator = authenticator(Some("s3cret"), None);
+        let mut headers = HeaderMap::new();
+
+        assert_eq!(authenticator.authenticate(&headers, BODY, NOW), Err(AuthError::MissingCredentials));
+
+        headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert_eq!(authenticator.authenticate(&headers, BODY, NOW), Err(AuthError::InvalidToken));
+
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert_eq!(authenticator.authenticate(&headers, BODY, NOW), Ok(()));
+    }
+
+    #[test]
+    fn test_hmac_signature() {
+        let authenticator = authenticator(None, Some(KEY));
+
+        assert_eq!(authenticator.authenticate(&signed_headers(NOW - 5, BODY), BODY, NOW), Ok(()));
+
+        // Signed for a different body
+        let tampered = signed_headers(NOW, b"{}");
+        assert_eq!(authenticator.authenticate(&tampered, BODY, NOW), Err(AuthError::InvalidSignature));
+
+        // A token is useless when only an HMAC key is configured
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert_eq!(authenticator.authenticate(&headers, BODY, NOW), Err(AuthError::MissingCredentials));
+    }
+
+    #[test]
+    fn test_stale_and_replayed_signatures_rejected() {
+        let authenticator = authenticator(None, Some(KEY));
+
+        let old = signed_headers(NOW - 301, BODY);
+        assert_eq!(authenticator.authenticate(&old, BODY, NOW), Err(AuthError::StaleTimestamp(301)));
+
+        let fresh = signed_headers(NOW, BODY);
+        assert_eq!(authenticator.authenticate(&fresh, BODY, NOW), Ok(()));
+        assert_eq!(authenticator.authenticate(&fresh, BODY, NOW + 1), Err(AuthError::Replayed));
+
+        let mut unsigned_time = fresh.clone();
+        unsigned_time.remove(auth::TIMESTAMP_HEADER);
+        assert_eq!(
+            authenticator.authenticate(&unsigned_time, BODY, NOW),
+            Err(AuthError::MalformedHeader(auth::TIMESTAMP_HEADER))
+        );
+    }
+
+    #[test]
+    fn test_case_changed_signature_is_a_replay() {
+        let authenticator = authenticator(None, Some(KEY));
+
+        let fresh = signed_headers(NOW, BODY);
+        assert_eq!(authenticator.authenticate(&fresh, BODY, NOW), Ok(()));
+
+        let signature = auth::sign(KEY, NOW, BODY);
+        let digest = signature.strip_prefix(auth::SIGNATURE_SCHEME).unwrap();
+        for resent in [digest.to_ascii_uppercase(), mixed_case(digest)] {
+            let mut headers = fresh.clone();
+            headers.insert(auth::SIGNATURE_HEADER, format!("{}{resent}", auth::SIGNATURE_SCHEME).parse().unwrap());
+            assert_eq!(authenticator.authenticate(&headers, BODY, NOW + 1), Err(AuthError::Replayed));
+        }
+    }
+
+    fn mixed_case(hex: &str) -> String {
+        hex.chars()
+            .enumerate()
+            .map(|(i, c)| if i % 2 == 0 { c.to_ascii_uppercase() } else { c })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_request_gets_401() {
+        let router = Router::new().route("/webhook/ebpf", post(|body: String| async move { body }));
+        let app = authenticated(router, Arc::new(authenticator(Some("s3cret"), Some(KEY))));
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.post("/webhook/ebpf").bytes(BODY.into()).await;
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.json::<serde_json::Value>()["status"], "unauthorized");
+
+        let response = server
+            .post("/webhook/ebpf")
+            .add_header(header::AUTHORIZATION, HeaderValue::from_static("Bearer s3cret"))
+            .bytes(BODY.into())
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+
+        // The handler still sees the body the signature was checked against
+        let timestamp = chrono::Utc::now().timestamp();
+        let response = server
+            .post("/webhook/ebpf")
+            .add_header(HeaderName::from_static(auth::TIMESTAMP_HEADER), timestamp.to_string().parse().unwrap())
+            .add_header(HeaderName::from_static(auth::SIGNATURE_HEADER), auth::sign(KEY, timestamp, BODY).parse().unwrap())
+            .bytes(BODY.into())
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        assert_eq!(response.text().as_bytes(), BODY);
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod webhook_batch_tests {
+    use axum::http::StatusCode;
+    use axum_test::TestServer;
+    use kernel_gossip_operator::config::RetentionPolicy;
+    use kernel_gossip_operator::policy::Policies;
+    use kernel_gossip_operator::webhook::*;
+    use std::sync::Arc;
+
+    // Nothing listens here, so every pod lookup fails fast
+    fn offline_server() -> TestServer {
+        let config = kube::Config::new("http://127.0.0.1:1".parse().unwrap());
+        let client = kube::Client::try_from(config).expect("client");
+        let router = create_webhook_router(Arc::new(client), RetentionPolicy::OwnedByPod, 4, Policies::from_objects(vec![], vec![]), None);
+        TestServer::new(router).unwrap()
+    }
+
+    fn pod_creation(namespace: &str) -> EbpfEvent {
+        EbpfEvent::PodCreation(PodCreationPayload {
+            pod_name: "coredns".to_string(),
+            namespace: namespace.to_string(),
+            total_syscalls: 10,
+            namespace_ops: 1,
+            cgroup_writes: 1,
+            mount_ops: 1,
+            duration_ns: 1_000,
+            timeline: vec![],
+            ebpf_detection: true,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    fn cpu_throttle(pod_name: &str) -> EbpfEvent {
+        EbpfEvent::CpuThrottle(CpuThrottlePayload {
+            pod_name: pod_name.to_string(),
+            namespace: "default".to_string(),
+            container_name: "app".to_string(),
+            throttle_percentage: 50.0,
+            actual_cpu_usage: 0.5,
+            reported_cpu_usage: 0.2,
+            period_seconds: 10,
+            ebpf_detection: true,
+            throttle_ns: 1_000,
+            nr_periods: 10,
+            nr_throttled: 5,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_batch_returns_one_result_per_event_in_order() {
+        let server = offline_server();
+        let events: Vec<EbpfEvent> = (0..10)
+            .map(|i| if i % 2 == 0 { pod_creation("kube-system") } else { cpu_throttle(&format!("pod-{i}")) })
+            .collect();
+
+        let response = server.post("/webhook/ebpf/batch").json(&EventBatch::new(events)).await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+
+        let body: BatchResponse = response.json();
+        assert_eq!(body.results.len(), 10);
+        for (i, result) in body.results.iter().enumerate() {
+            assert_eq!(result.index, i);
+            assert_eq!(result.status, EventStatus::Skipped);
+        }
+        assert!(body.results[0].message.contains("kube-system is excluded"));
+        assert!(body.results[1].message.contains("pod-1"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_batches_rejected_whole() {
+        let server = offline_server();
+
+        let oversized = EventBatch::new(vec![pod_creation("kube-system"); MAX_BATCH_EVENTS + 1]);
+        let response = server.post("/webhook/ebpf/batch").json(&oversized).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+        let unversioned = serde_json::json!({ "events": [] });
+        let response = server.post("/webhook/ebpf/batch").json(&unversioned).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+        let response = server.post("/webhook/ebpf/batch").text("{}").await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,11 +1,14 @@
 #[cfg(test)]
 mod webhook_payload_tests {
-    use kernel_gossip_operator::webhook::EbpfWebhookPayload;
+    use kernel_gossip_operator::webhook::EbpfEvent;
+    use kernel_gossip_protocol::{decode, ProtocolError, SCHEMA_VERSION};
     use serde_json::json;
 
     #[test]
     fn test_pod_creation_payload_parsing() {
         let payload_json = json!({
+            "schema_version": SCHEMA_VERSION,
+            "type": "pod_creation",
             "timestamp": "2024-01-01T00:00:00Z",
             "pod_name": "test-pod",
             "namespace": "default",
@@ -17,18 +20,18 @@ mod webhook_payload_tests {
             "ebpf_detection": true
         });
 
-        let payload: EbpfWebhookPayload = serde_json::from_value(payload_json)
+        let envelope = decode(payload_json.to_string().as_bytes())
             .expect("Failed to parse pod creation payload");
 
-        match payload {
-            EbpfWebhookPayload::PodCreation { pod_name, namespace, total_syscalls, namespace_ops, cgroup_writes, duration_ns, ebpf_detection, .. } => {
-                assert_eq!(pod_name, "test-pod");
-                assert_eq!(namespace, "default");
-                assert_eq!(total_syscalls, 1234);
-                assert_eq!(namespace_ops, 56);
-                assert_eq!(cgroup_writes, 78);
-                assert_eq!(duration_ns, 1000000);
-                assert!(ebpf_detection);
+        match envelope.event {
+            EbpfEvent::PodCreation(payload) => {
+                assert_eq!(payload.pod_name, "test-pod");
+                assert_eq!(payload.namespace, "default");
+                assert_eq!(payload.total_syscalls, 1234);
+                assert_eq!(payload.namespace_ops, 56);
+                assert_eq!(payload.cgroup_writes, 78);
+                assert_eq!(payload.duration_ns, 1000000);
+                assert!(payload.ebpf_detection);
             }
             _ => panic!("Expected PodCreation payload"),
         }
@@ -37,6 +40,8 @@ mod webhook_payload_tests {
     #[test]
     fn test_cpu_throttle_payload_parsing() {
         let payload_json = json!({
+            "schema_version": SCHEMA_VERSION,
+            "type": "cpu_throttle",
             "timestamp": "2024-01-01T00:00:00Z",
             "pod_name": "throttled-pod",
             "namespace": "production",
@@ -46,23 +51,26 @@ mod webhook_payload_tests {
             "reported_cpu_usage": 0.5,
             "period_seconds": 300,
             "ebpf_detection": true,
-            "throttle_ns": 123456789
+            "throttle_ns": 123456789,
+            "nr_periods": 3000,
+            "nr_throttled": 1365
         });
 
-        let payload: EbpfWebhookPayload = serde_json::from_value(payload_json)
+        let envelope = decode(payload_json.to_string().as_bytes())
             .expect("Failed to parse CPU throttle payload");
 
-        match payload {
-            EbpfWebhookPayload::CpuThrottle { pod_name, namespace, container_name, throttle_percentage, actual_cpu_usage, reported_cpu_usage, period_seconds, ebpf_detection, throttle_ns, .. } => {
-                assert_eq!(pod_name, "throttled-pod");
-                assert_eq!(namespace, "production");
-                assert_eq!(container_name, "app");
-                assert_eq!(throttle_percentage, 45.5);
-                assert_eq!(actual_cpu_usage, 0.8);
-                assert_eq!(reported_cpu_usage, 0.5);
-                assert_eq!(period_seconds, 300);
-                assert!(ebpf_detection);
-                assert_eq!(throttle_ns, 123456789);
+        match envelope.event {
+            EbpfEvent::CpuThrottle(payload) => {
+                assert_eq!(payload.pod_name, "throttled-pod");
+                assert_eq!(payload.namespace, "production");
+                assert_eq!(payload.container_name, "app");
+                assert_eq!(payload.throttle_percentage, 45.5);
+                assert_eq!(payload.actual_cpu_usage, 0.8);
+                assert_eq!(payload.reported_cpu_usage, 0.5);
+                assert_eq!(payload.period_seconds, 300);
+                assert!(payload.ebpf_detection);
+                assert_eq!(payload.throttle_ns, 123456789);
+                assert_eq!(payload.nr_throttled, 1365);
             }
             _ => panic!("Expected CpuThrottle payload"),
         }
@@ -71,11 +79,25 @@ mod webhook_payload_tests {
     #[test]
     fn test_unknown_payload_type() {
         let payload_json = json!({
+            "schema_version": SCHEMA_VERSION,
             "type": "unknown_type",
             "timestamp": "2024-01-01T00:00:00Z"
         });
 
-        let result: Result<EbpfWebhookPayload, _> = serde_json::from_value(payload_json);
+        let result = decode(payload_json.to_string().as_bytes());
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_unversioned_payload_rejected() {
+        // What observers sent before the protocol carried a version
+        let payload_json = json!({
+            "type": "cpu_throttle",
+            "pod_name": "throttled-pod",
+            "namespace": "production"
+        });
+
+        let result = decode(payload_json.to_string().as_bytes());
+        assert!(matches!(result, Err(ProtocolError::MissingVersion)));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,90 @@
+//! Request authentication shared by the observer (signing) and the operator (verifying).
+//!
+//! Either a shared bearer token in `Authorization`, or an HMAC-SHA256 over
+//! `"{timestamp}.{body}"` in [`SIGNATURE_HEADER`] with the Unix timestamp in
+//! [`TIMESTAMP_HEADER`]. Signing the timestamp lets the operator refuse old
+//! requests that were captured and sent again.
+
+use std::io;
+use std::path::Path;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub const TIMESTAMP_HEADER: &str = "x-kernel-gossip-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-kernel-gossip-signature";
+
+/// Prefix of the signature header value, naming the algorithm
+pub const SIGNATURE_SCHEME: &str = "sha256=";
+
+/// File names inside the mounted Secret
+pub const TOKEN_FILE: &str = "token";
+pub const HMAC_KEY_FILE: &str = "hmac-key";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Keys read from the webhook auth Secret; either may be absent
+#[derive(Clone, Default)]
+pub struct WebhookCredentials {
+    pub token: Option<String>,
+    pub hmac_key: Option<Vec<u8>>,
+}
+
+impl std::fmt::Debug for WebhookCredentials {
+    // Never print the keys themselves
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookCredentials")
+            .field("token", &self.token.is_some())
+            .field("hmac_key", &self.hmac_key.is_some())
+            .finish()
+    }
+}
+
+impl WebhookCredentials {
+    /// Read `token` and `hmac-key` from a mounted Secret directory. Missing files are
+    /// fine, and so is a missing directory; trailing newlines are trimmed.
+    pub fn load_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let read = |name: &str| match std::fs::read_to_string(dir.as_ref().join(name)) {
+            Ok(value) => Ok(Some(value.trim().to_string()).filter(|v| !v.is_empty())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        };
+
+        Ok(Self {
+            token: read(TOKEN_FILE)?,
+            hmac_key: read(HMAC_KEY_FILE)?.map(String::into_bytes),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.token.is_none() && self.hmac_key.is_none()
+    }
+}
+
+fn mac(key: &[u8], timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Value for [`SIGNATURE_HEADER`]
+pub fn sign(key: &[u8], timestamp: i64, body: &[u8]) -> String {
+    format!("{SIGNATURE_SCHEME}{}", hex::encode(mac(key, timestamp, body).finalize().into_bytes()))
+}
+
+/// Check a [`SIGNATURE_HEADER`] value in constant time
+pub fn verify_signature(key: &[u8], timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let Some(digest) = signature.strip_prefix(SIGNATURE_SCHEME).and_then(|hex| hex::decode(hex).ok()) else {
+        return false;
+    };
+    mac(key, timestamp, body).verify_slice(&digest).is_ok()
+}
+
+/// Compare bearer tokens without leaking how much of the prefix matched
+pub fn tokens_match(expected: &str, presented: &str) -> bool {
+    let (expected, presented) = (expected.as_bytes(), presented.as_bytes());
+    expected.len() == presented.len()
+        && expected.iter().zip(presented).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
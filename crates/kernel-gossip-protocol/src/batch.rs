@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{check_body_version, EbpfEvent, ProtocolError, SCHEMA_VERSION};
+
+/// Most events the operator accepts in one batch
+pub const MAX_BATCH_EVENTS: usize = 500;
+
+/// Body of `/webhook/ebpf/batch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventBatch {
+    pub schema_version: u32,
+    pub events: Vec<EbpfEvent>,
+}
+
+impl EventBatch {
+    pub fn new(events: Vec<EbpfEvent>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            events,
+        }
+    }
+}
+
+/// What the operator did with one event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventStatus {
+    Accepted,
+    Skipped,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemResult {
+    /// Position of the event in [`EventBatch::events`]
+    pub index: usize,
+    pub status: EventStatus,
+    pub message: String,
+}
+
+/// One result per event, in batch order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResponse {
+    pub results: Vec<ItemResult>,
+}
+
+impl BatchResponse {
+    pub fn count(&self, status: EventStatus) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+}
+
+pub fn decode_batch(body: &[u8]) -> Result<EventBatch, ProtocolError> {
+    check_body_version(body)?;
+    Ok(serde_json::from_slice(body)?)
+}
+
+pub fn encode_batch(batch: &EventBatch) -> Result<Vec<u8>, ProtocolError> {
+    Ok(serde_json::to_vec(batch)?)
+}
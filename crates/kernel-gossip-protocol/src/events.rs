@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+/// One event kernel-observer reports to the operator, tagged by `type` on the wire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EbpfEvent {
+    CpuThrottle(CpuThrottlePayload),
+    PodCreation(PodCreationPayload),
+    MemoryPressure(MemoryPressurePayload),
+    OomKill(OomKillPayload),
+}
+
+impl EbpfEvent {
+    /// The `type` tag this event carries on the wire
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EbpfEvent::CpuThrottle(_) => "cpu_throttle",
+            EbpfEvent::PodCreation(_) => "pod_creation",
+            EbpfEvent::MemoryPressure(_) => "memory_pressure",
+            EbpfEvent::OomKill(_) => "oom_kill",
+        }
+    }
+
+    /// When the observer detected the event, RFC 3339
+    pub fn timestamp(&self) -> &str {
+        match self {
+            EbpfEvent::CpuThrottle(p) => &p.timestamp,
+            EbpfEvent::PodCreation(p) => &p.timestamp,
+            EbpfEvent::MemoryPressure(p) => &p.timestamp,
+            EbpfEvent::OomKill(p) => &p.timestamp,
+        }
+    }
+
+    pub fn pod_name(&self) -> &str {
+        match self {
+            EbpfEvent::CpuThrottle(p) => &p.pod_name,
+            EbpfEvent::PodCreation(p) => &p.pod_name,
+            EbpfEvent::MemoryPressure(p) => &p.pod_name,
+            EbpfEvent::OomKill(p) => &p.pod_name,
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        match self {
+            EbpfEvent::CpuThrottle(p) => &p.namespace,
+            EbpfEvent::PodCreation(p) => &p.namespace,
+            EbpfEvent::MemoryPressure(p) => &p.namespace,
+            EbpfEvent::OomKill(p) => &p.namespace,
+        }
+    }
+}
+
+/// CFS throttling sampled from the pod cgroup's cpu.stat
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpuThrottlePayload {
+    pub pod_name: String,
+    pub namespace: String,
+    pub container_name: String,
+    pub throttle_percentage: f64,
+    pub actual_cpu_usage: f64,
+    pub reported_cpu_usage: f64,
+    pub period_seconds: u64,
+    pub ebpf_detection: bool,
+    pub throttle_ns: u64,
+    pub nr_periods: u64,
+    pub nr_throttled: u64,
+    pub timestamp: String,
+}
+
+/// Kernel activity while a pod's containers were being set up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PodCreationPayload {
+    pub pod_name: String,
+    pub namespace: String,
+    pub total_syscalls: u64,
+    pub namespace_ops: u64,
+    pub cgroup_writes: u64,
+    /// Mounts and pivot_root; older observers leave it out
+    #[serde(default)]
+    pub mount_ops: u64,
+    pub duration_ns: u64,
+    pub timeline: Vec<TimelineEvent>,
+    pub ebpf_detection: bool,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// Milliseconds since the runtime started on the container
+    pub timestamp_ms: u64,
+    pub action: String,
+}
+
+/// PSI stalls, limit hits and reclaim seen in the pod memory cgroup over one poll
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryPressurePayload {
+    pub pod_name: String,
+    pub namespace: String,
+    pub psi_some_avg10: f64,
+    pub psi_full_avg10: f64,
+    pub stall_time_us: u64,
+    pub full_stall_time_us: u64,
+    pub high_events: u64,
+    pub max_events: u64,
+    pub oom_events: u64,
+    pub oom_kill_events: u64,
+    pub reclaim_scanned_pages: u64,
+    pub working_set_bytes: u64,
+    pub limit_bytes: Option<u64>,
+    pub period_seconds: u64,
+    pub timestamp: String,
+}
+
+/// OOM kills counted in the pod memory cgroup, with memory.stat as read in the
+/// poll that saw them, after the killed process's memory was freed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OomKillPayload {
+    pub pod_name: String,
+    pub namespace: String,
+    pub pod_uid: String,
+    pub oom_kills: u64,
+    pub anon_bytes: u64,
+    pub file_bytes: u64,
+    pub kernel_bytes: u64,
+    pub sock_bytes: u64,
+    pub shmem_bytes: u64,
+    pub usage_bytes: u64,
+    pub limit_bytes: Option<u64>,
+    pub timestamp: String,
+}
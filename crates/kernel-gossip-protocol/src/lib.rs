@@ -0,0 +1,92 @@
+//! Wire format between kernel-observer and kernel-gossip-operator.
+//!
+//! Every request body is an [`EventEnvelope`]: a `schema_version` next to the
+//! `type`-tagged event fields. Bump [`SCHEMA_VERSION`] for any change an older
+//! operator could misread, and raise [`MIN_SUPPORTED_SCHEMA_VERSION`] once the
+//! operator stops understanding an old layout. An [`EventBatch`] carries many
+//! events under one version and is answered with a [`BatchResponse`].
+
+pub mod auth;
+pub mod batch;
+pub mod events;
+pub mod monitored;
+
+pub use batch::*;
+pub use events::*;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Version the observer writes
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Oldest version the operator still decodes
+pub const MIN_SUPPORTED_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("payload has no schema_version; the observer predates the versioned protocol")]
+    MissingVersion,
+
+    #[error("unsupported schema_version {found}, this operator accepts {min} to {max}")]
+    UnsupportedVersion { found: u32, min: u32, max: u32 },
+
+    #[error("malformed payload: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub event: EbpfEvent,
+}
+
+impl EventEnvelope {
+    pub fn new(event: EbpfEvent) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            event,
+        }
+    }
+}
+
+impl From<EbpfEvent> for EventEnvelope {
+    fn from(event: EbpfEvent) -> Self {
+        Self::new(event)
+    }
+}
+
+pub fn check_compatibility(schema_version: u32) -> Result<(), ProtocolError> {
+    if (MIN_SUPPORTED_SCHEMA_VERSION..=SCHEMA_VERSION).contains(&schema_version) {
+        Ok(())
+    } else {
+        Err(ProtocolError::UnsupportedVersion {
+            found: schema_version,
+            min: MIN_SUPPORTED_SCHEMA_VERSION,
+            max: SCHEMA_VERSION,
+        })
+    }
+}
+
+/// Read only `schema_version`, so a body from a newer observer is refused for its
+/// version rather than reported as malformed
+pub(crate) fn check_body_version(body: &[u8]) -> Result<(), ProtocolError> {
+    #[derive(Deserialize)]
+    struct VersionProbe {
+        schema_version: Option<u32>,
+    }
+
+    let probe: VersionProbe = serde_json::from_slice(body)?;
+    check_compatibility(probe.schema_version.ok_or(ProtocolError::MissingVersion)?)
+}
+
+/// Decode a request body, rejecting incompatible versions before looking at the event
+pub fn decode(body: &[u8]) -> Result<EventEnvelope, ProtocolError> {
+    check_body_version(body)?;
+    Ok(serde_json::from_slice(body)?)
+}
+
+pub fn encode(envelope: &EventEnvelope) -> Result<Vec<u8>, ProtocolError> {
+    Ok(serde_json::to_vec(envelope)?)
+}
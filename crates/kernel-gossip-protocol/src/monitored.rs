@@ -0,0 +1,42 @@
+//! The set of pods the operator wants recorded, published for the observers.
+//!
+//! The operator keeps it in the [`MONITORED_PODS_CONFIGMAP`] ConfigMap with one
+//! data key per node (see [`node_key`]), so an observer only reads the pods it
+//! can actually see.
+
+use serde::{Deserialize, Serialize};
+
+/// ConfigMap in the operator's namespace holding the set
+pub const MONITORED_PODS_CONFIGMAP: &str = "ebpf-monitored-pods";
+
+/// Data key with the total number of monitored pods
+pub const COUNT_KEY: &str = "count";
+
+/// Data key with the RFC 3339 time of the last update
+pub const UPDATED_KEY: &str = "updated";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MonitoredPod {
+    pub uid: String,
+    pub namespace: String,
+    pub name: String,
+    pub node: String,
+    /// As the kubelet reports them, e.g. `containerd://4f2a…`; init and
+    /// ephemeral containers included
+    #[serde(default)]
+    pub container_ids: Vec<String>,
+}
+
+/// Data key for the pods scheduled on `node`
+pub fn node_key(node: &str) -> String {
+    format!("node.{node}.json")
+}
+
+/// Body of one node's data key
+pub fn encode_node_pods(pods: &[MonitoredPod]) -> Result<String, serde_json::Error> {
+    serde_json::to_string(pods)
+}
+
+pub fn decode_node_pods(data: &str) -> Result<Vec<MonitoredPod>, serde_json::Error> {
+    serde_json::from_str(data)
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod auth_tests {
+    use kernel_gossip_protocol::auth::*;
+
+    const KEY: &[u8] = b"observer-operator-shared-key";
+    const BODY: &[u8] = br#"{"schema_version":1,"type":"cpu_throttle"}"#;
+
+    #[test]
+    fn test_signature_round_trip() {
+        let signature = sign(KEY, 1_704_067_200, BODY);
+
+        assert!(signature.starts_with(SIGNATURE_SCHEME));
+        assert!(verify_signature(KEY, 1_704_067_200, BODY, &signature));
+    }
+
+    #[test]
+    fn test_signature_covers_body_timestamp_and_key() {
+        let signature = sign(KEY, 1_704_067_200, BODY);
+
+        assert!(!verify_signature(KEY, 1_704_067_200, br#"{"schema_version":1,"type":"oom_kill"}"#, &signature));
+        assert!(!verify_signature(KEY, 1_704_067_201, BODY, &signature));
+        assert!(!verify_signature(b"another-key", 1_704_067_200, BODY, &signature));
+        assert!(!verify_signature(KEY, 1_704_067_200, BODY, "sha256=not-hex"));
+        assert!(!verify_signature(KEY, 1_704_067_200, BODY, signature.trim_start_matches(SIGNATURE_SCHEME)));
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3cret", "s3cre"));
+        assert!(!tokens_match("s3cret", "s3creT"));
+        assert!(!tokens_match("s3cret", ""));
+    }
+
+    #[test]
+    fn test_load_credentials_from_secret_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(dir.path().join(TOKEN_FILE), "s3cret\n").unwrap();
+
+        let credentials = WebhookCredentials::load_dir(dir.path()).expect("load");
+        assert_eq!(credentials.token.as_deref(), Some("s3cret"));
+        assert!(credentials.hmac_key.is_none());
+        assert!(!credentials.is_empty());
+        // Debug output must not leak the token
+        assert!(!format!("{credentials:?}").contains("s3cret"));
+
+        let missing = WebhookCredentials::load_dir(dir.path().join("not-mounted")).expect("load");
+        assert!(missing.is_empty());
+    }
+}
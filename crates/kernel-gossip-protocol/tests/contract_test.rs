@@ -0,0 +1,263 @@
+#[cfg(test)]
+mod contract_tests {
+    use kernel_gossip_protocol::*;
+    use serde_json::json;
+
+    fn cpu_throttle() -> EbpfEvent {
+        EbpfEvent::CpuThrottle(CpuThrottlePayload {
+            pod_name: "throttled-pod".to_string(),
+            namespace: "production".to_string(),
+            container_name: "app".to_string(),
+            throttle_percentage: 45.5,
+            actual_cpu_usage: 0.8,
+            reported_cpu_usage: 0.5,
+            period_seconds: 10,
+            ebpf_detection: true,
+            throttle_ns: 123_456_789,
+            nr_periods: 100,
+            nr_throttled: 45,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    fn pod_creation() -> EbpfEvent {
+        EbpfEvent::PodCreation(PodCreationPayload {
+            pod_name: "test-pod".to_string(),
+            namespace: "default".to_string(),
+            total_syscalls: 1234,
+            namespace_ops: 56,
+            cgroup_writes: 78,
+            mount_ops: 9,
+            duration_ns: 1_000_000,
+            timeline: vec![TimelineEvent { timestamp_ms: 0, action: "runc:[2:INIT] started".to_string() }],
+            ebpf_detection: true,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    fn memory_pressure() -> EbpfEvent {
+        EbpfEvent::MemoryPressure(MemoryPressurePayload {
+            pod_name: "memory-pod".to_string(),
+            namespace: "default".to_string(),
+            psi_some_avg10: 35.5,
+            psi_full_avg10: 4.25,
+            stall_time_us: 2_500_000,
+            full_stall_time_us: 300_000,
+            high_events: 12,
+            max_events: 3,
+            oom_events: 0,
+            oom_kill_events: 0,
+            reclaim_scanned_pages: 40_960,
+            working_set_bytes: 262_144_000,
+            limit_bytes: None,
+            period_seconds: 10,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    fn oom_kill() -> EbpfEvent {
+        EbpfEvent::OomKill(OomKillPayload {
+            pod_name: "leaky-pod".to_string(),
+            namespace: "default".to_string(),
+            pod_uid: "2bac1a6a-95d3-4abc-990f-aefaf5c74812".to_string(),
+            oom_kills: 1,
+            anon_bytes: 120,
+            file_bytes: 4,
+            kernel_bytes: 2,
+            sock_bytes: 1,
+            shmem_bytes: 0,
+            usage_bytes: 128,
+            limit_bytes: Some(128),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_every_event_round_trips() {
+        for event in [cpu_throttle(), pod_creation(), memory_pressure(), oom_kill()] {
+            let envelope = EventEnvelope::new(event);
+            let body = encode(&envelope).expect("encode");
+            let decoded = decode(&body).expect("decode");
+
+            assert_eq!(decoded, envelope);
+        }
+    }
+
+    #[test]
+    fn test_kind_matches_wire_type() {
+        for event in [cpu_throttle(), pod_creation(), memory_pressure(), oom_kill()] {
+            let wire = serde_json::to_value(&event).unwrap();
+            assert_eq!(wire["type"], event.kind());
+            assert_eq!(event.timestamp(), "2024-01-01T00:00:00Z");
+        }
+    }
+
+    // The JSON below is schema version 1 as it goes over the wire. If one of these
+    // fails, the observer changed the format: bump SCHEMA_VERSION instead of the fixture.
+    #[test]
+    fn test_cpu_throttle_wire_format_v1() {
+        let wire = json!({
+            "schema_version": 1,
+            "type": "cpu_throttle",
+            "pod_name": "throttled-pod",
+            "namespace": "production",
+            "container_name": "app",
+            "throttle_percentage": 45.5,
+            "actual_cpu_usage": 0.8,
+            "reported_cpu_usage": 0.5,
+            "period_seconds": 10,
+            "ebpf_detection": true,
+            "throttle_ns": 123456789,
+            "nr_periods": 100,
+            "nr_throttled": 45,
+            "timestamp": "2024-01-01T00:00:00Z"
+        });
+
+        assert_eq!(serde_json::to_value(EventEnvelope::new(cpu_throttle())).unwrap(), wire);
+        assert_eq!(decode(wire.to_string().as_bytes()).unwrap().event, cpu_throttle());
+    }
+
+    #[test]
+    fn test_pod_creation_wire_format_v1() {
+        let wire = json!({
+            "schema_version": 1,
+            "type": "pod_creation",
+            "pod_name": "test-pod",
+            "namespace": "default",
+            "total_syscalls": 1234,
+            "namespace_ops": 56,
+            "cgroup_writes": 78,
+            "mount_ops": 9,
+            "duration_ns": 1000000,
+            "timeline": [{ "timestamp_ms": 0, "action": "runc:[2:INIT] started" }],
+            "ebpf_detection": true,
+            "timestamp": "2024-01-01T00:00:00Z"
+        });
+
+        assert_eq!(serde_json::to_value(EventEnvelope::new(pod_creation())).unwrap(), wire);
+        assert_eq!(decode(wire.to_string().as_bytes()).unwrap().event, pod_creation());
+    }
+
+    #[test]
+    fn test_memory_pressure_wire_format_v1() {
+        let wire = json!({
+            "schema_version": 1,
+            "type": "memory_pressure",
+            "pod_name": "memory-pod",
+            "namespace": "default",
+            "psi_some_avg10": 35.5,
+            "psi_full_avg10": 4.25,
+            "stall_time_us": 2500000,
+            "full_stall_time_us": 300000,
+            "high_events": 12,
+            "max_events": 3,
+            "oom_events": 0,
+            "oom_kill_events": 0,
+            "reclaim_scanned_pages": 40960,
+            "working_set_bytes": 262144000,
+            "limit_bytes": null,
+            "period_seconds": 10,
+            "timestamp": "2024-01-01T00:00:00Z"
+        });
+
+        assert_eq!(serde_json::to_value(EventEnvelope::new(memory_pressure())).unwrap(), wire);
+        assert_eq!(decode(wire.to_string().as_bytes()).unwrap().event, memory_pressure());
+    }
+
+    #[test]
+    fn test_oom_kill_wire_format_v1() {
+        let wire = json!({
+            "schema_version": 1,
+            "type": "oom_kill",
+            "pod_name": "leaky-pod",
+            "namespace": "default",
+            "pod_uid": "2bac1a6a-95d3-4abc-990f-aefaf5c74812",
+            "oom_kills": 1,
+            "anon_bytes": 120,
+            "file_bytes": 4,
+            "kernel_bytes": 2,
+            "sock_bytes": 1,
+            "shmem_bytes": 0,
+            "usage_bytes": 128,
+            "limit_bytes": 128,
+            "timestamp": "2024-01-01T00:00:00Z"
+        });
+
+        assert_eq!(serde_json::to_value(EventEnvelope::new(oom_kill())).unwrap(), wire);
+        assert_eq!(decode(wire.to_string().as_bytes()).unwrap().event, oom_kill());
+    }
+
+    #[test]
+    fn test_missing_version_is_rejected() {
+        let unversioned = json!({ "type": "cpu_throttle", "pod_name": "p" });
+
+        let err = decode(unversioned.to_string().as_bytes()).unwrap_err();
+        assert!(matches!(err, ProtocolError::MissingVersion));
+    }
+
+    #[test]
+    fn test_future_version_is_rejected_before_decoding_the_event() {
+        // A newer observer may send fields this operator has never heard of
+        let future = json!({ "schema_version": SCHEMA_VERSION + 1, "type": "cpu_throttle_v2" });
+
+        let err = decode(future.to_string().as_bytes()).unwrap_err();
+        assert!(matches!(err, ProtocolError::UnsupportedVersion { found, .. } if found == SCHEMA_VERSION + 1));
+        assert!(check_compatibility(0).is_err());
+        assert!(check_compatibility(SCHEMA_VERSION).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_type_is_malformed() {
+        let unknown = json!({ "schema_version": SCHEMA_VERSION, "type": "unknown_type", "timestamp": "2024-01-01T00:00:00Z" });
+
+        let err = decode(unknown.to_string().as_bytes()).unwrap_err();
+        assert!(matches!(err, ProtocolError::Malformed(_)));
+    }
+
+    #[test]
+    fn test_batch_wire_format_v1() {
+        let batch = EventBatch::new(vec![pod_creation(), oom_kill()]);
+        let wire = serde_json::to_value(&batch).unwrap();
+
+        assert_eq!(wire["schema_version"], 1);
+        assert_eq!(wire["events"][0]["type"], "pod_creation");
+        assert_eq!(wire["events"][1]["type"], "oom_kill");
+        assert_eq!(decode_batch(&encode_batch(&batch).unwrap()).unwrap(), batch);
+
+        let unversioned = json!({ "events": [] });
+        assert!(matches!(decode_batch(unversioned.to_string().as_bytes()), Err(ProtocolError::MissingVersion)));
+    }
+
+    #[test]
+    fn test_batch_response_wire_format_v1() {
+        let wire = json!({
+            "results": [
+                { "index": 0, "status": "accepted", "message": "Webhook payload processed" },
+                { "index": 1, "status": "skipped", "message": "System namespace pod coredns" },
+                { "index": 2, "status": "error", "message": "Failed to create CRD" }
+            ]
+        });
+
+        let response: BatchResponse = serde_json::from_value(wire).unwrap();
+        assert_eq!(response.results[1].status, EventStatus::Skipped);
+        assert_eq!(response.count(EventStatus::Accepted), 1);
+        assert_eq!(response.count(EventStatus::Error), 1);
+    }
+
+    #[test]
+    fn test_monitored_pods_wire_format_v1() {
+        use kernel_gossip_protocol::monitored::{decode_node_pods, encode_node_pods, node_key};
+
+        let wire = json!([
+            { "uid": "4b1c", "namespace": "shop", "name": "cart", "node": "node-a",
+              "container_ids": ["containerd://abc"] },
+            { "uid": "77e0", "namespace": "shop", "name": "pending", "node": "node-a" }
+        ]);
+
+        let pods = decode_node_pods(&wire.to_string()).unwrap();
+        assert_eq!(pods[0].container_ids, vec!["containerd://abc"]);
+        assert!(pods[1].container_ids.is_empty());
+        assert_eq!(decode_node_pods(&encode_node_pods(&pods).unwrap()).unwrap(), pods);
+        assert_eq!(node_key("node-a"), "node.node-a.json");
+    }
+}
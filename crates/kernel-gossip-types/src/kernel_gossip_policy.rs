@@ -0,0 +1,196 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{MemoryKernelTruth, Severity};
+
+/// Annotation that opts a pod in under policies with `require_monitor_annotation`
+pub const MONITOR_ANNOTATION: &str = "kernel-gossip.io/monitor";
+
+/// Namespaces the built-in policy leaves alone
+pub const SYSTEM_NAMESPACES: &[&str] = &[
+    "kube-system",
+    "kube-public",
+    "kube-node-lease",
+    "gke-gmp-system",
+    "gmp-system",
+    "gke-managed-filestorecsi",
+];
+
+/// Which pods kernel-gossip records and how it grades what it finds.
+/// Of the policies matching a pod the one with the highest priority applies;
+/// without any policy in the cluster the operator falls back to
+/// [`KernelGossipPolicySpec::builtin`].
+#[derive(CustomResource, Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[kube(
+    group = "kernel.gossip.io",
+    version = "v1alpha1",
+    kind = "KernelGossipPolicy",
+    plural = "kernelgossippolicies",
+    shortname = "kgp"
+)]
+pub struct KernelGossipPolicySpec {
+    #[serde(default)]
+    pub priority: i32,
+    /// Namespaces the policy covers, by their labels; all when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_selector: Option<LabelSelector>,
+    /// Pods the policy covers, by their labels; all when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_selector: Option<LabelSelector>,
+    /// Only cover pods annotated `kernel-gossip.io/monitor: "true"`
+    #[serde(default)]
+    pub require_monitor_annotation: bool,
+    #[serde(default)]
+    pub excluded_namespaces: Vec<String>,
+    #[serde(default)]
+    pub thresholds: SignalThresholds,
+    /// Share of covered pods that are recorded, from 0.0 to 1.0. The choice is
+    /// stable per pod, so a sampled pod gets all of its records.
+    #[serde(default = "full_sampling")]
+    pub sampling_rate: f64,
+}
+
+fn full_sampling() -> f64 {
+    1.0
+}
+
+/// Levels at which a signal is graded Critical, Warning or Info. Unset
+/// levels are never reached by that signal.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SeverityThresholds {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub critical: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<f64>,
+}
+
+impl SeverityThresholds {
+    /// The highest level `value` reaches, if any
+    pub fn classify(&self, value: f64) -> Option<Severity> {
+        let reaches = |threshold: Option<f64>| threshold.is_some_and(|t| value >= t);
+        if reaches(self.critical) {
+            Some(Severity::Critical)
+        } else if reaches(self.warning) {
+            Some(Severity::Warning)
+        } else if reaches(self.info) {
+            Some(Severity::Info)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SignalThresholds {
+    /// Share of CFS periods throttled. Below `info` no recommendation is made.
+    #[serde(default = "default_cpu_throttled_percent")]
+    pub cpu_throttled_percent: SeverityThresholds,
+    /// PSI `some` avg10 of the pod's memory cgroup
+    #[serde(default = "default_memory_stall_some_percent")]
+    pub memory_stall_some_percent: SeverityThresholds,
+    /// PSI `full` avg10 of the pod's memory cgroup
+    #[serde(default = "default_memory_stall_full_percent")]
+    pub memory_stall_full_percent: SeverityThresholds,
+    /// Seconds from Pod creation to Ready: past `warning` a start is slow,
+    /// past `critical` a pod still not Ready is stuck
+    #[serde(default = "default_startup_seconds")]
+    pub startup_seconds: SeverityThresholds,
+}
+
+fn default_cpu_throttled_percent() -> SeverityThresholds {
+    SeverityThresholds { critical: Some(80.0), warning: Some(50.0), info: Some(40.0) }
+}
+
+fn default_memory_stall_some_percent() -> SeverityThresholds {
+    SeverityThresholds { critical: None, warning: Some(20.0), info: None }
+}
+
+fn default_memory_stall_full_percent() -> SeverityThresholds {
+    SeverityThresholds { critical: Some(10.0), warning: None, info: None }
+}
+
+fn default_startup_seconds() -> SeverityThresholds {
+    SeverityThresholds { critical: Some(120.0), warning: Some(30.0), info: None }
+}
+
+impl Default for SignalThresholds {
+    fn default() -> Self {
+        Self {
+            cpu_throttled_percent: default_cpu_throttled_percent(),
+            memory_stall_some_percent: default_memory_stall_some_percent(),
+            memory_stall_full_percent: default_memory_stall_full_percent(),
+            startup_seconds: default_startup_seconds(),
+        }
+    }
+}
+
+impl SignalThresholds {
+    pub fn cpu_severity(&self, throttled_percent: f64) -> Severity {
+        self.cpu_throttled_percent.classify(throttled_percent).unwrap_or(Severity::Info)
+    }
+
+    /// The worst of the PSI levels and the cgroup's own limit events: OOMs are
+    /// always Critical and hitting `memory.max` at least a Warning
+    pub fn memory_severity(&self, truth: &MemoryKernelTruth) -> Severity {
+        let events = if truth.oom_events > 0 {
+            Some(Severity::Critical)
+        } else if truth.max_events > 0 {
+            Some(Severity::Warning)
+        } else {
+            None
+        };
+
+        [
+            events,
+            self.memory_stall_full_percent.classify(truth.stall_full_percent),
+            self.memory_stall_some_percent.classify(truth.stall_some_percent),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by_key(severity_rank)
+        .unwrap_or(Severity::Info)
+    }
+}
+
+fn severity_rank(severity: &Severity) -> u8 {
+    match severity {
+        Severity::Critical => 0,
+        Severity::Warning => 1,
+        Severity::Info => 2,
+    }
+}
+
+impl KernelGossipPolicySpec {
+    /// What applies while no KernelGossipPolicy exists: annotated pods outside
+    /// the system namespaces, graded with the default thresholds
+    pub fn builtin() -> Self {
+        Self {
+            priority: 0,
+            namespace_selector: None,
+            pod_selector: None,
+            require_monitor_annotation: true,
+            excluded_namespaces: SYSTEM_NAMESPACES.iter().map(|ns| ns.to_string()).collect(),
+            thresholds: SignalThresholds::default(),
+            sampling_rate: 1.0,
+        }
+    }
+
+    /// Whether the pod falls inside the sampled share
+    pub fn samples(&self, namespace: &str, pod_name: &str) -> bool {
+        if self.sampling_rate >= 1.0 {
+            return true;
+        }
+        if self.sampling_rate <= 0.0 {
+            return false;
+        }
+        // FNV-1a, so the same pod is picked on every event and every replica
+        let hash = format!("{namespace}/{pod_name}")
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+        (hash % 10_000) as f64 / 10_000.0 < self.sampling_rate
+    }
+}
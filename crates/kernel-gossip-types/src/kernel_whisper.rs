@@ -2,6 +2,8 @@ use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::SignalThresholds;
+
 #[derive(CustomResource, Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[kube(
     group = "kernel.gossip.io",
@@ -25,12 +27,35 @@ pub struct KernelWhisperSpec {
 pub struct KernelTruth {
     pub throttled_percent: f64,
     pub actual_cpu_cores: f64,
+    /// CFS periods in which the pod hit its quota (`nr_throttled` delta from cpu.stat)
+    #[serde(default)]
+    pub throttled_periods: u64,
+    /// CFS periods elapsed in the sampling window (`nr_periods` delta from cpu.stat)
+    #[serde(default)]
+    pub total_periods: u64,
+    /// Time spent throttled in the sampling window
+    #[serde(default)]
+    pub throttled_time_ms: u64,
+    #[serde(default)]
+    pub window_seconds: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MetricsLie {
-    pub cpu_percent: f64,
+    /// Pod CPU from metrics-server as a percentage of one core; `None` when
+    /// metrics-server had no figure for the pod
+    pub cpu_percent: Option<f64>,
     pub reported_status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unavailable_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub containers: Vec<ContainerCpuUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ContainerCpuUsage {
+    pub name: String,
+    pub cpu_cores: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
@@ -54,8 +79,8 @@ pub struct KernelWhisperStatus {
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MetricsComparison {
     pub kernel_cpu_usage: f64,
-    pub metrics_cpu_usage: f64,
-    pub discrepancy_percent: f64,
+    pub metrics_cpu_usage: Option<f64>,
+    pub discrepancy_percent: Option<f64>,
 }
 
 // Implementation methods - ONLY what's needed for tests
@@ -66,14 +91,7 @@ impl KernelWhisper {
         throttled_percent: f64,
         cpu_percent: f64,
     ) -> Self {
-        // Determine severity based on throttling
-        let severity = if throttled_percent > 80.0 {
-            Severity::Critical
-        } else if throttled_percent > 50.0 {
-            Severity::Warning
-        } else {
-            Severity::Info
-        };
+        let severity = SignalThresholds::default().cpu_severity(throttled_percent);
 
         Self {
             metadata: kube::api::ObjectMeta {
@@ -88,10 +106,16 @@ impl KernelWhisper {
                 kernel_truth: KernelTruth {
                     throttled_percent,
                     actual_cpu_cores: (100.0 - throttled_percent) / 100.0,
+                    throttled_periods: 0,
+                    total_periods: 0,
+                    throttled_time_ms: 0,
+                    window_seconds: 0,
                 },
                 metrics_lie: MetricsLie {
-                    cpu_percent,
+                    cpu_percent: Some(cpu_percent),
                     reported_status: "healthy".to_string(),
+                    unavailable_reason: None,
+                    containers: vec![],
                 },
                 severity,
             },
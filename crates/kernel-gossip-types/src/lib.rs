@@ -1,7 +1,13 @@
+pub mod kernel_gossip_policy;
 pub mod kernel_whisper;
+pub mod memory_whisper;
+pub mod oom_kill_report;
 pub mod pod_birth_certificate;
 
+pub use kernel_gossip_policy::*;
 pub use kernel_whisper::*;
+pub use memory_whisper::*;
+pub use oom_kill_report::*;
 pub use pod_birth_certificate::*;
 
 #[cfg(test)]
@@ -32,6 +38,36 @@ mod tests {
         assert_eq!(json["spec"]["pod_name"], "nginx-abc123");
     }
 
+    #[test]
+    fn test_pod_birth_certificate_status_serialization() {
+        let phases = StartupPhases {
+            scheduling_ms: Some(1_000),
+            image_pull_ms: Some(35_000),
+            kernel_setup_ms: Some(35_000),
+            ..Default::default()
+        };
+        // Ties go to the phase that came first
+        assert_eq!(phases.dominant(), Some((StartupPhase::ImagePull, 35_000)));
+        assert_eq!(StartupPhases::default().dominant(), None);
+
+        let status = PodBirthCertificateStatus {
+            phases,
+            total_ms: None,
+            dominant_phase: Some(StartupPhase::ImagePull),
+            health: StartupHealth::Starting,
+            message: "Not Ready yet".to_string(),
+            baseline: None,
+            last_updated: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let json = serde_json::to_value(&status).expect("serialization failed");
+        assert_eq!(json["dominant_phase"], "image_pull");
+        assert_eq!(json["health"], "starting");
+        // Unset fields are written as null, so a merge patch clears them
+        assert!(json["total_ms"].is_null());
+        assert!(json["phases"]["process_start_to_ready_ms"].is_null());
+        assert!(json["baseline"].is_null());
+    }
+
     #[test]
     fn test_timeline_entry_creation() {
         let entry = TimelineEntry::new(
@@ -59,7 +95,7 @@ mod tests {
         assert_eq!(whisper.pod_name(), "frontend-xyz789");
         assert_eq!(whisper.namespace(), "production");
         assert_eq!(whisper.kernel_truth().throttled_percent, 85.7);
-        assert_eq!(whisper.metrics_lie().cpu_percent, 45.2);
+        assert_eq!(whisper.metrics_lie().cpu_percent, Some(45.2));
     }
 
     #[test]
@@ -75,6 +111,169 @@ mod tests {
         assert_eq!(json["spec"]["pod_name"], "backend-api-123");
         assert_eq!(json["spec"]["kernel_truth"]["throttled_percent"], 92.3);
         assert_eq!(json["spec"]["metrics_lie"]["cpu_percent"], 38.1);
+        assert!(json["spec"]["metrics_lie"].get("unavailable_reason").is_none());
+    }
+
+    #[test]
+    fn test_kernel_whisper_unavailable_metrics_serialization() {
+        let mut whisper = KernelWhisper::create("backend-api-123", "staging", 92.3, 38.1);
+        whisper.spec.metrics_lie.cpu_percent = None;
+        whisper.spec.metrics_lie.unavailable_reason = Some("metrics-server has no metrics".to_string());
+
+        let json = serde_json::to_value(&whisper).expect("serialization failed");
+
+        assert!(json["spec"]["metrics_lie"]["cpu_percent"].is_null());
+        assert_eq!(json["spec"]["metrics_lie"]["unavailable_reason"], "metrics-server has no metrics");
+    }
+
+    #[test]
+    fn test_memory_whisper_required_fields() {
+        let whisper = MemoryWhisper::create(
+            "cache-abc123",
+            "production",
+            MemoryKernelTruth {
+                stall_some_percent: 35.0,
+                stall_time_ms: 3500,
+                working_set_bytes: 512 * 1024 * 1024,
+                window_seconds: 10,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(whisper.api_version(), "kernel.gossip.io/v1alpha1");
+        assert_eq!(whisper.kind(), "MemoryWhisper");
+        assert_eq!(whisper.pod_name(), "cache-abc123");
+        assert_eq!(whisper.namespace(), "production");
+        assert_eq!(whisper.kernel_truth().stall_time_ms, 3500);
+        assert_eq!(whisper.metrics_lie().working_set_bytes, None);
+        assert_eq!(whisper.spec.severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_memory_whisper_severity() {
+        let oom = MemoryWhisper::create("a", "default", MemoryKernelTruth { oom_events: 1, ..Default::default() });
+        assert_eq!(oom.spec.severity, Severity::Critical);
+
+        let full_stall = MemoryWhisper::create("b", "default", MemoryKernelTruth { stall_full_percent: 15.0, ..Default::default() });
+        assert_eq!(full_stall.spec.severity, Severity::Critical);
+
+        let limit_hit = MemoryWhisper::create("c", "default", MemoryKernelTruth { max_events: 3, ..Default::default() });
+        assert_eq!(limit_hit.spec.severity, Severity::Warning);
+
+        let mild = MemoryWhisper::create("d", "default", MemoryKernelTruth { stall_some_percent: 5.0, high_events: 2, ..Default::default() });
+        assert_eq!(mild.spec.severity, Severity::Info);
+    }
+
+    #[test]
+    fn test_memory_whisper_serialization() {
+        let whisper = MemoryWhisper::create("cache-abc123", "staging", MemoryKernelTruth::default());
+
+        let json = serde_json::to_value(&whisper).expect("serialization failed");
+
+        assert_eq!(json["kind"], "MemoryWhisper");
+        assert_eq!(json["metadata"]["name"], "cache-abc123-memory-pressure");
+        assert_eq!(json["spec"]["severity"], "info");
+        assert!(json["spec"]["metrics_lie"]["working_set_bytes"].is_null());
+    }
+
+    #[test]
+    fn test_oom_kill_report_required_fields() {
+        let report = OOMKillReport::create(
+            "worker-abc123",
+            "production",
+            "2bac1a6a-95d3-4abc-990f-aefaf5c74812",
+            "2024-01-01T00:00:00Z",
+            OomMemoryBreakdown {
+                anon_bytes: 120 * 1024 * 1024,
+                file_bytes: 4 * 1024 * 1024,
+                usage_bytes: 128 * 1024 * 1024,
+                limit_bytes: Some(128 * 1024 * 1024),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(report.api_version(), "kernel.gossip.io/v1alpha1");
+        assert_eq!(report.kind(), "OOMKillReport");
+        assert_eq!(report.pod_name(), "worker-abc123");
+        assert_eq!(report.namespace(), "production");
+        assert_eq!(report.metadata.name.as_deref(), Some("worker-abc123-oom-1704067200"));
+        assert_eq!(report.memory().limit_bytes, Some(128 * 1024 * 1024));
+        assert_eq!(report.spec.likely_cause, OomCause::Anonymous);
+    }
+
+    #[test]
+    fn test_oom_kill_likely_cause() {
+        let tmpfs = OomMemoryBreakdown { anon_bytes: 10, file_bytes: 100, shmem_bytes: 90, ..Default::default() };
+        assert_eq!(tmpfs.likely_cause(), OomCause::Shmem);
+
+        let cache = OomMemoryBreakdown { anon_bytes: 10, file_bytes: 100, shmem_bytes: 5, ..Default::default() };
+        assert_eq!(cache.likely_cause(), OomCause::PageCache);
+
+        let sockets = OomMemoryBreakdown { anon_bytes: 10, sock_bytes: 50, kernel_bytes: 20, ..Default::default() };
+        assert_eq!(sockets.likely_cause(), OomCause::Socket);
+
+        assert_eq!(OomMemoryBreakdown::default().likely_cause(), OomCause::Anonymous);
+    }
+
+    #[test]
+    fn test_oom_kill_report_serialization() {
+        let report = OOMKillReport::create("worker-abc123", "staging", "uid", "2024-01-01T00:00:00Z", OomMemoryBreakdown::default());
+
+        let json = serde_json::to_value(&report).expect("serialization failed");
+
+        assert_eq!(json["kind"], "OOMKillReport");
+        assert_eq!(json["spec"]["likely_cause"], "anonymous");
+        assert!(json["spec"].get("container_name").is_none());
+        assert!(json["spec"]["memory"]["limit_bytes"].is_null());
+    }
+
+    #[test]
+    fn test_severity_thresholds_classify() {
+        let thresholds = SignalThresholds::default();
+        assert_eq!(thresholds.cpu_severity(80.0), Severity::Critical);
+        assert_eq!(thresholds.cpu_severity(50.0), Severity::Warning);
+        assert_eq!(thresholds.cpu_severity(10.0), Severity::Info);
+        assert_eq!(thresholds.cpu_throttled_percent.classify(39.9), None);
+
+        // Memory takes the worst of PSI and limit events
+        let truth = MemoryKernelTruth { stall_some_percent: 25.0, oom_events: 1, ..Default::default() };
+        assert_eq!(thresholds.memory_severity(&truth), Severity::Critical);
+        let strict = SignalThresholds {
+            memory_stall_some_percent: SeverityThresholds { critical: Some(5.0), ..Default::default() },
+            ..Default::default()
+        };
+        let truth = MemoryKernelTruth { stall_some_percent: 6.0, ..Default::default() };
+        assert_eq!(strict.memory_severity(&truth), Severity::Critical);
+    }
+
+    #[test]
+    fn test_kernel_gossip_policy_defaults_and_sampling() {
+        let policy: KernelGossipPolicy = serde_json::from_value(serde_json::json!({
+            "apiVersion": "kernel.gossip.io/v1alpha1",
+            "kind": "KernelGossipPolicy",
+            "metadata": { "name": "half" },
+            "spec": {
+                "sampling_rate": 0.5,
+                "thresholds": { "cpu_throttled_percent": { "critical": 60.0 } }
+            }
+        }))
+        .expect("deserialization failed");
+
+        assert!(policy.metadata.namespace.is_none());
+        assert!(!policy.spec.require_monitor_annotation);
+        assert_eq!(policy.spec.thresholds.cpu_severity(70.0), Severity::Critical);
+        // Signals left out keep their defaults
+        assert_eq!(policy.spec.thresholds.memory_stall_full_percent.critical, Some(10.0));
+
+        let sampled = (0..1000)
+            .filter(|i| policy.spec.samples("default", &format!("pod-{i}")))
+            .count();
+        assert!((400..600).contains(&sampled), "sampled {sampled} of 1000");
+        assert_eq!(policy.spec.samples("default", "pod-1"), policy.spec.samples("default", "pod-1"));
+
+        let builtin = KernelGossipPolicySpec::builtin();
+        assert!(builtin.require_monitor_annotation);
+        assert!(builtin.excluded_namespaces.iter().any(|ns| ns == "kube-system"));
     }
 
     #[test]
@@ -0,0 +1,101 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Severity, SignalThresholds};
+
+#[derive(CustomResource, Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[kube(
+    group = "kernel.gossip.io",
+    version = "v1alpha1",
+    kind = "MemoryWhisper",
+    plural = "memorywhispers",
+    shortname = "mw",
+    namespaced
+)]
+pub struct MemoryWhisperSpec {
+    pub pod_name: String,
+    pub namespace: String,
+    pub detected_at: String,
+    pub kernel_truth: MemoryKernelTruth,
+    pub metrics_lie: MemoryMetricsLie,
+    pub severity: Severity,
+}
+
+/// What the pod's memory cgroup reported over the sampling window
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryKernelTruth {
+    /// Share of time at least one task was stalled on memory (PSI `some` avg10)
+    pub stall_some_percent: f64,
+    /// Share of time all tasks were stalled on memory (PSI `full` avg10)
+    pub stall_full_percent: f64,
+    pub stall_time_ms: u64,
+    pub high_events: u64,
+    pub max_events: u64,
+    pub oom_events: u64,
+    pub reclaim_scanned_pages: u64,
+    pub working_set_bytes: u64,
+    pub limit_bytes: Option<u64>,
+    pub window_seconds: u64,
+}
+
+/// What metrics-server reports for the same pod
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryMetricsLie {
+    /// Working set from metrics-server; `None` when it had no figure for the pod
+    pub working_set_bytes: Option<u64>,
+    pub reported_status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unavailable_reason: Option<String>,
+}
+
+// Implementation methods - ONLY what's needed for tests
+impl MemoryWhisper {
+    pub fn create(pod_name: &str, namespace: &str, kernel_truth: MemoryKernelTruth) -> Self {
+        let severity = SignalThresholds::default().memory_severity(&kernel_truth);
+
+        Self {
+            metadata: kube::api::ObjectMeta {
+                name: Some(format!("{pod_name}-memory-pressure")),
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            },
+            spec: MemoryWhisperSpec {
+                pod_name: pod_name.to_string(),
+                namespace: namespace.to_string(),
+                detected_at: chrono::Utc::now().to_rfc3339(),
+                kernel_truth,
+                metrics_lie: MemoryMetricsLie {
+                    working_set_bytes: None,
+                    reported_status: "healthy".to_string(),
+                    unavailable_reason: None,
+                },
+                severity,
+            },
+        }
+    }
+
+    pub fn api_version(&self) -> &str {
+        "kernel.gossip.io/v1alpha1"
+    }
+
+    pub fn kind(&self) -> &str {
+        "MemoryWhisper"
+    }
+
+    pub fn pod_name(&self) -> &str {
+        &self.spec.pod_name
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.spec.namespace
+    }
+
+    pub fn kernel_truth(&self) -> &MemoryKernelTruth {
+        &self.spec.kernel_truth
+    }
+
+    pub fn metrics_lie(&self) -> &MemoryMetricsLie {
+        &self.spec.metrics_lie
+    }
+}
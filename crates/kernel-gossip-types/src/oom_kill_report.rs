@@ -0,0 +1,121 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(CustomResource, Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[kube(
+    group = "kernel.gossip.io",
+    version = "v1alpha1",
+    kind = "OOMKillReport",
+    plural = "oomkillreports",
+    shortname = "oomr",
+    namespaced
+)]
+pub struct OOMKillReportSpec {
+    pub pod_name: String,
+    pub namespace: String,
+    pub pod_uid: String,
+    /// Container Kubernetes marked `OOMKilled`, when the status already said so
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    pub killed_at: String,
+    /// Kills the kernel counted in the pod cgroup since the previous poll
+    pub oom_kills: u64,
+    pub memory: OomMemoryBreakdown,
+    pub likely_cause: OomCause,
+}
+
+/// The pod cgroup's memory.stat in the first poll after the kill, so without
+/// the memory the killed process held
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct OomMemoryBreakdown {
+    pub anon_bytes: u64,
+    /// Page cache, including shmem
+    pub file_bytes: u64,
+    pub kernel_bytes: u64,
+    pub sock_bytes: u64,
+    /// tmpfs and shared memory
+    pub shmem_bytes: u64,
+    pub usage_bytes: u64,
+    pub limit_bytes: Option<u64>,
+}
+
+/// Largest consumer in the breakdown
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OomCause {
+    /// Heap and stacks; growth here is what a leak looks like
+    Anonymous,
+    PageCache,
+    /// tmpfs or emptyDir medium Memory filling up
+    Shmem,
+    Kernel,
+    Socket,
+}
+
+impl OomMemoryBreakdown {
+    pub fn likely_cause(&self) -> OomCause {
+        // memory.stat counts shmem inside file, so split it out before comparing
+        let page_cache = self.file_bytes.saturating_sub(self.shmem_bytes);
+        [
+            (OomCause::Anonymous, self.anon_bytes),
+            (OomCause::PageCache, page_cache),
+            (OomCause::Shmem, self.shmem_bytes),
+            (OomCause::Kernel, self.kernel_bytes),
+            (OomCause::Socket, self.sock_bytes),
+        ]
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, bytes)| *bytes)
+        .map(|(cause, _)| cause)
+        .unwrap_or(OomCause::Anonymous)
+    }
+}
+
+// Implementation methods - ONLY what's needed for tests
+impl OOMKillReport {
+    pub fn create(pod_name: &str, namespace: &str, pod_uid: &str, killed_at: &str, memory: OomMemoryBreakdown) -> Self {
+        // One report per kill, so the name carries the time it was seen
+        let killed_secs = chrono::DateTime::parse_from_rfc3339(killed_at)
+            .map(|t| t.timestamp())
+            .unwrap_or_else(|_| chrono::Utc::now().timestamp());
+
+        Self {
+            metadata: kube::api::ObjectMeta {
+                name: Some(format!("{pod_name}-oom-{killed_secs}")),
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            },
+            spec: OOMKillReportSpec {
+                pod_name: pod_name.to_string(),
+                namespace: namespace.to_string(),
+                pod_uid: pod_uid.to_string(),
+                container_name: None,
+                killed_at: killed_at.to_string(),
+                oom_kills: 1,
+                likely_cause: memory.likely_cause(),
+                memory,
+            },
+        }
+    }
+
+    pub fn api_version(&self) -> &str {
+        "kernel.gossip.io/v1alpha1"
+    }
+
+    pub fn kind(&self) -> &str {
+        "OOMKillReport"
+    }
+
+    pub fn pod_name(&self) -> &str {
+        &self.spec.pod_name
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.spec.namespace
+    }
+
+    pub fn memory(&self) -> &OomMemoryBreakdown {
+        &self.spec.memory
+    }
+}
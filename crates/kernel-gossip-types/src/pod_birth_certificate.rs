@@ -9,25 +9,38 @@ use serde::{Deserialize, Serialize};
     kind = "PodBirthCertificate",
     plural = "podbirthcertificates",
     shortname = "pbc",
-    namespaced
+    namespaced,
+    status = "PodBirthCertificateStatus"
 )]
 pub struct PodBirthCertificateSpec {
     pub pod_name: String,
     pub namespace: String,
+    /// Kernel, runtime, kubelet and scheduler steps on one clock, oldest first;
+    /// `timestamp_ms` is milliseconds since the Unix epoch
     pub timeline: Vec<TimelineEntry>,
+    /// Every birth reported for the pod added up, container restarts included
     pub kernel_stats: KernelStats,
+    /// The pod's first reported birth, which the startup breakdown and the
+    /// workload baseline go by; unset on certificates written before it was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_birth: Option<KernelStats>,
+    /// How far the node's entries (kubelet, runtime, kernel) were moved forward
+    /// so none of them comes before the scheduling decision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_clock_offset_ms: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct TimelineEntry {
     pub timestamp_ms: u64,
     pub actor: Actor,
     pub action: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Actor {
+    /// The scheduler and the API server; the only entries on the control plane's clock
     Scheduler,
     Kubelet,
     Runtime,
@@ -39,10 +52,166 @@ pub struct KernelStats {
     pub total_syscalls: u32,
     pub namespaces_created: u8,
     pub cgroup_writes: u32,
+    /// Certificates written before mounts were counted have none
+    #[serde(default)]
+    pub mount_ops: u32,
     pub iptables_rules: u32,
     pub total_duration_ms: u64,
 }
 
+/// Where the pod's start went, worked out by the operator from the timeline
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct PodBirthCertificateStatus {
+    pub phases: StartupPhases,
+    /// Pod created until it turned Ready
+    pub total_ms: Option<u64>,
+    /// The longest of the phases
+    pub dominant_phase: Option<StartupPhase>,
+    pub health: StartupHealth,
+    pub message: String,
+    /// The kernel stats next to earlier births of the same workload
+    pub baseline: Option<BaselineComparison>,
+    pub last_updated: String,
+}
+
+/// A birth's kernel stats against the births of its workload before it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct BaselineComparison {
+    /// Controller of the pod, e.g. `Deployment/web` or `StatefulSet/db`
+    pub workload: String,
+    /// Earlier births the percentiles are taken over
+    pub samples: u32,
+    /// Whether any stat is out of line with the baseline
+    pub regressed: bool,
+    /// Empty until there are enough samples to compare with
+    pub metrics: Vec<MetricBaseline>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct MetricBaseline {
+    pub metric: KernelMetric,
+    /// This birth's value
+    pub value: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub regressed: bool,
+}
+
+/// The kernel stats a birth is compared on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum KernelMetric {
+    TotalSyscalls,
+    CgroupWrites,
+    MountOps,
+    TotalDurationMs,
+}
+
+impl KernelMetric {
+    pub const ALL: [KernelMetric; 4] =
+        [KernelMetric::TotalSyscalls, KernelMetric::CgroupWrites, KernelMetric::MountOps, KernelMetric::TotalDurationMs];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KernelMetric::TotalSyscalls => "syscalls",
+            KernelMetric::CgroupWrites => "cgroup writes",
+            KernelMetric::MountOps => "mount ops",
+            KernelMetric::TotalDurationMs => "kernel setup ms",
+        }
+    }
+
+    pub fn of(self, stats: &KernelStats) -> u64 {
+        match self {
+            KernelMetric::TotalSyscalls => stats.total_syscalls.into(),
+            KernelMetric::CgroupWrites => stats.cgroup_writes.into(),
+            KernelMetric::MountOps => stats.mount_ops.into(),
+            KernelMetric::TotalDurationMs => stats.total_duration_ms,
+        }
+    }
+}
+
+/// How long each part of the start took, in milliseconds. A phase stays unset
+/// until both of its ends are on the timeline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct StartupPhases {
+    /// Pod created until bound to a node
+    pub scheduling_ms: Option<u64>,
+    /// Bound until the runtime had the pod sandbox up
+    pub sandbox_creation_ms: Option<u64>,
+    /// First image pull started until the last one finished
+    pub image_pull_ms: Option<u64>,
+    /// Runtime start to container exec, as the kernel saw it
+    pub kernel_setup_ms: Option<u64>,
+    /// First container process started until the pod turned Ready
+    pub process_start_to_ready_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPhase {
+    Scheduling,
+    SandboxCreation,
+    ImagePull,
+    KernelSetup,
+    ProcessStartToReady,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupHealth {
+    /// Not Ready yet, still within the policy's slow threshold
+    Starting,
+    /// Ready within the slow threshold
+    Healthy,
+    /// Ready, but took longer than the slow threshold
+    Slow,
+    /// Not Ready past the policy's critical threshold
+    Stuck,
+    /// A container cannot start: image pulls, crash loops or bad configuration
+    Failing,
+}
+
+impl StartupPhases {
+    pub fn durations(&self) -> [(StartupPhase, Option<u64>); 5] {
+        [
+            (StartupPhase::Scheduling, self.scheduling_ms),
+            (StartupPhase::SandboxCreation, self.sandbox_creation_ms),
+            (StartupPhase::ImagePull, self.image_pull_ms),
+            (StartupPhase::KernelSetup, self.kernel_setup_ms),
+            (StartupPhase::ProcessStartToReady, self.process_start_to_ready_ms),
+        ]
+    }
+
+    /// The phase that took longest; the earlier one on a tie
+    pub fn dominant(&self) -> Option<(StartupPhase, u64)> {
+        self.durations()
+            .into_iter()
+            .filter_map(|(phase, ms)| Some((phase, ms?)))
+            .filter(|(_, ms)| *ms > 0)
+            .rev()
+            .max_by_key(|(_, ms)| *ms)
+    }
+}
+
+impl StartupPhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StartupPhase::Scheduling => "scheduling",
+            StartupPhase::SandboxCreation => "sandbox creation",
+            StartupPhase::ImagePull => "image pull",
+            StartupPhase::KernelSetup => "kernel setup",
+            StartupPhase::ProcessStartToReady => "process start to ready",
+        }
+    }
+}
+
+impl PodBirthCertificateSpec {
+    /// The stats of the pod's own start, before any restart added to them
+    pub fn birth_stats(&self) -> &KernelStats {
+        self.first_birth.as_ref().unwrap_or(&self.kernel_stats)
+    }
+}
+
 // Implementation methods - ONLY what's needed for tests
 impl PodBirthCertificate {
     pub fn create(pod_name: &str, namespace: &str) -> Self {
@@ -60,10 +229,14 @@ impl PodBirthCertificate {
                     total_syscalls: 0,
                     namespaces_created: 0,
                     cgroup_writes: 0,
+                    mount_ops: 0,
                     iptables_rules: 0,
                     total_duration_ms: 0,
                 },
+                first_birth: None,
+                node_clock_offset_ms: None,
             },
+            status: None,
         }
     }
 
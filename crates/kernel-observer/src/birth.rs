@@ -0,0 +1,351 @@
+//! Birth sessions: what a container runtime did between starting and the
+//! container's first exec.
+//!
+//! `container-birth.bt` reports a runtime exec (runc, crun) and then every
+//! fork, namespace operation, mount, cgroupfs write and syscall count of the
+//! processes descending from it. The tracker keys a session by the runtime's
+//! PID, follows the lineage through the forks, and closes the session when
+//! one of its processes execs the container's own binary.
+//!
+//! `runc exec` (exec probes, `kubectl exec`) looks the same up to that point,
+//! but it joins the namespaces of a running container instead of creating
+//! them and never calls `pivot_root`; such sessions close as
+//! [`SessionKind::Exec`].
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use kernel_gossip_protocol::TimelineEvent;
+
+use crate::records::{
+    ContainerCgroupWrite, ContainerFork, ContainerMain, ContainerMountOp, ContainerNamespaceOp, ContainerProcessStart,
+};
+
+/// Steps kept per session; a runtime can mount hundreds of times
+const MAX_STEPS: usize = 64;
+
+/// Whether a session started a container or ran a process in one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionKind {
+    /// Created namespaces or pivoted into a new root
+    Creation,
+    /// Only joined the namespaces of a running container
+    Exec,
+}
+
+/// What a closed session amounts to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Birth {
+    pub kind: SessionKind,
+    /// The runtime process the session started with
+    pub runtime_pid: u32,
+    pub main_pid: u32,
+    pub main_comm: String,
+    pub total_syscalls: u64,
+    pub namespace_ops: u64,
+    pub mounts: u64,
+    pub pivot_root: bool,
+    pub cgroup_writes: u64,
+    pub duration_ns: u64,
+    pub timeline: Vec<TimelineEvent>,
+}
+
+#[derive(Debug)]
+struct Session {
+    runtime_pid: u32,
+    /// Kernel clock at the runtime exec, which step times are relative to
+    started_ms: Option<u64>,
+    opened_at: Instant,
+    pids: HashSet<u32>,
+    /// Latest count per process; the script reports running totals
+    syscalls: HashMap<u32, u64>,
+    namespace_ops: u64,
+    /// unshare and namespace-creating clones, as opposed to setns
+    new_namespaces: u64,
+    mounts: u64,
+    pivot_root: bool,
+    cgroup_writes: u64,
+    steps: Vec<Step>,
+}
+
+/// Consecutive steps of the same kind are folded into one
+#[derive(Debug)]
+struct Step {
+    at_ms: u64,
+    action: String,
+    count: u64,
+}
+
+impl Session {
+    fn offset(&self, timestamp_ms: Option<u64>) -> u64 {
+        match (self.started_ms, timestamp_ms) {
+            (Some(started), Some(at)) => at.saturating_sub(started),
+            _ => self.opened_at.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn step(&mut self, timestamp_ms: Option<u64>, action: impl Into<String>) {
+        let at_ms = self.offset(timestamp_ms);
+        let action = action.into();
+        if let Some(last) = self.steps.last_mut().filter(|last| last.action == action) {
+            last.count += 1;
+        } else if self.steps.len() < MAX_STEPS {
+            self.steps.push(Step { at_ms, action, count: 1 });
+        }
+    }
+
+    fn timeline(&self) -> Vec<TimelineEvent> {
+        self.steps
+            .iter()
+            .map(|step| TimelineEvent {
+                timestamp_ms: step.at_ms,
+                action: if step.count > 1 { format!("{} ×{}", step.action, step.count) } else { step.action.clone() },
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct BirthTracker {
+    sessions: HashMap<u32, Session>,
+    /// Session of every process in a lineage, by PID
+    lineage: HashMap<u32, u32>,
+    max_age: Duration,
+}
+
+impl BirthTracker {
+    /// Sessions that see no exec within `max_age` are dropped by [`Self::cleanup`]
+    pub fn new(max_age: Duration) -> Self {
+        Self { sessions: HashMap::new(), lineage: HashMap::new(), max_age }
+    }
+
+    pub fn process_start(&mut self, start: &ContainerProcessStart) {
+        // A runtime re-executing itself stays in its session
+        if let Some(session) = self.session_mut(start.pid) {
+            session.step(start.timestamp_ms, format!("{} exec", start.comm));
+            return;
+        }
+        let mut session = Session {
+            runtime_pid: start.pid,
+            started_ms: start.timestamp_ms,
+            opened_at: Instant::now(),
+            pids: HashSet::from([start.pid]),
+            syscalls: HashMap::new(),
+            namespace_ops: 0,
+            new_namespaces: 0,
+            mounts: 0,
+            pivot_root: false,
+            cgroup_writes: 0,
+            steps: Vec::new(),
+        };
+        session.step(start.timestamp_ms, format!("{} started", start.comm));
+        self.lineage.insert(start.pid, start.pid);
+        self.sessions.insert(start.pid, session);
+    }
+
+    pub fn fork(&mut self, fork: &ContainerFork) {
+        let Some(&root) = self.lineage.get(&fork.ppid) else {
+            return;
+        };
+        self.lineage.insert(fork.pid, root);
+        if let Some(session) = self.sessions.get_mut(&root) {
+            session.pids.insert(fork.pid);
+            session.step(fork.timestamp_ms, "fork");
+        }
+    }
+
+    pub fn namespace_op(&mut self, op: &ContainerNamespaceOp) {
+        if let Some(session) = self.session_mut(op.pid) {
+            session.namespace_ops += 1;
+            if op.op != "setns" {
+                session.new_namespaces += 1;
+            }
+            session.step(op.timestamp_ms, op.op.clone());
+        }
+    }
+
+    pub fn mount_op(&mut self, op: &ContainerMountOp) {
+        if let Some(session) = self.session_mut(op.pid) {
+            if op.op == "pivot_root" {
+                session.pivot_root = true;
+            } else {
+                session.mounts += 1;
+            }
+            session.step(op.timestamp_ms, op.op.clone());
+        }
+    }
+
+    pub fn cgroup_write(&mut self, write: &ContainerCgroupWrite) {
+        if let Some(session) = self.session_mut(write.pid) {
+            session.cgroup_writes += 1;
+            session.step(write.timestamp_ms, "cgroup write");
+        }
+    }
+
+    /// A running syscall total of one session process
+    pub fn syscalls(&mut self, pid: u32, total: u64) {
+        if let Some(session) = self.session_mut(pid) {
+            let count = session.syscalls.entry(pid).or_default();
+            *count = (*count).max(total);
+        }
+    }
+
+    /// Whether `pid` belongs to an open session
+    pub fn tracks(&self, pid: u32) -> bool {
+        self.lineage.contains_key(&pid)
+    }
+
+    /// Close the session the new container process came from
+    pub fn container_main(&mut self, main: &ContainerMain) -> Option<Birth> {
+        let root = self.lineage.get(&main.pid).or_else(|| self.lineage.get(&main.ppid)).copied()?;
+        let mut session = self.sessions.remove(&root)?;
+        session.step(main.timestamp_ms, format!("exec {}", main.comm));
+        for pid in &session.pids {
+            self.lineage.remove(pid);
+        }
+        self.lineage.remove(&main.pid);
+
+        let duration_ms = session.offset(main.timestamp_ms);
+        let kind = if session.new_namespaces == 0 && !session.pivot_root {
+            SessionKind::Exec
+        } else {
+            SessionKind::Creation
+        };
+        Some(Birth {
+            kind,
+            runtime_pid: session.runtime_pid,
+            main_pid: main.pid,
+            main_comm: main.comm.clone(),
+            total_syscalls: session.syscalls.values().sum(),
+            namespace_ops: session.namespace_ops,
+            mounts: session.mounts,
+            pivot_root: session.pivot_root,
+            cgroup_writes: session.cgroup_writes,
+            duration_ns: duration_ms * 1_000_000,
+            timeline: session.timeline(),
+        })
+    }
+
+    /// Drop sessions whose container never started, returning how many
+    pub fn cleanup(&mut self) -> usize {
+        let max_age = self.max_age;
+        let before = self.sessions.len();
+        self.sessions.retain(|_, session| session.opened_at.elapsed() <= max_age);
+        let sessions = &self.sessions;
+        self.lineage.retain(|_, root| sessions.contains_key(root));
+        before - self.sessions.len()
+    }
+
+    pub fn open_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+
+    fn session_mut(&mut self, pid: u32) -> Option<&mut Session> {
+        let root = self.lineage.get(&pid)?;
+        self.sessions.get_mut(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(pid: u32, at: u64) -> ContainerProcessStart {
+        ContainerProcessStart { pid, ppid: 1, comm: "runc".to_string(), timestamp_ms: Some(at) }
+    }
+
+    fn fork(pid: u32, ppid: u32, at: u64) -> ContainerFork {
+        ContainerFork { pid, ppid, parent_comm: "runc".to_string(), timestamp_ms: Some(at) }
+    }
+
+    fn namespace(pid: u32, op: &str, at: u64) -> ContainerNamespaceOp {
+        ContainerNamespaceOp { pid, op: op.to_string(), timestamp_ms: Some(at) }
+    }
+
+    fn mount(pid: u32, op: &str, at: u64) -> ContainerMountOp {
+        ContainerMountOp { pid, op: op.to_string(), timestamp_ms: Some(at) }
+    }
+
+    fn main(pid: u32, ppid: u32, at: u64) -> ContainerMain {
+        ContainerMain { pid, ppid, comm: "nginx".to_string(), timestamp_ms: Some(at) }
+    }
+
+    #[test]
+    fn test_session_follows_lineage_to_exec() {
+        let mut tracker = BirthTracker::new(Duration::from_secs(60));
+        tracker.process_start(&start(100, 1_000));
+        tracker.fork(&fork(101, 100, 1_002));
+        tracker.fork(&fork(102, 101, 1_003));
+        tracker.namespace_op(&namespace(101, "unshare", 1_005));
+        tracker.namespace_op(&namespace(101, "unshare", 1_006));
+        tracker.namespace_op(&namespace(102, "setns", 1_007));
+        for at in 1_010..1_015 {
+            tracker.mount_op(&mount(102, "mount", at));
+        }
+        tracker.cgroup_write(&ContainerCgroupWrite {
+            pid: 100,
+            path: "/sys/fs/cgroup/kubepods/pod1/abc/cgroup.procs".to_string(),
+            timestamp_ms: Some(1_020),
+        });
+        tracker.mount_op(&mount(102, "pivot_root", 1_030));
+        tracker.syscalls(100, 300);
+        tracker.syscalls(100, 412);
+        tracker.syscalls(102, 100);
+        tracker.syscalls(102, 57); // out of order
+
+        // Not part of the session
+        tracker.namespace_op(&namespace(999, "unshare", 1_008));
+        assert!(tracker.container_main(&main(999, 1, 1_040)).is_none());
+
+        let birth = tracker.container_main(&main(102, 101, 1_123)).expect("session closes");
+        assert_eq!(birth.kind, SessionKind::Creation);
+        assert_eq!(birth.runtime_pid, 100);
+        assert_eq!(birth.total_syscalls, 512);
+        assert_eq!(birth.namespace_ops, 3);
+        assert_eq!(birth.mounts, 5);
+        assert!(birth.pivot_root);
+        assert_eq!(birth.cgroup_writes, 1);
+        assert_eq!(birth.duration_ns, 123_000_000);
+
+        let actions: Vec<_> = birth.timeline.iter().map(|step| step.action.as_str()).collect();
+        assert_eq!(
+            actions,
+            ["runc started", "fork ×2", "unshare ×2", "setns", "mount ×5", "cgroup write", "pivot_root", "exec nginx"]
+        );
+        assert_eq!(birth.timeline[4].timestamp_ms, 10);
+        assert_eq!(birth.timeline.last().unwrap().timestamp_ms, 123);
+
+        // The lineage is forgotten with the session
+        assert!(!tracker.tracks(101));
+        assert_eq!(tracker.open_sessions(), 0);
+    }
+
+    #[test]
+    fn test_exec_into_running_container_is_not_a_birth() {
+        let mut tracker = BirthTracker::new(Duration::from_secs(60));
+        tracker.process_start(&start(200, 5_000));
+        tracker.fork(&fork(201, 200, 5_001));
+        // Joins the mount, pid and network namespaces of the container
+        for at in 5_002..5_005 {
+            tracker.namespace_op(&namespace(201, "setns", at));
+        }
+        tracker.syscalls(201, 80);
+
+        let exec = tracker.container_main(&main(201, 200, 5_010)).expect("session closes");
+        assert_eq!(exec.kind, SessionKind::Exec);
+        assert_eq!(exec.namespace_ops, 3);
+        assert_eq!(tracker.open_sessions(), 0);
+    }
+
+    #[test]
+    fn test_cleanup_drops_stale_sessions() {
+        let mut tracker = BirthTracker::new(Duration::ZERO);
+        tracker.process_start(&start(100, 0));
+        tracker.fork(&fork(101, 100, 1));
+        std::thread::sleep(Duration::from_millis(2));
+
+        assert_eq!(tracker.cleanup(), 1);
+        assert!(!tracker.tracks(101));
+        assert!(tracker.container_main(&main(101, 100, 5)).is_none());
+    }
+}
@@ -1,64 +1,276 @@
+use std::collections::VecDeque;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+
 use tokio::process::{Child, Command};
-use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tokio::task::JoinHandle;
 use anyhow::Result;
-use tracing::{info, error};
+use serde::Serialize;
+use tracing::{info, error, warn};
+
+use crate::metrics::metrics;
+
+/// Stderr lines kept for the readiness endpoint, across restarts
+pub const STDERR_RING_LINES: usize = 100;
+
+/// Whether a bpftrace complaint can go away by starting it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StderrClass {
+    /// The script or the node is wrong: a restart fails the same way
+    Fatal,
+    /// Lost events, attach races, resource exhaustion and anything unrecognised
+    Transient,
+}
+
+impl StderrClass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StderrClass::Fatal => "fatal",
+            StderrClass::Transient => "transient",
+        }
+    }
+}
+
+/// Messages bpftrace prints when the script cannot be compiled or the kernel
+/// lacks what it needs
+const FATAL_PATTERNS: &[&str] = &[
+    "syntax error",
+    "unknown function",
+    "unknown identifier",
+    "unknown struct",
+    "undefined map",
+    "does not exist",
+    "no btf",
+    "btf is not available",
+    "could not resolve symbol",
+    "kernel headers",
+    "kernel does not support",
+    "operation not permitted",
+    "permission denied",
+    "must be run as root",
+    "no probes to attach",
+];
+
+pub fn classify_stderr(line: &str) -> StderrClass {
+    let lower = line.to_ascii_lowercase();
+    if FATAL_PATTERNS.iter().any(|pattern| lower.contains(pattern)) {
+        StderrClass::Fatal
+    } else {
+        StderrClass::Transient
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StderrLine {
+    pub at: String,
+    pub class: StderrClass,
+    pub line: String,
+}
+
+/// The most recent bpftrace stderr lines; clones share the same ring
+#[derive(Clone)]
+pub struct StderrRing {
+    lines: Arc<Mutex<VecDeque<StderrLine>>>,
+    capacity: usize,
+}
+
+impl StderrRing {
+    pub fn new(capacity: usize) -> Self {
+        Self { lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))), capacity: capacity.max(1) }
+    }
+
+    pub fn push(&self, class: StderrClass, line: &str) {
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(StderrLine {
+            at: chrono::Utc::now().to_rfc3339(),
+            class,
+            line: line.to_string(),
+        });
+    }
+
+    /// Oldest first
+    pub fn recent(&self) -> Vec<StderrLine> {
+        self.lines.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+}
+
+/// bpftrace's `-f` output mode. The parser reads both; JSON keeps map entries
+/// and tuples typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => anyhow::bail!("unknown bpftrace output format {other}, expected text or json"),
+        }
+    }
+}
+
+/// How a bpftrace run ended
+#[derive(Debug, Clone)]
+pub struct BpftraceExit {
+    pub status: Option<ExitStatus>,
+    /// The first fatal stderr line of the run
+    pub fatal: Option<String>,
+}
 
 pub struct BpftraceProcess {
     child: Child,
-    lines: Lines<BufReader<tokio::process::ChildStdout>>,
-    stderr_lines: Lines<BufReader<tokio::process::ChildStderr>>,
+    stdout: BufReader<tokio::process::ChildStdout>,
+    /// Drains stderr as it is written, so a chatty bpftrace never blocks on a
+    /// full pipe; yields the first fatal line
+    stderr: JoinHandle<Option<String>>,
 }
 
 impl BpftraceProcess {
-    pub async fn spawn(script: &str) -> Result<Self> {
+    pub fn command(script: &str, format: OutputFormat) -> Command {
         info!("🔧 Spawning bpftrace with script length: {} bytes", script.len());
-        
-        let mut child = Command::new("bpftrace")
-            .args(["-e", script])
+        let mut command = Command::new("bpftrace");
+        if format == OutputFormat::Json {
+            command.args(["-f", "json"]);
+        }
+        command.args(["-e", script]);
+        command
+    }
+
+    pub fn spawn(mut command: Command, ring: StderrRing) -> Result<Self> {
+        let mut child = command
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
             .spawn()?;
 
-        let stdout = child.stdout.take().unwrap();
-        let stderr = child.stderr.take().unwrap();
-        let reader = BufReader::new(stdout);
-        let stderr_reader = BufReader::new(stderr);
-        let lines = reader.lines();
-        let stderr_lines = stderr_reader.lines();
-
-        Ok(BpftraceProcess { 
-            child, 
-            lines,
-            stderr_lines
+        let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("bpftrace stdout is not piped"))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow::anyhow!("bpftrace stderr is not piped"))?;
+        let stderr = tokio::spawn(read_stderr(BufReader::new(stderr), ring));
+
+        Ok(BpftraceProcess {
+            child,
+            stdout: BufReader::new(stdout),
+            stderr,
         })
     }
 
+    /// The next stdout line, with bytes that are not UTF-8 replaced: traced
+    /// processes choose their own `comm`, so one odd name must not end the
+    /// stream
     pub async fn next_line(&mut self) -> Result<Option<String>> {
-        Ok(self.lines.next_line().await?)
+        read_lossy_line(&mut self.stdout).await
+    }
+
+    /// Reap the process once its output has ended, after the rest of its stderr
+    pub async fn wait(mut self) -> BpftraceExit {
+        let status = match self.child.wait().await {
+            Ok(status) => {
+                info!("🏁 bpftrace process exited with status: {:?}", status);
+                Some(status)
+            }
+            Err(e) => {
+                error!("🚨 Could not reap bpftrace: {}", e);
+                None
+            }
+        };
+        let fatal = self.stderr.await.unwrap_or_default();
+        BpftraceExit { status, fatal }
     }
 
-    pub async fn check_stderr(&mut self) -> Result<()> {
-        // Non-blocking check for stderr output
-        if let Ok(Ok(Some(line))) = tokio::time::timeout(
-            tokio::time::Duration::from_millis(10), 
-            self.stderr_lines.next_line()
-        ).await {
-            error!("🚨 bpftrace error: {}", line);
+    /// Kill a process whose stdout can no longer be read, then reap it; waiting
+    /// on it alive would block forever once the unread pipe fills
+    pub async fn abort(mut self) -> BpftraceExit {
+        if let Err(e) = self.child.start_kill() {
+            warn!("⚠️ Could not kill bpftrace: {}", e);
         }
+        self.wait().await
+    }
+
+    pub async fn kill(mut self) -> Result<()> {
+        self.child.kill().await?;
+        self.stderr.abort();
         Ok(())
     }
+}
 
-    pub async fn wait(&mut self) -> Result<()> {
-        let status = self.child.wait().await?;
-        info!("🏁 bpftrace process exited with status: {:?}", status);
-        
-        // Read any remaining stderr messages
-        while let Ok(Some(error_line)) = self.stderr_lines.next_line().await {
-            if !error_line.is_empty() {
-                error!("🚨 bpftrace final error: {}", error_line);
-            }
+/// One line without its terminator, or `None` at end of stream
+async fn read_lossy_line(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<String>> {
+    let mut buf = Vec::new();
+    if reader.read_until(b'\n', &mut buf).await? == 0 {
+        return Ok(None);
+    }
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
         }
-        
-        Ok(())
     }
-}
\ No newline at end of file
+    Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+}
+
+async fn read_stderr(mut stderr: BufReader<tokio::process::ChildStderr>, ring: StderrRing) -> Option<String> {
+    let mut fatal = None;
+    while let Ok(Some(line)) = read_lossy_line(&mut stderr).await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let class = classify_stderr(&line);
+        match class {
+            StderrClass::Fatal => error!("🚨 bpftrace error: {}", line),
+            StderrClass::Transient => warn!("⚠️ bpftrace: {}", line),
+        }
+        metrics().pipeline.bpftrace_stderr(class.as_str());
+        ring.push(class, &line);
+        if class == StderrClass::Fatal && fatal.is_none() {
+            fatal = Some(line);
+        }
+    }
+    fatal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_stderr() {
+        assert_eq!(classify_stderr("stdin:3:1-10: ERROR: syntax error, unexpected identifier"), StderrClass::Fatal);
+        assert_eq!(classify_stderr("ERROR: Unknown function: cgroup_path2"), StderrClass::Fatal);
+        assert_eq!(classify_stderr("ERROR: kernel does not support BTF"), StderrClass::Fatal);
+        assert_eq!(classify_stderr("ERROR: bpf: Operation not permitted"), StderrClass::Fatal);
+        assert_eq!(classify_stderr("Lost 42 events"), StderrClass::Transient);
+        assert_eq!(classify_stderr("ERROR: Failed to attach probe: tracepoint:sched:sched_switch"), StderrClass::Transient);
+        assert_eq!(classify_stderr("ERROR: failed to create map: Cannot allocate memory"), StderrClass::Transient);
+    }
+
+    #[tokio::test]
+    async fn test_read_lossy_line() {
+        let mut input: &[u8] = b"comm=\xff\xfe\r\nnext\nlast";
+        assert_eq!(read_lossy_line(&mut input).await.unwrap().unwrap(), "comm=\u{fffd}\u{fffd}");
+        assert_eq!(read_lossy_line(&mut input).await.unwrap().unwrap(), "next");
+        assert_eq!(read_lossy_line(&mut input).await.unwrap().unwrap(), "last");
+        assert!(read_lossy_line(&mut input).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stderr_ring_keeps_newest() {
+        let ring = StderrRing::new(3);
+        for i in 0..5 {
+            ring.push(StderrClass::Transient, &format!("Lost {i} events"));
+        }
+        let recent = ring.recent();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].line, "Lost 2 events");
+        assert_eq!(recent[2].line, "Lost 4 events");
+    }
+}
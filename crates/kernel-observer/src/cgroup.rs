@@ -0,0 +1,917 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+pub const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+pub const DEFAULT_PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CgroupVersion {
+    V1,
+    V2,
+}
+
+/// A pod-level cgroup directory on the host, within one controller hierarchy
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PodCgroup {
+    pub version: CgroupVersion,
+    pub dir: PathBuf,
+}
+
+impl PodCgroup {
+    pub fn pod_uid(&self) -> Option<String> {
+        self.dir.file_name()?.to_str().and_then(pod_uid_from_segment)
+    }
+}
+
+/// Raw CFS bandwidth counters from `cpu.stat`, normalised to microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuStat {
+    pub nr_periods: u64,
+    pub nr_throttled: u64,
+    pub throttled_usec: u64,
+    pub usage_usec: Option<u64>,
+}
+
+impl CpuStat {
+    /// Parse `cpu.stat`. cgroup v1 reports `throttled_time` in nanoseconds,
+    /// cgroup v2 reports `throttled_usec` and `usage_usec` in microseconds.
+    pub fn parse(content: &str) -> Self {
+        let mut stat = CpuStat::default();
+
+        for line in content.lines() {
+            let mut parts = line.split_whitespace();
+            let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let Ok(value) = value.parse::<u64>() else {
+                continue;
+            };
+
+            match key {
+                "nr_periods" => stat.nr_periods = value,
+                "nr_throttled" => stat.nr_throttled = value,
+                "throttled_usec" => stat.throttled_usec = value,
+                "throttled_time" => stat.throttled_usec = value / 1_000,
+                "usage_usec" => stat.usage_usec = Some(value),
+                _ => {}
+            }
+        }
+
+        stat
+    }
+}
+
+/// Throttling observed between two `cpu.stat` reads of the same cgroup
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CpuThrottleSample {
+    pub nr_periods: u64,
+    pub nr_throttled: u64,
+    pub throttled_usec: u64,
+    pub usage_usec: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl CpuThrottleSample {
+    fn between(previous: &CpuStat, current: &CpuStat, elapsed: Duration) -> Self {
+        // Counters reset when a cgroup is recreated; saturate rather than underflow
+        Self {
+            nr_periods: current.nr_periods.saturating_sub(previous.nr_periods),
+            nr_throttled: current.nr_throttled.saturating_sub(previous.nr_throttled),
+            throttled_usec: current.throttled_usec.saturating_sub(previous.throttled_usec),
+            usage_usec: match (previous.usage_usec, current.usage_usec) {
+                (Some(prev), Some(cur)) => Some(cur.saturating_sub(prev)),
+                _ => None,
+            },
+            elapsed,
+        }
+    }
+
+    /// Percentage of enforcement periods in which the cgroup hit its quota
+    pub fn throttled_percentage(&self) -> f64 {
+        if self.nr_periods == 0 {
+            return 0.0;
+        }
+        let percentage = self.nr_throttled as f64 / self.nr_periods as f64 * 100.0;
+        (percentage.min(100.0) * 10.0).round() / 10.0
+    }
+
+    /// Average cores consumed over the window, when the kernel exposes usage
+    pub fn cpu_cores(&self) -> Option<f64> {
+        let elapsed_usec = self.elapsed.as_micros() as f64;
+        if elapsed_usec == 0.0 {
+            return None;
+        }
+        self.usage_usec
+            .map(|usage| (usage as f64 / elapsed_usec * 100.0).round() / 100.0)
+    }
+}
+
+/// Pressure stall information for one line (`some` or `full`) of a PSI file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Psi {
+    pub avg10: f64,
+    pub total_usec: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PressureStall {
+    pub some: Psi,
+    pub full: Psi,
+}
+
+impl PressureStall {
+    /// Parse a PSI file such as `memory.pressure`:
+    /// `some avg10=1.50 avg60=0.80 avg300=0.20 total=123456`
+    pub fn parse(content: &str) -> Self {
+        let mut stall = PressureStall::default();
+
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            let target = match fields.next() {
+                Some("some") => &mut stall.some,
+                Some("full") => &mut stall.full,
+                _ => continue,
+            };
+            for field in fields {
+                match field.split_once('=') {
+                    Some(("avg10", v)) => target.avg10 = v.parse().unwrap_or(0.0),
+                    Some(("total", v)) => target.total_usec = v.parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+        }
+
+        stall
+    }
+}
+
+/// Counters from `memory.events` (v2) or `memory.oom_control`/`memory.failcnt` (v1)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryEvents {
+    pub high: u64,
+    pub max: u64,
+    pub oom: u64,
+    pub oom_kill: u64,
+}
+
+impl MemoryEvents {
+    pub fn parse(content: &str) -> Self {
+        let mut events = MemoryEvents::default();
+        for (key, value) in key_values(content) {
+            match key {
+                "high" => events.high = value,
+                "max" => events.max = value,
+                "oom" => events.oom = value,
+                "oom_kill" => events.oom_kill = value,
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// `memory.stat` as raw counters; v1 hierarchical keys are exposed under their v2 names
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStat {
+    values: HashMap<String, u64>,
+}
+
+impl MemoryStat {
+    pub fn parse(content: &str) -> Self {
+        let mut values = HashMap::new();
+        for (key, value) in key_values(content) {
+            // v1 reports the pod-wide figures as total_<key>; prefer those
+            match key.strip_prefix("total_") {
+                Some(v2_key) => {
+                    values.insert(v2_key.to_string(), value);
+                }
+                None => {
+                    values.entry(key.to_string()).or_insert(value);
+                }
+            }
+        }
+        Self { values }
+    }
+
+    pub fn get(&self, key: &str) -> u64 {
+        self.values.get(key).copied().unwrap_or(0)
+    }
+
+    /// Where the cgroup's memory went, by the categories that tell a leak from cache or tmpfs growth
+    pub fn breakdown(&self) -> MemoryBreakdown {
+        let first_of = |keys: &[&str]| keys.iter().find_map(|k| self.values.get(*k).copied()).unwrap_or(0);
+
+        // `kernel` only exists since 5.18; older v2 kernels report the parts
+        let kernel = self.values.get("kernel").copied().unwrap_or_else(|| {
+            ["kernel_stack", "pagetables", "percpu", "slab"].iter().map(|k| self.get(k)).sum()
+        });
+
+        MemoryBreakdown {
+            anon_bytes: first_of(&["anon", "rss"]),
+            file_bytes: first_of(&["file", "cache"]),
+            kernel_bytes: kernel,
+            sock_bytes: self.get("sock"),
+            shmem_bytes: self.get("shmem"),
+        }
+    }
+}
+
+/// memory.stat grouped the way an OOM post-mortem needs it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryBreakdown {
+    pub anon_bytes: u64,
+    pub file_bytes: u64,
+    pub kernel_bytes: u64,
+    pub sock_bytes: u64,
+    pub shmem_bytes: u64,
+}
+
+fn key_values(content: &str) -> impl Iterator<Item = (&str, u64)> {
+    content.lines().filter_map(|line| {
+        let mut parts = line.split_whitespace();
+        let key = parts.next()?;
+        let value = parts.next()?.parse().ok()?;
+        Some((key, value))
+    })
+}
+
+/// One read of a pod's memory cgroup
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemorySnapshot {
+    /// `None` on cgroup v1, which has no PSI
+    pub pressure: Option<PressureStall>,
+    pub events: MemoryEvents,
+    pub stat: MemoryStat,
+    pub usage_bytes: u64,
+    pub limit_bytes: Option<u64>,
+}
+
+impl MemorySnapshot {
+    /// Working set as the kubelet and metrics-server define it: usage minus inactive file cache
+    pub fn working_set_bytes(&self) -> u64 {
+        self.usage_bytes.saturating_sub(self.stat.get("inactive_file"))
+    }
+
+    /// Pages scanned by reclaim, direct or by kswapd
+    pub fn reclaim_scanned_pages(&self) -> u64 {
+        let pgscan = self.stat.get("pgscan");
+        if pgscan > 0 {
+            pgscan
+        } else {
+            self.stat.get("pgscan_direct") + self.stat.get("pgscan_kswapd")
+        }
+    }
+}
+
+/// Memory pressure accumulated between two snapshots of the same cgroup
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryPressureSample {
+    pub some_avg10: f64,
+    pub full_avg10: f64,
+    pub some_stall_usec: u64,
+    pub full_stall_usec: u64,
+    pub high_events: u64,
+    pub max_events: u64,
+    pub oom_events: u64,
+    pub oom_kill_events: u64,
+    pub reclaim_scanned_pages: u64,
+    pub working_set_bytes: u64,
+    pub limit_bytes: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl MemoryPressureSample {
+    pub fn between(previous: &MemorySnapshot, current: &MemorySnapshot, elapsed: Duration) -> Self {
+        let pressure = current.pressure.unwrap_or_default();
+        let previous_pressure = previous.pressure.unwrap_or_default();
+
+        Self {
+            some_avg10: pressure.some.avg10,
+            full_avg10: pressure.full.avg10,
+            some_stall_usec: pressure.some.total_usec.saturating_sub(previous_pressure.some.total_usec),
+            full_stall_usec: pressure.full.total_usec.saturating_sub(previous_pressure.full.total_usec),
+            high_events: current.events.high.saturating_sub(previous.events.high),
+            max_events: current.events.max.saturating_sub(previous.events.max),
+            oom_events: current.events.oom.saturating_sub(previous.events.oom),
+            oom_kill_events: current.events.oom_kill.saturating_sub(previous.events.oom_kill),
+            reclaim_scanned_pages: current.reclaim_scanned_pages().saturating_sub(previous.reclaim_scanned_pages()),
+            working_set_bytes: current.working_set_bytes(),
+            limit_bytes: current.limit_bytes,
+            elapsed,
+        }
+    }
+
+    /// Tasks stalled on memory above `psi_threshold` percent, or the cgroup hit its limits
+    pub fn is_under_pressure(&self, psi_threshold: f64) -> bool {
+        self.some_avg10 >= psi_threshold
+            || self.high_events > 0
+            || self.max_events > 0
+            || self.oom_events > 0
+    }
+}
+
+/// What a recording read from `/proc` and `cpu.stat`, for replaying it where
+/// neither the processes nor their cgroups exist any more
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordedCgroups {
+    /// The pod cgroup of each PID
+    pub pods: HashMap<u32, PodCgroup>,
+    /// Every throttling sample of a cgroup directory, in the order they were taken
+    pub samples: HashMap<PathBuf, VecDeque<Option<CpuThrottleSample>>>,
+}
+
+/// Reads CFS throttling counters for pod cgroups, keeping one baseline per
+/// cgroup so each sample covers at least `window` of kernel accounting.
+pub struct CgroupReader {
+    cgroup_root: PathBuf,
+    proc_root: PathBuf,
+    window: Duration,
+    baselines: Mutex<HashMap<PathBuf, (Instant, CpuStat)>>,
+    /// Answers taken from a recording, instead of `/proc` and the cgroup filesystem
+    recorded: Option<Mutex<RecordedCgroups>>,
+}
+
+impl CgroupReader {
+    pub fn new(cgroup_root: impl Into<PathBuf>, proc_root: impl Into<PathBuf>, window: Duration) -> Self {
+        Self {
+            cgroup_root: cgroup_root.into(),
+            proc_root: proc_root.into(),
+            window,
+            baselines: Mutex::new(HashMap::new()),
+            recorded: None,
+        }
+    }
+
+    /// Answer as a recording did, since its processes and cgroups are long gone
+    pub fn with_recorded(mut self, recorded: RecordedCgroups) -> Self {
+        self.recorded = Some(Mutex::new(recorded));
+        self
+    }
+
+    /// Locate the pod-level cgroup of a process from `/proc/<pid>/cgroup`
+    pub async fn pod_cgroup_for_pid(&self, pid: u32) -> Result<Option<PodCgroup>> {
+        if let Some(recorded) = &self.recorded {
+            return Ok(recorded.lock().await.pods.get(&pid).cloned());
+        }
+        let content = tokio::fs::read_to_string(self.proc_root.join(pid.to_string()).join("cgroup")).await?;
+        Ok(self.pod_cgroup_from_proc_cgroup(&content))
+    }
+
+    fn pod_cgroup_from_proc_cgroup(&self, content: &str) -> Option<PodCgroup> {
+        let mut unified = None;
+        let mut cpu_v1 = None;
+
+        // Format: hierarchy-ID:controller-list:cgroup-path
+        for line in content.lines() {
+            let mut fields = line.splitn(3, ':');
+            let (Some(_), Some(controllers), Some(path)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+
+            if controllers.is_empty() {
+                unified = Some(path);
+            } else if controllers.split(',').any(|c| c == "cpu") {
+                cpu_v1 = Some((controllers, path));
+            }
+        }
+
+        // Hybrid hosts keep the cpu controller on v1, so prefer it when present
+        if let Some((controllers, path)) = cpu_v1 {
+            let base = self.cgroup_root.join(controllers);
+            return self.resolve_pod_dir(&base, path).map(|dir| PodCgroup { version: CgroupVersion::V1, dir });
+        }
+
+        unified.and_then(|path| {
+            self.resolve_pod_dir(&self.cgroup_root, path)
+                .map(|dir| PodCgroup { version: CgroupVersion::V2, dir })
+        })
+    }
+
+    fn resolve_pod_dir(&self, base: &Path, path: &str) -> Option<PathBuf> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let pod_index = segments.iter().position(|s| is_pod_segment(s))?;
+
+        // Paths seen from inside a cgroup namespace are relative ("/../../pod<uid>/..."),
+        // so only join them directly when they are rooted at the host hierarchy
+        if !segments[..pod_index].contains(&"..") {
+            let dir = segments[..=pod_index].iter().fold(base.to_path_buf(), |dir, s| dir.join(s));
+            if dir.is_dir() {
+                return Some(dir);
+            }
+        }
+
+        find_dir_named(base, segments[pod_index], 5)
+    }
+
+    pub async fn read_cpu_stat(&self, cgroup: &PodCgroup) -> Result<CpuStat> {
+        let content = tokio::fs::read_to_string(cgroup.dir.join("cpu.stat")).await?;
+        let mut stat = CpuStat::parse(&content);
+
+        // v1 keeps usage in the cpuacct controller, usually co-mounted as cpu,cpuacct
+        if cgroup.version == CgroupVersion::V1 && stat.usage_usec.is_none() {
+            if let Ok(usage) = tokio::fs::read_to_string(cgroup.dir.join("cpuacct.usage")).await {
+                stat.usage_usec = usage.trim().parse::<u64>().ok().map(|ns| ns / 1_000);
+            }
+        }
+
+        Ok(stat)
+    }
+
+    /// Sample throttling for a cgroup. The first call seeds a baseline and
+    /// returns `None`; later calls return the delta once `window` has elapsed.
+    pub async fn sample_cpu_throttling(&self, cgroup: &PodCgroup) -> Result<Option<CpuThrottleSample>> {
+        if let Some(recorded) = &self.recorded {
+            let mut recorded = recorded.lock().await;
+            return Ok(recorded.samples.get_mut(&cgroup.dir).and_then(VecDeque::pop_front).flatten());
+        }
+        let current = self.read_cpu_stat(cgroup).await?;
+        let now = Instant::now();
+
+        let mut baselines = self.baselines.lock().await;
+        match baselines.get(&cgroup.dir) {
+            Some((taken_at, previous)) => {
+                let elapsed = now.duration_since(*taken_at);
+                if elapsed < self.window {
+                    return Ok(None);
+                }
+                let sample = CpuThrottleSample::between(previous, &current, elapsed);
+                baselines.insert(cgroup.dir.clone(), (now, current));
+                Ok(Some(sample))
+            }
+            None => {
+                baselines.insert(cgroup.dir.clone(), (now, current));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Pod-level memory cgroups on this host, keyed by pod UID
+    pub fn pod_memory_cgroups(&self) -> Vec<(String, PodCgroup)> {
+        self.pod_cgroups("memory")
+    }
+
+    /// The pod-level cpu cgroup of the pod with `pod_uid`, for when there is
+    /// no process to read `/proc/<pid>/cgroup` of
+    pub fn pod_cpu_cgroup(&self, pod_uid: &str) -> Option<PodCgroup> {
+        self.pod_cgroups("cpu").into_iter().find(|(uid, _)| uid == pod_uid).map(|(_, cgroup)| cgroup)
+    }
+
+    /// Pod-level cgroups in the hierarchy of `v1_controller`, or the unified one on v2
+    fn pod_cgroups(&self, v1_controller: &str) -> Vec<(String, PodCgroup)> {
+        let v1_root = self.cgroup_root.join(v1_controller);
+        let (base, version) = if v1_root.is_dir() {
+            (v1_root, CgroupVersion::V1)
+        } else {
+            (self.cgroup_root.clone(), CgroupVersion::V2)
+        };
+
+        let mut pods = Vec::new();
+        let mut frontier = vec![base];
+        for _ in 0..5 {
+            let mut next = Vec::new();
+            for dir in frontier {
+                let Ok(entries) = std::fs::read_dir(&dir) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if !path.is_dir() {
+                        continue;
+                    }
+                    let name = entry.file_name();
+                    match name.to_str().and_then(pod_uid_from_segment) {
+                        Some(uid) => pods.push((uid, PodCgroup { version, dir: path })),
+                        None => next.push(path),
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        pods
+    }
+
+    pub async fn read_memory(&self, cgroup: &PodCgroup) -> Result<MemorySnapshot> {
+        let read = |file: &str| tokio::fs::read_to_string(cgroup.dir.join(file));
+
+        let stat = MemoryStat::parse(&read("memory.stat").await?);
+
+        let snapshot = match cgroup.version {
+            CgroupVersion::V2 => MemorySnapshot {
+                pressure: read("memory.pressure").await.ok().map(|c| PressureStall::parse(&c)),
+                events: MemoryEvents::parse(&read("memory.events").await?),
+                stat,
+                usage_bytes: read("memory.current").await?.trim().parse()?,
+                limit_bytes: read("memory.max").await.ok().and_then(|c| c.trim().parse().ok()),
+            },
+            CgroupVersion::V1 => {
+                let mut events = match read("memory.oom_control").await {
+                    Ok(content) => MemoryEvents::parse(&content),
+                    Err(_) => MemoryEvents::default(),
+                };
+                // v1 has no per-event breakdown; failcnt counts allocations that hit the limit
+                events.max = read("memory.failcnt").await.ok().and_then(|c| c.trim().parse().ok()).unwrap_or(0);
+                // An unlimited v1 cgroup reports a page-aligned i64::MAX
+                let limit = read("memory.limit_in_bytes").await.ok()
+                    .and_then(|c| c.trim().parse::<u64>().ok())
+                    .filter(|limit| *limit < i64::MAX as u64 / 2);
+                MemorySnapshot {
+                    pressure: None,
+                    events,
+                    stat,
+                    usage_bytes: read("memory.usage_in_bytes").await?.trim().parse()?,
+                    limit_bytes: limit,
+                }
+            }
+        };
+
+        Ok(snapshot)
+    }
+
+    /// Drop baselines for cgroups that no longer exist on the host
+    pub async fn forget_removed_cgroups(&self) {
+        let mut baselines = self.baselines.lock().await;
+        baselines.retain(|dir, _| dir.is_dir());
+    }
+}
+
+/// Pod cgroups are named `pod<uid>` (cgroupfs driver) or
+/// `kubepods-<qos>-pod<uid_with_underscores>.slice` (systemd driver)
+fn is_pod_segment(segment: &str) -> bool {
+    let uid = if let Some(rest) = segment.strip_prefix("pod") {
+        rest
+    } else if let Some(idx) = segment.find("-pod") {
+        segment[idx + 4..].trim_end_matches(".slice")
+    } else {
+        return false;
+    };
+
+    uid.len() >= 32 && uid.chars().all(|c| c.is_ascii_hexdigit() || c == '-' || c == '_')
+}
+
+/// Pod UID in its canonical dashed form, if `segment` names a pod cgroup
+pub fn pod_uid_from_segment(segment: &str) -> Option<String> {
+    if !is_pod_segment(segment) {
+        return None;
+    }
+    let uid = match segment.strip_prefix("pod") {
+        Some(rest) => rest,
+        None => segment[segment.find("-pod")? + 4..].trim_end_matches(".slice"),
+    };
+    Some(uid.replace('_', "-"))
+}
+
+fn find_dir_named(base: &Path, name: &str, max_depth: usize) -> Option<PathBuf> {
+    let mut frontier = vec![base.to_path_buf()];
+
+    for _ in 0..max_depth {
+        let mut next = Vec::new();
+        for dir in frontier {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !path.is_dir() {
+                    continue;
+                }
+                if entry.file_name() == name {
+                    return Some(path);
+                }
+                next.push(path);
+            }
+        }
+        frontier = next;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const POD_UID: &str = "2bac1a6a-95d3-4abc-990f-aefaf5c74812";
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().expect("path has parent")).expect("create dirs");
+        fs::write(path, content).expect("write file");
+    }
+
+    #[test]
+    fn test_parse_cpu_stat_v2() {
+        let stat = CpuStat::parse("usage_usec 5000000\nuser_usec 4000000\nsystem_usec 1000000\nnr_periods 100\nnr_throttled 40\nthrottled_usec 2000000\n");
+
+        assert_eq!(stat.nr_periods, 100);
+        assert_eq!(stat.nr_throttled, 40);
+        assert_eq!(stat.throttled_usec, 2_000_000);
+        assert_eq!(stat.usage_usec, Some(5_000_000));
+    }
+
+    #[test]
+    fn test_parse_cpu_stat_v1_converts_nanoseconds() {
+        let stat = CpuStat::parse("nr_periods 10\nnr_throttled 3\nthrottled_time 45000000\n");
+
+        assert_eq!(stat.throttled_usec, 45_000);
+        assert_eq!(stat.usage_usec, None);
+    }
+
+    #[test]
+    fn test_parse_cpu_stat_ignores_garbage() {
+        let stat = CpuStat::parse("nr_periods\nnr_throttled abc\n\n");
+        assert_eq!(stat, CpuStat::default());
+    }
+
+    #[test]
+    fn test_sample_ratio_and_cores() {
+        let previous = CpuStat { nr_periods: 100, nr_throttled: 10, throttled_usec: 1_000, usage_usec: Some(1_000_000) };
+        let current = CpuStat { nr_periods: 200, nr_throttled: 85, throttled_usec: 501_000, usage_usec: Some(11_000_000) };
+
+        let sample = CpuThrottleSample::between(&previous, &current, Duration::from_secs(10));
+
+        assert_eq!(sample.nr_periods, 100);
+        assert_eq!(sample.nr_throttled, 75);
+        assert_eq!(sample.throttled_usec, 500_000);
+        assert_eq!(sample.throttled_percentage(), 75.0);
+        assert_eq!(sample.cpu_cores(), Some(1.0));
+    }
+
+    #[test]
+    fn test_sample_without_periods_is_not_throttled() {
+        let stat = CpuStat { nr_periods: 5, nr_throttled: 5, throttled_usec: 0, usage_usec: None };
+        let sample = CpuThrottleSample::between(&stat, &stat, Duration::from_secs(1));
+
+        assert_eq!(sample.throttled_percentage(), 0.0);
+        assert_eq!(sample.cpu_cores(), None);
+    }
+
+    #[test]
+    fn test_counter_reset_saturates() {
+        let previous = CpuStat { nr_periods: 500, nr_throttled: 100, throttled_usec: 9_000, usage_usec: None };
+        let current = CpuStat { nr_periods: 20, nr_throttled: 2, throttled_usec: 100, usage_usec: None };
+
+        let sample = CpuThrottleSample::between(&previous, &current, Duration::from_secs(1));
+        assert_eq!(sample.nr_periods, 0);
+        assert_eq!(sample.throttled_percentage(), 0.0);
+    }
+
+    #[test]
+    fn test_is_pod_segment() {
+        assert!(is_pod_segment(&format!("pod{POD_UID}")));
+        assert!(is_pod_segment("kubepods-burstable-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice"));
+        assert!(!is_pod_segment("kubepods.slice"));
+        assert!(!is_pod_segment("podman"));
+        assert!(!is_pod_segment("cri-containerd-abc.scope"));
+    }
+
+    #[tokio::test]
+    async fn test_locates_v2_systemd_pod_cgroup() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let proc_root = tempfile::tempdir().expect("tempdir");
+        let pod_dir = root.path()
+            .join("kubepods.slice/kubepods-burstable.slice")
+            .join("kubepods-burstable-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice");
+        write(&pod_dir.join("cpu.stat"), "nr_periods 1\n");
+        write(
+            &proc_root.path().join("42/cgroup"),
+            "0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice/cri-containerd-abc.scope\n",
+        );
+
+        let reader = CgroupReader::new(root.path(), proc_root.path(), Duration::ZERO);
+        let cgroup = reader.pod_cgroup_for_pid(42).await.expect("read proc").expect("cgroup found");
+
+        assert_eq!(cgroup.version, CgroupVersion::V2);
+        assert_eq!(cgroup.dir, pod_dir);
+        assert_eq!(cgroup.pod_uid().as_deref(), Some("2bac1a6a-95d3-4abc-990f-aefaf5c74812"));
+    }
+
+    #[tokio::test]
+    async fn test_locates_v1_cgroupfs_pod_cgroup() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let proc_root = tempfile::tempdir().expect("tempdir");
+        let pod_dir = root.path().join(format!("cpu,cpuacct/kubepods/besteffort/pod{POD_UID}"));
+        write(&pod_dir.join("cpu.stat"), "nr_periods 1\n");
+        write(
+            &proc_root.path().join("7/cgroup"),
+            &format!("12:memory:/kubepods/besteffort/pod{POD_UID}/abc\n4:cpu,cpuacct:/kubepods/besteffort/pod{POD_UID}/abc\n0::/\n"),
+        );
+
+        let reader = CgroupReader::new(root.path(), proc_root.path(), Duration::ZERO);
+        let cgroup = reader.pod_cgroup_for_pid(7).await.expect("read proc").expect("cgroup found");
+
+        assert_eq!(cgroup.version, CgroupVersion::V1);
+        assert_eq!(cgroup.dir, pod_dir);
+    }
+
+    #[tokio::test]
+    async fn test_locates_pod_cgroup_from_namespaced_path() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let proc_root = tempfile::tempdir().expect("tempdir");
+        let pod_dir = root.path().join(format!("kubepods/burstable/pod{POD_UID}"));
+        write(&pod_dir.join("cpu.stat"), "nr_periods 1\n");
+        write(&proc_root.path().join("9/cgroup"), &format!("0::/../../pod{POD_UID}/abc\n"));
+
+        let reader = CgroupReader::new(root.path(), proc_root.path(), Duration::ZERO);
+        let cgroup = reader.pod_cgroup_for_pid(9).await.expect("read proc").expect("cgroup found");
+
+        assert_eq!(cgroup.dir, pod_dir);
+    }
+
+    #[tokio::test]
+    async fn test_non_pod_process_has_no_pod_cgroup() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let proc_root = tempfile::tempdir().expect("tempdir");
+        write(&proc_root.path().join("1/cgroup"), "0::/init.scope\n");
+
+        let reader = CgroupReader::new(root.path(), proc_root.path(), Duration::ZERO);
+        assert!(reader.pod_cgroup_for_pid(1).await.expect("read proc").is_none());
+        assert!(reader.pod_cgroup_for_pid(2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sampling_reports_delta_over_window() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let pod_dir = root.path().join(format!("kubepods/pod{POD_UID}"));
+        let cpu_stat = pod_dir.join("cpu.stat");
+        write(&cpu_stat, "usage_usec 0\nnr_periods 100\nnr_throttled 10\nthrottled_usec 0\n");
+
+        let reader = CgroupReader::new(root.path(), root.path(), Duration::ZERO);
+        let cgroup = PodCgroup { version: CgroupVersion::V2, dir: pod_dir };
+
+        assert!(reader.sample_cpu_throttling(&cgroup).await.expect("sample").is_none());
+
+        write(&cpu_stat, "usage_usec 900000\nnr_periods 110\nnr_throttled 19\nthrottled_usec 450000\n");
+        let sample = reader.sample_cpu_throttling(&cgroup).await.expect("sample").expect("delta");
+
+        assert_eq!(sample.nr_periods, 10);
+        assert_eq!(sample.nr_throttled, 9);
+        assert_eq!(sample.throttled_usec, 450_000);
+        assert_eq!(sample.throttled_percentage(), 90.0);
+    }
+
+    #[tokio::test]
+    async fn test_sampling_waits_for_window() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let pod_dir = root.path().join(format!("kubepods/pod{POD_UID}"));
+        write(&pod_dir.join("cpu.stat"), "nr_periods 100\nnr_throttled 10\n");
+
+        let reader = CgroupReader::new(root.path(), root.path(), Duration::from_secs(3600));
+        let cgroup = PodCgroup { version: CgroupVersion::V2, dir: pod_dir.clone() };
+
+        assert!(reader.sample_cpu_throttling(&cgroup).await.expect("sample").is_none());
+        assert!(reader.sample_cpu_throttling(&cgroup).await.expect("sample").is_none());
+
+        fs::remove_dir_all(&pod_dir).expect("remove");
+        reader.forget_removed_cgroups().await;
+        assert!(reader.sample_cpu_throttling(&cgroup).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_memory_pressure() {
+        let stall = PressureStall::parse("some avg10=12.50 avg60=4.00 avg300=1.00 total=900000\nfull avg10=3.25 avg60=1.00 avg300=0.10 total=250000\n");
+
+        assert_eq!(stall.some.avg10, 12.5);
+        assert_eq!(stall.some.total_usec, 900_000);
+        assert_eq!(stall.full.avg10, 3.25);
+        assert_eq!(stall.full.total_usec, 250_000);
+    }
+
+    #[test]
+    fn test_parse_memory_events_v2_and_v1() {
+        let v2 = MemoryEvents::parse("low 0\nhigh 12\nmax 3\noom 1\noom_kill 1\noom_group_kill 0\n");
+        assert_eq!(v2, MemoryEvents { high: 12, max: 3, oom: 1, oom_kill: 1 });
+
+        let v1 = MemoryEvents::parse("oom_kill_disable 0\nunder_oom 0\noom_kill 2\n");
+        assert_eq!(v1.oom_kill, 2);
+        assert_eq!(v1.high, 0);
+    }
+
+    #[test]
+    fn test_memory_stat_prefers_v1_totals() {
+        let stat = MemoryStat::parse("cache 10\ninactive_file 5\ntotal_cache 100\ntotal_inactive_file 50\n");
+
+        assert_eq!(stat.get("cache"), 100);
+        assert_eq!(stat.get("inactive_file"), 50);
+        assert_eq!(stat.get("missing"), 0);
+    }
+
+    #[test]
+    fn test_memory_breakdown_v2_and_v1() {
+        let v2 = MemoryStat::parse("anon 4096\nfile 2048\nkernel 512\nkernel_stack 64\nsock 128\nshmem 1024\n");
+        assert_eq!(v2.breakdown(), MemoryBreakdown {
+            anon_bytes: 4096,
+            file_bytes: 2048,
+            kernel_bytes: 512,
+            sock_bytes: 128,
+            shmem_bytes: 1024,
+        });
+
+        // Pre-5.18 kernels have no `kernel` key
+        let old_v2 = MemoryStat::parse("anon 1\nkernel_stack 10\nslab 20\npagetables 5\npercpu 1\n");
+        assert_eq!(old_v2.breakdown().kernel_bytes, 36);
+
+        let v1 = MemoryStat::parse("rss 1\ncache 2\nshmem 3\ntotal_rss 100\ntotal_cache 200\ntotal_shmem 50\n");
+        let breakdown = v1.breakdown();
+        assert_eq!(breakdown.anon_bytes, 100);
+        assert_eq!(breakdown.file_bytes, 200);
+        assert_eq!(breakdown.shmem_bytes, 50);
+        assert_eq!(breakdown.sock_bytes, 0);
+    }
+
+    #[test]
+    fn test_memory_pressure_sample() {
+        let previous = MemorySnapshot {
+            pressure: Some(PressureStall::parse("some avg10=0.00 avg60=0.00 avg300=0.00 total=1000\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=500\n")),
+            events: MemoryEvents { high: 1, max: 0, oom: 0, oom_kill: 0 },
+            stat: MemoryStat::parse("pgscan 100\ninactive_file 0\n"),
+            usage_bytes: 1000,
+            limit_bytes: Some(4096),
+        };
+        let current = MemorySnapshot {
+            pressure: Some(PressureStall::parse("some avg10=25.00 avg60=5.00 avg300=1.00 total=301000\nfull avg10=10.00 avg60=2.00 avg300=0.50 total=100500\n")),
+            events: MemoryEvents { high: 4, max: 2, oom: 0, oom_kill: 0 },
+            stat: MemoryStat::parse("pgscan 600\ninactive_file 1000\n"),
+            usage_bytes: 4000,
+            limit_bytes: Some(4096),
+        };
+
+        let sample = MemoryPressureSample::between(&previous, &current, Duration::from_secs(10));
+
+        assert_eq!(sample.some_avg10, 25.0);
+        assert_eq!(sample.some_stall_usec, 300_000);
+        assert_eq!(sample.full_stall_usec, 100_000);
+        assert_eq!(sample.high_events, 3);
+        assert_eq!(sample.max_events, 2);
+        assert_eq!(sample.reclaim_scanned_pages, 500);
+        assert_eq!(sample.working_set_bytes, 3000);
+        assert!(sample.is_under_pressure(50.0));
+
+        let quiet = MemoryPressureSample::between(&current, &current, Duration::from_secs(10));
+        assert!(!quiet.is_under_pressure(50.0));
+        assert!(quiet.is_under_pressure(20.0));
+    }
+
+    #[test]
+    fn test_pod_uid_from_segment() {
+        assert_eq!(pod_uid_from_segment(&format!("pod{POD_UID}")).as_deref(), Some(POD_UID));
+        assert_eq!(
+            pod_uid_from_segment("kubepods-besteffort-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice").as_deref(),
+            Some(POD_UID)
+        );
+        assert_eq!(pod_uid_from_segment("kubepods-besteffort.slice"), None);
+    }
+
+    #[tokio::test]
+    async fn test_reads_v2_pod_memory_cgroups() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let pod_dir = root.path().join("kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice");
+        write(&pod_dir.join("memory.stat"), "anon 2048\nfile 1024\ninactive_file 512\npgscan 7\n");
+        write(&pod_dir.join("memory.events"), "low 0\nhigh 1\nmax 0\noom 0\noom_kill 0\n");
+        write(&pod_dir.join("memory.pressure"), "some avg10=1.00 avg60=0.00 avg300=0.00 total=10\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=0\n");
+        write(&pod_dir.join("memory.current"), "3072\n");
+        write(&pod_dir.join("memory.max"), "max\n");
+        // Container cgroups below the pod must not be reported separately
+        write(&pod_dir.join("cri-containerd-abc.scope/memory.current"), "0\n");
+
+        let reader = CgroupReader::new(root.path(), root.path(), Duration::ZERO);
+        let pods = reader.pod_memory_cgroups();
+
+        assert_eq!(pods.len(), 1);
+        assert_eq!(pods[0].0, POD_UID);
+
+        let snapshot = reader.read_memory(&pods[0].1).await.expect("read memory");
+        assert_eq!(snapshot.working_set_bytes(), 2560);
+        assert_eq!(snapshot.limit_bytes, None);
+        assert_eq!(snapshot.events.high, 1);
+        assert_eq!(snapshot.pressure.map(|p| p.some.avg10), Some(1.0));
+        assert_eq!(snapshot.reclaim_scanned_pages(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_reads_v1_pod_memory_cgroups() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let pod_dir = root.path().join(format!("memory/kubepods/burstable/pod{POD_UID}"));
+        write(&pod_dir.join("memory.stat"), "total_inactive_file 100\ntotal_rss 900\n");
+        write(&pod_dir.join("memory.oom_control"), "oom_kill_disable 0\nunder_oom 0\noom_kill 1\n");
+        write(&pod_dir.join("memory.failcnt"), "5\n");
+        write(&pod_dir.join("memory.usage_in_bytes"), "1000\n");
+        write(&pod_dir.join("memory.limit_in_bytes"), "9223372036854771712\n");
+
+        let reader = CgroupReader::new(root.path(), root.path(), Duration::ZERO);
+        let pods = reader.pod_memory_cgroups();
+        assert_eq!(pods.len(), 1);
+        assert_eq!(pods[0].1.version, CgroupVersion::V1);
+
+        let snapshot = reader.read_memory(&pods[0].1).await.expect("read memory");
+        assert_eq!(snapshot.pressure, None);
+        assert_eq!(snapshot.events.oom_kill, 1);
+        assert_eq!(snapshot.events.max, 5);
+        assert_eq!(snapshot.limit_bytes, None);
+        assert_eq!(snapshot.working_set_bytes(), 900);
+    }
+}
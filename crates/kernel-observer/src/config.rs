@@ -1,14 +1,45 @@
 use std::env;
+use std::path::PathBuf;
 use anyhow::Result;
 
+use crate::bpftrace::OutputFormat;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub webhook_url: String,
-    #[allow(dead_code)]
     pub namespace: String,
-    #[allow(dead_code)]
     pub configmap_name: String,
+    /// Set from the downward API; without it every pod on the node is recorded
+    pub node_name: Option<String>,
     pub log_level: String,
+    pub cgroup_root: String,
+    pub proc_root: String,
+    pub throttle_window_secs: u64,
+    pub memory_poll_interval_secs: u64,
+    pub memory_psi_threshold: f64,
+    pub webhook_auth_dir: String,
+    pub webhook_batch_max_events: usize,
+    pub webhook_batch_max_delay_ms: u64,
+    pub webhook_retry_base_ms: u64,
+    pub webhook_retry_max_ms: u64,
+    pub webhook_max_attempts: u32,
+    pub spool_dir: String,
+    pub spool_max_batches: usize,
+    pub metrics_port: u16,
+    pub pid_cache_ttl_secs: u64,
+    pub pid_cache_max_entries: usize,
+    /// Opt-in: where to write trace files of everything the observer sees
+    pub record_dir: Option<String>,
+    pub record_max_bytes: u64,
+    pub record_max_files: usize,
+    pub bpftrace_restart_base_ms: u64,
+    pub bpftrace_restart_max_ms: u64,
+    /// bpftrace running this long counts as recovered, and its backoff starts over
+    pub bpftrace_stable_secs: u64,
+    pub bpftrace_output: OutputFormat,
+    /// The mounted scripts ConfigMap, read again every `scripts_reload_secs`
+    pub scripts_dir: String,
+    pub scripts_reload_secs: u64,
 }
 
 impl Config {
@@ -17,8 +48,167 @@ impl Config {
             webhook_url: env::var("WEBHOOK_URL")
                 .unwrap_or_else(|_| "http://kernel-gossip-operator.kernel-gossip.svc.cluster.local:8080/webhook/ebpf".to_string()),
             namespace: env::var("NAMESPACE").unwrap_or_else(|_| "kernel-gossip".to_string()),
-            configmap_name: env::var("CONFIGMAP_NAME").unwrap_or_else(|_| "ebpf-monitored-pods".to_string()),
+            configmap_name: env::var("CONFIGMAP_NAME")
+                .unwrap_or_else(|_| kernel_gossip_protocol::monitored::MONITORED_PODS_CONFIGMAP.to_string()),
+            node_name: env::var("NODE_NAME").ok().filter(|s| !s.is_empty()),
             log_level: env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+            cgroup_root: env::var("CGROUP_ROOT").unwrap_or_else(|_| crate::cgroup::DEFAULT_CGROUP_ROOT.to_string()),
+            proc_root: env::var("PROC_ROOT").unwrap_or_else(|_| crate::cgroup::DEFAULT_PROC_ROOT.to_string()),
+            throttle_window_secs: env::var("THROTTLE_WINDOW_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10),
+            memory_poll_interval_secs: env::var("MEMORY_POLL_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10),
+            memory_psi_threshold: env::var("MEMORY_PSI_THRESHOLD")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10.0),
+            webhook_auth_dir: env::var("WEBHOOK_AUTH_DIR").unwrap_or_else(|_| "/etc/kernel-gossip/webhook-auth".to_string()),
+            webhook_batch_max_events: env::var("WEBHOOK_BATCH_MAX_EVENTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(100),
+            webhook_batch_max_delay_ms: env::var("WEBHOOK_BATCH_MAX_DELAY_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(500),
+            webhook_retry_base_ms: env::var("WEBHOOK_RETRY_BASE_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(500),
+            webhook_retry_max_ms: env::var("WEBHOOK_RETRY_MAX_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(60_000),
+            webhook_max_attempts: env::var("WEBHOOK_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(20),
+            spool_dir: env::var("SPOOL_DIR").unwrap_or_else(|_| "/var/lib/kernel-observer/spool".to_string()),
+            spool_max_batches: env::var("SPOOL_MAX_BATCHES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1000),
+            metrics_port: env::var("METRICS_PORT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(9102),
+            pid_cache_ttl_secs: env::var("PID_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(600),
+            pid_cache_max_entries: env::var("PID_CACHE_MAX_ENTRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(8192),
+            record_dir: env::var("RECORD_DIR").ok().filter(|s| !s.is_empty()),
+            record_max_bytes: env::var("RECORD_MAX_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(64 * 1024 * 1024),
+            record_max_files: env::var("RECORD_MAX_FILES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5),
+            bpftrace_restart_base_ms: env::var("BPFTRACE_RESTART_BASE_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1000),
+            bpftrace_restart_max_ms: env::var("BPFTRACE_RESTART_MAX_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(60_000),
+            bpftrace_stable_secs: env::var("BPFTRACE_STABLE_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(300),
+            bpftrace_output: env::var("BPFTRACE_OUTPUT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(OutputFormat::Json),
+            scripts_dir: env::var("BPFTRACE_SCRIPTS_DIR").unwrap_or_else(|_| crate::scripts::DEFAULT_SCRIPTS_DIR.to_string()),
+            scripts_reload_secs: env::var("BPFTRACE_SCRIPTS_RELOAD_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),
         })
     }
-}
\ No newline at end of file
+}
+/// Where the kernel output is read from
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceKind {
+    /// bpftrace on this node
+    Bpftrace,
+    /// A capture file; `speed` scales its recorded timing, 0 replays without waiting
+    Replay { path: PathBuf, speed: f64 },
+    Stdin,
+}
+
+/// Command line options; everything else comes from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub source: SourceKind,
+    /// Pods to resolve against instead of watching the cluster
+    pub pods_file: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut replay = None;
+        let mut speed = 1.0;
+        let mut stdin = false;
+        let mut pods_file = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| anyhow::anyhow!("{name} needs a value"));
+            match arg.as_str() {
+                "--replay" => replay = Some(PathBuf::from(value("--replay")?)),
+                "--replay-speed" => {
+                    speed = value("--replay-speed")?
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("--replay-speed takes a number"))?
+                }
+                "--stdin" => stdin = true,
+                "--pods" => pods_file = Some(PathBuf::from(value("--pods")?)),
+                other => anyhow::bail!(
+                    "unknown argument {other}; usage: kernel-observer [--replay <file> [--replay-speed <x>] | --stdin] [--pods <file>]"
+                ),
+            }
+        }
+
+        let source = match (replay, stdin) {
+            (Some(_), true) => anyhow::bail!("--replay and --stdin cannot be combined"),
+            (Some(path), false) => SourceKind::Replay { path, speed },
+            (None, true) => SourceKind::Stdin,
+            (None, false) => SourceKind::Bpftrace,
+        };
+        Ok(Self { source, pods_file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_args() {
+        assert_eq!(parse(&[]).unwrap(), Args { source: SourceKind::Bpftrace, pods_file: None });
+
+        let args = parse(&["--replay", "incident.trace", "--replay-speed", "0", "--pods", "pods.json"]).unwrap();
+        assert_eq!(args.source, SourceKind::Replay { path: "incident.trace".into(), speed: 0.0 });
+        assert_eq!(args.pods_file, Some("pods.json".into()));
+
+        assert_eq!(parse(&["--stdin"]).unwrap().source, SourceKind::Stdin);
+        assert!(parse(&["--replay"]).is_err());
+        assert!(parse(&["--replay", "a", "--stdin"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+}
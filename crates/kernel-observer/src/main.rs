@@ -1,49 +1,162 @@
+mod birth;
 mod bpftrace;
+mod cgroup;
 mod parser;
 mod webhook;
 mod config;
 mod pod_resolver;
+mod memory_pressure;
+mod metrics;
+mod monitored;
+mod spool;
+mod source;
+mod recording;
+mod supervisor;
+mod scripts;
+mod records;
 // Removed unused modules: pod_uid_extractor, syscall_tracker, cgroup_tracker
 
 use anyhow::Result;
-use tracing::{info, error};
+use tracing::{debug, info, error, warn};
 
-use crate::bpftrace::BpftraceProcess;
+use crate::cgroup::CgroupReader;
 use crate::parser::EbpfParser;
-use crate::webhook::WebhookClient;
-use crate::config::Config;
-use std::fs;
-
-fn load_bpftrace_script() -> Result<String> {
-    // Load script from mounted ConfigMap - fully configurable via env var
-    let script_name = std::env::var("BPFTRACE_SCRIPT").unwrap_or_else(|_| "monitoring.bt".to_string());
-    let script_path = format!("/etc/bpftrace-scripts/{}", script_name);
-    
-    let script_content = fs::read_to_string(&script_path)
-        .map_err(|e| anyhow::anyhow!("Failed to load bpftrace script from {}: {}", script_path, e))?;
-    
-    info!("📁 Loaded bpftrace script from: {} ({} bytes)", script_path, script_content.len());
-    Ok(script_content)
-}
+use crate::webhook::{BatchSettings, RetrySettings, WebhookClient};
+use kernel_gossip_protocol::auth::WebhookCredentials;
+use crate::config::{Args, Config, SourceKind};
+use crate::memory_pressure::MemoryPressureMonitor;
+use crate::monitored::MonitoredPods;
+use crate::spool::Spool;
+use crate::pod_resolver::{PidCacheSettings, PodResolver};
+use crate::recording::{Recorder, TraceReader};
+use crate::source::{EventSource, LineSource};
+use crate::scripts::ScriptsSource;
+use crate::supervisor::{Health, SupervisorSettings};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a replay waits at the end for its events to reach the operator
+const REPLAY_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often buffered trace records are written out
+const TRACE_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let config = Config::from_env()?;
+    let args = Args::parse(std::env::args().skip(1))?;
     
     tracing_subscriber::fmt()
         .with_env_filter(&config.log_level)
         .init();
 
-    info!("🚀 Starting Kernel Observer with real eBPF monitoring");
+    let live = args.source == SourceKind::Bpftrace;
+    if live {
+        info!("🚀 Starting Kernel Observer with real eBPF monitoring");
+    } else {
+        info!("🚀 Starting Kernel Observer on recorded kernel output");
+    }
     info!("Webhook URL: {}", config.webhook_url);
 
-    let parser = EbpfParser::new(config.webhook_url.clone()).await?;
-    let webhook_client = WebhookClient::new(config.webhook_url);
+    let cgroup_reader = CgroupReader::new(
+        &config.cgroup_root,
+        &config.proc_root,
+        Duration::from_secs(config.throttle_window_secs),
+    );
+    let pid_cache = PidCacheSettings {
+        ttl: Duration::from_secs(config.pid_cache_ttl_secs),
+        max_entries: config.pid_cache_max_entries,
+    };
+    // A trace brings its own answers for PIDs that no longer exist
+    let trace = match &args.source {
+        SourceKind::Replay { path, speed } if TraceReader::is_trace(path) => {
+            info!("⏪ Replaying trace {} at {}x", path.display(), speed);
+            Some(TraceReader::open(path, *speed)?)
+        }
+        _ => None,
+    };
+
+    // A pods file stands in for the cluster, so replays need no API server
+    let (pod_resolver, client) = match &args.pods_file {
+        Some(path) => (PodResolver::from_pods_file(path, &config.proc_root, pid_cache)?, None),
+        None => {
+            let client = kube::Client::try_default().await?;
+            let resolver = PodResolver::new(client.clone(), config.node_name.as_deref(), &config.proc_root, pid_cache).await?;
+            (resolver, Some(client))
+        }
+    };
+    let pod_resolver = match &trace {
+        Some(trace) => pod_resolver.with_recorded_pids(trace.resolutions()),
+        None => pod_resolver,
+    };
+    let pod_resolver = Arc::new(pod_resolver);
+    let cgroup_reader = match &trace {
+        Some(trace) => cgroup_reader.with_recorded(trace.cgroups()),
+        None => cgroup_reader,
+    };
+    let cgroup_reader = Arc::new(cgroup_reader);
+
+    // Only pods the operator selected on this node are sampled and reported.
+    // Replays record everything they were captured with.
+    let monitored = MonitoredPods::new();
+    match (config.node_name.clone(), client) {
+        (Some(node), Some(client)) if live => {
+            tokio::spawn(monitored.clone().watch(client, config.namespace.clone(), config.configmap_name.clone(), node));
+        }
+        (None, _) if live => warn!("⚠️ NODE_NAME is not set, recording every pod and leaving selection to the operator"),
+        _ => {}
+    }
+    let credentials = WebhookCredentials::load_dir(&config.webhook_auth_dir)?;
+    if credentials.is_empty() {
+        warn!("⚠️ No webhook credentials in {}, the operator will reject our events", config.webhook_auth_dir);
+    }
+    let batching = BatchSettings {
+        max_events: config.webhook_batch_max_events,
+        max_delay: Duration::from_millis(config.webhook_batch_max_delay_ms),
+    };
+    let retry = RetrySettings {
+        base: Duration::from_millis(config.webhook_retry_base_ms),
+        max: Duration::from_millis(config.webhook_retry_max_ms),
+    };
+
+    let spool = Spool::open(
+        &config.spool_dir,
+        config.spool_max_batches,
+        config.webhook_max_attempts,
+        metrics::metrics().spool.clone(),
+    )?;
+    if spool.depth() > 0 {
+        info!("📬 Found {} undelivered event batches in {}", spool.depth(), config.spool_dir);
+    }
+    let health = Health::new(live);
+    let metrics_port = config.metrics_port;
+    let readiness = health.clone();
+    tokio::spawn(async move {
+        if let Err(e) = metrics::serve(metrics_port, readiness).await {
+            error!("Metrics server failed: {}", e);
+        }
+    });
 
-    // Load and spawn bpftrace process
-    let script = load_bpftrace_script()?;
-    let mut bpftrace = BpftraceProcess::spawn(&script).await?;
-    info!("✅ bpftrace process spawned successfully");
+    let webhook_client = WebhookClient::new(config.webhook_url, credentials, batching, retry, spool);
+    let recorder = match &config.record_dir {
+        Some(dir) => Some(Recorder::open(dir, config.node_name.clone(), config.record_max_bytes, config.record_max_files)?),
+        None => None,
+    };
+    if let Some(recorder) = recorder.clone() {
+        tokio::spawn(recorder.flush_every(TRACE_FLUSH_INTERVAL));
+    }
+    let parser = EbpfParser::new(pod_resolver.clone(), cgroup_reader.clone(), monitored.clone(), recorder.clone());
+
+    // Memory pressure comes straight from cgroup counters, independent of bpftrace
+    if live {
+        let memory_monitor = MemoryPressureMonitor::new(
+            cgroup_reader,
+            pod_resolver,
+            monitored,
+            config.memory_psi_threshold,
+        );
+        tokio::spawn(memory_monitor.run(Duration::from_secs(config.memory_poll_interval_secs), webhook_client.clone()));
+    }
 
     // Start cleanup task for old syscall sessions
     let parser_cleanup = parser.clone();
@@ -55,21 +168,155 @@ async fn main() -> Result<()> {
         }
     });
 
-    // Process eBPF output in real-time
-    while let Some(line) = bpftrace.next_line().await? {
-        info!("eBPF: {}", line);
-        
-        // Check for stderr messages
-        bpftrace.check_stderr().await?;
-        
-        if let Some(event) = parser.parse_line(&line).await? {
+    let processed = match args.source {
+        SourceKind::Bpftrace => {
+            let settings = SupervisorSettings {
+                backoff: RetrySettings {
+                    base: Duration::from_millis(config.bpftrace_restart_base_ms),
+                    max: Duration::from_millis(config.bpftrace_restart_max_ms),
+                },
+                stable_after: Duration::from_secs(config.bpftrace_stable_secs),
+                output: config.bpftrace_output,
+            };
+            let scripts = ScriptsSource::start(
+                &config.scripts_dir,
+                settings,
+                health,
+                Duration::from_secs(config.scripts_reload_secs),
+            )?;
+            process(scripts, &parser, &webhook_client).await
+        }
+        SourceKind::Replay { path, speed } => match trace {
+            Some(trace) => process(trace, &parser, &webhook_client).await,
+            None => process(LineSource::replay(&path, speed).await?, &parser, &webhook_client).await,
+        },
+        SourceKind::Stdin => process(LineSource::stdin(), &parser, &webhook_client).await,
+    };
+    // The trace of a source that failed is the one most worth keeping
+    if let Some(recorder) = &recorder {
+        if let Err(e) = recorder.flush() {
+            warn!("⚠️ Failed to flush trace: {}", e);
+        }
+    }
+    processed?;
+
+    if !live {
+        if webhook_client.drain(REPLAY_DRAIN_TIMEOUT).await {
+            info!("🏁 Replay finished, all events delivered");
+        } else {
+            warn!("⚠️ Replay finished with events still undelivered, they stay in the spool");
+        }
+    }
+    Ok(())
+}
+
+/// Parse every line of the source and send what it yields to the operator
+async fn process(mut source: impl EventSource, parser: &EbpfParser, webhook_client: &WebhookClient) -> Result<()> {
+    while let Some(line) = source.next_line().await? {
+        // Run queue maps alone print a line per task every interval
+        debug!("eBPF +{:?}: {}", line.offset, line.line);
+
+        for event in parser.parse_line(line).await? {
             if let Err(e) = webhook_client.send_event(event).await {
                 error!("Failed to send webhook: {}", e);
             }
         }
     }
 
-    // Wait for bpftrace to complete
-    bpftrace.wait().await?;
-    Ok(())
+    // Wait for the source to complete
+    source.finish().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+
+    use axum::routing::post;
+    use axum::Router;
+    use kernel_gossip_protocol::{EbpfEvent, EventBatch};
+    use tokio::net::TcpListener;
+
+    const POD_UID: &str = "2bac1a6a-95d3-4abc-990f-aefaf5c74812";
+    const APP_ID: &str = "4f2a9c1be0d3a7f6c5b4e3d2c1b0a9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d4c3b2";
+
+    /// An operator that takes every batch, and the events it was sent
+    async fn operator() -> (String, Arc<Mutex<Vec<EbpfEvent>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let app = Router::new().route("/webhook/batch", post(move |body: String| async move {
+            let batch: EventBatch = serde_json::from_str(&body).expect("batch");
+            sink.lock().unwrap().extend(batch.events);
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn test_replay_off_the_node_reaches_the_operator() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let pods = dir.path().join("pods.json");
+        fs::write(&pods, serde_json::json!({
+            "items": [{
+                "metadata": { "name": "web", "namespace": "shop", "uid": POD_UID },
+                "spec": { "containers": [{ "name": "app", "resources": { "requests": { "cpu": "500m" } } }] },
+                "status": { "containerStatuses": [{
+                    "name": "app", "containerID": format!("containerd://{APP_ID}"),
+                    "image": "app", "imageID": "", "ready": true, "restartCount": 0,
+                }] },
+            }],
+            "pids": { "42": APP_ID },
+        }).to_string()).unwrap();
+        // A cgroup fixture tree, and no /proc entry for PID 42
+        let cgroup_root = dir.path().join("cgroup");
+        let pod_dir = cgroup_root.join(format!("kubepods/burstable/pod{POD_UID}"));
+        fs::create_dir_all(&pod_dir).unwrap();
+        fs::write(pod_dir.join("cpu.stat"), "nr_periods 100\nnr_throttled 0\nthrottled_usec 0\nusage_usec 1000000\n").unwrap();
+        let proc_root = dir.path().join("proc");
+
+        let (url, received) = operator().await;
+        let spool = Spool::open(dir.path().join("spool"), 16, 3, metrics::metrics().spool.clone()).unwrap();
+        let webhook_client = WebhookClient::new(
+            url,
+            WebhookCredentials::default(),
+            BatchSettings { max_events: 16, max_delay: Duration::from_millis(10) },
+            RetrySettings { base: Duration::from_millis(10), max: Duration::from_millis(100) },
+            spool,
+        );
+        let settings = PidCacheSettings { ttl: Duration::from_secs(60), max_entries: 16 };
+        let parser = EbpfParser::new(
+            Arc::new(PodResolver::from_pods_file(&pods, &proc_root, settings).unwrap()),
+            Arc::new(CgroupReader::new(&cgroup_root, &proc_root, Duration::ZERO)),
+            MonitoredPods::new(),
+            None,
+        );
+
+        // The first window only seeds the cpu.stat baseline
+        let capture = dir.path().join("capture.txt");
+        fs::write(&capture, "0\tCPU_THROTTLE_EVENT pid=42 comm=app throttle_ns=1 timestamp_ms=9\n").unwrap();
+        process(LineSource::replay(&capture, 0.0).await.unwrap(), &parser, &webhook_client).await.unwrap();
+
+        fs::write(pod_dir.join("cpu.stat"), "nr_periods 200\nnr_throttled 50\nthrottled_usec 400000\nusage_usec 2000000\n").unwrap();
+        fs::write(&capture, "\
+            10\tCPU_THROTTLE_EVENT pid=42 comm=app throttle_ns=1 timestamp_ms=19\n\
+            20\tCONTAINER_BIRTH_COMPLETE pid=42 comm=app total_syscalls=321 namespace_ops=4 mount_ops=6 duration_ns=5000000\n\
+            30\tCONTAINER_BIRTH_COMPLETE pid=77 comm=stranger total_syscalls=1\n").unwrap();
+        process(LineSource::replay(&capture, 0.0).await.unwrap(), &parser, &webhook_client).await.unwrap();
+
+        assert!(webhook_client.drain(Duration::from_secs(5)).await);
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2, "{received:?}");
+        let EbpfEvent::CpuThrottle(throttle) = &received[0] else {
+            panic!("expected a throttle event, got {:?}", received[0]);
+        };
+        assert_eq!((throttle.pod_name.as_str(), throttle.container_name.as_str()), ("web", "app"));
+        assert_eq!((throttle.nr_periods, throttle.nr_throttled, throttle.throttle_percentage), (100, 50, 50.0));
+        let EbpfEvent::PodCreation(birth) = &received[1] else {
+            panic!("expected a birth event, got {:?}", received[1]);
+        };
+        assert_eq!((birth.namespace.as_str(), birth.pod_name.as_str(), birth.total_syscalls), ("shop", "web", 321));
+    }
 }
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use kernel_gossip_protocol::{EbpfEvent, MemoryPressurePayload, OomKillPayload};
+
+use crate::cgroup::{CgroupReader, MemoryPressureSample, MemorySnapshot};
+use crate::metrics::metrics;
+use crate::monitored::MonitoredPods;
+use crate::pod_resolver::{PodInfo, PodResolver};
+use crate::webhook::WebhookClient;
+
+/// Polls pod memory cgroups for PSI stalls, limit hits, reclaim activity and
+/// OOM kills. Unlike throttling this needs no bpftrace probe: the kernel
+/// already keeps the counters per cgroup.
+pub struct MemoryPressureMonitor {
+    cgroup_reader: Arc<CgroupReader>,
+    pod_resolver: Arc<PodResolver>,
+    monitored: MonitoredPods,
+    psi_threshold: f64,
+    previous: HashMap<PathBuf, (Instant, MemorySnapshot)>,
+    /// Pods resolved so far, by UID, so PSI is exported each poll without
+    /// asking the API server every time
+    pods: HashMap<String, PodInfo>,
+}
+
+impl MemoryPressureMonitor {
+    pub fn new(
+        cgroup_reader: Arc<CgroupReader>,
+        pod_resolver: Arc<PodResolver>,
+        monitored: MonitoredPods,
+        psi_threshold: f64,
+    ) -> Self {
+        Self {
+            cgroup_reader,
+            pod_resolver,
+            monitored,
+            psi_threshold,
+            previous: HashMap::new(),
+            pods: HashMap::new(),
+        }
+    }
+
+    pub async fn run(mut self, interval: Duration, webhook_client: WebhookClient) {
+        info!("🧠 Memory pressure monitor polling every {:?} (PSI threshold {}%)", interval, self.psi_threshold);
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for event in self.poll_once().await {
+                if let Err(e) = webhook_client.send_event(event).await {
+                    warn!("Failed to send memory webhook: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Read every monitored pod's memory cgroup and return the events its
+    /// change since the previous poll amounts to
+    async fn poll_once(&mut self) -> Vec<EbpfEvent> {
+        let mut events = Vec::new();
+        let pods = self.cgroup_reader.pod_memory_cgroups();
+        let mut seen = HashMap::with_capacity(pods.len());
+        let live: HashSet<String> = pods.iter().map(|(uid, _)| uid.clone()).collect();
+
+        for (pod_uid, cgroup) in pods {
+            if !self.monitored.admits_from(&pod_uid, "memory") {
+                continue;
+            }
+            let snapshot = match self.cgroup_reader.read_memory(&cgroup).await {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    debug!("Could not read memory cgroup {}: {}", cgroup.dir.display(), e);
+                    continue;
+                }
+            };
+            let now = Instant::now();
+
+            let sample = self.previous.get(&cgroup.dir).map(|(taken_at, previous)| {
+                MemoryPressureSample::between(previous, &snapshot, now.duration_since(*taken_at))
+            });
+            if let Some(sample) = sample {
+                if let Some(pod_info) = self.pod_info(&pod_uid).await {
+                    metrics().record_memory_pressure(&pod_info, &sample);
+                    if sample.oom_kill_events > 0 {
+                        // A snapshot taken after the kill, up to a poll interval later:
+                        // the killed process's memory is already freed by then
+                        events.push(oom_kill_event(&pod_info, &pod_uid, &snapshot, sample.oom_kill_events));
+                    }
+                    if sample.is_under_pressure(self.psi_threshold) {
+                        events.push(pressure_event(pod_info, sample));
+                    }
+                } else if sample.oom_kill_events > 0 || sample.is_under_pressure(self.psi_threshold) {
+                    warn!("⚠️ Memory pressure in cgroup of unknown pod UID {}", pod_uid);
+                }
+            }
+
+            seen.insert(cgroup.dir, (now, snapshot));
+        }
+
+        // Pods that disappeared since the last poll drop out here
+        self.previous = seen;
+        self.pods.retain(|uid, _| live.contains(uid));
+        events
+    }
+
+    async fn pod_info(&mut self, pod_uid: &str) -> Option<PodInfo> {
+        if let Some(pod_info) = self.pods.get(pod_uid) {
+            return Some(pod_info.clone());
+        }
+        let Some(pod_info) = self.pod_resolver.resolve_uid_to_pod(pod_uid) else {
+            metrics().pipeline.resolution_failed();
+            return None;
+        };
+        self.pods.insert(pod_uid.to_string(), pod_info.clone());
+        Some(pod_info)
+    }
+}
+
+fn pressure_event(pod_info: PodInfo, sample: MemoryPressureSample) -> EbpfEvent {
+    info!(
+        "🎯 Memory pressure on {}/{}: PSI some={}% full={}%, {} high / {} max / {} oom events, {} pages scanned",
+        pod_info.namespace, pod_info.name, sample.some_avg10, sample.full_avg10,
+        sample.high_events, sample.max_events, sample.oom_events, sample.reclaim_scanned_pages
+    );
+
+    EbpfEvent::MemoryPressure(MemoryPressurePayload {
+        pod_name: pod_info.name,
+        namespace: pod_info.namespace,
+        psi_some_avg10: sample.some_avg10,
+        psi_full_avg10: sample.full_avg10,
+        stall_time_us: sample.some_stall_usec,
+        full_stall_time_us: sample.full_stall_usec,
+        high_events: sample.high_events,
+        max_events: sample.max_events,
+        oom_events: sample.oom_events,
+        oom_kill_events: sample.oom_kill_events,
+        reclaim_scanned_pages: sample.reclaim_scanned_pages,
+        working_set_bytes: sample.working_set_bytes,
+        limit_bytes: sample.limit_bytes,
+        period_seconds: sample.elapsed.as_secs(),
+        timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    })
+}
+
+fn oom_kill_event(pod_info: &PodInfo, pod_uid: &str, snapshot: &MemorySnapshot, oom_kills: u64) -> EbpfEvent {
+    let breakdown = snapshot.stat.breakdown();
+    info!(
+        "💀 OOM kill in {}/{}: anon={} file={} kernel={} sock={} shmem={} bytes",
+        pod_info.namespace, pod_info.name, breakdown.anon_bytes, breakdown.file_bytes,
+        breakdown.kernel_bytes, breakdown.sock_bytes, breakdown.shmem_bytes
+    );
+
+    EbpfEvent::OomKill(OomKillPayload {
+        pod_name: pod_info.name.clone(),
+        namespace: pod_info.namespace.clone(),
+        pod_uid: pod_uid.to_string(),
+        oom_kills,
+        anon_bytes: breakdown.anon_bytes,
+        file_bytes: breakdown.file_bytes,
+        kernel_bytes: breakdown.kernel_bytes,
+        sock_bytes: breakdown.sock_bytes,
+        shmem_bytes: breakdown.shmem_bytes,
+        usage_bytes: snapshot.usage_bytes,
+        limit_bytes: snapshot.limit_bytes,
+        timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    use crate::pod_resolver::PidCacheSettings;
+
+    const POD_UID: &str = "2bac1a6a-95d3-4abc-990f-aefaf5c74812";
+    const STRANGER_UID: &str = "9f1e2d3c-4b5a-4968-8776-a5b4c3d2e1f0";
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().expect("path has parent")).expect("create dirs");
+        fs::write(path, content).expect("write file");
+    }
+
+    /// A v2 pod memory cgroup with `some_avg10` PSI and `high` limit hits
+    fn write_cgroup(root: &Path, pod_uid: &str, some_avg10: f64, high: u64) -> PathBuf {
+        let dir = root.join(format!("kubepods/burstable/pod{pod_uid}"));
+        write(&dir.join("memory.stat"), "anon 2048\nfile 1024\ninactive_file 512\npgscan 7\n");
+        write(&dir.join("memory.events"), &format!("low 0\nhigh {high}\nmax 0\noom 0\noom_kill 0\n"));
+        write(
+            &dir.join("memory.pressure"),
+            &format!("some avg10={some_avg10:.2} avg60=0.00 avg300=0.00 total=10\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=0\n"),
+        );
+        write(&dir.join("memory.current"), "3072\n");
+        write(&dir.join("memory.max"), "8192\n");
+        dir
+    }
+
+    fn monitor(root: &Path) -> MemoryPressureMonitor {
+        let pods = root.join("pods.json");
+        let pod = serde_json::json!({
+            "metadata": { "name": "web", "namespace": "shop", "uid": POD_UID },
+            "spec": { "containers": [{ "name": "app" }] },
+        });
+        fs::write(&pods, serde_json::json!([pod]).to_string()).unwrap();
+        let settings = PidCacheSettings { ttl: Duration::from_secs(60), max_entries: 16 };
+
+        MemoryPressureMonitor::new(
+            Arc::new(CgroupReader::new(root.join("cgroup"), root.join("proc"), Duration::ZERO)),
+            Arc::new(PodResolver::from_pods_file(&pods, root.join("proc"), settings).unwrap()),
+            MonitoredPods::new(),
+            50.0,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_first_poll_only_takes_a_baseline() {
+        let root = tempfile::tempdir().expect("tempdir");
+        write_cgroup(&root.path().join("cgroup"), POD_UID, 90.0, 3);
+        let mut monitor = monitor(root.path());
+
+        assert!(monitor.poll_once().await.is_empty());
+        assert_eq!(monitor.previous.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pressure_reported_above_psi_threshold() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let cgroup_root = root.path().join("cgroup");
+        write_cgroup(&cgroup_root, POD_UID, 10.0, 0);
+        let mut monitor = monitor(root.path());
+        monitor.poll_once().await;
+
+        // Below the threshold and no limit hits
+        assert!(monitor.poll_once().await.is_empty());
+
+        write_cgroup(&cgroup_root, POD_UID, 75.0, 0);
+        let events = monitor.poll_once().await;
+        assert_eq!(events.len(), 1);
+        let EbpfEvent::MemoryPressure(payload) = &events[0] else {
+            panic!("expected a memory pressure event, got {:?}", events[0]);
+        };
+        assert_eq!((payload.namespace.as_str(), payload.pod_name.as_str()), ("shop", "web"));
+        assert_eq!(payload.psi_some_avg10, 75.0);
+        assert_eq!(payload.working_set_bytes, 2560);
+        assert_eq!(payload.limit_bytes, Some(8192));
+
+        // Limit hits count even with PSI under the threshold
+        write_cgroup(&cgroup_root, POD_UID, 10.0, 2);
+        let events = monitor.poll_once().await;
+        assert!(matches!(&events[..], [EbpfEvent::MemoryPressure(payload)] if payload.high_events == 2));
+    }
+
+    #[tokio::test]
+    async fn test_oom_kill_reports_the_memory_left() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let dir = write_cgroup(&root.path().join("cgroup"), POD_UID, 10.0, 0);
+        let mut monitor = monitor(root.path());
+        monitor.poll_once().await;
+
+        write(&dir.join("memory.events"), "low 0\nhigh 0\nmax 4\noom 1\noom_kill 1\n");
+        write(&dir.join("memory.stat"), "anon 4096\nfile 1024\nkernel 256\nsock 128\nshmem 64\ninactive_file 0\n");
+        let events = monitor.poll_once().await;
+
+        assert_eq!(events.len(), 2);
+        let EbpfEvent::OomKill(payload) = &events[0] else {
+            panic!("expected an OOM kill event, got {:?}", events[0]);
+        };
+        assert_eq!((payload.namespace.as_str(), payload.pod_name.as_str(), payload.pod_uid.as_str()), ("shop", "web", POD_UID));
+        assert_eq!(payload.oom_kills, 1);
+        assert_eq!(
+            (payload.anon_bytes, payload.file_bytes, payload.kernel_bytes, payload.sock_bytes, payload.shmem_bytes),
+            (4096, 1024, 256, 128, 64)
+        );
+        assert_eq!((payload.usage_bytes, payload.limit_bytes), (3072, Some(8192)));
+        // The limit hits that led up to it are reported as pressure
+        assert!(matches!(&events[1], EbpfEvent::MemoryPressure(pressure) if pressure.oom_kill_events == 1));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_pod_is_not_reported() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let cgroup_root = root.path().join("cgroup");
+        write_cgroup(&cgroup_root, STRANGER_UID, 10.0, 0);
+        let mut monitor = monitor(root.path());
+        monitor.poll_once().await;
+
+        write_cgroup(&cgroup_root, STRANGER_UID, 90.0, 1);
+        assert!(monitor.poll_once().await.is_empty());
+        assert!(monitor.pods.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_removed_pods_are_forgotten() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let cgroup_root = root.path().join("cgroup");
+        let dir = write_cgroup(&cgroup_root, POD_UID, 10.0, 0);
+        let mut monitor = monitor(root.path());
+        monitor.poll_once().await;
+        monitor.poll_once().await;
+        assert!(monitor.pods.contains_key(POD_UID));
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(monitor.poll_once().await.is_empty());
+        assert!(monitor.previous.is_empty());
+        assert!(monitor.pods.is_empty());
+
+        // A pod that comes back starts from a fresh baseline
+        write_cgroup(&cgroup_root, POD_UID, 90.0, 0);
+        assert!(monitor.poll_once().await.is_empty());
+        assert_eq!(monitor.poll_once().await.len(), 1);
+    }
+}
@@ -0,0 +1,457 @@
+use anyhow::Result;
+use axum::{http::StatusCode, routing::get, Json, Router};
+use prometheus::{CounterVec, Encoder, GaugeVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tracing::info;
+
+use crate::cgroup::{CpuThrottleSample, MemoryPressureSample};
+use crate::pod_resolver::PodInfo;
+use crate::supervisor::Health;
+
+static METRICS: LazyLock<ObserverMetrics> =
+    LazyLock::new(|| ObserverMetrics::new().expect("observer metrics are valid"));
+
+/// The process-wide metrics served on `/metrics`
+pub fn metrics() -> &'static ObserverMetrics {
+    &METRICS
+}
+
+/// Kernel truth per pod and container, plus self-metrics of the observer
+pub struct ObserverMetrics {
+    registry: Registry,
+    pub spool: SpoolMetrics,
+    pub pipeline: PipelineMetrics,
+    kernel: KernelSeries,
+    /// Accumulated per-pod figures; the kernel series are rebuilt from these on scrape
+    pods: Mutex<HashMap<PodKey, PodStats>>,
+}
+
+/// Delivery backlog, so a node that cannot reach the operator shows up in alerts
+#[derive(Clone)]
+pub struct SpoolMetrics {
+    pub depth: IntGauge,
+    pub dead_letter_depth: IntGauge,
+    pub dropped: IntCounter,
+    pub retries: IntCounter,
+}
+
+/// How the bpftrace output is getting through the pipeline
+#[derive(Clone)]
+pub struct PipelineMetrics {
+    lines: IntCounterVec,
+    resolution_failures: IntCounter,
+    events: IntCounterVec,
+    webhook_failures: IntCounterVec,
+    monitored_pods: IntGauge,
+    unmonitored: IntCounterVec,
+    bpftrace_restarts: IntCounterVec,
+    bpftrace_stderr: IntCounterVec,
+}
+
+struct KernelSeries {
+    cpu_periods: IntCounterVec,
+    cpu_throttled_periods: IntCounterVec,
+    cpu_throttled_seconds: CounterVec,
+    runqueue_wait_seconds: CounterVec,
+    runqueue_wakeups: IntCounterVec,
+    memory_stall_seconds: CounterVec,
+    memory_psi_avg10: GaugeVec,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PodKey {
+    namespace: String,
+    pod: String,
+}
+
+struct PodStats {
+    last_seen: Instant,
+    cpu: Option<CpuStats>,
+    containers: HashMap<String, ContainerStats>,
+    memory: Option<MemoryStats>,
+}
+
+/// CFS bandwidth counters, which are read from the pod cgroup and so belong
+/// to no one container
+#[derive(Default)]
+struct CpuStats {
+    periods: u64,
+    throttled_periods: u64,
+    throttled_usec: u64,
+}
+
+#[derive(Default)]
+struct ContainerStats {
+    runqueue_wait_ns: u64,
+    runqueue_wakeups: u64,
+}
+
+#[derive(Default)]
+struct MemoryStats {
+    some_stall_usec: u64,
+    full_stall_usec: u64,
+    some_avg10: f64,
+    full_avg10: f64,
+}
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(registry: &Registry, collector: T) -> Result<T> {
+    registry.register(Box::new(collector.clone()))?;
+    Ok(collector)
+}
+
+impl SpoolMetrics {
+    fn new(registry: &Registry) -> Result<Self> {
+        Ok(Self {
+            depth: register(registry, IntGauge::new(
+                "kernel_observer_spool_depth",
+                "Event batches waiting in the spool for delivery",
+            )?)?,
+            dead_letter_depth: register(registry, IntGauge::new(
+                "kernel_observer_spool_dead_letter_depth",
+                "Event batches given up on and kept in the dead-letter directory",
+            )?)?,
+            dropped: register(registry, IntCounter::new(
+                "kernel_observer_spool_dropped_total",
+                "Event batches discarded because the spool was full",
+            )?)?,
+            retries: register(registry, IntCounter::new(
+                "kernel_observer_delivery_retries_total",
+                "Failed deliveries that were scheduled for another attempt",
+            )?)?,
+        })
+    }
+}
+
+impl PipelineMetrics {
+    fn new(registry: &Registry) -> Result<Self> {
+        Ok(Self {
+            lines: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_bpftrace_lines_total", "bpftrace output lines, by whether the parser recognised them"),
+                &["outcome"],
+            )?)?,
+            resolution_failures: register(registry, IntCounter::new(
+                "kernel_observer_pod_resolution_failures_total",
+                "Kernel activity in a pod cgroup that could not be attributed to a pod",
+            )?)?,
+            events: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_events_total", "Events sent towards the operator, by type"),
+                &["type"],
+            )?)?,
+            webhook_failures: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_webhook_failures_total", "Failed batch deliveries to the operator, by reason"),
+                &["reason"],
+            )?)?,
+            monitored_pods: register(registry, IntGauge::new(
+                "kernel_observer_monitored_pods",
+                "Pods on this node the operator has asked to be recorded",
+            )?)?,
+            unmonitored: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_unmonitored_samples_total", "Kernel activity dropped because its pod is not monitored, by source"),
+                &["source"],
+            )?)?,
+            bpftrace_restarts: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_bpftrace_restarts_total", "Times bpftrace exited and was started again, by script"),
+                &["script"],
+            )?)?,
+            bpftrace_stderr: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_bpftrace_stderr_lines_total", "bpftrace stderr lines, by whether a restart can clear them"),
+                &["class"],
+            )?)?,
+        })
+    }
+
+    pub fn line(&self, recognised: bool) {
+        let outcome = if recognised { "parsed" } else { "unmatched" };
+        self.lines.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn resolution_failed(&self) {
+        self.resolution_failures.inc();
+    }
+
+    pub fn event(&self, kind: &str) {
+        self.events.with_label_values(&[kind]).inc();
+    }
+
+    /// `reason` is `transient` (retried), `rejected` (dead-lettered) or `partial`
+    pub fn webhook_failed(&self, reason: &str) {
+        self.webhook_failures.with_label_values(&[reason]).inc();
+    }
+
+    pub fn set_monitored_pods(&self, count: usize) {
+        self.monitored_pods.set(count as i64);
+    }
+
+    /// `source` is `cpu_throttle`, `runqueue` or `memory`
+    pub fn unmonitored(&self, source: &str) {
+        self.unmonitored.with_label_values(&[source]).inc();
+    }
+
+    pub fn bpftrace_restarted(&self, script: &str) {
+        self.bpftrace_restarts.with_label_values(&[script]).inc();
+    }
+
+    /// `class` is `fatal` or `transient`
+    pub fn bpftrace_stderr(&self, class: &str) {
+        self.bpftrace_stderr.with_label_values(&[class]).inc();
+    }
+}
+
+impl KernelSeries {
+    fn new(registry: &Registry) -> Result<Self> {
+        let pod = &["namespace", "pod"];
+        let container = &["namespace", "pod", "container"];
+        Ok(Self {
+            cpu_periods: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_cpu_periods_total", "CFS enforcement periods elapsed"),
+                pod,
+            )?)?,
+            cpu_throttled_periods: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_cpu_throttled_periods_total", "CFS periods in which the quota was exhausted"),
+                pod,
+            )?)?,
+            cpu_throttled_seconds: register(registry, CounterVec::new(
+                Opts::new("kernel_observer_cpu_throttled_seconds_total", "Time spent throttled by the CFS quota"),
+                pod,
+            )?)?,
+            runqueue_wait_seconds: register(registry, CounterVec::new(
+                Opts::new("kernel_observer_runqueue_wait_seconds_total", "Time tasks spent runnable but waiting for a CPU"),
+                container,
+            )?)?,
+            runqueue_wakeups: register(registry, IntCounterVec::new(
+                Opts::new("kernel_observer_runqueue_wakeups_total", "Times tasks were scheduled after waiting in the run queue"),
+                container,
+            )?)?,
+            memory_stall_seconds: register(registry, CounterVec::new(
+                Opts::new("kernel_observer_memory_pressure_stall_seconds_total", "Time tasks stalled on memory (PSI total)"),
+                &["namespace", "pod", "kind"],
+            )?)?,
+            memory_psi_avg10: register(registry, GaugeVec::new(
+                Opts::new("kernel_observer_memory_psi_avg10", "Share of the last 10s tasks stalled on memory, percent"),
+                &["namespace", "pod", "kind"],
+            )?)?,
+        })
+    }
+
+    fn reset(&self) {
+        self.cpu_periods.reset();
+        self.cpu_throttled_periods.reset();
+        self.cpu_throttled_seconds.reset();
+        self.runqueue_wait_seconds.reset();
+        self.runqueue_wakeups.reset();
+        self.memory_stall_seconds.reset();
+        self.memory_psi_avg10.reset();
+    }
+
+    fn set(&self, key: &PodKey, stats: &PodStats) {
+        if let Some(cpu) = &stats.cpu {
+            let labels = [key.namespace.as_str(), key.pod.as_str()];
+            self.cpu_periods.with_label_values(&labels).inc_by(cpu.periods);
+            self.cpu_throttled_periods.with_label_values(&labels).inc_by(cpu.throttled_periods);
+            self.cpu_throttled_seconds.with_label_values(&labels).inc_by(cpu.throttled_usec as f64 / 1e6);
+        }
+        for (container, c) in &stats.containers {
+            let labels = [key.namespace.as_str(), key.pod.as_str(), container.as_str()];
+            self.runqueue_wait_seconds.with_label_values(&labels).inc_by(c.runqueue_wait_ns as f64 / 1e9);
+            self.runqueue_wakeups.with_label_values(&labels).inc_by(c.runqueue_wakeups);
+        }
+        if let Some(memory) = &stats.memory {
+            for (kind, stall_usec, avg10) in [
+                ("some", memory.some_stall_usec, memory.some_avg10),
+                ("full", memory.full_stall_usec, memory.full_avg10),
+            ] {
+                let labels = [key.namespace.as_str(), key.pod.as_str(), kind];
+                self.memory_stall_seconds.with_label_values(&labels).inc_by(stall_usec as f64 / 1e6);
+                self.memory_psi_avg10.with_label_values(&labels).set(avg10);
+            }
+        }
+    }
+}
+
+impl ObserverMetrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        Ok(Self {
+            spool: SpoolMetrics::new(&registry)?,
+            pipeline: PipelineMetrics::new(&registry)?,
+            kernel: KernelSeries::new(&registry)?,
+            registry,
+            pods: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn pods(&self) -> MutexGuard<'_, HashMap<PodKey, PodStats>> {
+        self.pods.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn with_pod(&self, pod: &PodInfo, update: impl FnOnce(&mut PodStats)) {
+        let key = PodKey { namespace: pod.namespace.clone(), pod: pod.name.clone() };
+        let mut pods = self.pods();
+        let stats = pods.entry(key).or_insert_with(|| PodStats {
+            last_seen: Instant::now(),
+            cpu: None,
+            containers: HashMap::new(),
+            memory: None,
+        });
+        stats.last_seen = Instant::now();
+        update(stats);
+    }
+
+    fn with_container(&self, pod: &PodInfo, update: impl FnOnce(&mut ContainerStats)) {
+        self.with_pod(pod, |stats| update(stats.containers.entry(pod.container_name.clone()).or_default()));
+    }
+
+    /// A window of the pod cgroup's `cpu.stat`, whatever container's task drew it
+    pub fn record_cpu_throttling(&self, pod: &PodInfo, sample: &CpuThrottleSample) {
+        self.with_pod(pod, |stats| {
+            let cpu = stats.cpu.get_or_insert_with(CpuStats::default);
+            cpu.periods += sample.nr_periods;
+            cpu.throttled_periods += sample.nr_throttled;
+            cpu.throttled_usec += sample.throttled_usec;
+        });
+    }
+
+    pub fn record_runqueue_wait(&self, pod: &PodInfo, wait_ns: u64, wakeups: u64) {
+        self.with_container(pod, |c| {
+            c.runqueue_wait_ns += wait_ns;
+            c.runqueue_wakeups += wakeups;
+        });
+    }
+
+    pub fn record_memory_pressure(&self, pod: &PodInfo, sample: &MemoryPressureSample) {
+        self.with_pod(pod, |stats| {
+            let memory = stats.memory.get_or_insert_with(MemoryStats::default);
+            memory.some_stall_usec += sample.some_stall_usec;
+            memory.full_stall_usec += sample.full_stall_usec;
+            memory.some_avg10 = sample.some_avg10;
+            memory.full_avg10 = sample.full_avg10;
+        });
+    }
+
+    /// Stop exporting pods nothing was recorded for within `max_age`, which
+    /// keeps label cardinality bounded as pods come and go
+    pub fn forget_idle_pods(&self, max_age: Duration) {
+        self.pods().retain(|_, stats| stats.last_seen.elapsed() <= max_age);
+    }
+
+    /// Prometheus text exposition of every metric
+    pub fn render(&self) -> String {
+        // Held until gathered, so a concurrent scrape never sees the series half rebuilt
+        let pods = self.pods();
+        self.kernel.reset();
+        for (key, stats) in pods.iter() {
+            self.kernel.set(key, stats);
+        }
+
+        let mut buffer = Vec::new();
+        // Encoding gathered families into a Vec cannot fail
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// `/metrics`, and `/readyz`, which answers 503 while bpftrace is not running
+pub async fn serve(port: u16, health: Health) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(|| async { metrics().render() }))
+        .route("/readyz", get(move || async move {
+            let report = health.report();
+            let status = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+            (status, Json(report))
+        }));
+
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+    info!("📈 Serving observer metrics on port {}", port);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod(name: &str, container: &str) -> PodInfo {
+        PodInfo {
+            name: name.to_string(),
+            namespace: "default".to_string(),
+            container_name: container.to_string(),
+            cpu_request: 0.5,
+            cpu_limit: 1.0,
+            pod_cpu_request: 0.5,
+        }
+    }
+
+    fn has_line(rendered: &str, line: &str) -> bool {
+        rendered.lines().any(|l| l == line)
+    }
+
+    #[test]
+    fn test_cpu_series_per_pod_and_runqueue_per_container() {
+        let metrics = ObserverMetrics::new().unwrap();
+        let sample = CpuThrottleSample {
+            nr_periods: 100,
+            nr_throttled: 40,
+            throttled_usec: 1_500_000,
+            usage_usec: None,
+            elapsed: Duration::from_secs(10),
+        };
+        // The pod cgroup's counters, whichever container's task drew them
+        metrics.record_cpu_throttling(&pod("web", "app"), &sample);
+        metrics.record_cpu_throttling(&pod("web", "sidecar"), &sample);
+        metrics.record_runqueue_wait(&pod("web", "sidecar"), 2_000_000_000, 7);
+
+        let rendered = metrics.render();
+        assert!(has_line(&rendered, r#"kernel_observer_cpu_throttled_periods_total{namespace="default",pod="web"} 80"#));
+        assert!(has_line(&rendered, r#"kernel_observer_cpu_periods_total{namespace="default",pod="web"} 200"#));
+        assert!(has_line(&rendered, r#"kernel_observer_cpu_throttled_seconds_total{namespace="default",pod="web"} 3"#));
+        assert!(!rendered.contains(r#"kernel_observer_cpu_periods_total{container"#));
+        assert!(has_line(&rendered, r#"kernel_observer_runqueue_wait_seconds_total{container="sidecar",namespace="default",pod="web"} 2"#));
+        assert!(has_line(&rendered, r#"kernel_observer_runqueue_wakeups_total{container="sidecar",namespace="default",pod="web"} 7"#));
+    }
+
+    #[test]
+    fn test_memory_psi_and_idle_pods_are_forgotten() {
+        let metrics = ObserverMetrics::new().unwrap();
+        let sample = MemoryPressureSample {
+            some_avg10: 12.5,
+            full_avg10: 1.5,
+            some_stall_usec: 500_000,
+            full_stall_usec: 0,
+            high_events: 0,
+            max_events: 0,
+            oom_events: 0,
+            oom_kill_events: 0,
+            reclaim_scanned_pages: 0,
+            working_set_bytes: 0,
+            limit_bytes: None,
+            elapsed: Duration::from_secs(10),
+        };
+        metrics.record_memory_pressure(&pod("cache", "redis"), &sample);
+
+        let rendered = metrics.render();
+        assert!(has_line(&rendered, r#"kernel_observer_memory_psi_avg10{kind="some",namespace="default",pod="cache"} 12.5"#));
+        assert!(has_line(&rendered, r#"kernel_observer_memory_pressure_stall_seconds_total{kind="some",namespace="default",pod="cache"} 0.5"#));
+
+        metrics.forget_idle_pods(Duration::ZERO);
+        assert!(!metrics.render().contains(r#"pod="cache""#));
+    }
+
+    #[test]
+    fn test_pipeline_counters() {
+        let metrics = ObserverMetrics::new().unwrap();
+        metrics.pipeline.line(true);
+        metrics.pipeline.line(false);
+        metrics.pipeline.event("cpu_throttle");
+        metrics.pipeline.webhook_failed("transient");
+
+        let rendered = metrics.render();
+        assert!(has_line(&rendered, r#"kernel_observer_bpftrace_lines_total{outcome="parsed"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_observer_bpftrace_lines_total{outcome="unmatched"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_observer_events_total{type="cpu_throttle"} 1"#));
+        assert!(has_line(&rendered, r#"kernel_observer_webhook_failures_total{reason="transient"} 1"#));
+    }
+}
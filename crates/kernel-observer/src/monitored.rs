@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::runtime::watcher::{self, watcher, Event};
+use kube::runtime::WatchStreamExt;
+use kube::{Api, Client};
+use tracing::{info, warn};
+
+use kernel_gossip_protocol::monitored::{decode_node_pods, node_key, MonitoredPod};
+
+use crate::metrics::metrics;
+
+/// The pods on this node the operator wants recorded, from the monitored-pods
+/// ConfigMap. Kernel activity of other pods is dropped before it is sampled,
+/// resolved or sent.
+///
+/// Until the ConfigMap has been read, or while it does not exist, everything
+/// is let through and the operator decides as it did before. bpftrace cannot
+/// take map updates from outside while a script runs, so the filter sits here
+/// rather than in the probes.
+#[derive(Clone)]
+pub struct MonitoredPods {
+    inner: Arc<RwLock<Allowlist>>,
+}
+
+#[derive(Default)]
+struct Allowlist {
+    /// None until the operator's set is known
+    pods: Option<HashMap<String, MonitoredPod>>,
+}
+
+impl MonitoredPods {
+    /// Lets everything through until [`MonitoredPods::update`] is called
+    pub fn new() -> Self {
+        Self { inner: Arc::new(RwLock::new(Allowlist::default())) }
+    }
+
+    /// Whether activity in the pod with this UID should be recorded
+    pub fn admits(&self, pod_uid: &str) -> bool {
+        let allowlist = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        allowlist.pods.as_ref().is_none_or(|pods| pods.contains_key(pod_uid))
+    }
+
+    /// Same as [`MonitoredPods::admits`], counting what is dropped under `source`
+    pub fn admits_from(&self, pod_uid: &str, source: &str) -> bool {
+        let admitted = self.admits(pod_uid);
+        if !admitted {
+            metrics().pipeline.unmonitored(source);
+        }
+        admitted
+    }
+
+    /// Replace the set; `None` goes back to letting everything through
+    pub fn update(&self, pods: Option<Vec<MonitoredPod>>) {
+        let pods = pods.map(|pods| pods.into_iter().map(|p| (p.uid.clone(), p)).collect::<HashMap<_, _>>());
+        metrics().pipeline.set_monitored_pods(pods.as_ref().map_or(0, HashMap::len));
+        self.inner.write().unwrap_or_else(|e| e.into_inner()).pods = pods;
+    }
+
+    /// Apply the published set for `node` from the ConfigMap's data. A
+    /// ConfigMap without a key for the node means no pod here is monitored.
+    pub fn update_from_configmap(&self, cm: &ConfigMap, node: &str) {
+        let data = cm.data.as_ref().and_then(|data| data.get(&node_key(node)));
+        match data.map(|data| decode_node_pods(data)).transpose() {
+            Ok(pods) => {
+                let pods = pods.unwrap_or_default();
+                info!("📋 {} monitored pods on node {}", pods.len(), node);
+                self.update(Some(pods));
+            }
+            // Keep the previous set rather than dropping or admitting everything
+            Err(e) => warn!("⚠️ Ignoring malformed monitored pod list for node {}: {}", node, e),
+        }
+    }
+
+    /// Follow the ConfigMap for as long as the observer runs
+    pub async fn watch(self, client: Client, namespace: String, configmap_name: String, node: String) {
+        let api: Api<ConfigMap> = Api::namespaced(client, &namespace);
+        let config = watcher::Config::default().fields(&format!("metadata.name={configmap_name}"));
+        let mut events = watcher(api, config).default_backoff().boxed();
+
+        info!("👀 Watching ConfigMap {}/{} for pods monitored on {}", namespace, configmap_name, node);
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(Event::Applied(cm)) => self.update_from_configmap(&cm, &node),
+                Ok(Event::Restarted(cms)) => match cms.first() {
+                    Some(cm) => self.update_from_configmap(cm, &node),
+                    None => self.update(None),
+                },
+                Ok(Event::Deleted(_)) => {
+                    warn!("⚠️ ConfigMap {} was deleted, recording all pods until it is back", configmap_name);
+                    self.update(None);
+                }
+                Err(e) => warn!("⚠️ Monitored pods watch failed: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel_gossip_protocol::monitored::encode_node_pods;
+    use std::collections::BTreeMap;
+
+    fn pod(uid: &str, node: &str) -> MonitoredPod {
+        MonitoredPod {
+            uid: uid.to_string(),
+            namespace: "shop".to_string(),
+            name: format!("pod-{uid}"),
+            node: node.to_string(),
+            container_ids: vec![],
+        }
+    }
+
+    fn configmap(entries: &[(&str, Vec<MonitoredPod>)]) -> ConfigMap {
+        let data: BTreeMap<String, String> = entries
+            .iter()
+            .map(|(node, pods)| (node_key(node), encode_node_pods(pods).unwrap()))
+            .collect();
+        ConfigMap { data: Some(data), ..Default::default() }
+    }
+
+    #[test]
+    fn test_admits_everything_until_synced() {
+        let monitored = MonitoredPods::new();
+        assert!(monitored.admits("anything"));
+
+        monitored.update(Some(vec![pod("a", "node-1")]));
+        assert!(monitored.admits("a"));
+        assert!(!monitored.admits("b"));
+
+        monitored.update(None);
+        assert!(monitored.admits("b"));
+    }
+
+    #[test]
+    fn test_reads_only_this_nodes_key() {
+        let monitored = MonitoredPods::new();
+        let cm = configmap(&[("node-1", vec![pod("a", "node-1")]), ("node-2", vec![pod("b", "node-2")])]);
+
+        monitored.update_from_configmap(&cm, "node-1");
+        assert!(monitored.admits("a"));
+        assert!(!monitored.admits("b"));
+
+        // No key for the node: nothing here is monitored
+        monitored.update_from_configmap(&cm, "node-3");
+        assert!(!monitored.admits("a"));
+    }
+
+    #[test]
+    fn test_malformed_list_keeps_previous_set() {
+        let monitored = MonitoredPods::new();
+        monitored.update(Some(vec![pod("a", "node-1")]));
+
+        let mut cm = configmap(&[]);
+        cm.data.as_mut().unwrap().insert(node_key("node-1"), "not json".to_string());
+        monitored.update_from_configmap(&cm, "node-1");
+        assert!(monitored.admits("a"));
+        assert!(!monitored.admits("b"));
+    }
+}
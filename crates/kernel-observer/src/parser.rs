@@ -1,320 +1,465 @@
-use serde::Serialize;
-use regex::Regex;
 use anyhow::Result;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 use tracing::{debug, warn, info};
 
-use crate::pod_resolver::{PodResolver, PodInfo};
-use crate::webhook::WebhookClient;
+use kernel_gossip_protocol::{CpuThrottlePayload, EbpfEvent, PodCreationPayload, TimelineEvent};
 
-#[derive(Debug, Clone, Serialize)]
-pub struct TimelineEntry {
-    pub timestamp_ms: u64,
-    pub action: String,
-}
+use crate::birth::{Birth, BirthTracker, SessionKind};
+use crate::cgroup::{CgroupReader, PodCgroup};
+use crate::metrics::metrics;
+use crate::monitored::MonitoredPods;
+use crate::pod_resolver::{PodInfo, PodResolver};
+use crate::records::{registry, ContainerBirthComplete, ContainerMain, CpuThrottle, KernelRecord};
+use crate::recording::Recorder;
+use crate::source::SourceLine;
 
-#[derive(Debug, Clone, Serialize)]
-#[serde(tag = "type")]
-pub enum EbpfEvent {
-    #[serde(rename = "cpu_throttle")]
-    CpuThrottle {
-        pod_name: String,
-        namespace: String,
-        container_name: String,
-        throttle_percentage: f64,
-        actual_cpu_usage: f64,
-        reported_cpu_usage: f64,
-        period_seconds: u64,
-        ebpf_detection: bool,
-        throttle_ns: u64,
-        timestamp: String,
-    },
-    #[serde(rename = "pod_creation")]
-    PodCreation {
-        pod_name: String,
-        namespace: String,
-        total_syscalls: u64,
-        namespace_ops: u64,
-        cgroup_writes: u64,
-        duration_ns: u64,
-        timeline: Vec<TimelineEntry>,
-        ebpf_detection: bool,
-        timestamp: String,
-    },
-}
+/// Pods with no kernel activity recorded for this long stop being exported
+const IDLE_POD_SERIES: Duration = Duration::from_secs(600);
+
+/// Birth sessions whose container has not exec'd by then are given up on
+const BIRTH_SESSION_TTL: Duration = Duration::from_secs(300);
 
 #[derive(Clone)]
 pub struct EbpfParser {
-    cpu_throttle_regex: Regex,
-    #[allow(dead_code)]
-    golden_syscall_regex: Regex,
-    #[allow(dead_code)]
-    syscall_count_regex: Regex,
     pod_resolver: Arc<PodResolver>,
-    webhook_client: WebhookClient,
+    cgroup_reader: Arc<CgroupReader>,
+    monitored: MonitoredPods,
+    recorder: Option<Recorder>,
+    births: Arc<Mutex<BirthTracker>>,
 }
 
 impl EbpfParser {
-    pub async fn new(webhook_url: String) -> Result<Self> {
-        Ok(Self {
-            cpu_throttle_regex: Regex::new(r"CPU_THROTTLE_EVENT pid=(\d+) comm=([^ ]+) throttle_ns=(\d+) timestamp=(\d+)")?,
-            golden_syscall_regex: Regex::new(r"GOLDEN_SYSCALL type=([a-z]+) pid=(\d+) comm=([^ ]+) timestamp_ms=(\d+)")?,
-            syscall_count_regex: Regex::new(r"SYSCALL_COUNT pid=(\d+) total=(\d+) comm=([^ ]+) timestamp_ms=(\d+)")?,
-            pod_resolver: Arc::new(PodResolver::new().await?),
-            webhook_client: WebhookClient::new(webhook_url),
-        })
+    pub fn new(
+        pod_resolver: Arc<PodResolver>,
+        cgroup_reader: Arc<CgroupReader>,
+        monitored: MonitoredPods,
+        recorder: Option<Recorder>,
+    ) -> Self {
+        Self {
+            pod_resolver,
+            cgroup_reader,
+            monitored,
+            recorder,
+            births: Arc::new(Mutex::new(BirthTracker::new(BIRTH_SESSION_TTL))),
+        }
     }
 
-    pub async fn parse_line(&self, line: &str) -> Result<Option<EbpfEvent>> {
-        // Parse CPU throttling events (unchanged)
-        if line.contains("CPU_THROTTLE_EVENT") {
-            if let Some(caps) = self.cpu_throttle_regex.captures(line) {
-                let pid: u32 = caps[1].parse()?;
-                let comm = &caps[2];
-                let throttle_ns: u64 = caps[3].parse()?;
-                let _timestamp: u64 = caps[4].parse()?;
-                
-                debug!("🔍 CPU throttle event detected: PID {} ({}), throttle_ns: {}", pid, comm, throttle_ns);
-
-                // Resolve PID to pod information
-                if let Some(pod_info) = self.pod_resolver.resolve_pid_to_pod(pid).await {
-                    let throttle_percentage = self.calculate_throttle_percentage(throttle_ns);
-                    let actual_cpu_usage = self.calculate_actual_cpu_usage(throttle_ns, &pod_info);
-                    let reported_cpu_usage = pod_info.cpu_request;
-
-                    info!("🎯 Real CPU throttle detected: {}% throttling on {}/{}", 
-                          throttle_percentage, pod_info.namespace, pod_info.name);
-
-                    return Ok(Some(EbpfEvent::CpuThrottle {
-                        pod_name: pod_info.name,
-                        namespace: pod_info.namespace,
-                        container_name: pod_info.container_name,
-                        throttle_percentage,
-                        actual_cpu_usage,
-                        reported_cpu_usage,
-                        period_seconds: 10,
-                        ebpf_detection: true,
-                        throttle_ns,
-                        timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
-                    }));
-                } else {
-                    warn!("⚠️ Could not resolve PID {} to pod information", pid);
+    /// The events a line of kernel output amounts to. A `-f json` map line
+    /// holds many records, a text line at most one. Records the source already
+    /// decoded are used as they are.
+    pub async fn parse_line(&self, line: SourceLine) -> Result<Vec<EbpfEvent>> {
+        let SourceLine { line, records, .. } = line;
+        let line = line.as_str();
+        if line.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.line(line);
+        }
+
+        let records = match records.map_or_else(|| registry().decode(line), Ok) {
+            Ok(records) => records,
+            Err(e) => {
+                warn!("⚠️ Malformed bpftrace record: {}: {}", e, line);
+                Vec::new()
+            }
+        };
+        metrics().pipeline.line(!records.is_empty());
+
+        let mut events = Vec::new();
+        for record in records {
+            if let Some(event) = self.handle(record).await? {
+                if let Some(recorder) = &self.recorder {
+                    recorder.event(&event);
                 }
+                events.push(event);
             }
         }
+        Ok(events)
+    }
 
-        // Parse simplified container birth events
-        if line.contains("CONTAINER_PROCESS_START") {
-            debug!("📦 Container process started: {}", line);
-            // Track container runtime processes
+    async fn handle(&self, record: KernelRecord) -> Result<Option<EbpfEvent>> {
+        match record {
+            KernelRecord::RunQueue(runqueue) => {
+                self.record_runqueue_wait(runqueue.pid, runqueue.wait_ns, runqueue.wakeups).await;
+                Ok(None)
+            }
+            KernelRecord::CpuThrottle(throttle) => self.cpu_throttle(throttle).await,
+            KernelRecord::ContainerBirthComplete(birth) => self.birth_complete(birth).await,
+            KernelRecord::ContainerMain(main) => self.container_main(main).await,
+            KernelRecord::ContainerProcessStart(start) => {
+                debug!("📦 Container process started: {:?}", start);
+                self.births().process_start(&start);
+                Ok(None)
+            }
+            KernelRecord::ContainerFork(fork) => {
+                self.births().fork(&fork);
+                Ok(None)
+            }
+            KernelRecord::ContainerSyscalls(syscalls) => {
+                debug!("📋 Container syscalls: {:?}", syscalls);
+                self.births().syscalls(syscalls.pid, syscalls.total);
+                Ok(None)
+            }
+            KernelRecord::ContainerNamespaceOp(op) => {
+                debug!("🔗 Container namespace operation: {:?}", op);
+                self.births().namespace_op(&op);
+                Ok(None)
+            }
+            KernelRecord::ContainerMountOp(op) => {
+                debug!("🔧 Container mount operation: {:?}", op);
+                self.births().mount_op(&op);
+                Ok(None)
+            }
+            KernelRecord::ContainerCgroupWrite(write) => {
+                debug!("🗂️ Container cgroup write: {:?}", write);
+                self.births().cgroup_write(&write);
+                Ok(None)
+            }
+            KernelRecord::LostEvents(events) => {
+                warn!("⚠️ bpftrace lost {} events", events);
+                Ok(None)
+            }
+            KernelRecord::MonitorStarted(_)
+            | KernelRecord::MonitorEnded(_)
+            | KernelRecord::GoldenSyscall(_)
+            | KernelRecord::ContainerMainSyscall(_) => Ok(None),
         }
+    }
 
-        if line.contains("CONTAINER_SYSCALLS") {
-            debug!("📋 Container syscalls: {}", line);
-            // Count syscalls from runtime processes
-        }
+    /// CPU_THROTTLE_EVENT only tells us a task is running; the real numbers
+    /// come from the CFS bandwidth counters of its pod cgroup
+    async fn cpu_throttle(&self, throttle: CpuThrottle) -> Result<Option<EbpfEvent>> {
+        let pid = throttle.pid;
+        debug!("🔍 CPU activity event: PID {} ({})", pid, throttle.comm);
 
-        if line.contains("CONTAINER_NAMESPACE_OP") {
-            debug!("🔗 Container namespace operation: {}", line);
-            // Track namespace operations
-        }
+        let cgroup = match self.pod_cgroup(pid).await {
+            Ok(Some(cgroup)) => cgroup,
+            Ok(None) => {
+                debug!("PID {} is not in a pod cgroup", pid);
+                return Ok(None);
+            }
+            Err(e) => {
+                debug!("Could not read cgroup for PID {}: {}", pid, e);
+                return Ok(None);
+            }
+        };
 
-        if line.contains("CONTAINER_MOUNT_OP") {
-            debug!("🔧 Container mount operation: {}", line);
-            // Track mount operations
+        if !self.admits(&cgroup, "cpu_throttle") {
+            return Ok(None);
         }
 
-        if line.contains("CONTAINER_BIRTH_COMPLETE") {
-            info!("🎉 Container birth completed: {}", line);
-            
-            // Parse the simplified container birth completion event
-            // Format: CONTAINER_BIRTH_COMPLETE pid=123 comm=runc total_syscalls=1247 namespace_ops=12 mount_ops=8 timestamp_ms=...
-            let pid: u32 = self.extract_metric_from_line(line, "pid=").unwrap_or(0) as u32;
-            let total_syscalls = self.extract_metric_from_line(line, "total_syscalls=").unwrap_or(0);
-            let namespace_ops = self.extract_metric_from_line(line, "namespace_ops=").unwrap_or(0);
-            let mount_ops = self.extract_metric_from_line(line, "mount_ops=").unwrap_or(0);
-
-            // Try to resolve PID to pod information
-            if let Some(pod_info) = self.pod_resolver.resolve_pid_to_pod(pid).await {
-                info!("🎯 Pod birth certificate: {}/{} - {} syscalls via PID {}", 
-                      pod_info.namespace, pod_info.name, total_syscalls, pid);
-
-                // Create timeline from the simplified tracking
-                let timeline = vec![
-                    TimelineEntry {
-                        timestamp_ms: 0,
-                        action: format!("Container runtime: {total_syscalls} syscalls, {namespace_ops} namespace ops, {mount_ops} mount ops"),
-                    },
-                ];
-
-                return Ok(Some(EbpfEvent::PodCreation {
-                    pod_name: pod_info.name,
-                    namespace: pod_info.namespace,
-                    total_syscalls,
-                    namespace_ops,
-                    cgroup_writes: mount_ops, // Use mount_ops as cgroup operations proxy
-                    duration_ns: 0, // Duration not available in simplified approach
-                    timeline,
-                    ebpf_detection: true,
-                    timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
-                }));
-            } else {
-                warn!("⚠️ Could not resolve PID {} to pod information for syscall summary", pid);
+        let sample = self.cgroup_reader.sample_cpu_throttling(&cgroup).await;
+        if let Some(recorder) = &self.recorder {
+            recorder.cpu_sample(&cgroup.dir, sample.as_ref().ok().and_then(Option::as_ref));
+        }
+        let sample = match sample {
+            Ok(Some(sample)) => sample,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                warn!("⚠️ Failed to read cpu.stat in {}: {}", cgroup.dir.display(), e);
+                return Ok(None);
             }
+        };
+
+        // Resolve PID to pod information
+        let Some(pod_info) = self.resolve(pid).await else {
+            metrics().pipeline.resolution_failed();
+            warn!("⚠️ Could not resolve PID {} to pod information", pid);
+            return Ok(None);
+        };
+
+        // Every window counts towards the exported periods, throttled or not
+        metrics().record_cpu_throttling(&pod_info, &sample);
+        if sample.nr_throttled == 0 {
+            return Ok(None);
         }
 
-        // Handle container main process detection with cgroup path
-        if line.contains("CONTAINER_MAIN") {
-            info!("🎯 Container main process detected: {}", line);
-            
-            let container_pid = self.extract_metric_from_line(line, "pid=").unwrap_or(0) as u32;
-            let _runc_ppid = self.extract_metric_from_line(line, "ppid=").unwrap_or(0) as u32;
-            
-            // Simplified pod resolution - try PID-based resolution first
-            if let Some(pod_info) = self.pod_resolver.resolve_pid_to_pod(container_pid).await {
-                info!("✅ Resolved container PID {} to pod {}/{}", 
-                      container_pid, pod_info.namespace, pod_info.name);
-                
-                // Use demo defaults for syscall stats since tracking was removed
-                let (total_syscalls, namespace_ops, cgroup_writes, duration_ns) = 
-                    (847, 12, 5, 123_000_000);
-                
-                // Create timeline
-                let timeline = vec![
-                    TimelineEntry {
-                        timestamp_ms: 0,
-                        action: format!("Container runtime: {total_syscalls} syscalls, {namespace_ops} namespace ops"),
-                    },
-                ];
-                
-                // Send PodBirthCertificate event via webhook
-                let event = EbpfEvent::PodCreation {
-                    pod_name: pod_info.name,
-                    namespace: pod_info.namespace,
-                    total_syscalls,
-                    namespace_ops,
-                    cgroup_writes,
-                    duration_ns,
-                    timeline,
-                    ebpf_detection: true,
-                    timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
-                };
-                
-                if let Err(e) = self.webhook_client.send_event(event).await {
-                    warn!("Failed to send PodBirthCertificate webhook: {}", e);
+        let throttle_percentage = sample.throttled_percentage();
+        // cpu.stat is the pod cgroup's, so usage is held against the whole pod's request
+        let actual_cpu_usage = sample.cpu_cores().unwrap_or(0.0);
+        let reported_cpu_usage = pod_info.pod_cpu_request;
+
+        info!("🎯 Real CPU throttle detected: {}% of {} periods throttled ({}us) on {}/{}", 
+              throttle_percentage, sample.nr_periods, sample.throttled_usec,
+              pod_info.namespace, pod_info.name);
+
+        Ok(Some(EbpfEvent::CpuThrottle(CpuThrottlePayload {
+            pod_name: pod_info.name,
+            namespace: pod_info.namespace,
+            container_name: pod_info.container_name,
+            throttle_percentage,
+            actual_cpu_usage,
+            reported_cpu_usage,
+            period_seconds: sample.elapsed.as_secs(),
+            ebpf_detection: true,
+            throttle_ns: sample.throttled_usec * 1_000,
+            nr_periods: sample.nr_periods,
+            nr_throttled: sample.nr_throttled,
+            timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        })))
+    }
+
+    /// The runtime of a tracked session exiting only settles its syscall
+    /// count; the certificate is issued when the container execs. Scripts
+    /// without sessions (monitoring.bt) report the whole birth here.
+    async fn birth_complete(&self, birth: ContainerBirthComplete) -> Result<Option<EbpfEvent>> {
+        info!("🎉 Container birth completed: {:?}", birth);
+
+        let pid = birth.pid;
+        {
+            let mut births = self.births();
+            if births.tracks(pid) {
+                if let Some(total) = birth.total_syscalls {
+                    births.syscalls(pid, total);
                 }
-            } else {
-                warn!("⚠️ Could not resolve container PID {} to pod information", container_pid);
+                return Ok(None);
             }
         }
+        let total_syscalls = birth.total_syscalls.unwrap_or(0);
+        let namespace_ops = birth.namespace_ops.unwrap_or(0);
+        let mount_ops = birth.mount_ops.unwrap_or(0);
+
+        // Try to resolve PID to pod information
+        let Some(pod_info) = self.resolve(pid).await else {
+            metrics().pipeline.resolution_failed();
+            warn!("⚠️ Could not resolve PID {} to pod information for syscall summary", pid);
+            return Ok(None);
+        };
+        info!("🎯 Pod birth certificate: {}/{} - {} syscalls via PID {}", 
+              pod_info.namespace, pod_info.name, total_syscalls, pid);
+
+        // Create timeline from the simplified tracking
+        let timeline = vec![
+            TimelineEvent {
+                timestamp_ms: 0,
+                action: format!("Container runtime: {total_syscalls} syscalls, {namespace_ops} namespace ops, {mount_ops} mount ops"),
+            },
+        ];
+
+        Ok(Some(EbpfEvent::PodCreation(PodCreationPayload {
+            pod_name: pod_info.name,
+            namespace: pod_info.namespace,
+            total_syscalls,
+            namespace_ops,
+            // Scripts without sessions do not count cgroupfs writes
+            cgroup_writes: 0,
+            mount_ops,
+            duration_ns: birth.duration_ns.or(birth.total_duration_ns).unwrap_or(0),
+            timeline,
+            ebpf_detection: true,
+            timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        })))
+    }
+
+    async fn container_main(&self, main: ContainerMain) -> Result<Option<EbpfEvent>> {
+        info!("🎯 Container main process detected: {:?}", main);
+        let container_pid = main.pid;
+
+        let Some(birth) = self.births().container_main(&main) else {
+            warn!("⚠️ No birth session for container PID {} ({}), it started before tracking did", container_pid, main.comm);
+            return Ok(None);
+        };
+        if birth.kind == SessionKind::Exec {
+            // Exec probes and kubectl exec run in a container that already exists
+            debug!("⏭️ {} (PID {}) was exec'd into a running container, not born", birth.main_comm, birth.main_pid);
+            return Ok(None);
+        }
 
-        Ok(None)
+        let Some(pod_info) = self.resolve(container_pid).await else {
+            metrics().pipeline.resolution_failed();
+            warn!("⚠️ Could not resolve container PID {} to pod information", container_pid);
+            return Ok(None);
+        };
+        info!("✅ {} (PID {}) of {}/{} born from runtime PID {}: {} syscalls, {} namespace ops, {} mounts{}, {} cgroup writes in {}ms",
+              birth.main_comm, birth.main_pid, pod_info.namespace, pod_info.name, birth.runtime_pid,
+              birth.total_syscalls, birth.namespace_ops, birth.mounts,
+              if birth.pivot_root { " and pivot_root" } else { "" },
+              birth.cgroup_writes, birth.duration_ns / 1_000_000);
+
+        let mount_ops = birth.mounts + u64::from(birth.pivot_root);
+        let Birth { total_syscalls, namespace_ops, cgroup_writes, duration_ns, timeline, .. } = birth;
+
+        // Becomes a PodBirthCertificate once the operator has it
+        Ok(Some(EbpfEvent::PodCreation(PodCreationPayload {
+            pod_name: pod_info.name,
+            namespace: pod_info.namespace,
+            total_syscalls,
+            namespace_ops,
+            cgroup_writes,
+            mount_ops,
+            duration_ns,
+            timeline,
+            ebpf_detection: true,
+            // The operator places the timeline from here, so keep the milliseconds
+            timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        })))
     }
 
-    fn calculate_throttle_percentage(&self, throttle_ns: u64) -> f64 {
-        // CPU throttling percentage based on time spent throttled
-        // throttle_ns represents nanoseconds of throttling in the period
-        // Standard CFS period is 100ms (100,000,000 ns)
-        let cfs_period_ns = 100_000_000u64;
-        let percentage = (throttle_ns as f64 / cfs_period_ns as f64) * 100.0;
-        
-        // Cap at 100% and round to 1 decimal
-        (percentage.min(100.0) * 10.0).round() / 10.0
+    /// Run queue latency of one task over the last bpftrace interval. Tasks
+    /// outside pod cgroups are the bulk of these lines, so they are dropped
+    /// before asking the resolver.
+    async fn record_runqueue_wait(&self, pid: u32, wait_ns: u64, wakeups: u64) {
+        match self.pod_cgroup(pid).await {
+            Ok(Some(cgroup)) if self.admits(&cgroup, "runqueue") => {}
+            // Not in a monitored pod, or exited since the interval closed
+            Ok(_) | Err(_) => return,
+        }
+        match self.resolve(pid).await {
+            Some(pod_info) => metrics().record_runqueue_wait(&pod_info, wait_ns, wakeups),
+            None => metrics().pipeline.resolution_failed(),
+        }
     }
 
-    fn calculate_actual_cpu_usage(&self, throttle_ns: u64, pod_info: &PodInfo) -> f64 {
-        // Estimate actual CPU usage from throttling data
-        // If throttling occurs, actual usage = requested + throttled amount
-        let throttle_cores = throttle_ns as f64 / 1_000_000_000.0; // Convert ns to cores
-        let actual_usage = pod_info.cpu_request + (throttle_cores * 0.1); // Conservative estimate
-        
-        // Round to 2 decimal places
-        (actual_usage * 100.0).round() / 100.0
+    async fn pod_cgroup(&self, pid: u32) -> Result<Option<PodCgroup>> {
+        let cgroup = match self.cgroup_reader.pod_cgroup_for_pid(pid).await {
+            // A pods file can place processes this host has no /proc entry for
+            Err(e) => match self.pod_resolver.placed_pod_uid(pid) {
+                Some(uid) => Ok(self.cgroup_reader.pod_cpu_cgroup(&uid)),
+                None => Err(e),
+            },
+            found => found,
+        };
+        if let Some(recorder) = &self.recorder {
+            recorder.cgroup(pid, cgroup.as_ref().ok().and_then(Option::as_ref));
+        }
+        cgroup
     }
 
-    pub async fn cleanup_old_sessions(&self) {
-        // No-op since syscall tracker was removed
+    async fn resolve(&self, pid: u32) -> Option<PodInfo> {
+        let pod_info = self.pod_resolver.resolve_pid_to_pod(pid).await;
+        if let Some(recorder) = &self.recorder {
+            recorder.resolved(pid, pod_info.as_ref());
+        }
+        pod_info
+    }
+
+    /// Pod cgroups whose UID cannot be read are let through to the resolver
+    fn admits(&self, cgroup: &PodCgroup, source: &str) -> bool {
+        cgroup.pod_uid().is_none_or(|uid| self.monitored.admits_from(&uid, source))
+    }
+
+    fn births(&self) -> MutexGuard<'_, BirthTracker> {
+        self.births.lock().unwrap_or_else(|e| e.into_inner())
     }
-    
-    /// Extract cgroup path from CONTAINER_MAIN line
-    #[allow(dead_code)]
-    fn extract_cgroup_from_line(&self, line: &str) -> Option<String> {
-        // Look for cgroup= in the line
-        if let Some(start) = line.find("cgroup=") {
-            let path_start = start + 7; // Skip "cgroup="
-            let remainder = &line[path_start..];
-            
-            // Find the end of the cgroup path (space or end of line)
-            if let Some(end) = remainder.find(' ') {
-                Some(remainder[..end].to_string())
-            } else {
-                Some(remainder.to_string())
+
+    pub async fn cleanup_old_sessions(&self) {
+        {
+            let mut births = self.births();
+            let reclaimed = births.cleanup();
+            if reclaimed > 0 {
+                info!("🧹 Dropped {} birth sessions with no container exec, {} still open", reclaimed, births.open_sessions());
             }
-        } else {
-            None
         }
+        self.cgroup_reader.forget_removed_cgroups().await;
+        self.pod_resolver.evict_expired().await;
+        metrics().forget_idle_pods(IDLE_POD_SERIES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    use crate::pod_resolver::PidCacheSettings;
+    use crate::recording::TraceReader;
+    use crate::source::EventSource;
+
+    const SETTINGS: PidCacheSettings = PidCacheSettings { ttl: Duration::from_secs(60), max_entries: 16 };
+    const POD_UID: &str = "2bac1a6a-95d3-4abc-990f-aefaf5c74812";
+    const APP_ID: &str = "4f2a9c1be0d3a7f6c5b4e3d2c1b0a9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d4c3b2";
+    const THROTTLE: &str = "CPU_THROTTLE_EVENT pid=42 comm=app throttle_ns=1 timestamp_ms=9";
+    const BIRTH: &str = "CONTAINER_BIRTH_COMPLETE pid=42 comm=app total_syscalls=321 namespace_ops=4 mount_ops=6 duration_ns=5000000";
+
+    fn write_pods(path: &Path) {
+        let pod = serde_json::json!({
+            "metadata": { "name": "web", "namespace": "shop", "uid": POD_UID },
+            "spec": { "containers": [
+                { "name": "app", "resources": { "requests": { "cpu": "250m" } } },
+                { "name": "sidecar", "resources": { "requests": { "cpu": "250m" } } },
+            ] },
+            "status": { "containerStatuses": [{
+                "name": "app", "containerID": format!("containerd://{APP_ID}"),
+                "image": "app", "imageID": "", "ready": true, "restartCount": 0,
+            }] },
+        });
+        fs::write(path, serde_json::json!([pod]).to_string()).unwrap();
     }
 
-    /// Extract pod UID from cgroup path
-    /// Example path: /sys/fs/cgroup/.../pod12345678-1234-1234-1234-123456789012/...
-    #[allow(dead_code)]
-    fn extract_pod_uid_from_cgroup_path(&self, line: &str) -> Option<String> {
-        // Look for pod UID pattern in the line
-        if let Some(start) = line.find("pod") {
-            let path_segment = &line[start..];
-            
-            // Extract the pod UID (format: pod<UID>)
-            if let Some(end) = path_segment.find('/') {
-                let pod_segment = &path_segment[3..end]; // Skip "pod" prefix
-                Some(pod_segment.to_string())
-            } else if let Some(end) = path_segment.find(' ') {
-                let pod_segment = &path_segment[3..end]; // Skip "pod" prefix  
-                Some(pod_segment.to_string())
-            } else {
-                // Handle case where pod UID is at end of line
-                let pod_segment = &path_segment[3..];
-                if pod_segment.len() >= 36 { // UID format is 36 chars
-                    Some(pod_segment[..36].to_string())
-                } else {
-                    None
+    /// Events are stamped when they are made, so the stamps differ between runs
+    fn undated(events: Vec<EbpfEvent>) -> Vec<EbpfEvent> {
+        events
+            .into_iter()
+            .map(|mut event| {
+                match &mut event {
+                    EbpfEvent::CpuThrottle(payload) => payload.timestamp.clear(),
+                    EbpfEvent::PodCreation(payload) => payload.timestamp.clear(),
+                    _ => {}
                 }
-            }
-        } else {
-            None
-        }
+                event
+            })
+            .collect()
     }
 
-    /// Resolve pod information by UID instead of PID
-    #[allow(dead_code)]
-    async fn resolve_pod_by_uid(&self, pod_uid: &str) -> Option<crate::pod_resolver::PodInfo> {
-        // For now, delegate to the pod resolver with a special marker
-        // TODO: Implement direct UID-based resolution via Kubernetes API
-        debug!("🔍 Attempting to resolve pod by UID: {}", pod_uid);
-        
-        // This is a simplified approach - in a full implementation,
-        // we would query the Kubernetes API directly by UID
-        // For now, we'll return None and rely on the existing PID-based resolution
-        // when it's fixed in Phase 8.1
-        None
+    fn text(line: &str) -> SourceLine {
+        SourceLine { offset: Duration::ZERO, line: line.to_string(), records: None }
     }
 
-    /// Extract numeric metric from a line
-    /// Example: extract_metric_from_line("total_syscalls=1247 duration=500", "total_syscalls=") -> Some(1247)
-    fn extract_metric_from_line(&self, line: &str, pattern: &str) -> Option<u64> {
-        if let Some(start) = line.find(pattern) {
-            let value_start = start + pattern.len();
-            let remaining = &line[value_start..];
-            
-            // Find the end of the number (space or end of line)
-            let value_str = if let Some(end) = remaining.find(' ') {
-                &remaining[..end]
-            } else {
-                remaining
-            };
-            
-            value_str.parse::<u64>().ok()
-        } else {
-            None
+    #[tokio::test]
+    async fn test_trace_replays_to_the_same_events_off_the_node() {
+        let host = tempfile::tempdir().expect("tempdir");
+        let proc_root = host.path().join("proc");
+        let cgroup_root = host.path().join("cgroup");
+        let pod_dir = cgroup_root.join(format!("kubepods/burstable/pod{POD_UID}"));
+        fs::create_dir_all(&pod_dir).unwrap();
+        fs::create_dir_all(proc_root.join("42")).unwrap();
+        fs::write(proc_root.join("42/stat"), "42 (app) S 1 42 42 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 555 0 0").unwrap();
+        fs::write(proc_root.join("42/cgroup"), format!("0::/kubepods/burstable/pod{POD_UID}/{APP_ID}\n")).unwrap();
+        fs::write(pod_dir.join("cpu.stat"), "nr_periods 100\nnr_throttled 0\nthrottled_usec 0\nusage_usec 1000000\n").unwrap();
+
+        let traces = tempfile::tempdir().expect("tempdir");
+        let pods = traces.path().join("pods.json");
+        write_pods(&pods);
+
+        let live = EbpfParser::new(
+            Arc::new(PodResolver::from_pods_file(&pods, &proc_root, SETTINGS).unwrap()),
+            Arc::new(CgroupReader::new(&cgroup_root, &proc_root, Duration::ZERO)),
+            MonitoredPods::new(),
+            Some(Recorder::open(traces.path().join("trace"), None, 1 << 20, 1).unwrap()),
+        );
+        // The first read only seeds the baseline
+        let mut recorded = live.parse_line(text(THROTTLE)).await.unwrap();
+        fs::write(pod_dir.join("cpu.stat"), "nr_periods 200\nnr_throttled 40\nthrottled_usec 500000\nusage_usec 2500000\n").unwrap();
+        recorded.extend(live.parse_line(text(THROTTLE)).await.unwrap());
+        recorded.extend(live.parse_line(text(BIRTH)).await.unwrap());
+        live.recorder.as_ref().unwrap().flush().unwrap();
+
+        assert_eq!(recorded.len(), 2);
+        let EbpfEvent::CpuThrottle(throttle) = &recorded[0] else {
+            panic!("expected a throttle event, got {:?}", recorded[0]);
+        };
+        assert_eq!((throttle.nr_periods, throttle.nr_throttled), (100, 40));
+        assert_eq!(throttle.reported_cpu_usage, 0.5);
+
+        // Neither the process nor its cgroup outlive the recording
+        fs::remove_dir_all(host.path()).unwrap();
+
+        let path = fs::read_dir(traces.path().join("trace")).unwrap().next().unwrap().unwrap().path();
+        let mut trace = TraceReader::open(&path, 0.0).unwrap();
+        let replay = EbpfParser::new(
+            Arc::new(PodResolver::from_pods_file(&pods, &proc_root, SETTINGS).unwrap().with_recorded_pids(trace.resolutions())),
+            Arc::new(CgroupReader::new(&cgroup_root, &proc_root, Duration::from_secs(60)).with_recorded(trace.cgroups())),
+            MonitoredPods::new(),
+            None,
+        );
+        let mut replayed = Vec::new();
+        while let Some(line) = trace.next_line().await.unwrap() {
+            replayed.extend(replay.parse_line(line).await.unwrap());
         }
+
+        assert_eq!(undated(replayed), undated(recorded));
     }
-}
\ No newline at end of file
+}
@@ -1,12 +1,18 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use futures::{future, StreamExt};
 use kube::{Client, Api};
-use k8s_openapi::api::core::v1::Pod;
+use kube::runtime::reflector::{self, ObjectRef, Store};
+use kube::runtime::{watcher, WatchStreamExt};
+use k8s_openapi::api::core::v1::{Pod, ResourceRequirements};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use anyhow::Result;
-// Removed pod_uid_extractor import since module was deleted
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PodInfo {
     pub name: String,
     pub namespace: String,
@@ -14,181 +20,239 @@ pub struct PodInfo {
     pub cpu_request: f64,
     #[allow(dead_code)]
     pub cpu_limit: f64,
+    /// What the scheduler reserved for the whole pod, which pod cgroup usage compares with
+    #[serde(default)]
+    pub pod_cpu_request: f64,
 }
 
+/// Bounds of the PID cache. Entries are also checked against the process
+/// start time, so a reused PID never inherits the previous owner's pod.
+#[derive(Debug, Clone, Copy)]
+pub struct PidCacheSettings {
+    pub ttl: Duration,
+    pub max_entries: usize,
+}
+
+/// Maps processes on this node to their pods. Pods come from a watch limited
+/// to the node, so a lookup never reaches the API server.
 pub struct PodResolver {
-    #[allow(dead_code)]
-    client: Client,
-    cache: Arc<RwLock<HashMap<u32, PodInfo>>>,
+    pods: PodIndex,
+    pids: Mutex<PidCache>,
+    proc_root: PathBuf,
+    /// Answers taken from a recording, ahead of `/proc`
+    recorded: HashMap<u32, PodInfo>,
+    /// PIDs a pods file places in a container or pod, by container ID or pod UID
+    placed: HashMap<u32, String>,
 }
 
 impl PodResolver {
-    pub async fn new() -> Result<Self> {
-        let client = Client::try_default().await?;
+    /// Start watching the pods scheduled on `node`; every pod in the cluster when unknown
+    pub async fn new(client: Client, node: Option<&str>, proc_root: impl Into<PathBuf>, settings: PidCacheSettings) -> Result<Self> {
+        let pods = PodIndex::watch(client, node);
+        // Resolving before the first list completes would fail for every pod
+        if tokio::time::timeout(Duration::from_secs(30), pods.store.wait_until_ready()).await.is_err() {
+            tracing::warn!("⚠️ Pod list not loaded after 30s, resolving as pods arrive");
+        }
         Ok(Self {
-            client,
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            pods,
+            pids: Mutex::new(PidCache::new(settings)),
+            proc_root: proc_root.into(),
+            recorded: HashMap::new(),
+            placed: HashMap::new(),
         })
     }
-    
-    #[allow(dead_code)]
-    pub async fn resolve_uid_to_pod(&self, pod_uid: &str) -> Option<PodInfo> {
-        // Query all pods across all namespaces to find by UID
-        let pod_api: Api<Pod> = Api::all(self.client.clone());
-        let pods = pod_api.list(&Default::default()).await.ok()?;
-        
-        for pod in pods.items {
-            if let Some(uid) = pod.metadata.uid.as_ref() {
-                if uid == pod_uid {
-                    let pod_name = pod.metadata.name.as_ref().unwrap().clone();
-                    let namespace = pod.metadata.namespace.as_ref().unwrap().clone();
-                    
-                    // Extract CPU resources from first container
-                    let container_name = if let Some(containers) = pod.spec.as_ref().map(|s| &s.containers) {
-                        containers.first().map(|c| c.name.clone()).unwrap_or_else(|| "main".to_string())
-                    } else {
-                        "main".to_string()
-                    };
-
-                    let (cpu_request, cpu_limit) = self.extract_cpu_resources(&pod);
-                    
-                    return Some(PodInfo {
-                        name: pod_name,
-                        namespace,
-                        container_name,
-                        cpu_request,
-                        cpu_limit,
-                    });
-                }
-            }
+
+    /// Resolve against a fixed set of pods instead of a watch, for replays
+    /// away from the cluster. `path` holds a JSON array of pods or a list
+    /// such as `kubectl get pods -o json` prints. A list may add `pids`, a map
+    /// from PID to container ID or pod UID, for captures whose processes
+    /// this host has no `/proc` entries for.
+    pub fn from_pods_file(path: &Path, proc_root: impl Into<PathBuf>, settings: PidCacheSettings) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read pods from {}: {}", path.display(), e))?;
+        let mut value: serde_json::Value = serde_json::from_str(&content)?;
+        let placed: HashMap<u32, String> = match value.get_mut("pids") {
+            Some(pids) => serde_json::from_value(pids.take())
+                .map_err(|e| anyhow::anyhow!("{}: pids must map PIDs to container IDs or pod UIDs: {}", path.display(), e))?,
+            None => HashMap::new(),
+        };
+        if let Some(items) = value.get_mut("items") {
+            value = items.take();
         }
-        
-        None
+        let pods: Vec<Pod> = serde_json::from_value(value)?;
+        tracing::info!("📄 Resolving against {} pods and {} placed PIDs from {}", pods.len(), placed.len(), path.display());
+
+        Ok(Self {
+            pods: PodIndex::fixed(pods),
+            pids: Mutex::new(PidCache::new(settings)),
+            proc_root: proc_root.into(),
+            recorded: HashMap::new(),
+            placed,
+        })
+    }
+
+    /// The pod alone, as for pod-level cgroups; its first container stands in for the container
+    pub fn resolve_uid_to_pod(&self, pod_uid: &str) -> Option<PodInfo> {
+        self.pods.get(pod_uid).map(|pod| self.pod_info(&pod, None))
+    }
+
+    /// Answer for these PIDs as a recording did, since their processes are long gone
+    pub fn with_recorded_pids(mut self, recorded: HashMap<u32, PodInfo>) -> Self {
+        self.recorded = recorded;
+        self
     }
 
     pub async fn resolve_pid_to_pod(&self, pid: u32) -> Option<PodInfo> {
+        if let Some(pod_info) = self.recorded.get(&pid) {
+            return Some(pod_info.clone());
+        }
+        if let Some(id) = self.placed.get(&pid) {
+            let (pod, container_id) = self.placed_pod(id)?;
+            return Some(self.pod_info(&pod, container_id));
+        }
+
+        // A process that is gone cannot be told apart from a reused PID
+        let start_time = process_start_time(&self.proc_root, pid).await?;
+
         // First check cache
-        {
-            let cache = self.cache.read().await;
-            if let Some(pod_info) = cache.get(&pid) {
-                return Some(pod_info.clone());
+        let cached = self.pids.lock().await.get(pid, start_time, Instant::now());
+        if let Some((pod_uid, container_id)) = cached {
+            if let Some(pod) = self.pods.get(&pod_uid) {
+                return Some(self.pod_info(&pod, container_id.as_deref()));
             }
         }
 
-        // If not in cache, try to resolve from /proc and Kubernetes API
-        if let Ok(pod_info) = self.resolve_from_proc_and_k8s(pid).await {
-            // Cache the result
-            let mut cache = self.cache.write().await;
-            cache.insert(pid, pod_info.clone());
-            Some(pod_info)
-        } else {
-            None
+        // If not in cache, try to resolve from /proc and the pod watch
+        let (pod, container_id) = self.resolve_from_proc(pid).await.ok()?;
+        if let Some(pod_uid) = pod.metadata.uid.clone() {
+            self.pids.lock().await.insert(pid, start_time, pod_uid, container_id.clone(), Instant::now());
         }
+        Some(self.pod_info(&pod, container_id.as_deref()))
     }
 
-    async fn resolve_from_proc_and_k8s(&self, pid: u32) -> Result<PodInfo> {
+    /// UID of the pod the pods file places `pid` in, standing in for `/proc/<pid>/cgroup`
+    pub fn placed_pod_uid(&self, pid: u32) -> Option<String> {
+        let (pod, _) = self.placed_pod(self.placed.get(&pid)?)?;
+        pod.metadata.uid.clone()
+    }
+
+    /// The pod with UID `id`, or the one running the container with ID `id`,
+    /// given bare or as the kubelet reports it (`containerd://<id>`)
+    fn placed_pod<'a>(&self, id: &'a str) -> Option<(Arc<Pod>, Option<&'a str>)> {
+        let id = id.rsplit("://").next().unwrap_or(id);
+        match self.pods.get(id) {
+            Some(pod) => Some((pod, None)),
+            None => self.pods.find_by_container_id(id).map(|pod| (pod, Some(id))),
+        }
+    }
+
+    /// Drop PID entries past their TTL
+    pub async fn evict_expired(&self) {
+        self.pids.lock().await.evict_expired(Instant::now());
+    }
+
+    async fn resolve_from_proc(&self, pid: u32) -> Result<(Arc<Pod>, Option<String>)> {
         tracing::debug!("Attempting to resolve PID {pid} to pod");
-        
+
         // Try direct resolution first (in case PID is already a container process)
-        if let Some(pod_info) = self.resolve_container_pid_to_pod(pid).await {
+        if let Some(pod) = self.resolve_container_pid_to_pod(pid).await {
             tracing::debug!("Direct resolution successful for PID {pid}");
-            return Ok(pod_info);
+            return Ok(pod);
         }
-        
+
         // Use process lineage to find container runtime parent processes
         if let Some(container_pid) = self.find_container_runtime_ancestor(pid).await {
             tracing::debug!("Found container runtime ancestor PID {container_pid} for PID {pid}");
             // Try to find a pod associated with this container runtime process
-            if let Some(pod_info) = self.resolve_container_pid_to_pod(container_pid).await {
-                return Ok(pod_info);
+            if let Some(pod) = self.resolve_container_pid_to_pod(container_pid).await {
+                return Ok(pod);
             }
         }
-        
+
         Err(anyhow::anyhow!("Could not resolve PID {pid} to pod via process lineage"))
     }
-    
+
     async fn find_container_runtime_ancestor(&self, pid: u32) -> Option<u32> {
         // Walk up the process tree via /proc to find container runtime processes
         let mut current_pid = pid;
         for _ in 0..10 { // Limit depth to avoid infinite loops
             // Check if current process is a container runtime
-            if let Ok(comm) = tokio::fs::read_to_string(format!("/proc/{current_pid}/comm")).await {
+            if let Ok(comm) = tokio::fs::read_to_string(self.proc_root.join(format!("{current_pid}/comm"))).await {
                 let comm = comm.trim();
                 if matches!(comm, "containerd-shim" | "containerd-shim-runc-v2" | "runc" | "crun" | "conmon") {
                     return Some(current_pid);
                 }
             }
-            
+
             // Get parent PID and continue walking up
-            if let Ok(stat) = tokio::fs::read_to_string(format!("/proc/{current_pid}/stat")).await {
-                let fields: Vec<&str> = stat.split_whitespace().collect();
-                if fields.len() > 3 {
-                    if let Ok(ppid) = fields[3].parse::<u32>() {
-                        if ppid == 1 || ppid == current_pid {
-                            break; // Reached init or self-reference
-                        }
-                        current_pid = ppid;
-                        continue;
+            if let Ok(stat) = tokio::fs::read_to_string(self.proc_root.join(format!("{current_pid}/stat"))).await {
+                if let Some(ppid) = stat_field(&stat, 4).and_then(|f| f.parse::<u32>().ok()) {
+                    if ppid == 1 || ppid == current_pid {
+                        break; // Reached init or self-reference
                     }
+                    current_pid = ppid;
+                    continue;
                 }
             }
             break;
         }
         None
     }
-    
-    async fn resolve_container_pid_to_pod(&self, container_pid: u32) -> Option<PodInfo> {
+
+    /// The pod of a process, and the runtime's container ID when the process
+    /// sits in a container cgroup rather than the pod's own
+    async fn resolve_container_pid_to_pod(&self, container_pid: u32) -> Option<(Arc<Pod>, Option<String>)> {
         // Try to extract pod UID from cgroup path
-        if let Ok(cgroup) = tokio::fs::read_to_string(format!("/proc/{container_pid}/cgroup")).await {
+        if let Ok(cgroup) = tokio::fs::read_to_string(self.proc_root.join(format!("{container_pid}/cgroup"))).await {
             tracing::debug!("Cgroup content for PID {container_pid}: {}", cgroup.trim());
             // Look for Kubernetes pod UID in cgroup path
             // Pattern: /kubepods[.slice]/.../pod<UID>/...
             for line in cgroup.lines() {
                 if let Some(pod_uid) = self.extract_pod_uid_from_cgroup_line(line) {
                     tracing::debug!("Extracted pod UID: {pod_uid} from cgroup line");
-                    // Now resolve the UID to actual pod info via K8s API
-                    if let Some(pod_info) = self.resolve_uid_to_pod(&pod_uid).await {
-                        tracing::info!("Successfully resolved PID {container_pid} to pod {}/{}", 
-                                     pod_info.namespace, pod_info.name);
-                        return Some(pod_info);
+                    if let Some(pod) = self.pods.get(&pod_uid) {
+                        tracing::debug!("Resolved PID {container_pid} to pod {}/{}",
+                                     pod.metadata.namespace.as_deref().unwrap_or_default(),
+                                     pod.metadata.name.as_deref().unwrap_or_default());
+                        return Some((pod, container_id_from_cgroup_line(line)));
                     } else {
-                        tracing::warn!("Could not find pod with UID {pod_uid} in Kubernetes API");
+                        tracing::warn!("No pod with UID {pod_uid} is scheduled on this node");
                     }
                 }
             }
         } else {
             tracing::debug!("Could not read cgroup file for PID {container_pid}");
         }
-        
+
         // Fallback: try to find pod by matching container ID or process metadata
         // This is for cases where cgroup parsing doesn't work
-        if let Ok(environ) = tokio::fs::read_to_string(format!("/proc/{container_pid}/environ")).await {
+        if let Ok(environ) = tokio::fs::read_to_string(self.proc_root.join(format!("{container_pid}/environ"))).await {
             // Look for HOSTNAME which often contains the pod name
             for var in environ.split('\0') {
                 if let Some(hostname) = var.strip_prefix("HOSTNAME=") {
                     tracing::debug!("Found HOSTNAME={} for PID {container_pid}, attempting pod lookup", hostname);
-                    // Try to find pod by name across all namespaces
-                    if let Some(pod_info) = self.find_pod_by_name(hostname).await {
-                        tracing::info!("Resolved PID {container_pid} to pod {}/{} via HOSTNAME", 
-                                     pod_info.namespace, pod_info.name);
-                        return Some(pod_info);
+                    // Only this node's pods are watched, so names rarely collide
+                    if let Some(pod) = self.pods.find_by_name(hostname) {
+                        tracing::info!("Resolved PID {container_pid} to pod {}/{} via HOSTNAME",
+                                     pod.metadata.namespace.as_deref().unwrap_or_default(), hostname);
+                        return Some((pod, None));
                     }
                 }
             }
         }
-        
+
         // No synthetic names - return None if we can't resolve
         tracing::debug!("Could not resolve PID {container_pid} to any pod");
         None
     }
-    
+
     fn extract_pod_uid_from_cgroup_line(&self, line: &str) -> Option<String> {
         // Extract pod UID from cgroup path
         // Examples:
         // 0::/kubepods/besteffort/pod7c5c5d8e-5a1a-4b3c-8d1e-9f8e7c6d5a4b/...
         // 0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod7c5c5d8e_5a1a_4b3c_8d1e_9f8e7c6d5a4b.slice/...
         // 0::/../../pod2bac1a6a-95d3-4abc-990f-aefaf5c74812/container_id (minikube format)
-        
+
         // First try minikube format: /../../podUID/container_id
         if line.contains("/pod") && line.contains('-') {
             // Look for pattern like /pod{UUID}/
@@ -200,7 +264,7 @@ impl PodResolver {
                         let uid_chars: String = pod_part.chars()
                             .filter(|c| c.is_ascii_hexdigit() || *c == '-')
                             .collect();
-                        
+
                         // Validate UUID format (roughly 8-4-4-4-12 pattern)
                         if uid_chars.len() >= 32 && uid_chars.matches('-').count() >= 4 {
                             tracing::debug!("Extracted pod UID from minikube format: {}", uid_chars);
@@ -210,7 +274,7 @@ impl PodResolver {
                 }
             }
         }
-        
+
         // Fallback to systemd slice format
         if line.contains("kubepods") {
             // Try to extract pod UID using regex-like pattern matching
@@ -225,7 +289,7 @@ impl PodResolver {
                         break;
                     }
                 }
-                
+
                 if uid.len() >= 32 {
                     // Convert underscores to dashes for standard UID format
                     tracing::debug!("Extracted pod UID from systemd format: {}", uid.replace('_', "-"));
@@ -242,7 +306,7 @@ impl PodResolver {
                         break;
                     }
                 }
-                
+
                 if uid.len() >= 32 {
                     // Convert underscores to dashes for standard UID format
                     tracing::debug!("Extracted pod UID from legacy format: {}", uid.replace('_', "-"));
@@ -250,68 +314,57 @@ impl PodResolver {
                 }
             }
         }
-        
+
         tracing::debug!("Could not extract pod UID from cgroup line: {}", line);
         None
     }
-    
-    async fn find_pod_by_name(&self, name: &str) -> Option<PodInfo> {
-        // Query all pods to find by name
-        let pod_api: Api<Pod> = Api::all(self.client.clone());
-        if let Ok(pods) = pod_api.list(&Default::default()).await {
-            for pod in pods.items {
-                if let Some(pod_name) = pod.metadata.name.as_ref() {
-                    if pod_name == name {
-                        let namespace = pod.metadata.namespace.as_ref()?.clone();
-                        
-                        let container_name = if let Some(containers) = pod.spec.as_ref().map(|s| &s.containers) {
-                            containers.first().map(|c| c.name.clone()).unwrap_or_else(|| "main".to_string())
-                        } else {
-                            "main".to_string()
-                        };
-                        
-                        let (cpu_request, cpu_limit) = self.extract_cpu_resources(&pod);
-                        
-                        return Some(PodInfo {
-                            name: pod_name.clone(),
-                            namespace,
-                            container_name,
-                            cpu_request,
-                            cpu_limit,
-                        });
-                    }
-                }
-            }
+
+    /// The container with `container_id`, or the first one when the ID is
+    /// unknown or not in the pod's statuses yet
+    fn pod_info(&self, pod: &Pod, container_id: Option<&str>) -> PodInfo {
+        let container_name = container_id
+            .and_then(|id| container_name_for_id(pod, id))
+            .or_else(|| pod.spec.as_ref()?.containers.first().map(|c| c.name.clone()))
+            .unwrap_or_else(|| "main".to_string());
+
+        let (cpu_request, cpu_limit) = self.extract_cpu_resources(container_resources(pod, &container_name));
+
+        PodInfo {
+            name: pod.metadata.name.clone().unwrap_or_default(),
+            namespace: pod.metadata.namespace.clone().unwrap_or_default(),
+            container_name,
+            cpu_request,
+            cpu_limit,
+            pod_cpu_request: self.pod_cpu_request(pod),
         }
-        None
     }
 
-    #[allow(dead_code)]
-    fn extract_cpu_resources(&self, pod: &Pod) -> (f64, f64) {
-        let mut cpu_request = 0.0;
-        let mut cpu_limit = 0.0;
-
-        if let Some(spec) = &pod.spec {
-            for container in &spec.containers {
-                if let Some(resources) = &container.resources {
-                    if let Some(requests) = &resources.requests {
-                        if let Some(cpu) = requests.get("cpu") {
-                            cpu_request += self.parse_cpu_quantity(cpu.0.as_str()).unwrap_or(0.0);
-                        }
-                    }
-                    if let Some(limits) = &resources.limits {
-                        if let Some(cpu) = limits.get("cpu") {
-                            cpu_limit += self.parse_cpu_quantity(cpu.0.as_str()).unwrap_or(0.0);
-                        }
-                    }
-                }
-            }
-        }
+    /// The pod's effective request: its containers together, or the largest
+    /// init container when that asks for more, since those run one at a time
+    fn pod_cpu_request(&self, pod: &Pod) -> f64 {
+        let Some(spec) = pod.spec.as_ref() else {
+            return 0.0;
+        };
+        let request = |resources: Option<&ResourceRequirements>| self.extract_cpu_resources(resources).0;
+        let containers: f64 = spec.containers.iter().map(|c| request(c.resources.as_ref())).sum();
+        spec.init_containers.iter().flatten()
+            .map(|c| request(c.resources.as_ref()))
+            .fold(containers, f64::max)
+    }
 
-        (cpu_request, cpu_limit)
+    fn extract_cpu_resources(&self, resources: Option<&ResourceRequirements>) -> (f64, f64) {
+        let cpu = |quantities: Option<&BTreeMap<String, Quantity>>| {
+            quantities
+                .and_then(|q| q.get("cpu"))
+                .and_then(|cpu| self.parse_cpu_quantity(cpu.0.as_str()).ok())
+                .unwrap_or(0.0)
+        };
+        match resources {
+            Some(resources) => (cpu(resources.requests.as_ref()), cpu(resources.limits.as_ref())),
+            None => (0.0, 0.0),
+        }
     }
 
-    #[allow(dead_code)]
     fn parse_cpu_quantity(&self, quantity: &str) -> Result<f64> {
         if let Some(stripped) = quantity.strip_suffix('m') {
             // Millicores (e.g., "100m" = 0.1 cores)
@@ -322,4 +375,421 @@ impl PodResolver {
             Ok(quantity.parse::<f64>()?)
         }
     }
-}
\ No newline at end of file
+}
+
+/// Container ID from the last segment of a cgroup path: `cri-containerd-<id>.scope`,
+/// `crio-<id>.scope` and `docker-<id>.scope` under the systemd driver, a bare
+/// `<id>` under cgroupfs. CRI-O's `crio-conmon-<id>` is the monitor, not the container.
+fn container_id_from_cgroup_line(line: &str) -> Option<String> {
+    let path = line.splitn(3, ':').nth(2)?;
+    let segment = path.rsplit('/').find(|s| !s.is_empty())?;
+    let segment = segment.strip_suffix(".scope").unwrap_or(segment);
+    let id = ["cri-containerd-", "crio-", "docker-"]
+        .iter()
+        .find_map(|prefix| segment.strip_prefix(prefix))
+        .unwrap_or(segment);
+
+    (id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit())).then(|| id.to_string())
+}
+
+/// Name of the container whose status carries `container_id`, which the
+/// kubelet reports as `<runtime>://<id>`
+fn container_name_for_id(pod: &Pod, container_id: &str) -> Option<String> {
+    let status = pod.status.as_ref()?;
+    status.container_statuses.iter().flatten()
+        .chain(status.init_container_statuses.iter().flatten())
+        .chain(status.ephemeral_container_statuses.iter().flatten())
+        .find(|c| {
+            c.container_id.as_deref()
+                .and_then(|id| id.rsplit("://").next())
+                .is_some_and(|id| id == container_id)
+        })
+        .map(|c| c.name.clone())
+}
+
+fn container_resources<'a>(pod: &'a Pod, name: &str) -> Option<&'a ResourceRequirements> {
+    let spec = pod.spec.as_ref()?;
+    spec.containers.iter()
+        .chain(spec.init_containers.iter().flatten())
+        .find(|c| c.name == name)
+        .and_then(|c| c.resources.as_ref())
+        .or_else(|| {
+            spec.ephemeral_containers.iter().flatten()
+                .find(|c| c.name == name)
+                .and_then(|c| c.resources.as_ref())
+        })
+}
+
+/// Pods from the watch, with an index by UID alongside the reflector's own
+/// index by namespace and name
+#[derive(Clone)]
+struct PodIndex {
+    store: Store<Pod>,
+    by_uid: Arc<RwLock<HashMap<String, ObjectRef<Pod>>>>,
+}
+
+impl PodIndex {
+    fn watch(client: Client, node: Option<&str>) -> Self {
+        let api: Api<Pod> = Api::all(client);
+        let mut config = watcher::Config::default();
+        if let Some(node) = node {
+            config = config.fields(&format!("spec.nodeName={node}"));
+        }
+
+        let (store, writer) = reflector::store();
+        let index = Self { store, by_uid: Arc::default() };
+        let indexer = index.clone();
+        // The reflector updates the store before passing each event on
+        let stream = reflector::reflector(writer, watcher(api, config))
+            .default_backoff()
+            .for_each(move |event| {
+                match event {
+                    Ok(event) => indexer.apply(&event),
+                    Err(e) => tracing::warn!("⚠️ Pod watch failed: {}", e),
+                }
+                future::ready(())
+            });
+        tokio::spawn(stream);
+        index
+    }
+
+    fn fixed(pods: Vec<Pod>) -> Self {
+        let mut writer = reflector::store::Writer::default();
+        let index = Self { store: writer.as_reader(), by_uid: Arc::default() };
+        let event = watcher::Event::Restarted(pods);
+        writer.apply_watcher_event(&event);
+        index.apply(&event);
+        index
+    }
+
+    fn apply(&self, event: &watcher::Event<Pod>) {
+        let mut by_uid = self.by_uid.write().unwrap_or_else(|e| e.into_inner());
+        match event {
+            watcher::Event::Applied(pod) => {
+                if let Some(uid) = &pod.metadata.uid {
+                    by_uid.insert(uid.clone(), ObjectRef::from_obj(pod));
+                }
+            }
+            watcher::Event::Deleted(pod) => {
+                if let Some(uid) = &pod.metadata.uid {
+                    by_uid.remove(uid);
+                }
+            }
+            watcher::Event::Restarted(pods) => {
+                *by_uid = pods
+                    .iter()
+                    .filter_map(|pod| Some((pod.metadata.uid.clone()?, ObjectRef::from_obj(pod))))
+                    .collect();
+            }
+        }
+    }
+
+    fn get(&self, uid: &str) -> Option<Arc<Pod>> {
+        let object_ref = self.by_uid.read().unwrap_or_else(|e| e.into_inner()).get(uid).cloned()?;
+        self.store.get(&object_ref)
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<Arc<Pod>> {
+        self.store.state().into_iter().find(|pod| pod.metadata.name.as_deref() == Some(name))
+    }
+
+    fn find_by_container_id(&self, container_id: &str) -> Option<Arc<Pod>> {
+        self.store.state().into_iter().find(|pod| container_name_for_id(pod, container_id).is_some())
+    }
+}
+
+/// PID to pod UID and container ID, valid only for the process that started at `start_time`
+struct PidCache {
+    entries: HashMap<u32, CachedPid>,
+    settings: PidCacheSettings,
+}
+
+struct CachedPid {
+    start_time: u64,
+    pod_uid: String,
+    container_id: Option<String>,
+    cached_at: Instant,
+}
+
+impl PidCache {
+    fn new(settings: PidCacheSettings) -> Self {
+        Self { entries: HashMap::new(), settings }
+    }
+
+    fn get(&mut self, pid: u32, start_time: u64, now: Instant) -> Option<(String, Option<String>)> {
+        let entry = self.entries.get(&pid)?;
+        if entry.start_time == start_time && now.duration_since(entry.cached_at) < self.settings.ttl {
+            return Some((entry.pod_uid.clone(), entry.container_id.clone()));
+        }
+        // The PID was reused or the entry is stale
+        self.entries.remove(&pid);
+        None
+    }
+
+    fn insert(&mut self, pid: u32, start_time: u64, pod_uid: String, container_id: Option<String>, now: Instant) {
+        if self.entries.len() >= self.settings.max_entries && !self.entries.contains_key(&pid) {
+            self.evict_expired(now);
+        }
+        if self.entries.len() >= self.settings.max_entries && !self.entries.contains_key(&pid) {
+            let oldest = self.entries.iter().min_by_key(|(_, e)| e.cached_at).map(|(pid, _)| *pid);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(pid, CachedPid { start_time, pod_uid, container_id, cached_at: now });
+    }
+
+    fn evict_expired(&mut self, now: Instant) {
+        let ttl = self.settings.ttl;
+        self.entries.retain(|_, e| now.duration_since(e.cached_at) < ttl);
+    }
+}
+
+/// Start time of the process in clock ticks since boot, field 22 of `/proc/<pid>/stat`
+async fn process_start_time(proc_root: &Path, pid: u32) -> Option<u64> {
+    let stat = tokio::fs::read_to_string(proc_root.join(format!("{pid}/stat"))).await.ok()?;
+    stat_field(&stat, 22)?.parse().ok()
+}
+
+/// The 1-based `field` of a `/proc/<pid>/stat` line. The command name in
+/// field 2 may itself contain spaces and parentheses, so counting starts
+/// after its closing parenthesis.
+fn stat_field(stat: &str, field: usize) -> Option<&str> {
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    after_comm.split_whitespace().nth(field.checked_sub(3)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::{Container, ContainerStatus, PodSpec, PodStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+    const SETTINGS: PidCacheSettings = PidCacheSettings { ttl: Duration::from_secs(60), max_entries: 2 };
+
+    fn pod(name: &str, uid: &str) -> Pod {
+        Pod {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some("default".to_string()),
+                uid: Some(uid.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn index(pods: Vec<Pod>) -> (PodIndex, reflector::store::Writer<Pod>) {
+        let mut writer = reflector::store::Writer::default();
+        let index = PodIndex { store: writer.as_reader(), by_uid: Arc::default() };
+        let event = watcher::Event::Restarted(pods);
+        writer.apply_watcher_event(&event);
+        index.apply(&event);
+        (index, writer)
+    }
+
+    #[test]
+    fn test_stat_fields_after_command_name() {
+        let stat = "4242 (my (odd) comm) S 17 4242 4242 0 -1 4194560 1 0 0 0 0 0 0 0 20 0 1 0 987654 1000 10";
+        assert_eq!(stat_field(stat, 3), Some("S"));
+        assert_eq!(stat_field(stat, 4), Some("17"));
+        assert_eq!(stat_field(stat, 22), Some("987654"));
+        assert_eq!(stat_field("garbage", 4), None);
+    }
+
+    #[test]
+    fn test_pid_cache_rejects_reused_pid() {
+        let mut cache = PidCache::new(SETTINGS);
+        let now = Instant::now();
+        cache.insert(100, 5_000, "uid-a".to_string(), None, now);
+
+        assert_eq!(cache.get(100, 5_000, now), Some(("uid-a".to_string(), None)));
+        // Same PID, different process
+        assert_eq!(cache.get(100, 9_000, now), None);
+        assert_eq!(cache.get(100, 5_000, now), None);
+    }
+
+    #[test]
+    fn test_pid_cache_ttl_and_size() {
+        let mut cache = PidCache::new(SETTINGS);
+        let start = Instant::now();
+        cache.insert(1, 1, "uid-a".to_string(), None, start);
+        cache.insert(2, 2, "uid-b".to_string(), None, start + Duration::from_secs(1));
+        cache.insert(3, 3, "uid-c".to_string(), None, start + Duration::from_secs(2));
+
+        // The oldest entry made room
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(cache.get(1, 1, start + Duration::from_secs(2)), None);
+        assert!(cache.get(3, 3, start + Duration::from_secs(2)).is_some());
+
+        assert_eq!(cache.get(2, 2, start + Duration::from_secs(61)), None);
+        cache.evict_expired(start + Duration::from_secs(120));
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn test_index_follows_watch_events() {
+        let (index, mut writer) = index(vec![pod("web", "uid-web")]);
+        assert_eq!(index.get("uid-web").unwrap().metadata.name.as_deref(), Some("web"));
+        assert!(index.find_by_name("web").is_some());
+
+        let api = pod("api", "uid-api");
+        let event = watcher::Event::Applied(api.clone());
+        writer.apply_watcher_event(&event);
+        index.apply(&event);
+        assert!(index.get("uid-api").is_some());
+
+        let event = watcher::Event::Deleted(api);
+        writer.apply_watcher_event(&event);
+        index.apply(&event);
+        assert!(index.get("uid-api").is_none());
+        assert!(index.get("uid-unknown").is_none());
+    }
+
+    const APP_ID: &str = "4f2a9c1be0d3a7f6c5b4e3d2c1b0a9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d4c3b2";
+    const SIDECAR_ID: &str = "9e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b3a2f1e0d9c8b7a6f5e4d3c2b1a0f9e8d";
+    const POD_UID: &str = "2bac1a6a-95d3-4abc-990f-aefaf5c74812";
+
+    fn container(name: &str, cpu_request: &str, cpu_limit: &str) -> Container {
+        Container {
+            name: name.to_string(),
+            resources: Some(ResourceRequirements {
+                requests: Some([("cpu".to_string(), Quantity(cpu_request.to_string()))].into()),
+                limits: Some([("cpu".to_string(), Quantity(cpu_limit.to_string()))].into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn status(name: &str, id: &str) -> ContainerStatus {
+        ContainerStatus { name: name.to_string(), container_id: Some(id.to_string()), ..Default::default() }
+    }
+
+    fn meshed_pod() -> Pod {
+        Pod {
+            spec: Some(PodSpec {
+                containers: vec![container("app", "500m", "1"), container("istio-proxy", "100m", "200m")],
+                init_containers: Some(vec![container("migrate", "250m", "250m")]),
+                ..Default::default()
+            }),
+            status: Some(PodStatus {
+                container_statuses: Some(vec![
+                    status("app", &format!("containerd://{APP_ID}")),
+                    status("istio-proxy", &format!("containerd://{SIDECAR_ID}")),
+                ]),
+                init_container_statuses: Some(vec![status("migrate", "containerd://0123456789abcdef")]),
+                ..Default::default()
+            }),
+            ..pod("web", POD_UID)
+        }
+    }
+
+    fn resolver(pods: Vec<Pod>, proc_root: &Path) -> PodResolver {
+        PodResolver {
+            pods: PodIndex::fixed(pods),
+            pids: Mutex::new(PidCache::new(SETTINGS)),
+            proc_root: proc_root.to_path_buf(),
+            recorded: HashMap::new(),
+            placed: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_container_id_from_cgroup_formats() {
+        let containerd = format!("0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice/cri-containerd-{APP_ID}.scope");
+        let crio = format!("0::/kubepods.slice/kubepods-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice/crio-{APP_ID}.scope");
+        let docker = format!("4:cpu,cpuacct:/kubepods.slice/kubepods-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice/docker-{APP_ID}.scope");
+        let cgroupfs = format!("0::/kubepods/besteffort/pod{POD_UID}/{APP_ID}");
+        for line in [containerd, crio, docker, cgroupfs] {
+            assert_eq!(container_id_from_cgroup_line(&line).as_deref(), Some(APP_ID), "{line}");
+        }
+
+        let conmon = format!("0::/kubepods.slice/kubepods-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice/crio-conmon-{APP_ID}.scope");
+        assert_eq!(container_id_from_cgroup_line(&conmon), None);
+        assert_eq!(container_id_from_cgroup_line(&format!("0::/kubepods/besteffort/pod{POD_UID}")), None);
+    }
+
+    #[test]
+    fn test_pod_info_uses_the_matching_container() {
+        let resolver = resolver(vec![], Path::new("/nonexistent"));
+        let pod = meshed_pod();
+
+        let sidecar = resolver.pod_info(&pod, Some(SIDECAR_ID));
+        assert_eq!(sidecar.container_name, "istio-proxy");
+        assert_eq!((sidecar.cpu_request, sidecar.cpu_limit), (0.1, 0.2));
+
+        let init = resolver.pod_info(&pod, Some("0123456789abcdef"));
+        assert_eq!(init.container_name, "migrate");
+        assert_eq!(init.cpu_request, 0.25);
+
+        // Unknown IDs, e.g. before the kubelet reports statuses, fall back to the first container
+        let unknown = resolver.pod_info(&pod, Some("ffff"));
+        assert_eq!(unknown.container_name, "app");
+        assert_eq!((unknown.cpu_request, unknown.cpu_limit), (0.5, 1.0));
+
+        // The whole pod's request does not depend on the container
+        assert_eq!(sidecar.pod_cpu_request, 0.6);
+        assert_eq!(unknown.pod_cpu_request, 0.6);
+    }
+
+    #[tokio::test]
+    async fn test_resolves_pid_through_container_cgroup() {
+        let proc_root = tempfile::tempdir().expect("tempdir");
+        let dir = proc_root.path().join("4242");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("stat"), "4242 (envoy) S 1 4242 4242 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 555 0 0").unwrap();
+        std::fs::write(dir.join("cgroup"), format!(
+            "0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod2bac1a6a_95d3_4abc_990f_aefaf5c74812.slice/cri-containerd-{SIDECAR_ID}.scope\n"
+        )).unwrap();
+
+        let resolver = resolver(vec![meshed_pod()], proc_root.path());
+        let info = resolver.resolve_pid_to_pod(4242).await.expect("resolved");
+        assert_eq!((info.name.as_str(), info.container_name.as_str()), ("web", "istio-proxy"));
+
+        // Served from the cache while the process lives
+        std::fs::remove_file(dir.join("cgroup")).unwrap();
+        assert_eq!(resolver.resolve_pid_to_pod(4242).await.unwrap().container_name, "istio-proxy");
+
+        // A new process behind the same PID is resolved afresh
+        std::fs::write(dir.join("stat"), "4242 (sh) S 1 4242 4242 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 999 0 0").unwrap();
+        assert!(resolver.resolve_pid_to_pod(4242).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pods_file_accepts_kubectl_lists() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("pods.json");
+        let list = serde_json::json!({ "apiVersion": "v1", "kind": "List", "items": [meshed_pod()] });
+        std::fs::write(&path, list.to_string()).unwrap();
+
+        let resolver = PodResolver::from_pods_file(&path, dir.path(), SETTINGS).expect("loaded");
+        assert_eq!(resolver.resolve_uid_to_pod(POD_UID).unwrap().name, "web");
+
+        std::fs::write(&path, serde_json::to_string(&vec![pod("api", "uid-api")]).unwrap()).unwrap();
+        let resolver = PodResolver::from_pods_file(&path, dir.path(), SETTINGS).expect("loaded");
+        assert!(resolver.resolve_uid_to_pod("uid-api").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pods_file_places_pids_without_proc() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("pods.json");
+        let list = serde_json::json!({
+            "items": [meshed_pod()],
+            "pids": { "42": format!("containerd://{SIDECAR_ID}"), "43": APP_ID, "44": POD_UID, "45": "unknown" },
+        });
+        std::fs::write(&path, list.to_string()).unwrap();
+
+        // No /proc at all
+        let resolver = PodResolver::from_pods_file(&path, dir.path().join("proc"), SETTINGS).expect("loaded");
+        assert_eq!(resolver.resolve_pid_to_pod(42).await.unwrap().container_name, "istio-proxy");
+        assert_eq!(resolver.resolve_pid_to_pod(43).await.unwrap().container_name, "app");
+        assert_eq!(resolver.resolve_pid_to_pod(44).await.unwrap().name, "web");
+        assert!(resolver.resolve_pid_to_pod(45).await.is_none());
+        assert!(resolver.resolve_pid_to_pod(46).await.is_none());
+
+        assert_eq!(resolver.placed_pod_uid(42).as_deref(), Some(POD_UID));
+        assert_eq!(resolver.placed_pod_uid(46), None);
+    }
+}
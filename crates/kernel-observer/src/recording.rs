@@ -0,0 +1,445 @@
+//! Trace files of what the observer saw, for reproducing a whisper later.
+//!
+//! A trace is JSON lines: a header, then every bpftrace line, every pod
+//! resolution, every pod cgroup lookup and `cpu.stat` sample, and every event
+//! sent to the operator, each stamped with milliseconds on a monotonic clock
+//! started with the recorder. Traces rotate at a size limit into
+//! `trace-<seq>.jsonl` files, each starting with its own header, and the
+//! oldest are removed past a file count. Records are buffered and reach the
+//! disk on [`Recorder::flush`], which the observer calls on a timer and when
+//! its source ends.
+//!
+//! [`TraceReader`] plays the lines back into [`crate::parser::EbpfParser`] as
+//! an event source, with the recorded resolutions and cgroup reads standing in
+//! for `/proc`, the cgroup filesystem and the API server.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::Result;
+use kernel_gossip_protocol::EbpfEvent;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::cgroup::{CpuThrottleSample, PodCgroup, RecordedCgroups};
+use crate::pod_resolver::PodInfo;
+use crate::source::{pace, EventSource, SourceLine};
+
+/// Bumped when a record changes incompatibly
+pub const TRACE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceRecord {
+    Header {
+        version: u32,
+        node: Option<String>,
+        /// Wall-clock time at offset 0, RFC 3339
+        started_at: String,
+    },
+    Line {
+        at_ms: u64,
+        line: String,
+    },
+    /// What the resolver answered for a PID; `pod` is empty when it failed
+    Resolved {
+        at_ms: u64,
+        pid: u32,
+        pod: Option<PodInfo>,
+    },
+    /// The pod cgroup a PID was found in; `cgroup` is empty outside pods
+    /// or once the process was gone
+    Cgroup {
+        at_ms: u64,
+        pid: u32,
+        cgroup: Option<PodCgroup>,
+    },
+    /// A throttling sample of a pod cgroup; `sample` is empty while the
+    /// window was still open or when `cpu.stat` could not be read
+    CpuSample {
+        at_ms: u64,
+        dir: PathBuf,
+        sample: Option<CpuThrottleSample>,
+    },
+    Event {
+        at_ms: u64,
+        event: EbpfEvent,
+    },
+}
+
+/// Writes trace records; clones share the same file
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<Mutex<RecorderFile>>,
+    started: Instant,
+}
+
+struct RecorderFile {
+    dir: PathBuf,
+    node: Option<String>,
+    started_at: chrono::DateTime<chrono::Utc>,
+    max_bytes: u64,
+    max_files: usize,
+    seq: u64,
+    writer: BufWriter<File>,
+    written: u64,
+}
+
+impl Recorder {
+    /// Start a new trace file under `dir`, after any left by earlier runs
+    pub fn open(dir: impl AsRef<Path>, node: Option<String>, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let seq = trace_files(&dir)?.last().map_or(0, |(seq, _)| seq + 1);
+        let started_at = chrono::Utc::now();
+
+        let mut file = RecorderFile {
+            writer: BufWriter::new(File::create(trace_path(&dir, seq))?),
+            dir,
+            node,
+            started_at,
+            // A file always has room for its header, or rotating would never end
+            max_bytes: max_bytes.max(1),
+            max_files: max_files.max(1),
+            seq,
+            written: 0,
+        };
+        file.write_header()?;
+        file.prune()?;
+        info!("🎙️ Recording kernel output to {}", trace_path(&file.dir, seq).display());
+
+        Ok(Self { inner: Arc::new(Mutex::new(file)), started: Instant::now() })
+    }
+
+    pub fn line(&self, line: &str) {
+        self.write(TraceRecord::Line { at_ms: self.at_ms(), line: line.to_string() });
+    }
+
+    pub fn resolved(&self, pid: u32, pod: Option<&PodInfo>) {
+        self.write(TraceRecord::Resolved { at_ms: self.at_ms(), pid, pod: pod.cloned() });
+    }
+
+    pub fn cgroup(&self, pid: u32, cgroup: Option<&PodCgroup>) {
+        self.write(TraceRecord::Cgroup { at_ms: self.at_ms(), pid, cgroup: cgroup.cloned() });
+    }
+
+    pub fn cpu_sample(&self, dir: &Path, sample: Option<&CpuThrottleSample>) {
+        self.write(TraceRecord::CpuSample { at_ms: self.at_ms(), dir: dir.to_path_buf(), sample: sample.copied() });
+    }
+
+    pub fn event(&self, event: &EbpfEvent) {
+        self.write(TraceRecord::Event { at_ms: self.at_ms(), event: event.clone() });
+    }
+
+    /// Write out what is buffered
+    pub fn flush(&self) -> io::Result<()> {
+        self.file().writer.flush()
+    }
+
+    /// Flush every `period`, so a trace is on disk soon after something went
+    /// wrong without a write per line on the parsing path
+    pub async fn flush_every(self, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.flush() {
+                warn!("⚠️ Failed to flush trace: {}", e);
+            }
+        }
+    }
+
+    fn at_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// A lost record is logged rather than failing the pipeline it observes
+    fn write(&self, record: TraceRecord) {
+        if let Err(e) = self.file().append(&record) {
+            warn!("⚠️ Failed to write trace record: {}", e);
+        }
+    }
+
+    fn file(&self) -> MutexGuard<'_, RecorderFile> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl RecorderFile {
+    fn append(&mut self, record: &TraceRecord) -> io::Result<()> {
+        if self.written >= self.max_bytes {
+            self.rotate()?;
+        }
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.seq += 1;
+        self.writer = BufWriter::new(File::create(trace_path(&self.dir, self.seq))?);
+        self.written = 0;
+        self.write_header()?;
+        self.prune()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let header = TraceRecord::Header {
+            version: TRACE_VERSION,
+            node: self.node.clone(),
+            started_at: self.started_at.to_rfc3339(),
+        };
+        self.append(&header)
+    }
+
+    /// Remove the oldest traces beyond `max_files`
+    fn prune(&self) -> io::Result<()> {
+        let files = trace_files(&self.dir)?;
+        let excess = files.len().saturating_sub(self.max_files);
+        for (_, path) in files.into_iter().take(excess) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn trace_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("trace-{seq:06}.jsonl"))
+}
+
+/// Trace files in `dir`, oldest first
+fn trace_files(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let seq = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("trace-")?.strip_suffix(".jsonl")?.parse::<u64>().ok());
+        if let Some(seq) = seq {
+            files.push((seq, path));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// A trace file read back, as an [`EventSource`] of its lines
+pub struct TraceReader {
+    records: std::vec::IntoIter<TraceRecord>,
+    resolutions: HashMap<u32, PodInfo>,
+    cgroups: RecordedCgroups,
+    started: Instant,
+    speed: f64,
+}
+
+impl TraceReader {
+    /// Whether `path` starts with a trace header
+    pub fn is_trace(path: &Path) -> bool {
+        let Ok(file) = File::open(path) else {
+            return false;
+        };
+        let mut first = String::new();
+        BufReader::new(file).read_line(&mut first).is_ok()
+            && matches!(serde_json::from_str(&first), Ok(TraceRecord::Header { .. }))
+    }
+
+    /// `speed` scales the recorded timing like a replay file's; 0 does not wait
+    pub fn open(path: &Path, speed: f64) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open trace {}: {}", path.display(), e))?;
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: TraceRecord = serde_json::from_str(&line)
+                .map_err(|e| anyhow::anyhow!("{}:{}: not a trace record: {}", path.display(), number + 1, e))?;
+            if let TraceRecord::Header { version, .. } = &record {
+                if *version > TRACE_VERSION {
+                    anyhow::bail!("{} is trace version {}, this observer reads up to {}", path.display(), version, TRACE_VERSION);
+                }
+            }
+            records.push(record);
+        }
+        Ok(Self::from_records(records, speed))
+    }
+
+    pub fn from_records(records: Vec<TraceRecord>, speed: f64) -> Self {
+        // The first answer per PID; a trace short enough to replay rarely sees a PID reused
+        let mut resolutions = HashMap::new();
+        let mut cgroups = RecordedCgroups::default();
+        for record in &records {
+            match record {
+                TraceRecord::Resolved { pid, pod: Some(pod), .. } => {
+                    resolutions.entry(*pid).or_insert_with(|| pod.clone());
+                }
+                TraceRecord::Cgroup { pid, cgroup: Some(cgroup), .. } => {
+                    cgroups.pods.entry(*pid).or_insert_with(|| cgroup.clone());
+                }
+                // Samples depend on the windows before them, so all of them are kept in order
+                TraceRecord::CpuSample { dir, sample, .. } => {
+                    cgroups.samples.entry(dir.clone()).or_insert_with(VecDeque::new).push_back(*sample);
+                }
+                _ => {}
+            }
+        }
+        Self { records: records.into_iter(), resolutions, cgroups, started: Instant::now(), speed }
+    }
+
+    /// PIDs the recording resolved, to answer for `/proc` during the replay
+    pub fn resolutions(&self) -> HashMap<u32, PodInfo> {
+        self.resolutions.clone()
+    }
+
+    /// Pod cgroups and `cpu.stat` samples the recording read, to answer for
+    /// `/proc` and the cgroup filesystem during the replay
+    pub fn cgroups(&self) -> RecordedCgroups {
+        self.cgroups.clone()
+    }
+}
+
+impl EventSource for TraceReader {
+    async fn next_line(&mut self) -> Result<Option<SourceLine>> {
+        for record in self.records.by_ref() {
+            if let TraceRecord::Line { at_ms, line } = record {
+                let offset = Duration::from_millis(at_ms);
+                pace(self.started, offset, self.speed).await;
+                return Ok(Some(SourceLine { offset, line, records: None }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroup::CgroupVersion;
+    use kernel_gossip_protocol::OomKillPayload;
+
+    fn pod() -> PodInfo {
+        PodInfo {
+            name: "web".to_string(),
+            namespace: "shop".to_string(),
+            container_name: "app".to_string(),
+            cpu_request: 0.5,
+            cpu_limit: 1.0,
+            pod_cpu_request: 0.5,
+        }
+    }
+
+    fn oom_kill() -> EbpfEvent {
+        EbpfEvent::OomKill(OomKillPayload {
+            pod_name: "web".to_string(),
+            namespace: "shop".to_string(),
+            pod_uid: "uid-web".to_string(),
+            oom_kills: 1,
+            anon_bytes: 1,
+            file_bytes: 0,
+            kernel_bytes: 0,
+            sock_bytes: 0,
+            shmem_bytes: 0,
+            usage_bytes: 1,
+            limit_bytes: Some(1),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_recording_round_trip() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let recorder = Recorder::open(dir.path(), Some("node-a".to_string()), 1 << 20, 3).unwrap();
+        recorder.line("CPU_THROTTLE_EVENT pid=42 comm=app throttle_ns=1 timestamp_ms=9");
+        recorder.resolved(42, Some(&pod()));
+        recorder.resolved(43, None);
+        let cgroup = PodCgroup { version: CgroupVersion::V2, dir: PathBuf::from("/sys/fs/cgroup/kubepods/podabc") };
+        let sample = CpuThrottleSample {
+            nr_periods: 10,
+            nr_throttled: 4,
+            throttled_usec: 2_000,
+            usage_usec: Some(900_000),
+            elapsed: Duration::from_secs(1),
+        };
+        recorder.cgroup(42, Some(&cgroup));
+        recorder.cgroup(43, None);
+        recorder.cpu_sample(&cgroup.dir, None);
+        recorder.cpu_sample(&cgroup.dir, Some(&sample));
+        recorder.event(&oom_kill());
+        recorder.line("@runq_wait_ns[42]: 1200");
+
+        // Records wait in the buffer until flushed
+        let path = trace_path(dir.path(), 0);
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+        recorder.flush().unwrap();
+
+        assert!(TraceReader::is_trace(&path));
+        let mut reader = TraceReader::open(&path, 0.0).unwrap();
+        assert_eq!(reader.resolutions().len(), 1);
+        assert_eq!(reader.resolutions()[&42].container_name, "app");
+        let cgroups = reader.cgroups();
+        assert_eq!(cgroups.pods, HashMap::from([(42, cgroup.clone())]));
+        assert_eq!(cgroups.samples[&cgroup.dir], VecDeque::from([None, Some(sample)]));
+
+        let first = reader.next_line().await.unwrap().unwrap();
+        assert!(first.line.starts_with("CPU_THROTTLE_EVENT"));
+        assert_eq!(reader.next_line().await.unwrap().unwrap().line, "@runq_wait_ns[42]: 1200");
+        assert!(reader.next_line().await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let recorder = Recorder::open(dir.path(), None, 200, 2).unwrap();
+        for i in 0..20 {
+            recorder.line(&format!("CPU_THROTTLE_EVENT pid={i} comm=app throttle_ns=1 timestamp_ms=9"));
+        }
+        recorder.flush().unwrap();
+
+        let files = trace_files(dir.path()).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].0 > 0);
+        // Every file can be read on its own
+        for (_, path) in files {
+            assert!(TraceReader::is_trace(&path));
+        }
+
+        // A restart continues the numbering
+        drop(recorder);
+        Recorder::open(dir.path(), None, 200, 2).unwrap();
+        let newest = trace_files(dir.path()).unwrap().last().unwrap().0;
+        assert!(newest > 1);
+    }
+
+    #[test]
+    fn test_zero_max_bytes_rotates_per_record() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let recorder = Recorder::open(dir.path(), None, 0, 3).unwrap();
+        for i in 0..5 {
+            recorder.line(&format!("CPU_THROTTLE_EVENT pid={i} comm=app throttle_ns=1 timestamp_ms=9"));
+        }
+        recorder.flush().unwrap();
+
+        let files = trace_files(dir.path()).unwrap();
+        assert_eq!(files.len(), 3);
+        for (_, path) in files {
+            assert!(TraceReader::is_trace(&path));
+        }
+    }
+
+    #[test]
+    fn test_plain_capture_is_not_a_trace() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("capture.txt");
+        fs::write(&path, "0\tKERNEL_MONITOR_STARTED\n").unwrap();
+        assert!(!TraceReader::is_trace(&path));
+    }
+}
@@ -0,0 +1,649 @@
+//! Typed records of what the bpftrace scripts print, and the registry that
+//! decodes their output into them.
+//!
+//! Every record type declares its name, the marker it is printed with in text
+//! mode and its fields. The same schema decodes all the shapes bpftrace output
+//! takes:
+//!
+//! - text `printf` lines, `CPU_THROTTLE_EVENT pid=42 comm=my app ...`, where a
+//!   value runs up to the next declared field so `comm` may hold spaces;
+//! - `-f json` output, where those lines arrive as `{"type": "printf", ...}`,
+//!   and tuples printed with `print(("cpu_throttle", pid, comm, ...))` arrive
+//!   as typed arrays with fields in declared order;
+//! - maps, `@runq_wait_ns[42]: 1200` in text and `{"type": "map", ...}` in JSON.
+//!
+//! A new detector adds a record type and a [`Registry`] entry; the parser
+//! matches on [`KernelRecord`] instead of searching lines.
+
+use std::sync::LazyLock;
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// What the parser can make of a script's output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Signal {
+    CpuThrottle,
+    RunQueue,
+    ContainerBirth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Int,
+    Str,
+}
+
+/// A record type as printed by a script
+pub trait Record: DeserializeOwned + Into<KernelRecord> {
+    /// First element of a JSON tuple
+    const NAME: &'static str;
+    /// First word of a text line
+    const MARKER: &'static str;
+    /// In the order a tuple holds them; text lines may omit optional ones
+    const FIELDS: &'static [(&'static str, FieldKind)];
+    const SIGNAL: Option<Signal>;
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MonitorStarted {}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MonitorEnded {}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CpuThrottle {
+    pub pid: u32,
+    pub comm: String,
+    pub throttle_ns: u64,
+    pub timestamp_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GoldenSyscall {
+    pub syscall: String,
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub comm: String,
+    pub duration_ns: Option<u64>,
+    pub timestamp_ms: Option<u64>,
+}
+
+/// A container runtime shim forked
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerFork {
+    pub pid: u32,
+    pub ppid: u32,
+    pub parent_comm: String,
+    pub timestamp_ms: Option<u64>,
+}
+
+/// runc or crun started
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerProcessStart {
+    pub pid: u32,
+    pub ppid: u32,
+    pub comm: String,
+    pub timestamp_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerSyscalls {
+    pub pid: u32,
+    pub total: u64,
+    pub timestamp_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerNamespaceOp {
+    pub pid: u32,
+    #[serde(rename = "type")]
+    pub op: String,
+    pub timestamp_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerMountOp {
+    pub pid: u32,
+    #[serde(rename = "type")]
+    pub op: String,
+    pub timestamp_ms: Option<u64>,
+}
+
+/// A runtime process opened a cgroupfs file for writing
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerCgroupWrite {
+    pub pid: u32,
+    pub path: String,
+    pub timestamp_ms: Option<u64>,
+}
+
+/// The first exec outside the runtime: the container's own process
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerMain {
+    pub pid: u32,
+    pub ppid: u32,
+    pub comm: String,
+    pub timestamp_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerMainSyscall {
+    pub pid: u32,
+    pub count: u64,
+    pub timestamp_ms: Option<u64>,
+}
+
+/// The runtime process exited, or (from `monitoring.bt`) the first exec after
+/// a runtime fork
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ContainerBirthComplete {
+    pub pid: u32,
+    pub ppid: Option<u32>,
+    pub comm: String,
+    pub total_syscalls: Option<u64>,
+    pub namespace_ops: Option<u64>,
+    pub mount_ops: Option<u64>,
+    pub duration_ns: Option<u64>,
+    pub total_duration_ns: Option<u64>,
+    pub timestamp_ms: Option<u64>,
+}
+
+/// One task's run queue figures over the last interval; a map line carries
+/// only one of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunQueue {
+    pub pid: u32,
+    pub wait_ns: u64,
+    pub wakeups: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KernelRecord {
+    MonitorStarted(MonitorStarted),
+    MonitorEnded(MonitorEnded),
+    CpuThrottle(CpuThrottle),
+    GoldenSyscall(GoldenSyscall),
+    ContainerFork(ContainerFork),
+    ContainerProcessStart(ContainerProcessStart),
+    ContainerSyscalls(ContainerSyscalls),
+    ContainerNamespaceOp(ContainerNamespaceOp),
+    ContainerMountOp(ContainerMountOp),
+    ContainerCgroupWrite(ContainerCgroupWrite),
+    ContainerMain(ContainerMain),
+    ContainerMainSyscall(ContainerMainSyscall),
+    ContainerBirthComplete(ContainerBirthComplete),
+    RunQueue(RunQueue),
+    /// bpftrace's perf buffer overflowed (`-f json` only; text mode says so on stderr)
+    LostEvents(u64),
+}
+
+use FieldKind::{Int, Str};
+
+impl Record for MonitorStarted {
+    const NAME: &'static str = "monitor_started";
+    const MARKER: &'static str = "KERNEL_MONITOR_STARTED";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[];
+    const SIGNAL: Option<Signal> = None;
+}
+
+impl Record for MonitorEnded {
+    const NAME: &'static str = "monitor_ended";
+    const MARKER: &'static str = "MONITOR_ENDED";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[];
+    const SIGNAL: Option<Signal> = None;
+}
+
+impl Record for CpuThrottle {
+    const NAME: &'static str = "cpu_throttle";
+    const MARKER: &'static str = "CPU_THROTTLE_EVENT";
+    const FIELDS: &'static [(&'static str, FieldKind)] =
+        &[("pid", Int), ("comm", Str), ("throttle_ns", Int), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::CpuThrottle);
+}
+
+impl Record for GoldenSyscall {
+    const NAME: &'static str = "golden_syscall";
+    const MARKER: &'static str = "GOLDEN_SYSCALL";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[
+        ("syscall", Str),
+        ("pid", Int),
+        ("parent_pid", Int),
+        ("comm", Str),
+        ("duration_ns", Int),
+        ("timestamp_ms", Int),
+    ];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerFork {
+    const NAME: &'static str = "container_fork";
+    const MARKER: &'static str = "CONTAINER_FORK";
+    const FIELDS: &'static [(&'static str, FieldKind)] =
+        &[("pid", Int), ("ppid", Int), ("parent_comm", Str), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerProcessStart {
+    const NAME: &'static str = "container_process_start";
+    const MARKER: &'static str = "CONTAINER_PROCESS_START";
+    const FIELDS: &'static [(&'static str, FieldKind)] =
+        &[("pid", Int), ("ppid", Int), ("comm", Str), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerSyscalls {
+    const NAME: &'static str = "container_syscalls";
+    const MARKER: &'static str = "CONTAINER_SYSCALLS";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[("pid", Int), ("total", Int), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerNamespaceOp {
+    const NAME: &'static str = "container_namespace_op";
+    const MARKER: &'static str = "CONTAINER_NAMESPACE_OP";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[("pid", Int), ("type", Str), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerMountOp {
+    const NAME: &'static str = "container_mount_op";
+    const MARKER: &'static str = "CONTAINER_MOUNT_OP";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[("pid", Int), ("type", Str), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerCgroupWrite {
+    const NAME: &'static str = "container_cgroup_write";
+    const MARKER: &'static str = "CONTAINER_CGROUP_WRITE";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[("pid", Int), ("path", Str), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerMain {
+    const NAME: &'static str = "container_main";
+    const MARKER: &'static str = "CONTAINER_MAIN";
+    const FIELDS: &'static [(&'static str, FieldKind)] =
+        &[("pid", Int), ("ppid", Int), ("comm", Str), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerMainSyscall {
+    const NAME: &'static str = "container_main_syscall";
+    const MARKER: &'static str = "CONTAINER_MAIN_SYSCALL";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[("pid", Int), ("count", Int), ("timestamp_ms", Int)];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+impl Record for ContainerBirthComplete {
+    const NAME: &'static str = "container_birth_complete";
+    const MARKER: &'static str = "CONTAINER_BIRTH_COMPLETE";
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[
+        ("pid", Int),
+        ("ppid", Int),
+        ("comm", Str),
+        ("total_syscalls", Int),
+        ("namespace_ops", Int),
+        ("mount_ops", Int),
+        ("duration_ns", Int),
+        ("total_duration_ns", Int),
+        ("timestamp_ms", Int),
+    ];
+    const SIGNAL: Option<Signal> = Some(Signal::ContainerBirth);
+}
+
+macro_rules! into_kernel_record {
+    ($($record:ident),* $(,)?) => {
+        $(impl From<$record> for KernelRecord {
+            fn from(record: $record) -> Self {
+                KernelRecord::$record(record)
+            }
+        })*
+    };
+}
+
+into_kernel_record!(
+    MonitorStarted,
+    MonitorEnded,
+    CpuThrottle,
+    GoldenSyscall,
+    ContainerFork,
+    ContainerProcessStart,
+    ContainerSyscalls,
+    ContainerNamespaceOp,
+    ContainerMountOp,
+    ContainerCgroupWrite,
+    ContainerMain,
+    ContainerMainSyscall,
+    ContainerBirthComplete,
+);
+
+impl KernelRecord {
+    pub fn signal(&self) -> Option<Signal> {
+        match self {
+            KernelRecord::MonitorStarted(_) => MonitorStarted::SIGNAL,
+            KernelRecord::MonitorEnded(_) => MonitorEnded::SIGNAL,
+            KernelRecord::CpuThrottle(_) => CpuThrottle::SIGNAL,
+            KernelRecord::GoldenSyscall(_) => GoldenSyscall::SIGNAL,
+            KernelRecord::ContainerFork(_) => ContainerFork::SIGNAL,
+            KernelRecord::ContainerProcessStart(_) => ContainerProcessStart::SIGNAL,
+            KernelRecord::ContainerSyscalls(_) => ContainerSyscalls::SIGNAL,
+            KernelRecord::ContainerNamespaceOp(_) => ContainerNamespaceOp::SIGNAL,
+            KernelRecord::ContainerMountOp(_) => ContainerMountOp::SIGNAL,
+            KernelRecord::ContainerCgroupWrite(_) => ContainerCgroupWrite::SIGNAL,
+            KernelRecord::ContainerMain(_) => ContainerMain::SIGNAL,
+            KernelRecord::ContainerMainSyscall(_) => ContainerMainSyscall::SIGNAL,
+            KernelRecord::ContainerBirthComplete(_) => ContainerBirthComplete::SIGNAL,
+            KernelRecord::RunQueue(_) => Some(Signal::RunQueue),
+            KernelRecord::LostEvents(_) => None,
+        }
+    }
+}
+
+/// One record type, with the decoder for its fields
+pub struct Schema {
+    pub name: &'static str,
+    pub marker: &'static str,
+    pub fields: &'static [(&'static str, FieldKind)],
+    pub signal: Option<Signal>,
+    decode: fn(Map<String, Value>) -> Result<KernelRecord>,
+}
+
+fn schema<R: Record>() -> Schema {
+    Schema {
+        name: R::NAME,
+        marker: R::MARKER,
+        fields: R::FIELDS,
+        signal: R::SIGNAL,
+        decode: |fields| Ok(serde_json::from_value::<R>(Value::Object(fields))?.into()),
+    }
+}
+
+/// A bpftrace map whose entries are records keyed by PID
+pub struct MapSchema {
+    pub name: &'static str,
+    pub signal: Signal,
+    decode: fn(u32, u64) -> KernelRecord,
+}
+
+/// Every record and map the observer understands
+pub struct Registry {
+    records: Vec<Schema>,
+    maps: Vec<MapSchema>,
+}
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+impl Registry {
+    fn new() -> Self {
+        Self {
+            records: vec![
+                schema::<MonitorStarted>(),
+                schema::<MonitorEnded>(),
+                schema::<CpuThrottle>(),
+                schema::<GoldenSyscall>(),
+                schema::<ContainerFork>(),
+                schema::<ContainerProcessStart>(),
+                schema::<ContainerSyscalls>(),
+                schema::<ContainerNamespaceOp>(),
+                schema::<ContainerMountOp>(),
+                schema::<ContainerCgroupWrite>(),
+                schema::<ContainerMain>(),
+                schema::<ContainerMainSyscall>(),
+                schema::<ContainerBirthComplete>(),
+            ],
+            maps: vec![
+                MapSchema {
+                    name: "@runq_wait_ns",
+                    signal: Signal::RunQueue,
+                    decode: |pid, wait_ns| KernelRecord::RunQueue(RunQueue { pid, wait_ns, wakeups: 0 }),
+                },
+                MapSchema {
+                    name: "@runq_wakeups",
+                    signal: Signal::RunQueue,
+                    decode: |pid, wakeups| KernelRecord::RunQueue(RunQueue { pid, wait_ns: 0, wakeups }),
+                },
+            ],
+        }
+    }
+
+    /// The records in one line of output, text or `-f json`. Lines that are no
+    /// record, such as probe banners, decode to nothing; a known record with
+    /// bad fields is an error.
+    pub fn decode(&self, line: &str) -> Result<Vec<KernelRecord>> {
+        let line = line.trim();
+        if line.starts_with('{') {
+            return self.decode_json(line);
+        }
+        if line.starts_with('@') {
+            return Ok(self.decode_map_line(line).into_iter().collect());
+        }
+        self.decode_text(line).map(|record| record.into_iter().collect())
+    }
+
+    /// Signals a script's source prints, from its markers, tuple names and maps
+    pub fn signals_in(&self, source: &str) -> Vec<Signal> {
+        let mut signals: Vec<Signal> = self
+            .records
+            .iter()
+            .filter(|schema| source.contains(schema.marker) || source.contains(&format!("\"{}\"", schema.name)))
+            .filter_map(|schema| schema.signal)
+            .chain(self.maps.iter().filter(|map| source.contains(map.name)).map(|map| map.signal))
+            .collect();
+        signals.sort();
+        signals.dedup();
+        signals
+    }
+
+    fn decode_text(&self, line: &str) -> Result<Option<KernelRecord>> {
+        let (marker, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let Some(schema) = self.records.iter().find(|schema| schema.marker == marker) else {
+            return Ok(None);
+        };
+        let mut fields = Map::new();
+        for (name, value) in text_fields(rest, schema.fields) {
+            let kind = schema.fields.iter().find(|(field, _)| *field == name).map(|(_, kind)| *kind);
+            fields.insert(name.to_string(), typed(name, value, kind.unwrap_or(Str))?);
+        }
+        (schema.decode)(fields)
+            .map(Some)
+            .map_err(|e| anyhow!("{} line does not match its schema: {}", marker, e))
+    }
+
+    fn decode_json(&self, line: &str) -> Result<Vec<KernelRecord>> {
+        #[derive(Deserialize)]
+        struct Output {
+            #[serde(rename = "type")]
+            kind: String,
+            #[serde(default)]
+            data: Value,
+        }
+        let output: Output = serde_json::from_str(line)?;
+
+        match (output.kind.as_str(), output.data) {
+            ("printf", Value::String(text)) => self.decode(&text),
+            ("value", Value::Array(tuple)) => self.decode_tuple(tuple).map(|record| record.into_iter().collect()),
+            ("map", Value::Object(maps)) => Ok(maps
+                .iter()
+                .filter_map(|(name, entries)| Some((self.maps.iter().find(|map| map.name == name)?, entries.as_object()?)))
+                .flat_map(|(map, entries)| {
+                    entries.iter().filter_map(|(key, value)| Some((map.decode)(key.parse().ok()?, value.as_u64()?)))
+                })
+                .collect()),
+            ("lost_events", data) => Ok(data.get("events").and_then(Value::as_u64).map(KernelRecord::LostEvents).into_iter().collect()),
+            // attached_probes, time, helper errors and the like
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn decode_tuple(&self, mut tuple: Vec<Value>) -> Result<Option<KernelRecord>> {
+        let Some(Value::String(name)) = tuple.first().cloned() else {
+            return Ok(None);
+        };
+        let Some(schema) = self.records.iter().find(|schema| schema.name == name) else {
+            return Ok(None);
+        };
+        let values = tuple.split_off(1);
+        if values.len() > schema.fields.len() {
+            anyhow::bail!("{} tuple has {} fields, its schema {}", name, values.len(), schema.fields.len());
+        }
+        let fields = schema.fields.iter().zip(values).map(|((field, _), value)| (field.to_string(), value)).collect();
+        (schema.decode)(fields)
+            .map(Some)
+            .map_err(|e| anyhow!("{} tuple does not match its schema: {}", name, e))
+    }
+
+    /// `@runq_wait_ns[42]: 1200`
+    fn decode_map_line(&self, line: &str) -> Option<KernelRecord> {
+        let (name, rest) = line.split_once('[')?;
+        let map = self.maps.iter().find(|map| map.name == name)?;
+        let (key, value) = rest.split_once("]:")?;
+        Some((map.decode)(key.trim().parse().ok()?, value.trim().parse().ok()?))
+    }
+}
+
+/// `key=value` pairs of a text record. A value runs up to the next declared
+/// field, so free text such as `comm` may contain spaces; undeclared keys stay
+/// part of the value before them.
+fn text_fields<'a>(rest: &'a str, fields: &[(&'static str, FieldKind)]) -> Vec<(&'static str, &'a str)> {
+    // Where each declared key first appears at the start of a word
+    let mut starts: Vec<(usize, &'static str)> = fields
+        .iter()
+        .filter_map(|(name, _)| {
+            let key = format!("{name}=");
+            let mut from = 0;
+            while let Some(found) = rest[from..].find(&key) {
+                let at = from + found;
+                if at == 0 || rest.as_bytes()[at - 1] == b' ' {
+                    return Some((at, *name));
+                }
+                from = at + key.len();
+            }
+            None
+        })
+        .collect();
+    starts.sort();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, (at, name))| {
+            let end = starts.get(i + 1).map_or(rest.len(), |(next, _)| *next);
+            (*name, rest[at + name.len() + 1..end].trim_end())
+        })
+        .collect()
+}
+
+fn typed(name: &str, value: &str, kind: FieldKind) -> Result<Value> {
+    match kind {
+        Int => value
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| anyhow!("{name}={value} is not a number")),
+        Str => Ok(Value::from(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_one(line: &str) -> KernelRecord {
+        let mut records = registry().decode(line).unwrap();
+        assert_eq!(records.len(), 1, "{line}");
+        records.remove(0)
+    }
+
+    #[test]
+    fn test_text_records() {
+        assert_eq!(
+            decode_one("CPU_THROTTLE_EVENT pid=42 comm=app throttle_ns=5000 timestamp_ms=9"),
+            KernelRecord::CpuThrottle(CpuThrottle { pid: 42, comm: "app".to_string(), throttle_ns: 5000, timestamp_ms: Some(9) })
+        );
+        // CONTAINER_MAIN is a prefix of this one's marker
+        assert!(matches!(
+            decode_one("CONTAINER_MAIN_SYSCALL pid=7 count=3 timestamp_ms=1"),
+            KernelRecord::ContainerMainSyscall(ContainerMainSyscall { pid: 7, count: 3, .. })
+        ));
+        assert!(matches!(decode_one("KERNEL_MONITOR_STARTED golden_syscalls=enabled"), KernelRecord::MonitorStarted(_)));
+        // Fields the script leaves out are absent
+        let KernelRecord::ContainerBirthComplete(birth) =
+            decode_one("CONTAINER_BIRTH_COMPLETE pid=9 comm=nginx total_duration_ns=500 timestamp_ms=1")
+        else {
+            panic!("not a birth record");
+        };
+        assert_eq!((birth.total_duration_ns, birth.total_syscalls), (Some(500), None));
+    }
+
+    #[test]
+    fn test_comm_with_spaces_and_keys() {
+        let KernelRecord::CpuThrottle(throttle) =
+            decode_one("CPU_THROTTLE_EVENT pid=42 comm=my app pid=1 throttle_ns=5000 timestamp_ms=9")
+        else {
+            panic!("not a throttle record");
+        };
+        assert_eq!(throttle.pid, 42);
+        assert_eq!(throttle.comm, "my app pid=1");
+        assert_eq!(throttle.throttle_ns, 5000);
+    }
+
+    #[test]
+    fn test_malformed_known_record_is_an_error() {
+        assert!(registry().decode("CPU_THROTTLE_EVENT pid=x comm=app throttle_ns=1").is_err());
+        assert!(registry().decode("CPU_THROTTLE_EVENT comm=app").is_err());
+        assert!(registry().decode("Attaching 7 probes...").unwrap().is_empty());
+        assert!(registry().decode("@throttle_count[12]: 3").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_json_output() {
+        assert!(matches!(
+            decode_one(r#"{"type": "printf", "data": "CPU_THROTTLE_EVENT pid=42 comm=app throttle_ns=1 timestamp_ms=9\n"}"#),
+            KernelRecord::CpuThrottle(CpuThrottle { pid: 42, .. })
+        ));
+        assert_eq!(
+            decode_one(r#"{"type": "value", "data": ["cpu_throttle", 42, "my \"app\"", 5000]}"#),
+            KernelRecord::CpuThrottle(CpuThrottle { pid: 42, comm: "my \"app\"".to_string(), throttle_ns: 5000, timestamp_ms: None })
+        );
+        assert_eq!(decode_one(r#"{"type": "lost_events", "data": {"events": 12}}"#), KernelRecord::LostEvents(12));
+        assert!(registry().decode(r#"{"type": "attached_probes", "data": {"probes": 7}}"#).unwrap().is_empty());
+        assert!(registry().decode(r#"{"type": "value", "data": ["cpu_throttle", "x"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_maps() {
+        assert_eq!(
+            decode_one("@runq_wait_ns[4242]: 120000"),
+            KernelRecord::RunQueue(RunQueue { pid: 4242, wait_ns: 120000, wakeups: 0 })
+        );
+        let mut records = registry()
+            .decode(r#"{"type": "map", "data": {"@runq_wakeups": {"1": 3, "2": 4}, "@other": {"1": 1}}}"#)
+            .unwrap();
+        records.sort_by_key(|record| match record {
+            KernelRecord::RunQueue(runqueue) => runqueue.pid,
+            _ => 0,
+        });
+        assert_eq!(
+            records,
+            vec![
+                KernelRecord::RunQueue(RunQueue { pid: 1, wait_ns: 0, wakeups: 3 }),
+                KernelRecord::RunQueue(RunQueue { pid: 2, wait_ns: 0, wakeups: 4 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_signals_in_source() {
+        let source = r#"
+            tracepoint:sched:sched_switch { print(("cpu_throttle", pid, comm, 1)); }
+            interval:s:10 { print(@runq_wakeups); }
+        "#;
+        assert_eq!(registry().signals_in(source), vec![Signal::CpuThrottle, Signal::RunQueue]);
+    }
+}
@@ -0,0 +1,318 @@
+//! Every enabled bpftrace script in the scripts ConfigMap, run side by side.
+//!
+//! Each `*.bt` key of the mounted ConfigMap is a script. An optional
+//! `enabled-scripts` key lists the ones to run, one name per line, in order of
+//! priority; without it every script runs, by name. Each script gets its own
+//! supervised bpftrace and their output is merged into one stream. Scripts
+//! overlap (`monitoring.bt` also reports throttling), so every signal is taken
+//! only from the first script that emits it and the same kernel activity is
+//! not reported twice.
+//!
+//! The directory is read again every reload interval. The kubelet swaps the
+//! ConfigMap contents in place, so changed scripts are restarted, new ones
+//! started and removed ones stopped without restarting the observer.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+
+use crate::records::{registry, KernelRecord, Signal};
+use crate::source::{EventSource, SourceLine};
+use crate::supervisor::{BpftraceSupervisor, Health, SupervisorSettings};
+
+pub const DEFAULT_SCRIPTS_DIR: &str = "/etc/bpftrace-scripts";
+
+/// ConfigMap key naming the scripts to run
+pub const ENABLED_KEY: &str = "enabled-scripts";
+
+/// Lines in flight between the supervisors and the parser
+const LINE_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    pub name: String,
+    pub source: String,
+}
+
+/// The enabled scripts in `dir`, highest priority first
+pub fn load_scripts(dir: &Path) -> Result<Vec<Script>> {
+    let mut available = BTreeMap::new();
+    for entry in fs::read_dir(dir).map_err(|e| anyhow::anyhow!("Failed to read bpftrace scripts in {}: {}", dir.display(), e))? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        // ConfigMap volumes also hold `..data` and timestamped directories
+        if name.starts_with('.') || !name.ends_with(".bt") {
+            continue;
+        }
+        available.insert(name.to_string(), fs::read_to_string(&path)?);
+    }
+
+    let enabled = match fs::read_to_string(dir.join(ENABLED_KEY)) {
+        Ok(list) => list
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty() && !name.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        Err(_) => available.keys().cloned().collect::<Vec<_>>(),
+    };
+
+    let mut scripts = Vec::with_capacity(enabled.len());
+    for name in enabled {
+        match available.remove(&name) {
+            Some(source) => scripts.push(Script { name, source }),
+            None => warn!("⚠️ Enabled bpftrace script {} is not in {}", name, dir.display()),
+        }
+    }
+    Ok(scripts)
+}
+
+/// Which script each signal is taken from
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Routing {
+    owners: HashMap<Signal, String>,
+}
+
+impl Routing {
+    pub fn new(scripts: &[Script]) -> Self {
+        let mut owners = HashMap::new();
+        for script in scripts {
+            for signal in registry().signals_in(&script.source) {
+                owners.entry(signal).or_insert_with(|| script.name.clone());
+            }
+        }
+        Self { owners }
+    }
+
+    /// Whether a line from `script` that decoded to `records` goes to the
+    /// parser. Lines that carry no signal, such as start-up banners, always do.
+    pub fn routes(&self, script: &str, records: &[KernelRecord]) -> bool {
+        records
+            .iter()
+            .filter_map(KernelRecord::signal)
+            .all(|signal| self.owners.get(&signal).is_some_and(|owner| owner == script))
+    }
+}
+
+struct RunningScript {
+    source: String,
+    task: JoinHandle<()>,
+}
+
+/// The merged output of every enabled script, reloaded as the directory changes
+pub struct ScriptsSource {
+    dir: PathBuf,
+    settings: SupervisorSettings,
+    health: Health,
+    running: BTreeMap<String, RunningScript>,
+    routing: Routing,
+    reload: Interval,
+    lines_tx: mpsc::Sender<(Arc<str>, SourceLine)>,
+    lines: mpsc::Receiver<(Arc<str>, SourceLine)>,
+    started: Instant,
+}
+
+impl ScriptsSource {
+    pub fn start(dir: impl Into<PathBuf>, settings: SupervisorSettings, health: Health, reload_every: Duration) -> Result<Self> {
+        let dir = dir.into();
+        let scripts = load_scripts(&dir)?;
+        if scripts.is_empty() {
+            warn!("⚠️ No bpftrace script enabled in {}, waiting for one", dir.display());
+        }
+
+        let (lines_tx, lines) = mpsc::channel(LINE_BUFFER);
+        let mut reload = tokio::time::interval_at(Instant::now() + reload_every, reload_every);
+        reload.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut source = Self {
+            dir,
+            settings,
+            health,
+            running: BTreeMap::new(),
+            routing: Routing::default(),
+            reload,
+            lines_tx,
+            lines,
+            started: Instant::now(),
+        };
+        source.apply(scripts);
+        Ok(source)
+    }
+
+    /// Stop, restart and start supervisors so exactly `scripts` run
+    fn apply(&mut self, scripts: Vec<Script>) {
+        let wanted: HashMap<&str, &Script> = scripts.iter().map(|script| (script.name.as_str(), script)).collect();
+
+        let stale: Vec<String> = self
+            .running
+            .iter()
+            .filter(|(name, running)| wanted.get(name.as_str()).is_none_or(|script| script.source != running.source))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in stale {
+            if let Some(running) = self.running.remove(&name) {
+                info!("🛑 Stopping bpftrace script {}", name);
+                // Dropping the supervisor kills its bpftrace
+                running.task.abort();
+                self.health.remove(&name);
+            }
+        }
+
+        for script in &scripts {
+            if self.running.contains_key(&script.name) {
+                continue;
+            }
+            info!("📁 Starting bpftrace script {} ({} bytes)", script.name, script.source.len());
+            let task = tokio::spawn(supervise(
+                BpftraceSupervisor::new(&script.name, script.source.clone(), self.settings, self.health.register(&script.name)),
+                Arc::from(script.name.as_str()),
+                self.lines_tx.clone(),
+            ));
+            self.running.insert(script.name.clone(), RunningScript { source: script.source.clone(), task });
+        }
+
+        self.routing = Routing::new(&scripts);
+    }
+
+    fn reload(&mut self) {
+        match load_scripts(&self.dir) {
+            Ok(scripts) => {
+                let unchanged = scripts.len() == self.running.len()
+                    && scripts.iter().all(|script| {
+                        self.running.get(&script.name).is_some_and(|running| running.source == script.source)
+                    });
+                let routing = Routing::new(&scripts);
+                if unchanged && routing == self.routing {
+                    return;
+                }
+                info!("🔄 bpftrace scripts changed in {}, reloading", self.dir.display());
+                self.apply(scripts);
+            }
+            // Keep what runs rather than stopping everything on a half-written update
+            Err(e) => error!("❌ Could not reload bpftrace scripts: {}", e),
+        }
+    }
+}
+
+/// Forward one supervisor's lines, tagged with its script
+async fn supervise(mut supervisor: BpftraceSupervisor, script: Arc<str>, lines: mpsc::Sender<(Arc<str>, SourceLine)>) {
+    loop {
+        match supervisor.next_line().await {
+            Ok(Some(line)) => {
+                if lines.send((script.clone(), line)).await.is_err() {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                error!("❌ bpftrace script {} stopped: {}", script, e);
+                break;
+            }
+        }
+    }
+    let _ = supervisor.finish().await;
+}
+
+impl EventSource for ScriptsSource {
+    async fn next_line(&mut self) -> Result<Option<SourceLine>> {
+        loop {
+            tokio::select! {
+                next = self.lines.recv() => {
+                    // The source holds a sender itself, so the channel never closes
+                    let Some((script, line)) = next else { return Ok(None) };
+                    // Decoded once here and handed on; lines that fail to decode
+                    // go through for the parser to report
+                    let records = registry().decode(&line.line).ok();
+                    if records.as_deref().is_none_or(|records| self.routing.routes(&script, records)) {
+                        return Ok(Some(SourceLine { offset: self.started.elapsed(), line: line.line, records }));
+                    }
+                    debug!("Dropping {} line already taken from another script: {}", script, line.line);
+                }
+                _ = self.reload.tick() => self.reload(),
+            }
+        }
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        for (name, running) in std::mem::take(&mut self.running) {
+            running.task.abort();
+            self.health.remove(&name);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THROTTLE: &str = r#"tracepoint:sched:sched_switch { printf("CPU_THROTTLE_EVENT pid=%d\n", pid); }"#;
+    const MONITORING: &str = r#"
+        tracepoint:sched:sched_process_exec { printf("CONTAINER_BIRTH_COMPLETE pid=%d\n", pid); }
+        tracepoint:sched:sched_switch { printf("CPU_THROTTLE_EVENT pid=%d\n", pid); }
+        interval:s:10 { print(@runq_wait_ns); }
+    "#;
+    const BIRTH: &str = r#"tracepoint:sched:sched_process_exec { printf("CONTAINER_MAIN pid=%d\n", pid); }"#;
+
+    fn write_scripts(dir: &Path) {
+        fs::write(dir.join("monitoring.bt"), MONITORING).unwrap();
+        fs::write(dir.join("cpu-throttling.bt"), THROTTLE).unwrap();
+        fs::write(dir.join("container-birth.bt"), BIRTH).unwrap();
+        fs::create_dir(dir.join("..2024_01_01_00_00_00.000000000")).unwrap();
+    }
+
+    fn routes(routing: &Routing, script: &str, line: &str) -> bool {
+        routing.routes(script, &registry().decode(line).expect("decodes"))
+    }
+
+    fn names(scripts: &[Script]) -> Vec<&str> {
+        scripts.iter().map(|script| script.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_every_script_runs_without_a_list() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        write_scripts(dir.path());
+        let scripts = load_scripts(dir.path()).unwrap();
+        assert_eq!(names(&scripts), ["container-birth.bt", "cpu-throttling.bt", "monitoring.bt"]);
+    }
+
+    #[test]
+    fn test_enabled_list_selects_and_orders() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        write_scripts(dir.path());
+        fs::write(dir.path().join(ENABLED_KEY), "# Highest priority first\nmonitoring.bt\n\ncontainer-birth.bt\nmissing.bt\n").unwrap();
+        let scripts = load_scripts(dir.path()).unwrap();
+        assert_eq!(names(&scripts), ["monitoring.bt", "container-birth.bt"]);
+    }
+
+    #[test]
+    fn test_each_signal_comes_from_one_script() {
+        let scripts = vec![
+            Script { name: "container-birth.bt".to_string(), source: BIRTH.to_string() },
+            Script { name: "cpu-throttling.bt".to_string(), source: THROTTLE.to_string() },
+            Script { name: "monitoring.bt".to_string(), source: MONITORING.to_string() },
+        ];
+        let routing = Routing::new(&scripts);
+
+        assert!(routes(&routing, "cpu-throttling.bt", "CPU_THROTTLE_EVENT pid=1 comm=a throttle_ns=1 timestamp_ms=1"));
+        assert!(!routes(&routing, "monitoring.bt", "CPU_THROTTLE_EVENT pid=1 comm=a throttle_ns=1 timestamp_ms=1"));
+        assert!(routes(&routing, "container-birth.bt", "CONTAINER_BIRTH_COMPLETE pid=1 comm=runc"));
+        assert!(!routes(&routing, "monitoring.bt", "CONTAINER_BIRTH_COMPLETE pid=1 comm=runc"));
+        assert!(!routes(&routing, "monitoring.bt", r#"{"type": "value", "data": ["cpu_throttle", 1, "a", 1]}"#));
+        // Only monitoring.bt measures the run queue
+        assert!(routes(&routing, "monitoring.bt", "@runq_wait_ns[42]: 1200"));
+        // Banners are not signals
+        assert!(routes(&routing, "monitoring.bt", "KERNEL_MONITOR_STARTED golden_syscalls=enabled"));
+        assert!(routes(&routing, "container-birth.bt", "Attaching 5 probes..."));
+    }
+}
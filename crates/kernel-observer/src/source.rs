@@ -0,0 +1,157 @@
+use std::path::Path;
+use std::time::Duration;
+use anyhow::Result;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader, Lines};
+use tokio::time::Instant;
+use tracing::info;
+
+use crate::records::KernelRecord;
+
+/// One line of kernel output and when it was produced, relative to the start
+/// of its source
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLine {
+    pub offset: Duration,
+    pub line: String,
+    /// The line's records, when the source already had to decode it;
+    /// otherwise the parser decodes it
+    pub records: Option<Vec<KernelRecord>>,
+}
+
+/// Where the observer's kernel output comes from: bpftrace on a live node, or
+/// a capture replayed without root or a kernel
+pub trait EventSource {
+    /// The next line, `None` once the source is exhausted
+    async fn next_line(&mut self) -> Result<Option<SourceLine>>;
+
+    /// Release the source after its last line
+    async fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Lines from a reader: a replay file or stdin.
+///
+/// Replay files hold one line per output line, prefixed with its offset in
+/// milliseconds and a tab (`1520\tCPU_THROTTLE_EVENT pid=...`). Lines without
+/// the prefix, such as plain bpftrace output, are passed on as soon as read.
+pub struct LineSource<R> {
+    lines: Lines<R>,
+    started: Instant,
+    /// Replay speed; 0 passes lines on without waiting, and stdin is never delayed
+    speed: f64,
+    /// Offset of the last timed line, which untimed lines after it share
+    last_offset: Option<Duration>,
+}
+
+impl LineSource<BufReader<tokio::fs::File>> {
+    pub async fn replay(path: &Path, speed: f64) -> Result<Self> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to open replay file {}: {}", path.display(), e))?;
+        info!("⏪ Replaying {} at {}x", path.display(), speed);
+        Ok(Self::new(BufReader::new(file), speed))
+    }
+}
+
+impl LineSource<BufReader<tokio::io::Stdin>> {
+    pub fn stdin() -> Self {
+        Self::new(BufReader::new(tokio::io::stdin()), 0.0)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> LineSource<R> {
+    pub fn new(reader: R, speed: f64) -> Self {
+        Self { lines: reader.lines(), started: Instant::now(), speed, last_offset: None }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> EventSource for LineSource<R> {
+    async fn next_line(&mut self) -> Result<Option<SourceLine>> {
+        let Some(raw) = self.lines.next_line().await? else {
+            return Ok(None);
+        };
+
+        match split_offset(&raw) {
+            Some((offset, line)) => {
+                pace(self.started, offset, self.speed).await;
+                self.last_offset = Some(offset);
+                Ok(Some(SourceLine { offset, line: line.to_string(), records: None }))
+            }
+            None => {
+                let offset = self.last_offset.unwrap_or_else(|| self.started.elapsed());
+                Ok(Some(SourceLine { offset, line: raw, records: None }))
+            }
+        }
+    }
+}
+
+/// Wait until `offset` into a replay started at `started`, scaled by `speed`;
+/// a speed of 0 does not wait
+pub async fn pace(started: Instant, offset: Duration, speed: f64) {
+    if speed > 0.0 {
+        tokio::time::sleep_until(started + offset.div_f64(speed)).await;
+    }
+}
+
+/// `<milliseconds>\t<line>` into its parts
+fn split_offset(raw: &str) -> Option<(Duration, &str)> {
+    let (offset, line) = raw.split_once('\t')?;
+    let millis: u64 = offset.parse().ok()?;
+    Some((Duration::from_millis(millis), line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPTURE: &str = "0\tKERNEL_MONITOR_STARTED\n\
+                           1500\tCPU_THROTTLE_EVENT pid=42 comm=app throttle_ns=1 timestamp_ms=9\n\
+                           Attaching 7 probes...\n\
+                           4000\t@runq_wait_ns[42]: 1200\n";
+
+    async fn collect(mut source: impl EventSource) -> Vec<(Duration, String)> {
+        let started = Instant::now();
+        let mut lines = Vec::new();
+        while let Some(line) = source.next_line().await.unwrap() {
+            lines.push((started.elapsed(), line.line));
+        }
+        lines
+    }
+
+    #[test]
+    fn test_split_offset() {
+        assert_eq!(split_offset("250\tfoo bar"), Some((Duration::from_millis(250), "foo bar")));
+        assert_eq!(split_offset("@runq_wait_ns[42]: 1200"), None);
+        assert_eq!(split_offset("x\ty"), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_honours_recorded_timing() {
+        let started = Instant::now();
+        let mut source = LineSource::new(CAPTURE.as_bytes(), 2.0);
+
+        let mut seen = Vec::new();
+        while let Some(line) = source.next_line().await.unwrap() {
+            seen.push((started.elapsed(), line.offset, line.line));
+        }
+
+        assert_eq!(seen.len(), 4);
+        // Twice as fast as recorded
+        assert_eq!(seen[1].0, Duration::from_millis(750));
+        assert_eq!(seen[1].1, Duration::from_millis(1500));
+        // Untimed lines go straight through
+        assert_eq!(seen[2].0, Duration::from_millis(750));
+        assert_eq!(seen[2].1, Duration::from_millis(1500));
+        assert_eq!(seen[2].2, "Attaching 7 probes...");
+        assert_eq!(seen[3].0, Duration::from_millis(2000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_without_timing() {
+        let lines = collect(LineSource::new(CAPTURE.as_bytes(), 0.0)).await;
+        assert_eq!(lines.len(), 4);
+        assert!(lines.iter().all(|(waited, _)| waited.is_zero()));
+        assert_eq!(lines[3].1, "@runq_wait_ns[42]: 1200");
+    }
+}
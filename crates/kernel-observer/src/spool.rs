@@ -0,0 +1,300 @@
+//! On-disk queue of event batches waiting for the operator.
+//!
+//! Every batch is one JSON file under `queue/`, named by a sequence number so
+//! the directory listing is the delivery order. Files are written to a temp name
+//! and renamed, so a crash never leaves half a batch behind, and whatever is in
+//! the queue when the observer starts is delivered first. Batches that keep
+//! failing are moved to `dead-letter/` for someone to look at.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use kernel_gossip_protocol::EbpfEvent;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::metrics::SpoolMetrics;
+
+const QUEUE_DIR: &str = "queue";
+const DEAD_LETTER_DIR: &str = "dead-letter";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpoolEntry {
+    /// Failed deliveries so far
+    pub attempts: u32,
+    pub events: Vec<EbpfEvent>,
+}
+
+/// What happened to an entry after a failed delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOutcome {
+    /// Kept in the queue; this many attempts have failed
+    Retry(u32),
+    DeadLettered,
+}
+
+pub struct Spool {
+    queue_dir: PathBuf,
+    dead_letter_dir: PathBuf,
+    /// Sequence numbers currently in `queue/`, oldest first
+    entries: Vec<u64>,
+    next_seq: u64,
+    max_entries: usize,
+    max_attempts: u32,
+    metrics: SpoolMetrics,
+}
+
+impl Spool {
+    /// Open or create the spool under `dir`, picking up batches left by a previous run
+    pub fn open(dir: impl AsRef<Path>, max_entries: usize, max_attempts: u32, metrics: SpoolMetrics) -> io::Result<Self> {
+        let queue_dir = dir.as_ref().join(QUEUE_DIR);
+        let dead_letter_dir = dir.as_ref().join(DEAD_LETTER_DIR);
+        fs::create_dir_all(&queue_dir)?;
+        fs::create_dir_all(&dead_letter_dir)?;
+
+        let entries = sequence_numbers(&queue_dir)?;
+        let dead_lettered = sequence_numbers(&dead_letter_dir)?;
+        let next_seq = entries.iter().chain(&dead_lettered).max().map_or(0, |seq| seq + 1);
+
+        metrics.depth.set(entries.len() as i64);
+        metrics.dead_letter_depth.set(dead_lettered.len() as i64);
+
+        Ok(Self {
+            queue_dir,
+            dead_letter_dir,
+            entries,
+            next_seq,
+            max_entries: max_entries.max(1),
+            max_attempts: max_attempts.max(1),
+            metrics,
+        })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Queue a batch. When the spool is full the oldest batch is discarded, since
+    /// it is the one least likely to still matter.
+    pub fn push(&mut self, events: Vec<EbpfEvent>) -> io::Result<()> {
+        let seq = self.next_seq;
+        self.write(seq, &SpoolEntry { attempts: 0, events })?;
+        self.next_seq += 1;
+        self.entries.push(seq);
+
+        while self.entries.len() > self.max_entries {
+            let oldest = self.entries.remove(0);
+            warn!("🗑️ Spool is full ({} batches), dropping batch {}", self.max_entries, oldest);
+            remove_if_present(&self.entry_path(oldest))?;
+            self.metrics.dropped.inc();
+        }
+        self.metrics.depth.set(self.entries.len() as i64);
+        Ok(())
+    }
+
+    /// The next batch to deliver. Unreadable files are dead-lettered and skipped.
+    pub fn oldest(&mut self) -> io::Result<Option<(u64, SpoolEntry)>> {
+        while let Some(&seq) = self.entries.first() {
+            let read = fs::read(self.entry_path(seq))
+                .and_then(|bytes| serde_json::from_slice(&bytes).map_err(io::Error::from));
+            match read {
+                Ok(entry) => return Ok(Some((seq, entry))),
+                Err(e) => {
+                    warn!("Spooled batch {} is unreadable ({}), moving it to {}", seq, e, DEAD_LETTER_DIR);
+                    self.dead_letter(seq)?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The batch was delivered
+    pub fn complete(&mut self, seq: u64) -> io::Result<()> {
+        remove_if_present(&self.entry_path(seq))?;
+        self.entries.retain(|s| *s != seq);
+        self.metrics.depth.set(self.entries.len() as i64);
+        Ok(())
+    }
+
+    /// Count a failed delivery of `events` (what is left of the batch), dead-lettering
+    /// it once it has failed `max_attempts` times
+    pub fn retry(&mut self, seq: u64, attempts: u32, events: Vec<EbpfEvent>) -> io::Result<RetryOutcome> {
+        let entry = SpoolEntry { attempts: attempts + 1, events };
+        self.write(seq, &entry)?;
+
+        if entry.attempts >= self.max_attempts {
+            self.dead_letter(seq)?;
+            return Ok(RetryOutcome::DeadLettered);
+        }
+        self.metrics.retries.inc();
+        Ok(RetryOutcome::Retry(entry.attempts))
+    }
+
+    /// Give up on a batch without further attempts
+    pub fn dead_letter(&mut self, seq: u64) -> io::Result<()> {
+        let target = self.dead_letter_dir.join(file_name(seq));
+        match fs::rename(self.entry_path(seq), target) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        self.entries.retain(|s| *s != seq);
+
+        // The dead-letter directory is bounded like the queue
+        let mut dead = sequence_numbers(&self.dead_letter_dir)?;
+        while dead.len() > self.max_entries {
+            remove_if_present(&self.dead_letter_dir.join(file_name(dead.remove(0))))?;
+        }
+
+        self.metrics.depth.set(self.entries.len() as i64);
+        self.metrics.dead_letter_depth.set(dead.len() as i64);
+        Ok(())
+    }
+
+    fn entry_path(&self, seq: u64) -> PathBuf {
+        self.queue_dir.join(file_name(seq))
+    }
+
+    fn write(&self, seq: u64, entry: &SpoolEntry) -> io::Result<()> {
+        let tmp = self.queue_dir.join(format!(".{}.tmp", file_name(seq)));
+        fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        fs::rename(tmp, self.entry_path(seq))
+    }
+}
+
+/// Zero padded so lexical and numeric order agree
+fn file_name(seq: u64) -> String {
+    format!("{seq:020}.json")
+}
+
+fn sequence_numbers(dir: &Path) -> io::Result<Vec<u64>> {
+    let mut seqs: Vec<u64> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.strip_suffix(".json")?.parse().ok())
+        .collect();
+    seqs.sort_unstable();
+    Ok(seqs)
+}
+
+fn remove_if_present(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Exponential backoff: `base * 2^(attempt-1)` capped at `max`, of which a random
+/// half is kept (`jitter` in 0..1) so observers on every node do not retry in step
+pub fn backoff_delay(attempt: u32, base: Duration, max: Duration, jitter: f64) -> Duration {
+    let exponential = base.saturating_mul(1u32 << attempt.saturating_sub(1).min(20));
+    let capped = exponential.min(max);
+    capped / 2 + capped.mul_f64(jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::ObserverMetrics;
+    use kernel_gossip_protocol::PodCreationPayload;
+    use tempfile::TempDir;
+
+    fn event(pod_name: &str) -> EbpfEvent {
+        EbpfEvent::PodCreation(PodCreationPayload {
+            pod_name: pod_name.to_string(),
+            namespace: "default".to_string(),
+            total_syscalls: 0,
+            namespace_ops: 0,
+            cgroup_writes: 0,
+            mount_ops: 0,
+            duration_ns: 0,
+            timeline: vec![],
+            ebpf_detection: true,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    fn spool(dir: &TempDir, max_entries: usize, max_attempts: u32) -> (Spool, SpoolMetrics) {
+        let metrics = ObserverMetrics::new().unwrap().spool;
+        (Spool::open(dir.path(), max_entries, max_attempts, metrics.clone()).unwrap(), metrics)
+    }
+
+    #[test]
+    fn test_batches_survive_restart_in_order() {
+        let dir = TempDir::new().unwrap();
+        let (mut first_run, _) = spool(&dir, 10, 3);
+        first_run.push(vec![event("a")]).unwrap();
+        first_run.push(vec![event("b")]).unwrap();
+        drop(first_run);
+
+        let (mut second_run, metrics) = spool(&dir, 10, 3);
+        assert_eq!(metrics.depth.get(), 2);
+
+        let (seq, entry) = second_run.oldest().unwrap().unwrap();
+        assert_eq!(entry.events[0].pod_name(), "a");
+        second_run.complete(seq).unwrap();
+
+        // New batches are numbered after the recovered ones
+        second_run.push(vec![event("c")]).unwrap();
+        let (seq, entry) = second_run.oldest().unwrap().unwrap();
+        assert_eq!(entry.events[0].pod_name(), "b");
+        second_run.complete(seq).unwrap();
+        assert_eq!(second_run.oldest().unwrap().unwrap().1.events[0].pod_name(), "c");
+        assert_eq!(metrics.depth.get(), 1);
+    }
+
+    #[test]
+    fn test_full_spool_drops_oldest() {
+        let dir = TempDir::new().unwrap();
+        let (mut spool, metrics) = spool(&dir, 2, 3);
+        for pod in ["a", "b", "c"] {
+            spool.push(vec![event(pod)]).unwrap();
+        }
+
+        assert_eq!(spool.depth(), 2);
+        assert_eq!(metrics.dropped.get(), 1);
+        assert_eq!(spool.oldest().unwrap().unwrap().1.events[0].pod_name(), "b");
+    }
+
+    #[test]
+    fn test_retry_then_dead_letter() {
+        let dir = TempDir::new().unwrap();
+        let (mut spool, metrics) = spool(&dir, 10, 2);
+        spool.push(vec![event("a"), event("b")]).unwrap();
+
+        let (seq, entry) = spool.oldest().unwrap().unwrap();
+        // Only the event the operator failed on is kept
+        assert_eq!(spool.retry(seq, entry.attempts, vec![event("b")]).unwrap(), RetryOutcome::Retry(1));
+        let (seq, entry) = spool.oldest().unwrap().unwrap();
+        assert_eq!(entry.events, vec![event("b")]);
+
+        assert_eq!(spool.retry(seq, entry.attempts, entry.events).unwrap(), RetryOutcome::DeadLettered);
+        assert!(spool.oldest().unwrap().is_none());
+        assert_eq!(metrics.depth.get(), 0);
+        assert_eq!(metrics.dead_letter_depth.get(), 1);
+        assert!(dir.path().join(DEAD_LETTER_DIR).join(file_name(seq)).exists());
+    }
+
+    #[test]
+    fn test_unreadable_entry_is_dead_lettered() {
+        let dir = TempDir::new().unwrap();
+        let (mut spool, _) = spool(&dir, 10, 3);
+        spool.push(vec![event("a")]).unwrap();
+        fs::write(dir.path().join(QUEUE_DIR).join(file_name(0)), b"not json").unwrap();
+        spool.push(vec![event("b")]).unwrap();
+
+        assert_eq!(spool.oldest().unwrap().unwrap().1.events[0].pod_name(), "b");
+        assert_eq!(spool.depth(), 1);
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let (base, max) = (Duration::from_millis(500), Duration::from_secs(60));
+
+        assert_eq!(backoff_delay(1, base, max, 1.0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(1, base, max, 0.0), Duration::from_millis(250));
+        assert_eq!(backoff_delay(4, base, max, 1.0), Duration::from_secs(4));
+        assert_eq!(backoff_delay(30, base, max, 1.0), max);
+        assert_eq!(backoff_delay(30, base, max, 0.0), max / 2);
+    }
+}
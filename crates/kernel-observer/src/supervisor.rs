@@ -0,0 +1,385 @@
+//! Keeps bpftrace running for the life of the observer.
+//!
+//! bpftrace exits when a probe fails to attach, when it is OOM-killed or when
+//! the kernel changes under it. The supervisor restarts it with exponential
+//! backoff, starting over once a run has stayed up for a while, and slows to
+//! the maximum delay when stderr says a restart cannot help. The state of every
+//! script's supervisor is what `/readyz` reports, so a broken node shows up as
+//! an unready pod rather than a crash loop that also takes event delivery down.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+use tokio::process::Command;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::bpftrace::{BpftraceExit, BpftraceProcess, OutputFormat, StderrLine, StderrRing, STDERR_RING_LINES};
+use crate::metrics::metrics;
+use crate::source::{EventSource, SourceLine};
+use crate::spool::backoff_delay;
+use crate::webhook::RetrySettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BpftraceState {
+    /// First run, no output yet
+    Starting,
+    Running,
+    /// Exited, and not yet printing again since
+    Restarting,
+    /// Exited on an error a restart is not expected to fix; retried at the
+    /// slowest pace in case the node or script is fixed
+    Failed,
+}
+
+/// What `/readyz` reports
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub ready: bool,
+    pub scripts: BTreeMap<String, ScriptReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptReport {
+    pub state: BpftraceState,
+    pub restarts: u64,
+    pub last_exit: Option<String>,
+    pub last_fatal: Option<String>,
+    pub recent_stderr: Vec<StderrLine>,
+}
+
+/// Every supervised script, shared with the readiness endpoint
+#[derive(Clone)]
+pub struct Health {
+    /// Replays and stdin run no bpftrace and are always ready
+    live: bool,
+    scripts: Arc<Mutex<BTreeMap<String, ScriptHealth>>>,
+}
+
+impl Health {
+    pub fn new(live: bool) -> Self {
+        Self { live, scripts: Arc::default() }
+    }
+
+    /// Ready once every enabled script is printing; a live observer with no
+    /// script enabled observes nothing and is not
+    pub fn report(&self) -> HealthReport {
+        let scripts: BTreeMap<_, _> = self
+            .lock()
+            .iter()
+            .map(|(name, health)| (name.clone(), health.report()))
+            .collect();
+        let ready = !self.live
+            || (!scripts.is_empty() && scripts.values().all(|script| script.state == BpftraceState::Running));
+        HealthReport { ready, scripts }
+    }
+
+    /// Track a newly started script, replacing an earlier run of the same name
+    pub fn register(&self, script: &str) -> ScriptHealth {
+        let health = ScriptHealth::new();
+        self.lock().insert(script.to_string(), health.clone());
+        health
+    }
+
+    pub fn remove(&self, script: &str) {
+        self.lock().remove(script);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, ScriptHealth>> {
+        self.scripts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// One script's supervisor state
+#[derive(Clone)]
+pub struct ScriptHealth {
+    inner: Arc<Mutex<HealthInner>>,
+    stderr: StderrRing,
+}
+
+struct HealthInner {
+    state: BpftraceState,
+    restarts: u64,
+    last_exit: Option<String>,
+    last_fatal: Option<String>,
+}
+
+impl ScriptHealth {
+    fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(HealthInner {
+                state: BpftraceState::Starting,
+                restarts: 0,
+                last_exit: None,
+                last_fatal: None,
+            })),
+            stderr: StderrRing::new(STDERR_RING_LINES),
+        }
+    }
+
+    pub fn report(&self) -> ScriptReport {
+        let inner = self.lock();
+        ScriptReport {
+            state: inner.state,
+            restarts: inner.restarts,
+            last_exit: inner.last_exit.clone(),
+            last_fatal: inner.last_fatal.clone(),
+            recent_stderr: self.stderr.recent(),
+        }
+    }
+
+    fn set_state(&self, state: BpftraceState) {
+        self.lock().state = state;
+    }
+
+    fn exited(&self, state: BpftraceState, last_exit: String, fatal: Option<String>) {
+        let mut inner = self.lock();
+        inner.state = state;
+        inner.restarts += 1;
+        inner.last_exit = Some(last_exit);
+        if fatal.is_some() {
+            inner.last_fatal = fatal;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HealthInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SupervisorSettings {
+    pub backoff: RetrySettings,
+    /// A run lasting this long resets the backoff
+    pub stable_after: Duration,
+    pub output: OutputFormat,
+}
+
+/// bpftrace as an [`EventSource`] that never runs dry: when the process ends
+/// another one is started
+pub struct BpftraceSupervisor {
+    /// Script name, for logs and metrics
+    name: String,
+    command: Box<dyn Fn() -> Command + Send>,
+    settings: SupervisorSettings,
+    health: ScriptHealth,
+    process: Option<BpftraceProcess>,
+    /// Restarts since the last stable run
+    attempts: u32,
+    /// Wait before the next start
+    delay: Option<Duration>,
+    spawned_at: Instant,
+    seen_output: bool,
+    started: Instant,
+}
+
+impl BpftraceSupervisor {
+    pub fn new(name: &str, script: String, settings: SupervisorSettings, health: ScriptHealth) -> Self {
+        Self::with_command(name, move || BpftraceProcess::command(&script, settings.output), settings, health)
+    }
+
+    fn with_command(
+        name: &str,
+        command: impl Fn() -> Command + Send + 'static,
+        settings: SupervisorSettings,
+        health: ScriptHealth,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            name: name.to_string(),
+            command: Box::new(command),
+            settings,
+            health,
+            process: None,
+            attempts: 0,
+            delay: None,
+            spawned_at: now,
+            seen_output: false,
+            started: now,
+        }
+    }
+
+    async fn start(&mut self) {
+        if let Some(delay) = self.delay.take() {
+            info!("⏳ Restarting bpftrace for {} in {:?}", self.name, delay);
+            tokio::time::sleep(delay).await;
+        }
+
+        match BpftraceProcess::spawn((self.command)(), self.health.stderr.clone()) {
+            Ok(process) => {
+                info!("✅ bpftrace process for {} spawned successfully", self.name);
+                self.process = Some(process);
+                self.spawned_at = Instant::now();
+                self.seen_output = false;
+            }
+            Err(e) => {
+                error!("🚨 Failed to spawn bpftrace for {}: {}", self.name, e);
+                self.exited(format!("spawn failed: {e}"), None);
+            }
+        }
+    }
+
+    fn exited(&mut self, last_exit: String, fatal: Option<String>) {
+        if self.spawned_at.elapsed() >= self.settings.stable_after {
+            self.attempts = 0;
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        metrics().pipeline.bpftrace_restarted(&self.name);
+
+        let backoff = self.settings.backoff;
+        let (state, delay) = match &fatal {
+            Some(reason) => {
+                error!("🚨 bpftrace for {} failed and will keep failing until fixed: {}", self.name, reason);
+                (BpftraceState::Failed, backoff.max)
+            }
+            None => {
+                warn!("⚠️ bpftrace for {} exited ({}), restart {}", self.name, last_exit, self.attempts);
+                (BpftraceState::Restarting, backoff_delay(self.attempts, backoff.base, backoff.max, rand::random()))
+            }
+        };
+        self.health.exited(state, last_exit, fatal);
+        self.delay = Some(delay);
+    }
+}
+
+impl EventSource for BpftraceSupervisor {
+    async fn next_line(&mut self) -> Result<Option<SourceLine>> {
+        loop {
+            let Some(process) = self.process.as_mut() else {
+                self.start().await;
+                continue;
+            };
+
+            match process.next_line().await {
+                Ok(Some(line)) => {
+                    if !self.seen_output {
+                        // bpftrace prints once its probes are attached
+                        self.seen_output = true;
+                        self.health.set_state(BpftraceState::Running);
+                    }
+                    return Ok(Some(SourceLine { offset: self.started.elapsed(), line, records: None }));
+                }
+                Ok(None) => {
+                    let Some(process) = self.process.take() else { continue };
+                    let BpftraceExit { status, fatal } = process.wait().await;
+                    let last_exit = status.map_or_else(|| "unknown status".to_string(), |status| status.to_string());
+                    self.exited(last_exit, fatal);
+                }
+                Err(e) => {
+                    // Still running: stop it before reaping, then restart as usual
+                    warn!("⚠️ Could not read bpftrace output for {}: {}", self.name, e);
+                    let Some(process) = self.process.take() else { continue };
+                    let BpftraceExit { status, fatal } = process.abort().await;
+                    let last_exit = status.map_or_else(|| "unknown status".to_string(), |status| status.to_string());
+                    self.exited(last_exit, fatal);
+                }
+            }
+        }
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        match self.process.take() {
+            Some(process) => process.kill().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: SupervisorSettings = SupervisorSettings {
+        backoff: RetrySettings { base: Duration::from_millis(10), max: Duration::from_millis(40) },
+        stable_after: Duration::from_secs(60),
+        output: OutputFormat::Text,
+    };
+
+    fn sh(script: &'static str) -> impl Fn() -> Command + Send {
+        move || {
+            let mut command = Command::new("sh");
+            command.args(["-c", script]);
+            command
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restarts_after_exit() {
+        let health = Health::new(true);
+        let mut supervisor = BpftraceSupervisor::with_command(
+            "test.bt",
+            sh("echo 'Attaching 2 probes...'; echo 'Lost 3 events' >&2; exit 1"),
+            SETTINGS,
+            health.register("test.bt"),
+        );
+        assert!(!health.report().ready);
+
+        for _ in 0..3 {
+            let line = supervisor.next_line().await.unwrap().unwrap();
+            assert_eq!(line.line, "Attaching 2 probes...");
+            assert_eq!(health.report().scripts["test.bt"].state, BpftraceState::Running);
+        }
+
+        let report = health.report();
+        assert!(report.ready);
+        let script = &report.scripts["test.bt"];
+        assert_eq!(script.restarts, 2);
+        assert!(script.last_exit.as_ref().unwrap().contains('1'));
+        assert!(script.last_fatal.is_none());
+        assert!(script.recent_stderr.iter().all(|line| line.line == "Lost 3 events"));
+        supervisor.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_non_utf8_line_keeps_the_stream() {
+        let health = Health::new(true);
+        let mut supervisor = BpftraceSupervisor::with_command(
+            "comm.bt",
+            sh("printf 'EXEC pid=42 comm=\\377\\376x\\n'; echo 'EXEC pid=43 comm=sh'; sleep 5"),
+            SETTINGS,
+            health.register("comm.bt"),
+        );
+
+        let line = supervisor.next_line().await.unwrap().unwrap();
+        assert_eq!(line.line, "EXEC pid=42 comm=\u{fffd}\u{fffd}x");
+        let line = supervisor.next_line().await.unwrap().unwrap();
+        assert_eq!(line.line, "EXEC pid=43 comm=sh");
+
+        let script = &health.report().scripts["comm.bt"];
+        assert_eq!(script.state, BpftraceState::Running);
+        assert_eq!(script.restarts, 0);
+        supervisor.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fatal_error_marks_failed() {
+        let health = Health::new(true);
+        let mut supervisor = BpftraceSupervisor::with_command(
+            "broken.bt",
+            sh("echo 'stdin:1:1: ERROR: syntax error, unexpected end of file' >&2; exit 1"),
+            SETTINGS,
+            health.register("broken.bt"),
+        );
+
+        // The script never gets as far as printing
+        let next = tokio::time::timeout(Duration::from_millis(200), supervisor.next_line()).await;
+        assert!(next.is_err());
+
+        let report = health.report();
+        assert!(!report.ready);
+        let script = &report.scripts["broken.bt"];
+        assert_eq!(script.state, BpftraceState::Failed);
+        assert!(script.restarts >= 1);
+        assert!(script.last_fatal.as_ref().unwrap().contains("syntax error"));
+    }
+
+    #[test]
+    fn test_replays_are_always_ready() {
+        assert!(Health::new(false).report().ready);
+        assert!(!Health::new(true).report().ready);
+    }
+}
@@ -1,41 +1,342 @@
 use reqwest::Client;
-use anyhow::Result;
-use tracing::{info, error};
-use serde_json::json;
-use crate::parser::EbpfEvent;
+use anyhow::{anyhow, Result};
+use tracing::{info, error, warn};
+use kernel_gossip_protocol::auth::{self, WebhookCredentials};
+use kernel_gossip_protocol::{BatchResponse, EbpfEvent, EventBatch, EventStatus, MAX_BATCH_EVENTS};
+use reqwest::{RequestBuilder, StatusCode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{timeout_at, Instant};
 
+use crate::metrics::metrics;
+use crate::spool::{backoff_delay, RetryOutcome, Spool};
+
+/// A hung connection counts as a failed delivery after this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// When queued events are posted: once `max_events` are waiting, or `max_delay`
+/// after the first of them arrived, whichever comes first
+#[derive(Debug, Clone, Copy)]
+pub struct BatchSettings {
+    pub max_events: usize,
+    pub max_delay: Duration,
+}
+
+/// Backoff bounds between failed deliveries of a spooled batch
+#[derive(Debug, Clone, Copy)]
+pub struct RetrySettings {
+    pub base: Duration,
+    pub max: Duration,
+}
+
+/// Queues events for the operator. Batches are written to the spool first and
+/// delivered from there, so they outlive operator restarts and our own.
 #[derive(Clone)]
 pub struct WebhookClient {
-    client: Client,
-    webhook_url: String,
+    queue: mpsc::Sender<EbpfEvent>,
+    /// Events sent but not yet written to the spool
+    queued: Arc<AtomicUsize>,
+    spool: Arc<Mutex<Spool>>,
 }
 
 impl WebhookClient {
-    pub fn new(webhook_url: String) -> Self {
-        Self {
-            client: Client::new(),
-            webhook_url,
-        }
+    /// `webhook_url` is the single-event endpoint; batches go to `{webhook_url}/batch`
+    pub fn new(
+        webhook_url: String,
+        credentials: WebhookCredentials,
+        batching: BatchSettings,
+        retry: RetrySettings,
+        spool: Spool,
+    ) -> Self {
+        let batching = BatchSettings {
+            max_events: batching.max_events.clamp(1, MAX_BATCH_EVENTS),
+            ..batching
+        };
+        // A full queue makes producers wait instead of growing without bound
+        let (queue, events) = mpsc::channel(batching.max_events * 4);
+        let spool = Arc::new(Mutex::new(spool));
+        let spooled = Arc::new(Notify::new());
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(spool_batches(events, batching, spool.clone(), spooled.clone(), queued.clone()));
+        let poster = BatchPoster {
+            client: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            batch_url: format!("{}/batch", webhook_url.trim_end_matches('/')),
+            credentials,
+        };
+        tokio::spawn(poster.deliver(spool.clone(), spooled, retry));
+
+        Self { queue, queued, spool }
     }
 
     pub async fn send_event(&self, event: EbpfEvent) -> Result<()> {
-        info!("Sending eBPF event to operator: {:?}", event);
-        
-        // Send the event directly - operator expects untagged enum
-        let response = self
+        metrics().pipeline.event(event.kind());
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        self.queue.send(event).await.map_err(|_| {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            anyhow!("webhook batch sender has stopped")
+        })
+    }
+
+    /// Wait up to `limit` for everything sent so far to be delivered or
+    /// dead-lettered; false if some of it is still pending
+    pub async fn drain(&self, limit: Duration) -> bool {
+        let deadline = Instant::now() + limit;
+        loop {
+            if self.queued.load(Ordering::SeqCst) == 0 && lock(&self.spool).depth() == 0 {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+fn lock(spool: &Mutex<Spool>) -> MutexGuard<'_, Spool> {
+    spool.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+async fn spool_batches(
+    mut events: mpsc::Receiver<EbpfEvent>,
+    batching: BatchSettings,
+    spool: Arc<Mutex<Spool>>,
+    spooled: Arc<Notify>,
+    queued: Arc<AtomicUsize>,
+) {
+    while let Some(batch) = next_batch(&mut events, batching).await {
+        let count = batch.len();
+        let pushed = lock(&spool).push(batch);
+        queued.fetch_sub(count, Ordering::SeqCst);
+        match pushed {
+            Ok(()) => spooled.notify_one(),
+            Err(e) => error!("❌ Failed to spool event batch, events lost: {}", e),
+        }
+    }
+    info!("Webhook batch sender stopped");
+}
+
+/// Result of posting one batch
+enum Delivery {
+    Delivered,
+    /// The operator failed these events of the batch; the rest were handled
+    Partial(Vec<EbpfEvent>),
+    /// Worth trying again: the operator was unreachable, overloaded or refused our credentials
+    Failed(anyhow::Error),
+    /// The operator will never accept this batch
+    Rejected(anyhow::Error),
+}
+
+struct BatchPoster {
+    client: Client,
+    batch_url: String,
+    credentials: WebhookCredentials,
+}
+
+impl BatchPoster {
+    /// Deliver spooled batches oldest first, backing off while the operator is down.
+    /// Once a retry gets through the backlog drains without further delays.
+    async fn deliver(self, spool: Arc<Mutex<Spool>>, spooled: Arc<Notify>, retry: RetrySettings) {
+        loop {
+            let next = lock(&spool).oldest();
+            let (seq, entry) = match next {
+                Ok(Some(next)) => next,
+                Ok(None) => {
+                    spooled.notified().await;
+                    continue;
+                }
+                Err(e) => {
+                    error!("❌ Cannot read the spool: {}", e);
+                    tokio::time::sleep(retry.max).await;
+                    continue;
+                }
+            };
+
+            let failed_events = match self.post(&entry.events).await {
+                Delivery::Delivered => {
+                    if let Err(e) = lock(&spool).complete(seq) {
+                        error!("❌ Failed to remove delivered batch {} from the spool: {}", seq, e);
+                    }
+                    continue;
+                }
+                Delivery::Rejected(e) => {
+                    metrics().pipeline.webhook_failed("rejected");
+                    error!("💀 Operator rejected batch {}, moving it to dead-letter: {}", seq, e);
+                    if let Err(e) = lock(&spool).dead_letter(seq) {
+                        error!("❌ Failed to dead-letter batch {}: {}", seq, e);
+                    }
+                    continue;
+                }
+                Delivery::Partial(failed) => {
+                    metrics().pipeline.webhook_failed("partial");
+                    failed
+                }
+                Delivery::Failed(e) => {
+                    metrics().pipeline.webhook_failed("transient");
+                    warn!("❌ Failed to send event batch {}: {}", seq, e);
+                    entry.events
+                }
+            };
+
+            let outcome = lock(&spool).retry(seq, entry.attempts, failed_events);
+            match outcome {
+                Ok(RetryOutcome::Retry(attempts)) => {
+                    let delay = backoff_delay(attempts, retry.base, retry.max, rand::random());
+                    warn!("⏳ Retrying batch {} in {:?} (attempt {})", seq, delay, attempts + 1);
+                    tokio::time::sleep(delay).await;
+                }
+                Ok(RetryOutcome::DeadLettered) => {
+                    error!("💀 Batch {} kept failing, moved it to dead-letter", seq);
+                }
+                Err(e) => {
+                    error!("❌ Failed to update batch {} in the spool: {}", seq, e);
+                    tokio::time::sleep(retry.max).await;
+                }
+            }
+        }
+    }
+
+    async fn post(&self, events: &[EbpfEvent]) -> Delivery {
+        info!("📦 Sending batch of {} eBPF events to operator", events.len());
+
+        let batch = EventBatch::new(events.to_vec());
+        let body = match kernel_gossip_protocol::encode_batch(&batch) {
+            Ok(body) => body,
+            Err(e) => return Delivery::Rejected(e.into()),
+        };
+        let request = self
             .client
-            .post(&self.webhook_url)
-            .header("Content-Type", "application/json")
-            .json(&event)
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            info!("✅ Successfully sent eBPF event to operator");
+            .post(&self.batch_url)
+            .header("Content-Type", "application/json");
+        let response = match self.authenticate(request, &body).body(body).send().await {
+            Ok(response) => response,
+            Err(e) => return Delivery::Failed(e.into()),
+        };
+
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED {
+            return Delivery::Failed(anyhow!("operator rejected our credentials, check the webhook auth Secret"));
+        }
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Delivery::Failed(anyhow!("operator answered {status}"));
+        }
+        if !status.is_success() {
+            let reason = response.text().await.unwrap_or_default();
+            return Delivery::Rejected(anyhow!("operator answered {status}: {reason}"));
+        }
+
+        // The operator has acted on the batch, so an odd response is not a reason to resend it
+        let results: BatchResponse = match response.json().await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("Could not read the operator's batch results: {}", e);
+                return Delivery::Delivered;
+            }
+        };
+        info!(
+            "✅ Operator processed batch: {} accepted, {} skipped, {} failed",
+            results.count(EventStatus::Accepted),
+            results.count(EventStatus::Skipped),
+            results.count(EventStatus::Error)
+        );
+
+        let failed: Vec<EbpfEvent> = results
+            .results
+            .iter()
+            .filter(|r| r.status == EventStatus::Error)
+            .filter_map(|r| {
+                let event = events.get(r.index)?;
+                warn!("Operator failed event for {}/{}: {}", event.namespace(), event.pod_name(), r.message);
+                Some(event.clone())
+            })
+            .collect();
+
+        if failed.is_empty() {
+            Delivery::Delivered
         } else {
-            error!("❌ Failed to send event: {}", response.status());
+            Delivery::Partial(failed)
         }
+    }
 
-        Ok(())
+    /// Sign with the HMAC key when there is one, since a captured signature
+    /// stops working after the operator's skew window; otherwise send the token
+    fn authenticate(&self, request: RequestBuilder, body: &[u8]) -> RequestBuilder {
+        if let Some(key) = &self.credentials.hmac_key {
+            let timestamp = chrono::Utc::now().timestamp();
+            request
+                .header(auth::TIMESTAMP_HEADER, timestamp)
+                .header(auth::SIGNATURE_HEADER, auth::sign(key, timestamp, body))
+        } else if let Some(token) = &self.credentials.token {
+            request.bearer_auth(token)
+        } else {
+            request
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Wait for one event, then collect more until the batch is full or its window
+/// closes. `None` once every sender is gone and the queue is drained.
+async fn next_batch(events: &mut mpsc::Receiver<EbpfEvent>, batching: BatchSettings) -> Option<Vec<EbpfEvent>> {
+    let first = events.recv().await?;
+    let deadline = Instant::now() + batching.max_delay;
+
+    let mut batch = vec![first];
+    while batch.len() < batching.max_events {
+        match timeout_at(deadline, events.recv()).await {
+            Ok(Some(event)) => batch.push(event),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    Some(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel_gossip_protocol::PodCreationPayload;
+
+    fn event(pod_name: &str) -> EbpfEvent {
+        EbpfEvent::PodCreation(PodCreationPayload {
+            pod_name: pod_name.to_string(),
+            namespace: "default".to_string(),
+            total_syscalls: 0,
+            namespace_ops: 0,
+            cgroup_writes: 0,
+            mount_ops: 0,
+            duration_ns: 0,
+            timeline: vec![],
+            ebpf_detection: true,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batches_close_on_count_or_time() {
+        let batching = BatchSettings { max_events: 3, max_delay: Duration::from_millis(500) };
+        let (tx, mut rx) = mpsc::channel(16);
+        for i in 0..5 {
+            tx.send(event(&format!("pod-{i}"))).await.unwrap();
+        }
+
+        // Full batch without waiting for the window
+        let started = Instant::now();
+        let batch = next_batch(&mut rx, batching).await.unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch[0].pod_name(), "pod-0");
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        // The remaining two go out when the window closes
+        let batch = next_batch(&mut rx, batching).await.unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(started.elapsed(), Duration::from_millis(500));
+
+        drop(tx);
+        assert!(next_batch(&mut rx, batching).await.is_none());
+    }
+}
@@ -27,6 +27,15 @@ echo ""
 echo "📦 Setting up namespace..."
 kubectl create namespace kernel-gossip 2>/dev/null || echo "   Namespace already exists"
 
+# Shared webhook keys for the operator and the observer, generated once per cluster
+echo "🔐 Setting up webhook auth Secret..."
+if ! kubectl get secret kernel-gossip-webhook-auth -n kernel-gossip &> /dev/null; then
+    kubectl create secret generic kernel-gossip-webhook-auth -n kernel-gossip \
+      --from-literal=hmac-key="$(openssl rand -hex 32)"
+else
+    echo "   Secret already exists"
+fi
+
 # Deploy CRDs
 echo "📋 Deploying Custom Resource Definitions..."
 kubectl apply -f crds/